@@ -0,0 +1,29 @@
+//! Criterion benchmarks of the escape-time kernels, one group per scene of
+//! `bench::BenchScene::standard` with one benchmark per backend compiled in:
+//!
+//! ```sh
+//! $ cargo bench --features parallel,simd
+//! ```
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use mandelbrot::bench::{Backend, BenchScene};
+
+/// Size of the rendered grids, small enough that the slow scenes take well under a second.
+const SIZE: (u32, u32) = (160, 120);
+
+fn scenes(c: &mut Criterion) {
+    let (width, height) = SIZE;
+    for scene in BenchScene::standard() {
+        let mut group = c.benchmark_group(scene.name);
+        group.sample_size(10).throughput(Throughput::Elements(width as u64 * height as u64));
+        for backend in Backend::ALL {
+            group.bench_function(backend.name(), |b| {
+                b.iter(|| backend.render(width, height, &scene.viewport, scene.iterations))
+            });
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, scenes);
+criterion_main!(benches);
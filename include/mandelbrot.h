@@ -0,0 +1,98 @@
+#ifndef MANDELBROT_H
+#define MANDELBROT_H
+
+/* Generated by cbindgen from src/ffi.rs, do not edit. */
+
+#include <stddef.h>
+#include <stdint.h>
+
+// Outcome of a render, returned by the functions that render.
+enum MandelbrotStatus
+#if __STDC_VERSION__ >= 202311L
+  : int32_t
+#endif // __STDC_VERSION__ >= 202311L
+ {
+  // The render succeeded.
+  MANDELBROT_STATUS_OK = 0,
+  // A pointer argument was null.
+  MANDELBROT_STATUS_NULL_POINTER = 1,
+  // The width or height is zero, or the image has too many pixels.
+  MANDELBROT_STATUS_INVALID_DIMENSIONS = 2,
+  // The iteration limit is above `error::MAX_ITERATIONS`.
+  MANDELBROT_STATUS_ITERATION_OVERFLOW = 3,
+  // The output buffer is smaller than the render.
+  MANDELBROT_STATUS_BUFFER_TOO_SMALL = 4,
+  // The zoom is not a finite number above zero, or the center is not finite.
+  MANDELBROT_STATUS_INVALID_VIEWPORT = 5,
+  // `struct_size` is smaller than the first version of `MandelbrotParams`.
+  MANDELBROT_STATUS_UNSUPPORTED_VERSION = 6,
+  // The library panicked; nothing was written.
+  MANDELBROT_STATUS_PANICKED = 7,
+};
+#if __STDC_VERSION__ >= 202311L
+typedef enum MandelbrotStatus MandelbrotStatus;
+#else
+typedef int32_t MandelbrotStatus;
+#endif // __STDC_VERSION__ >= 202311L
+
+// What to render, passed by pointer.
+typedef struct MandelbrotParams {
+  // Size in bytes of the struct as the caller knows it, `sizeof(MandelbrotParams)`.
+  size_t struct_size;
+  uint32_t width;
+  uint32_t height;
+  uint32_t iterations;
+  // Real part of the center of the view.
+  double center_x;
+  // Imaginary part of the center of the view.
+  double center_y;
+  // Zoom of the view, see `viewport::Viewport`.
+  double zoom;
+  // Whether to use smooth escape values, see `options::RenderOptions::smooth`; `0` is false.
+  uint8_t smooth;
+} MandelbrotParams;
+
+// Fills the `size` bytes at `params` with the parameters of the default view at 800 x 600
+// pixels, `struct_size` set to `size`, which is `sizeof(MandelbrotParams)` of the caller's
+// header. Fields newer than this library are zeroed.
+//
+// # Safety
+//
+// `params` must be null or point to `size` writable bytes.
+MandelbrotStatus mandelbrot_init_params(struct MandelbrotParams *params, size_t size);
+
+// Length in bytes of the buffer `mandelbrot_render` needs for `params`, or `0` if `params` is
+// null or of an unsupported version.
+//
+// # Safety
+//
+// `params` must be null or point to a `MandelbrotParams` of `struct_size` readable bytes.
+size_t mandelbrot_rgba_len(const struct MandelbrotParams *params);
+
+// Renders `params` into `out` as opaque RGBA rows, four bytes per pixel starting at the top
+// left, see `mandelbrot_img::render_rgba8`, and returns `Ok` or the problem. Nothing is
+// written unless the render succeeds.
+//
+// # Safety
+//
+// `params` must be null or point to a `MandelbrotParams` of `struct_size` readable bytes, and
+// `out` must be null or point to `out_len` writable bytes that no other thread accesses
+// during the call.
+MandelbrotStatus mandelbrot_render(const struct MandelbrotParams *params,
+                                   uint8_t *out,
+                                   size_t out_len);
+
+// Renders the escape counts of `params` into `out`, one per pixel row by row, the iteration
+// limit for points inside the set, and returns `Ok` or the problem. `out_len` counts elements,
+// not bytes. Nothing is written unless the render succeeds.
+//
+// # Safety
+//
+// `params` must be null or point to a `MandelbrotParams` of `struct_size` readable bytes, and
+// `out` must be null or point to `out_len` writable, aligned `u32`s that no other thread
+// accesses during the call.
+MandelbrotStatus mandelbrot_render_counts(const struct MandelbrotParams *params,
+                                          uint32_t *out,
+                                          size_t out_len);
+
+#endif  /* MANDELBROT_H */
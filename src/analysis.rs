@@ -0,0 +1,826 @@
+//! Answers questions about individual points of the complex plane rather than rendering grids.
+//!
+//! `classify` tells whether a point `c` belongs to the Mandelbrot set and how certain that answer
+//! is. A bare escape count cannot distinguish a point inside the set from one that simply needs
+//! more iterations to escape, so points that reach the iteration limit are only reported as
+//! `Inside` when membership is established by one of the following checks:
+//!
+//! * the point lies in the main cardioid or the period-2 bulb, which have closed-form boundaries;
+//! * the orbit of the point becomes periodic, detected with Brent's cycle detection.
+//!
+//! `orbit` returns the points a single `c` visits, to plot them over an image with
+//! `mandelbrot_img::draw_orbit` or to check periodicity and perturbation code against.
+//!
+//! `pixel_info` runs a point through the kernel like `escape` and also collects statistics of
+//! its orbit, the smallest and last `|z|` and the derivative `dz/dc`, which colorings other than
+//! the built-in ones are made of.
+//!
+//! `evaluate_points` runs arbitrary, scattered points through the escape-time kernel, for callers
+//! that sample the plane themselves (area estimation, boundary tracing, external tools) instead
+//! of rendering a regular grid.
+//!
+//! `bottcher` approximates the Böttcher coordinate `phi(c)` of a point outside the set, the
+//! conformal map from the exterior of the set to the exterior of the unit disk. Its argument is
+//! the external angle of `c`, which labels the external ray through `c`, and the logarithm of its
+//! modulus is the potential (Green's function). The product formula used converges quickly but
+//! takes principal branches, so angles of points very close to the boundary, where the orbit
+//! passes near `0` before escaping, may land on a neighbouring ray.
+//!
+//! `distance_estimate` bounds the distance from a point outside the set to the set itself, from
+//! the derivative `dz/dc` carried along the orbit. Unlike escape counts it does not depend on the
+//! iteration limit once the orbit escapes, which keeps filaments thinner than a pixel visible.
+
+use std::sync::atomic::AtomicBool;
+#[cfg(feature = "bigfloat")]
+use std::sync::atomic::Ordering;
+
+use num_complex::Complex64;
+
+#[cfg(feature = "bigfloat")]
+use crate::{
+    bigfloat::{render_arbitrary, PreciseCenter},
+    perturbation::render_perturbed,
+    viewport::Precision,
+};
+use crate::{
+    complex::IntoComplex,
+    fractal::FractalKind,
+    options::{RenderOptions, RenderStrategy},
+    scheduler::{map_slice, try_render_grid, RenderCancelled},
+    subdivision::try_render_subdivided,
+    viewport::Viewport,
+};
+
+/// Orbits are iterated until they leave this radius before computing the Böttcher coordinate,
+/// which makes the remaining terms of its product negligible.
+pub const BOTTCHER_RADIUS: f64 = 1e10;
+
+/// Orbits are iterated until they leave this radius before estimating the distance to the set,
+/// which makes the estimate accurate to a fraction of a percent.
+pub const DISTANCE_RADIUS: f64 = 1e10;
+
+/// Longest side of the previews `refine_iterations` renders.
+pub const REFINE_PREVIEW_SIZE: u32 = 160;
+
+/// Fraction of the pixels of a preview that may still escape after the iteration limit before
+/// `refine_iterations` doubles it.
+pub const REFINE_THRESHOLD: f64 = 0.001;
+
+/// Number of times `refine_iterations` doubles the iteration limit at most.
+pub const MAX_REFINEMENTS: u32 = 4;
+
+/// Two orbit points closer than this on both axes are treated as the same point.
+pub const PERIOD_EPSILON: f64 = 1e-12;
+
+/// Why a point is known to lie inside the Mandelbrot set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interior {
+    /// The point lies in the main cardioid.
+    MainCardioid,
+    /// The point lies in the period-2 bulb centered at `-1`.
+    PeriodTwoBulb,
+    /// The orbit of the point was found to cycle with the given period.
+    Periodic { period: u32 },
+}
+
+/// Membership of a point in the Mandelbrot set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Membership {
+    /// The point is inside the set.
+    Inside(Interior),
+    /// The orbit escaped the radius-2 disk after the given number of iterations.
+    Outside(u32),
+    /// The iteration limit was reached without escaping or detecting a cycle.
+    Unknown(u32),
+}
+
+impl Membership {
+    /// Returns `true` if the point is known to be inside the set.
+    pub fn is_inside(&self) -> bool {
+        matches!(self, Membership::Inside(_))
+    }
+
+    /// Returns `true` if the point is known to be outside the set.
+    pub fn is_outside(&self) -> bool {
+        matches!(self, Membership::Outside(_))
+    }
+}
+
+/// Returns `true` if `c` lies in the main cardioid.
+pub fn in_main_cardioid(c: impl IntoComplex) -> bool {
+    let Complex64 { re: x, im: y } = c.into_complex();
+    let q = (x - 0.25) * (x - 0.25) + y * y;
+    q * (q + (x - 0.25)) <= 0.25 * y * y
+}
+
+/// Returns `true` if `c` lies in the period-2 bulb.
+pub fn in_period_two_bulb(c: impl IntoComplex) -> bool {
+    let Complex64 { re: x, im: y } = c.into_complex();
+    (x + 1.0) * (x + 1.0) + y * y <= 0.0625
+}
+
+/// Classifies `c` as inside, outside or of unknown membership after at most `max_iter`
+/// iterations.
+///
+/// The escape count of `Outside` matches the value returned by `mandelbrot_img::mandelbrot`.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::analysis::{classify, Interior, Membership};
+///
+/// assert_eq!(classify((0.0, 0.0), 100), Membership::Inside(Interior::MainCardioid));
+/// assert_eq!(classify((-1.0, 0.0), 100), Membership::Inside(Interior::PeriodTwoBulb));
+/// assert_eq!(classify((1.0, 1.0), 100), Membership::Outside(1));
+/// ```
+pub fn classify(c: impl IntoComplex, max_iter: u32) -> Membership {
+    let c = c.into_complex();
+    if in_main_cardioid(c) {
+        return Membership::Inside(Interior::MainCardioid);
+    }
+    if in_period_two_bulb(c) {
+        return Membership::Inside(Interior::PeriodTwoBulb);
+    }
+
+    let Complex64 { re: cx, im: cy } = c;
+    let (mut x, mut y) = (0.0_f64, 0.0_f64);
+    // Brent's cycle detection: compare against a saved point and move it every power of two.
+    let (mut saved_x, mut saved_y) = (x, y);
+    let mut power = 1;
+    let mut lambda = 0;
+    for i in 0..max_iter {
+        let x_temp = x * x - y * y + cx;
+        y = 2.0 * x * y + cy;
+        x = x_temp;
+        if x * x + y * y > 4.0 {
+            return Membership::Outside(i);
+        }
+
+        lambda += 1;
+        if (x - saved_x).abs() < PERIOD_EPSILON && (y - saved_y).abs() < PERIOD_EPSILON {
+            return Membership::Inside(Interior::Periodic { period: lambda });
+        }
+        if lambda == power {
+            saved_x = x;
+            saved_y = y;
+            power *= 2;
+            lambda = 0;
+        }
+    }
+    Membership::Unknown(max_iter)
+}
+
+/// The orbit of `c`: the points `z` visits under `z = z * z + c`, starting with `z = c`, up to
+/// and including the first point outside the escape radius of `2`, or `max_iter` points if the
+/// orbit does not escape. An escaping orbit has one point more than the escape count of
+/// `mandelbrot_img::mandelbrot`.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::analysis::orbit;
+///
+/// assert_eq!(orbit((1.0, 1.0), 100), [(1.0, 1.0), (1.0, 3.0)]);
+/// assert_eq!(orbit(-1.0, 4), [(-1.0, 0.0), (0.0, 0.0), (-1.0, 0.0), (0.0, 0.0)]);
+/// ```
+pub fn orbit(c: impl IntoComplex, max_iter: u32) -> Vec<(f64, f64)> {
+    let c = c.into_complex();
+    let mut z = Complex64::new(0.0, 0.0);
+    let mut points = Vec::new();
+    for _ in 0..max_iter {
+        z = z * z + c;
+        points.push((z.re, z.im));
+        if z.norm_sqr() > 4.0 {
+            break;
+        }
+    }
+    points
+}
+
+/// Result of running a single point through the escape-time kernel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EscapeResult {
+    /// Iteration at which the orbit escaped, or the iteration limit if it did not.
+    pub iterations: u32,
+    /// Whether the orbit left the bailout radius within the iteration limit.
+    pub escaped: bool,
+    /// Continuous escape value, see `mandelbrot_img::mandelbrot_smooth`.
+    pub smooth: f64,
+    /// Last computed point of the orbit.
+    pub z: Complex64,
+}
+
+/// Runs `c` through the escape-time kernel of `opts.kind`.
+///
+/// With `RenderOptions::optimizations` points in the main cardioid or the period-2 bulb are
+/// reported as not escaping without iterating, and orbits that become periodic stop early. Both
+/// only skip iterations of points that never escape, so the counts do not change; only `z` of
+/// those points is no longer the last point of a full orbit.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::{analysis::escape, options::RenderOptions};
+/// use num_complex::Complex64;
+///
+/// let result = escape(Complex64::new(1.0, 1.0), &RenderOptions::new(100));
+/// assert!(result.escaped);
+/// assert_eq!(result.iterations, 1);
+/// ```
+pub fn escape(c: Complex64, opts: &RenderOptions) -> EscapeResult {
+    let bailout = opts.bailout.max(opts.kind.escape_radius());
+    let bailout_sqr = bailout * bailout;
+    let mut z = Complex64::new(0.0, 0.0);
+    let inside = |z| EscapeResult {
+        iterations: opts.iterations,
+        escaped: false,
+        smooth: opts.iterations as f64,
+        z,
+    };
+    if opts.optimizations
+        && opts.kind == FractalKind::Mandelbrot
+        && (in_main_cardioid(c) || in_period_two_bulb(c))
+    {
+        return inside(z);
+    }
+    // Brent's cycle detection, as in `classify`.
+    let (mut saved, mut power, mut lambda) = (z, 1, 0);
+    for i in 0..opts.iterations {
+        z = opts.kind.step(z, c);
+        let mag_sqr = z.norm_sqr();
+        if mag_sqr > bailout_sqr {
+            let smooth = smooth_escape(i, mag_sqr, opts.kind);
+            return EscapeResult { iterations: i, escaped: true, smooth, z };
+        }
+        if opts.optimizations {
+            lambda += 1;
+            if (z.re - saved.re).abs() < PERIOD_EPSILON && (z.im - saved.im).abs() < PERIOD_EPSILON
+            {
+                return inside(z);
+            }
+            if lambda == power {
+                (saved, power, lambda) = (z, power * 2, 0);
+            }
+        }
+    }
+    inside(z)
+}
+
+/// Continuous escape value of an orbit of `kind` that left the bailout radius at iteration
+/// `iteration` with `|z|^2 = mag_sqr`.
+fn smooth_escape(iteration: u32, mag_sqr: f64, kind: FractalKind) -> f64 {
+    let log_z = mag_sqr.ln() / 2.0;
+    let log_log_z = match kind {
+        FractalKind::Mandelbrot => log_z.log2(),
+        kind => log_z.ln() / kind.power().ln(),
+    };
+    (iteration as f64 + 1.0 - log_log_z).max(0.0)
+}
+
+/// Statistics of the orbit of a single point, for colorings computed outside the crate.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PixelInfo {
+    /// Iteration at which the orbit escaped, or the iteration limit if it did not.
+    pub iters: u32,
+    /// Continuous escape value, see `EscapeResult::smooth`.
+    pub smooth: f64,
+    /// `|z|` of the last computed point of the orbit.
+    pub final_mag: f64,
+    /// Smallest `|z|` over the orbit, not counting the starting point `0`.
+    pub min_mag: f64,
+    /// Derivative `dz/dc` at the last computed point, which `distance_estimate` and
+    /// `lighting::surface_normal` are computed from.
+    pub dz: Complex64,
+}
+
+/// Runs `c` through the escape-time kernel of `opts.kind` like `escape`, collecting the
+/// statistics of `PixelInfo` along the orbit. Every iteration is computed, whatever
+/// `RenderOptions::optimizations` says, so points inside the set report statistics of their
+/// full orbit.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::{analysis::pixel_info, options::RenderOptions};
+/// use num_complex::Complex64;
+///
+/// // The orbit of -1 alternates between -1 and 0.
+/// let info = pixel_info(Complex64::new(-1.0, 0.0), &RenderOptions::new(100));
+/// assert_eq!((info.iters, info.min_mag, info.final_mag), (100, 0.0, 0.0));
+/// let info = pixel_info(Complex64::new(1.0, 1.0), &RenderOptions::new(100));
+/// assert_eq!((info.iters, info.min_mag), (1, 2.0_f64.sqrt()));
+/// assert_eq!(info.dz, Complex64::new(3.0, 2.0));
+/// ```
+pub fn pixel_info(c: Complex64, opts: &RenderOptions) -> PixelInfo {
+    let bailout = opts.bailout.max(opts.kind.escape_radius());
+    let bailout_sqr = bailout * bailout;
+    let power = opts.kind.power();
+    let (mut z, mut dz) = (Complex64::new(0.0, 0.0), Complex64::new(0.0, 0.0));
+    let mut min_mag = f64::INFINITY;
+    for i in 0..opts.iterations {
+        dz = match opts.kind {
+            FractalKind::Mandelbrot => 2.0 * z * dz + 1.0,
+            _ if z == Complex64::new(0.0, 0.0) => Complex64::new(1.0, 0.0),
+            _ => power * z.powf(power - 1.0) * dz + 1.0,
+        };
+        z = opts.kind.step(z, c);
+        let mag_sqr = z.norm_sqr();
+        min_mag = min_mag.min(mag_sqr.sqrt());
+        if mag_sqr > bailout_sqr {
+            let smooth = smooth_escape(i, mag_sqr, opts.kind);
+            return PixelInfo { iters: i, smooth, final_mag: mag_sqr.sqrt(), min_mag, dz };
+        }
+    }
+    PixelInfo {
+        iters: opts.iterations,
+        smooth: opts.iterations as f64,
+        final_mag: z.norm(),
+        min_mag,
+        dz,
+    }
+}
+
+/// Runs every point of `points` through the escape-time kernel, in parallel with the `parallel`
+/// feature. The results are in the same order as `points`.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::{analysis::evaluate_points, options::RenderOptions};
+/// use num_complex::Complex64;
+///
+/// let points = [Complex64::new(0.0, 0.0), Complex64::new(2.0, 2.0)];
+/// let results = evaluate_points(&points, &RenderOptions::new(100));
+/// assert!(!results[0].escaped);
+/// assert!(results[1].escaped);
+/// ```
+pub fn evaluate_points(points: &[Complex64], opts: &RenderOptions) -> Vec<EscapeResult> {
+    map_slice(points, |c| escape(*c, opts))
+}
+
+/// Runs every pixel of a `width` x `height` grid over `viewport` through the escape-time kernel,
+/// in row-major order.
+///
+/// Uses `f64` arithmetic unless `Viewport::resolved_precision` asks for more and the `bigfloat`
+/// feature is enabled, in which case the pixels around the viewport center are iterated by
+/// `bigfloat::render_arbitrary` or `perturbation::render_perturbed`. Those only iterate the
+/// Mandelbrot set, so other kinds of `opts.kind` always use `f64`.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::{analysis::escape_viewport, options::RenderOptions, viewport::Viewport};
+///
+/// let results = escape_viewport(8, 6, &Viewport::default(), &RenderOptions::new(50));
+/// assert_eq!(results.len(), 48);
+/// assert!(results[0].escaped);
+/// ```
+pub fn escape_viewport(
+    width: u32,
+    height: u32,
+    viewport: &Viewport,
+    opts: &RenderOptions,
+) -> Vec<EscapeResult> {
+    escape_viewport_with_progress(width, height, viewport, opts, |_, _| {})
+}
+
+/// Like `escape_viewport`, but calls `on_progress(done, total)` with the number of evaluated
+/// pixels after every finished row, or tile with `RenderStrategy::BoundaryTrace`. The
+/// arbitrary-precision kernels only report when they are done.
+pub fn escape_viewport_with_progress<P>(
+    width: u32,
+    height: u32,
+    viewport: &Viewport,
+    opts: &RenderOptions,
+    on_progress: P,
+) -> Vec<EscapeResult>
+where
+    P: Fn(u64, u64) + Sync,
+{
+    let never = AtomicBool::new(false);
+    match try_escape_viewport(width, height, viewport, opts, on_progress, &never) {
+        Ok(escapes) => escapes,
+        Err(RenderCancelled) => unreachable!("the render was never cancelled"),
+    }
+}
+
+/// Like `escape_viewport_with_progress`, but stops once `cancel` is set, see
+/// `scheduler::try_render_grid`. The arbitrary-precision kernels only check it before they
+/// start.
+///
+/// # Errors
+///
+/// Returns `RenderCancelled` if the render was cancelled.
+pub fn try_escape_viewport<P>(
+    width: u32,
+    height: u32,
+    viewport: &Viewport,
+    opts: &RenderOptions,
+    on_progress: P,
+    cancel: &AtomicBool,
+) -> Result<Vec<EscapeResult>, RenderCancelled>
+where
+    P: Fn(u64, u64) + Sync,
+{
+    #[cfg(feature = "bigfloat")]
+    if opts.kind == FractalKind::Mandelbrot {
+        let center = || PreciseCenter::from_f64(viewport.center_x, viewport.center_y);
+        let escapes = match viewport.resolved_precision(width, height) {
+            Precision::Arbitrary { .. } | Precision::Perturbation
+                if cancel.load(Ordering::Relaxed) =>
+            {
+                return Err(RenderCancelled);
+            }
+            Precision::Arbitrary { digits } => {
+                Some(render_arbitrary(width, height, viewport, &center(), opts, digits))
+            }
+            Precision::Perturbation => {
+                let digits = viewport.required_digits(width, height);
+                Some(render_perturbed(width, height, viewport, &center(), opts, digits).0)
+            }
+            Precision::Auto | Precision::Double => None,
+        };
+        if let Some(escapes) = escapes {
+            let total = width as u64 * height as u64;
+            on_progress(total, total);
+            return Ok(escapes);
+        }
+    }
+    let f = |x, y| {
+        let (re, im) = viewport.to_complex_num(x, y, width, height);
+        escape(Complex64::new(re, im), opts)
+    };
+    // Row `y` of a symmetric viewport mirrors row `height - y`, so only the rows down to the
+    // real axis are computed. Row `0` has no mirror image.
+    let rows =
+        if opts.symmetry && viewport.is_symmetric() { height.min(height / 2 + 1) } else { height };
+    let total = width as u64 * height as u64;
+    let progress = |done: u64, computed: u64| on_progress(done * total / computed.max(1), total);
+    let mut escapes = match opts.strategy {
+        RenderStrategy::PerPixel => try_render_grid(width, rows, f, progress, cancel)?,
+        RenderStrategy::BoundaryTrace => {
+            let same = |a: &EscapeResult, b: &EscapeResult| {
+                (a.iterations, a.escaped) == (b.iterations, b.escaped)
+            };
+            try_render_subdivided(width, rows, f, same, progress, cancel)?
+        }
+    };
+    let width = width as usize;
+    for y in rows..height {
+        let mirror = (height - y) as usize * width;
+        for x in 0..width {
+            let result = escapes[mirror + x];
+            escapes.push(EscapeResult { z: result.z.conj(), ..result });
+        }
+    }
+    Ok(escapes)
+}
+
+/// An iteration limit of at least `opts.iterations` for a `width` x `height` render of
+/// `viewport`. A pixel that reaches the limit is either inside the set or needs more iterations,
+/// so the limit is doubled, up to `MAX_REFINEMENTS` times, as long as doubling it lets more than
+/// `REFINE_THRESHOLD` of the pixels of a small preview of the render escape.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::{analysis::refine_iterations, prelude::*};
+///
+/// let viewport = Viewport::new(-0.743643887037151, 0.13182590420533, 5000.0);
+/// assert!(refine_iterations(800, 600, &viewport, &RenderOptions::new(50)) > 50);
+/// ```
+pub fn refine_iterations(
+    width: u32,
+    height: u32,
+    viewport: &Viewport,
+    opts: &RenderOptions,
+) -> u32 {
+    let scale = REFINE_PREVIEW_SIZE as f64 / width.max(height).max(1) as f64;
+    let preview = |side: u32| ((side as f64 * scale.min(1.0)).round() as u32).max(1);
+    let (width, height) = (preview(width), preview(height));
+    let escaped = |iterations| {
+        let opts = RenderOptions { iterations, ..*opts };
+        let escapes = escape_viewport(width, height, viewport, &opts);
+        escapes.iter().filter(|result| result.escaped).count()
+    };
+    let threshold = REFINE_THRESHOLD * width as f64 * height as f64;
+    let (mut iterations, mut count) = (opts.iterations, escaped(opts.iterations));
+    for _ in 0..MAX_REFINEMENTS {
+        let doubled = iterations.saturating_mul(2);
+        let doubled_count = escaped(doubled);
+        if doubled == iterations || ((doubled_count - count) as f64) <= threshold {
+            break;
+        }
+        (iterations, count) = (doubled, doubled_count);
+    }
+    iterations
+}
+
+/// Approximates the Böttcher coordinate `phi(c)` of a point outside the set, or returns `None`
+/// if the orbit does not leave `BOTTCHER_RADIUS` within `max_iter` iterations.
+///
+/// Uses `log phi(c) = log c + sum(2^-n log(1 + c / z_n^2))` over the orbit `z_1 = c`,
+/// `z_(n+1) = z_n^2 + c`.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::analysis::bottcher;
+/// use num_complex::Complex64;
+///
+/// // Far from the set the Böttcher coordinate approaches `c` itself.
+/// let c = Complex64::new(0.0, 1000.0);
+/// assert!((bottcher(c, 100).unwrap() - c).norm() < 1.0);
+/// assert_eq!(bottcher(Complex64::new(0.0, 0.0), 100), None);
+/// ```
+pub fn bottcher(c: Complex64, max_iter: u32) -> Option<Complex64> {
+    if c.norm_sqr() == 0.0 {
+        return None;
+    }
+    let mut log_phi = c.ln();
+    let mut z = c;
+    let mut weight = 1.0;
+    for _ in 0..max_iter {
+        if z.norm() > BOTTCHER_RADIUS {
+            return Some(log_phi.exp());
+        }
+        weight /= 2.0;
+        log_phi += (1.0 + c / (z * z)).ln() * weight;
+        z = z * z + c;
+    }
+    None
+}
+
+/// External angle of `c` in turns, between `0.0` and `1.0`, or `None` if `c` does not escape
+/// within `max_iter` iterations. See `bottcher`.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::analysis::external_angle;
+/// use num_complex::Complex64;
+///
+/// // The real axis right of the cusp is the ray of angle `0`, left of the tip angle `1/2`.
+/// assert!(external_angle(Complex64::new(0.5, 0.0), 1000).unwrap().abs() < 1e-9);
+/// assert!((external_angle(Complex64::new(-2.5, 0.0), 1000).unwrap() - 0.5).abs() < 1e-9);
+/// ```
+pub fn external_angle(c: Complex64, max_iter: u32) -> Option<f64> {
+    let phi = bottcher(c, max_iter)?;
+    Some((phi.arg() / std::f64::consts::TAU).rem_euclid(1.0))
+}
+
+/// Potential `log |phi(c)|` of `c`, which is `0` on the set and grows like `log |c|` far away,
+/// or `None` if `c` does not escape within `max_iter` iterations.
+pub fn potential(c: Complex64, max_iter: u32) -> Option<f64> {
+    bottcher(c, max_iter).map(|phi| phi.norm().ln())
+}
+
+/// Estimates the distance from `c` to the Mandelbrot set as `|z| ln |z| / |dz/dc|`, where the
+/// derivative follows `dz/dc -> 2 z dz/dc + 1` along the orbit. The true distance lies between
+/// half and twice the estimate. Points that do not leave `DISTANCE_RADIUS` within `iterations`
+/// iterations are treated as part of the set and get `0.0`.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::analysis::distance_estimate;
+/// use num_complex::Complex64;
+///
+/// // The rightmost point of the set is the cusp at 0.25, so 1.0 is 0.75 away from it.
+/// let d = distance_estimate(Complex64::new(1.0, 0.0), 1000);
+/// assert!(d > 0.375 && d < 1.5);
+/// assert_eq!(distance_estimate(Complex64::new(-1.0, 0.0), 1000), 0.0);
+/// ```
+pub fn distance_estimate(c: Complex64, iterations: u32) -> f64 {
+    let radius_sqr = DISTANCE_RADIUS * DISTANCE_RADIUS;
+    let mut z = Complex64::new(0.0, 0.0);
+    let mut dz = Complex64::new(0.0, 0.0);
+    for _ in 0..iterations {
+        dz = 2.0 * z * dz + 1.0;
+        z = z * z + c;
+        if z.norm_sqr() > radius_sqr {
+            let norm = z.norm();
+            return norm * norm.ln() / dz.norm();
+        }
+    }
+    0.0
+}
+
+/// Runs `distance_estimate` for every pixel of a `width` x `height` grid over `viewport`, in
+/// row-major order. Always uses `f64` arithmetic.
+pub fn distance_viewport(
+    width: u32,
+    height: u32,
+    viewport: &Viewport,
+    iterations: u32,
+) -> Vec<f64> {
+    distance_viewport_with_progress(width, height, viewport, iterations, |_, _| {})
+}
+
+/// Like `distance_viewport`, but calls `on_progress(done, total)` with the number of evaluated
+/// pixels after every finished row.
+pub fn distance_viewport_with_progress<P>(
+    width: u32,
+    height: u32,
+    viewport: &Viewport,
+    iterations: u32,
+    on_progress: P,
+) -> Vec<f64>
+where
+    P: Fn(u64, u64) + Sync,
+{
+    let never = AtomicBool::new(false);
+    match try_distance_viewport(width, height, viewport, iterations, on_progress, &never) {
+        Ok(distances) => distances,
+        Err(RenderCancelled) => unreachable!("the render was never cancelled"),
+    }
+}
+
+/// Like `distance_viewport_with_progress`, but stops once `cancel` is set, see
+/// `scheduler::try_render_grid`.
+///
+/// # Errors
+///
+/// Returns `RenderCancelled` if the render was cancelled.
+pub fn try_distance_viewport<P>(
+    width: u32,
+    height: u32,
+    viewport: &Viewport,
+    iterations: u32,
+    on_progress: P,
+    cancel: &AtomicBool,
+) -> Result<Vec<f64>, RenderCancelled>
+where
+    P: Fn(u64, u64) + Sync,
+{
+    try_render_grid(
+        width,
+        height,
+        |x, y| {
+            let (re, im) = viewport.to_complex_num(x, y, width, height);
+            distance_estimate(Complex64::new(re, im), iterations)
+        },
+        on_progress,
+        cancel,
+    )
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mandelbrot_img::{mandelbrot, to_complex_num};
+
+    #[test]
+    fn test_mirrored_rows_equal_computed_rows() {
+        let opts = RenderOptions::new(300).with_escape_angle(true);
+        for (width, height) in [(64, 48), (33, 17), (5, 1), (8, 2)] {
+            for viewport in [Viewport::default(), Viewport::mini_mandelbrot()] {
+                let mirrored = escape_viewport(width, height, &viewport, &opts);
+                let computed =
+                    escape_viewport(width, height, &viewport, &opts.with_symmetry(false));
+                assert_eq!(mirrored, computed);
+            }
+        }
+        let cubic = opts.with_kind(FractalKind::Multibrot { power: 3.0 });
+        let mirrored = escape_viewport(40, 30, &Viewport::default(), &cubic);
+        assert_eq!(
+            mirrored,
+            escape_viewport(40, 30, &Viewport::default(), &cubic.with_symmetry(false))
+        );
+    }
+
+    #[test]
+    fn test_refinement_keeps_limits_that_suffice() {
+        // Inside the main cardioid nothing escapes, however long it is iterated.
+        let inside = Viewport::new(-0.1, 0.0, 20.0);
+        assert_eq!(refine_iterations(400, 300, &inside, &RenderOptions::new(100)), 100);
+
+        let viewport = Viewport::new(-0.743643887037151, 0.13182590420533, 5000.0);
+        let refined = refine_iterations(400, 300, &viewport, &RenderOptions::new(100));
+        assert!(refined > 100 && refined <= 100 << MAX_REFINEMENTS);
+        assert_eq!(refine_iterations(400, 300, &viewport, &RenderOptions::new(refined)), refined);
+    }
+
+    #[test]
+    fn test_distance_estimate_brackets_known_distances() {
+        // Along the positive real axis the nearest point of the set is the cusp at 0.25, and left
+        // of the tip at -2 the tip itself.
+        for (re, distance) in [(0.6, 0.35), (1.0, 0.75), (-2.1, 0.1), (-3.0, 1.0)] {
+            let estimate = distance_estimate(Complex64::new(re, 0.0), 10_000);
+            assert!(estimate > distance / 2.0 && estimate < distance * 2.0, "{re}: {estimate}");
+        }
+    }
+
+    #[test]
+    fn test_outside_matches_escape_count() {
+        for x in (0..800).step_by(7) {
+            for y in (0..800).step_by(11) {
+                let c = to_complex_num(x, y, 800, 800);
+                if let Membership::Outside(i) = classify(c, 255) {
+                    assert_eq!(i, mandelbrot(c, 255));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_inside_never_escapes() {
+        for x in (0..800).step_by(7) {
+            for y in (0..800).step_by(11) {
+                let c = to_complex_num(x, y, 800, 800);
+                if classify(c, 255).is_inside() {
+                    assert_eq!(mandelbrot(c, 2000), 2000, "{c:?} escaped");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_orbit_length_matches_escape_count() {
+        for x in (0..800).step_by(7) {
+            for y in (0..800).step_by(11) {
+                let c = to_complex_num(x, y, 800, 800);
+                let count = mandelbrot(c, 255) as usize;
+                let expected = if count == 255 { 255 } else { count + 1 };
+                assert_eq!(orbit(c, 255).len(), expected, "{c:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_periodic_orbit_outside_cardioid_and_bulb() {
+        // Center of the period-3 bulb on top of the main cardioid.
+        let c = (-0.1225611668766536, 0.7448617666197442);
+        assert!(!in_main_cardioid(c) && !in_period_two_bulb(c));
+        assert!(matches!(classify(c, 1000), Membership::Inside(Interior::Periodic { period: 3 })));
+    }
+
+    #[test]
+    fn test_evaluate_points_matches_kernels() {
+        use crate::mandelbrot_img::mandelbrot_smooth;
+
+        let opts = RenderOptions::new(255);
+        let points: Vec<Complex64> = (0..800)
+            .step_by(9)
+            .flat_map(|x| (0..800).step_by(13).map(move |y| to_complex_num(x, y, 800, 800)))
+            .map(|(re, im)| Complex64::new(re, im))
+            .collect();
+        let results = evaluate_points(&points, &opts);
+        assert_eq!(results.len(), points.len());
+        for (c, result) in points.iter().zip(&results) {
+            let c = (c.re, c.im);
+            assert_eq!(result.iterations, mandelbrot(c, 255));
+            assert_eq!(result.escaped, result.iterations < 255);
+            assert_eq!(result.smooth, mandelbrot_smooth(c, 255, 2.0));
+        }
+    }
+
+    #[test]
+    fn test_optimizations_leave_escape_values_unchanged() {
+        let plain = RenderOptions::new(1000);
+        let optimized = plain.with_optimizations(true);
+        for viewport in [
+            Viewport::default(),
+            Viewport::new(-0.743643887037151, 0.131825904205330, 300.0),
+            Viewport::new(-1.25, 0.0, 8.0),
+        ] {
+            let expected = escape_viewport(90, 60, &viewport, &plain);
+            let actual = escape_viewport(90, 60, &viewport, &optimized);
+            for (a, e) in actual.iter().zip(&expected) {
+                assert_eq!(
+                    (a.iterations, a.escaped, a.smooth),
+                    (e.iterations, e.escaped, e.smooth)
+                );
+            }
+        }
+        // The cardioid check answers without iterating at all.
+        let center = escape(Complex64::new(0.0, 0.0), &optimized);
+        assert_eq!((center.escaped, center.z), (false, Complex64::new(0.0, 0.0)));
+    }
+
+    #[test]
+    fn test_multibrot_escape_matches_kernel_and_symmetry() {
+        use crate::mandelbrot_img::multibrot;
+
+        let kind = FractalKind::Multibrot { power: 4.0 };
+        let opts = RenderOptions::new(100).with_kind(kind);
+        // The quartic set is symmetric under rotation by a third of a turn.
+        let rotation = Complex64::from_polar(1.0, 2.0 * std::f64::consts::PI / 3.0);
+        for (re, im) in [(0.5, 0.6), (-0.8, 0.1), (0.1, -0.95), (1.2, 0.0)] {
+            let c = Complex64::new(re, im);
+            let result = escape(c, &opts);
+            assert_eq!(result.iterations, multibrot((re, im), 4.0, 100));
+            let rotated = escape(c * rotation, &opts);
+            assert!((rotated.smooth - result.smooth).abs() < 1e-6, "{c}");
+        }
+    }
+
+    #[test]
+    fn test_external_angles_of_known_rays() {
+        // Rays 1/3 and 2/3 land on the root of the period-2 bulb at -3/4 from above and below.
+        let near = |c: Complex64, angle: f64| {
+            let found = external_angle(c, 10_000).unwrap();
+            (found - angle).abs() < 1e-3
+        };
+        assert!(near(Complex64::new(-0.75, 0.05), 1.0 / 3.0));
+        assert!(near(Complex64::new(-0.75, -0.05), 2.0 / 3.0));
+
+        // Conjugate points have opposite angles, and the potential falls towards the set.
+        let c = Complex64::new(0.3, 0.7);
+        let (a, b) = (external_angle(c, 1000).unwrap(), external_angle(c.conj(), 1000).unwrap());
+        assert!((a + b - 1.0).abs() < 1e-9);
+        assert!(potential(c, 1000).unwrap() < potential(c * 2.0, 1000).unwrap());
+    }
+}
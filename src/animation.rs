@@ -0,0 +1,767 @@
+//! Renders zoom animations as directories of numbered PNG frames.
+//!
+//! The viewport of every frame is interpolated with `Viewport::interpolate`, which scales the
+//! zoom geometrically, so each frame magnifies by the same factor and the dive looks steady. The
+//! frames are named `frame_0000.png`, `frame_0001.png`, ... with enough digits for the whole
+//! sequence, the pattern video encoders expect (`ffmpeg -i frame_%04d.png`).
+//!
+//! A `DiveSequence` renders the keyframes of a `dive::Dive` the same ways, each segment in its
+//! own palette, so tours through several locations need no code.
+//!
+//! `ColorCycle` animates the colors of a single render instead, turning a cyclic palette from
+//! frame to frame, and saves the loop as an animated PNG or GIF.
+//!
+//! With the `gif` feature a sequence can also be encoded straight into an animated GIF. Every
+//! frame gets its own 256-color palette, quantized from the rendered frame with NeuQuant.
+//!
+//! `ZoomSequence::render_video` encodes a sequence as MP4 or WebM video instead, by piping the raw
+//! frames into an `ffmpeg` process, which must be installed and on the `PATH`. No frame touches
+//! the disk, so long animations need no more space than the finished video.
+
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+#[cfg(feature = "gif")]
+use image::{
+    error::{EncodingError, ImageFormatHint, LimitError, LimitErrorKind},
+    ImageError, ImageFormat,
+};
+use image::{ImageResult, RgbImage};
+
+use crate::{
+    analysis::EscapeResult,
+    dive::Dive,
+    mandelbrot_img::compose_with_options,
+    options::RenderOptions,
+    palette::{colorize_escapes, compose_with_palette, BuiltinPalette, CyclicPalette, Palette},
+    viewport::Viewport,
+};
+
+/// Fewest digits used for frame numbers.
+pub const MIN_FRAME_DIGITS: usize = 4;
+
+/// A zoom from `start` to `end` rendered as `frames` images of `width` x `height`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ZoomSequence {
+    pub start: Viewport,
+    pub end: Viewport,
+    pub frames: u32,
+    pub width: u32,
+    pub height: u32,
+    pub options: RenderOptions,
+}
+
+impl ZoomSequence {
+    /// Viewport of frame `index`, where the first frame shows `start` and the last `end`.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::{animation::ZoomSequence, options::RenderOptions, viewport::Viewport};
+    ///
+    /// let (start, end) = (Viewport::classic(), Viewport::new(-0.75, 0.1, 1e4));
+    /// let sequence = ZoomSequence {
+    ///     start,
+    ///     end,
+    ///     frames: 5,
+    ///     width: 64,
+    ///     height: 48,
+    ///     options: RenderOptions::new(100),
+    /// };
+    /// assert_eq!(sequence.viewport(0), start);
+    /// assert_eq!(sequence.viewport(4), end);
+    /// assert!((sequence.viewport(2).zoom - 100.0).abs() < 1e-9);
+    /// ```
+    pub fn viewport(&self, index: u32) -> Viewport {
+        let t = match self.frames {
+            0 | 1 => 1.0,
+            frames => index as f64 / (frames - 1) as f64,
+        };
+        self.start.interpolate(&self.end, t)
+    }
+
+    /// Renders frame `index`.
+    pub fn render_frame(&self, index: u32) -> RgbImage {
+        compose_with_options(self.width, self.height, &self.viewport(index), &self.options)
+    }
+
+    /// Renders every frame and saves it as a numbered PNG file in `dir`, creating it if needed.
+    /// Returns the paths of the frames.
+    pub fn render(&self, dir: impl AsRef<Path>) -> ImageResult<Vec<PathBuf>> {
+        self.render_with_progress(dir, |_, _| {})
+    }
+
+    /// Like `render`, but calls `on_frame(done, frames)` after every saved frame.
+    pub fn render_with_progress<P>(
+        &self,
+        dir: impl AsRef<Path>,
+        on_frame: P,
+    ) -> ImageResult<Vec<PathBuf>>
+    where
+        P: FnMut(u32, u32),
+    {
+        save_frames(dir.as_ref(), self.frames, |index| Ok(self.render_frame(index)), on_frame)
+    }
+}
+
+/// Saves frames `0..frames`, made by `frame`, as numbered PNG files in `dir`.
+fn save_frames<F, P>(
+    dir: &Path,
+    frames: u32,
+    mut frame: F,
+    mut on_frame: P,
+) -> ImageResult<Vec<PathBuf>>
+where
+    F: FnMut(u32) -> io::Result<RgbImage>,
+    P: FnMut(u32, u32),
+{
+    fs::create_dir_all(dir)?;
+    let mut paths = Vec::with_capacity(frames as usize);
+    for index in 0..frames {
+        let path = frame_path(dir, index, frames);
+        frame(index)?.save(&path)?;
+        paths.push(path);
+        on_frame(index + 1, frames);
+    }
+    Ok(paths)
+}
+
+/// How `ZoomSequence::render_gif` encodes the animation.
+#[cfg(feature = "gif")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GifOptions {
+    /// Time each frame is shown, in hundredths of a second.
+    pub delay: u16,
+    /// Speed of the palette quantization from 1 (best colors, slowest) to 30 (fastest).
+    pub speed: i32,
+    /// Whether the animation loops forever instead of playing once.
+    pub repeat: bool,
+}
+
+#[cfg(feature = "gif")]
+impl Default for GifOptions {
+    fn default() -> Self {
+        Self { delay: 4, speed: 10, repeat: true }
+    }
+}
+
+#[cfg(feature = "gif")]
+impl GifOptions {
+    /// Options showing `fps` frames per second, as close as the GIF delay resolution allows.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::animation::GifOptions;
+    ///
+    /// assert_eq!(GifOptions::with_fps(25).delay, 4);
+    /// assert_eq!(GifOptions::with_fps(1000).delay, 1);
+    /// ```
+    pub fn with_fps(fps: u32) -> Self {
+        let delay = (100.0 / fps.max(1) as f64).round().clamp(1.0, u16::MAX as f64) as u16;
+        Self { delay, ..Self::default() }
+    }
+}
+
+#[cfg(feature = "gif")]
+impl ZoomSequence {
+    /// Renders every frame and encodes the sequence as an animated GIF at `path`.
+    pub fn render_gif(&self, path: impl AsRef<Path>, options: &GifOptions) -> ImageResult<()> {
+        self.render_gif_with_progress(path, options, |_, _| {})
+    }
+
+    /// Like `render_gif`, but calls `on_frame(done, frames)` after every encoded frame.
+    pub fn render_gif_with_progress<P>(
+        &self,
+        path: impl AsRef<Path>,
+        options: &GifOptions,
+        on_frame: P,
+    ) -> ImageResult<()>
+    where
+        P: FnMut(u32, u32),
+    {
+        let frame = |index| Ok(self.render_frame(index));
+        encode_gif(path.as_ref(), (self.width, self.height), self.frames, options, frame, on_frame)
+    }
+}
+
+/// Encodes frames `0..frames` of `width` x `height`, made by `frame`, as an animated GIF at
+/// `path`.
+#[cfg(feature = "gif")]
+fn encode_gif<F, P>(
+    path: &Path,
+    (width, height): (u32, u32),
+    frames: u32,
+    options: &GifOptions,
+    mut frame: F,
+    mut on_frame: P,
+) -> ImageResult<()>
+where
+    F: FnMut(u32) -> io::Result<RgbImage>,
+    P: FnMut(u32, u32),
+{
+    let (Ok(width), Ok(height)) = (u16::try_from(width), u16::try_from(height)) else {
+        let kind = LimitErrorKind::DimensionError;
+        return Err(ImageError::Limits(LimitError::from_kind(kind)));
+    };
+    let file = io::BufWriter::new(fs::File::create(path)?);
+    let mut encoder = gif::Encoder::new(file, width, height, &[]).map_err(gif_error)?;
+    if options.repeat {
+        encoder.set_repeat(gif::Repeat::Infinite).map_err(gif_error)?;
+    }
+    let speed = options.speed.clamp(1, 30);
+    for index in 0..frames {
+        let image = frame(index)?;
+        let mut gif_frame = gif::Frame::from_rgb_speed(width, height, image.as_raw(), speed);
+        gif_frame.delay = options.delay;
+        encoder.write_frame(&gif_frame).map_err(gif_error)?;
+        on_frame(index + 1, frames);
+    }
+    Ok(())
+}
+
+#[cfg(feature = "gif")]
+fn gif_error(error: gif::EncodingError) -> ImageError {
+    match error {
+        gif::EncodingError::Io(error) => ImageError::IoError(error),
+        error => ImageError::Encoding(EncodingError::new(
+            ImageFormatHint::Exact(ImageFormat::Gif),
+            error,
+        )),
+    }
+}
+
+/// Video container and codec of `ZoomSequence::render_video`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VideoFormat {
+    /// H.264 in an MP4 container, which every player supports.
+    #[default]
+    Mp4,
+    /// VP9 in a WebM container, for browsers.
+    Webm,
+}
+
+impl VideoFormat {
+    pub const ALL: [Self; 2] = [Self::Mp4, Self::Webm];
+
+    /// Name of the format, which is also the file extension of its videos.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Mp4 => "mp4",
+            Self::Webm => "webm",
+        }
+    }
+
+    /// The format called `name`, ignoring case.
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|format| format.name().eq_ignore_ascii_case(name))
+    }
+
+    /// The `ffmpeg` encoder of the format.
+    fn codec(self) -> &'static str {
+        match self {
+            Self::Mp4 => "libx264",
+            Self::Webm => "libvpx-vp9",
+        }
+    }
+}
+
+/// How `ZoomSequence::render_video` encodes the animation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VideoOptions {
+    pub format: VideoFormat,
+    /// Frames per second.
+    pub fps: u32,
+    /// Target bitrate in kilobits per second, or `None` for the constant quality default of the
+    /// codec.
+    pub bitrate: Option<u32>,
+}
+
+impl Default for VideoOptions {
+    fn default() -> Self {
+        Self { format: VideoFormat::default(), fps: 30, bitrate: None }
+    }
+}
+
+impl VideoOptions {
+    /// Arguments of the `ffmpeg` process that encodes raw RGB frames of `width` x `height` read
+    /// from its standard input into `path`. Odd sizes are padded by a pixel, since the YUV 4:2:0
+    /// pixels players expect come in pairs.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::animation::{VideoFormat, VideoOptions};
+    ///
+    /// let options = VideoOptions { format: VideoFormat::Webm, fps: 24, bitrate: Some(2000) };
+    /// let args = options.ffmpeg_args(640, 480, "zoom.webm");
+    /// assert!(args.windows(2).any(|arg| arg == ["-s", "640x480"]));
+    /// assert!(args.windows(2).any(|arg| arg == ["-b:v", "2000k"]));
+    /// assert_eq!(args.last().unwrap(), "zoom.webm");
+    /// ```
+    pub fn ffmpeg_args(&self, width: u32, height: u32, path: impl AsRef<Path>) -> Vec<String> {
+        let mut args: Vec<String> =
+            ["-y", "-loglevel", "error", "-f", "rawvideo", "-pix_fmt", "rgb24", "-s"]
+                .map(String::from)
+                .to_vec();
+        args.push(format!("{width}x{height}"));
+        args.extend(["-r".to_string(), self.fps.max(1).to_string(), "-i".into(), "-".into()]);
+        args.extend(["-c:v".to_string(), self.format.codec().to_string()]);
+        match (self.bitrate, self.format) {
+            (Some(bitrate), _) => args.extend(["-b:v".to_string(), format!("{bitrate}k")]),
+            // Without a bitrate VP9 needs `-b:v 0` to encode at constant quality.
+            (None, VideoFormat::Webm) => args.extend(["-crf", "32", "-b:v", "0"].map(String::from)),
+            (None, VideoFormat::Mp4) => {}
+        }
+        args.extend(
+            ["-vf", "pad=ceil(iw/2)*2:ceil(ih/2)*2", "-pix_fmt", "yuv420p"].map(String::from),
+        );
+        args.push(path.as_ref().to_string_lossy().into_owned());
+        args
+    }
+}
+
+impl ZoomSequence {
+    /// Renders every frame and encodes the sequence as a video at `path` with `ffmpeg`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `ffmpeg` cannot be started, stops reading frames, or fails; its own
+    /// messages are printed to standard error.
+    pub fn render_video(&self, path: impl AsRef<Path>, options: &VideoOptions) -> io::Result<()> {
+        self.render_video_with_progress(path, options, |_, _| {})
+    }
+
+    /// Like `render_video`, but calls `on_frame(done, frames)` after every encoded frame.
+    pub fn render_video_with_progress<P>(
+        &self,
+        path: impl AsRef<Path>,
+        options: &VideoOptions,
+        on_frame: P,
+    ) -> io::Result<()>
+    where
+        P: FnMut(u32, u32),
+    {
+        let frame = |index| Ok(self.render_frame(index));
+        encode_video(
+            path.as_ref(),
+            (self.width, self.height),
+            self.frames,
+            options,
+            frame,
+            on_frame,
+        )
+    }
+}
+
+/// Encodes frames `0..frames` of `width` x `height`, made by `frame`, as a video at `path` with
+/// `ffmpeg`.
+fn encode_video<F, P>(
+    path: &Path,
+    (width, height): (u32, u32),
+    frames: u32,
+    options: &VideoOptions,
+    mut frame: F,
+    mut on_frame: P,
+) -> io::Result<()>
+where
+    F: FnMut(u32) -> io::Result<RgbImage>,
+    P: FnMut(u32, u32),
+{
+    let mut ffmpeg = Command::new("ffmpeg")
+        .args(options.ffmpeg_args(width, height, path))
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| io::Error::new(e.kind(), format!("cannot run ffmpeg: {e}")))?;
+    let mut stdin = ffmpeg.stdin.take().expect("stdin is piped");
+    let mut written = Ok(());
+    for index in 0..frames {
+        written = frame(index).and_then(|image| stdin.write_all(image.as_raw()));
+        if written.is_err() {
+            break;
+        }
+        on_frame(index + 1, frames);
+    }
+    // Closing the pipe tells ffmpeg that the last frame was sent.
+    drop(stdin);
+    let status = ffmpeg.wait()?;
+    written?;
+    if !status.success() {
+        return Err(io::Error::other(format!("ffmpeg failed with {status}")));
+    }
+    Ok(())
+}
+
+/// A dive rendered at `width` x `height`, one frame per frame of the dive.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DiveSequence<'a> {
+    pub dive: &'a Dive,
+    pub width: u32,
+    pub height: u32,
+    /// Options of every frame, whose iteration limit the dive replaces.
+    pub options: RenderOptions,
+}
+
+impl DiveSequence<'_> {
+    /// Renders frame `index` in the palette of its segment, or gray if it has none.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `InvalidData` error if there is no such frame, or if its keyframes have an
+    /// invalid center or an unknown preset or palette.
+    pub fn render_frame(&self, index: u32) -> io::Result<RgbImage> {
+        let frame = self.dive.frame(index)?.ok_or_else(|| {
+            let message = format!("the dive has no frame {index}");
+            io::Error::new(io::ErrorKind::InvalidData, message)
+        })?;
+        let options = RenderOptions { iterations: frame.iterations, ..self.options };
+        let (width, height, viewport) = (self.width, self.height, &frame.viewport);
+        match frame.palette {
+            Some(name) => {
+                let palette = BuiltinPalette::parse(name).ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::InvalidData, format!("unknown palette {name:?}"))
+                })?;
+                Ok(compose_with_palette(width, height, viewport, &options, &palette))
+            }
+            None => Ok(compose_with_options(width, height, viewport, &options)),
+        }
+    }
+
+    /// Renders every frame and saves it as a numbered PNG file in `dir`, creating it if needed,
+    /// calling `on_frame(done, frames)` after every saved frame. Returns the paths of the frames.
+    pub fn render_with_progress<P>(
+        &self,
+        dir: impl AsRef<Path>,
+        on_frame: P,
+    ) -> ImageResult<Vec<PathBuf>>
+    where
+        P: FnMut(u32, u32),
+    {
+        save_frames(dir.as_ref(), self.dive.total_frames(), |i| self.render_frame(i), on_frame)
+    }
+
+    /// Renders every frame and encodes the dive as an animated GIF at `path`, calling
+    /// `on_frame(done, frames)` after every encoded frame.
+    #[cfg(feature = "gif")]
+    pub fn render_gif_with_progress<P>(
+        &self,
+        path: impl AsRef<Path>,
+        options: &GifOptions,
+        on_frame: P,
+    ) -> ImageResult<()>
+    where
+        P: FnMut(u32, u32),
+    {
+        let (size, frames) = ((self.width, self.height), self.dive.total_frames());
+        let frame = |index| self.render_frame(index);
+        encode_gif(path.as_ref(), size, frames, options, frame, on_frame)
+    }
+
+    /// Renders every frame and encodes the dive as a video at `path` with `ffmpeg`, calling
+    /// `on_frame(done, frames)` after every encoded frame.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a frame cannot be rendered, see `render_frame`, or if `ffmpeg`
+    /// fails, see `ZoomSequence::render_video`.
+    pub fn render_video_with_progress<P>(
+        &self,
+        path: impl AsRef<Path>,
+        options: &VideoOptions,
+        on_frame: P,
+    ) -> io::Result<()>
+    where
+        P: FnMut(u32, u32),
+    {
+        let (size, frames) = ((self.width, self.height), self.dive.total_frames());
+        let frame = |index| self.render_frame(index);
+        encode_video(path.as_ref(), size, frames, options, frame, on_frame)
+    }
+}
+
+/// Classic color cycling: a single render whose `CyclicPalette` turns by a fraction of its
+/// period from one frame to the next, so the colors flow through the image while nothing moves.
+///
+/// Only the colors change between frames, so the escapes are computed once, see
+/// `analysis::escape_viewport`, and every frame is a cheap recoloring of them. After `frames`
+/// frames the palette has turned a whole period, so the animation loops without a seam.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::{
+///     analysis::escape_viewport,
+///     animation::ColorCycle,
+///     palette::{BuiltinPalette, CyclicPalette},
+///     prelude::*,
+/// };
+///
+/// let opts = RenderOptions::new(200);
+/// let escapes = escape_viewport(64, 48, &Viewport::default(), &opts);
+/// let palette = CyclicPalette::new(BuiltinPalette::Twilight, 32.0, opts.iterations);
+/// let cycle = ColorCycle { width: 64, height: 48, escapes, options: opts, palette, frames: 16 };
+/// assert_ne!(cycle.render_frame(0), cycle.render_frame(8));
+/// assert_eq!(cycle.render_frame(0), cycle.render_frame(16));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorCycle<P> {
+    pub width: u32,
+    pub height: u32,
+    /// Escapes of the render, row by row.
+    pub escapes: Vec<EscapeResult>,
+    /// Options the escapes were rendered with.
+    pub options: RenderOptions,
+    /// Palette of the first frame.
+    pub palette: CyclicPalette<P>,
+    /// Frames per turn of the palette.
+    pub frames: u32,
+}
+
+impl<P: Palette> ColorCycle<P> {
+    /// Colors the escapes with the palette turned by `index` frames.
+    pub fn render_frame(&self, index: u32) -> RgbImage {
+        let palette = &self.palette;
+        let turn = index as f64 / self.frames.max(1) as f64;
+        let shifted = CyclicPalette {
+            inner: |t| palette.inner.color(t),
+            period: palette.period,
+            offset: palette.offset - turn * palette.period,
+            iterations: palette.iterations,
+        };
+        colorize_escapes(self.width, self.height, &self.escapes, &self.options, &shifted)
+    }
+
+    /// Encodes one turn of the palette as an animated GIF at `path`, calling
+    /// `on_frame(done, frames)` after every encoded frame.
+    #[cfg(feature = "gif")]
+    pub fn render_gif_with_progress<F>(
+        &self,
+        path: impl AsRef<Path>,
+        options: &GifOptions,
+        on_frame: F,
+    ) -> ImageResult<()>
+    where
+        F: FnMut(u32, u32),
+    {
+        let frame = |index| Ok(self.render_frame(index));
+        encode_gif(path.as_ref(), (self.width, self.height), self.frames, options, frame, on_frame)
+    }
+
+    /// Encodes one turn of the palette as an animated PNG at `path`, played at `fps` frames per
+    /// second in a loop, calling `on_frame(done, frames)` after every encoded frame. Unlike a
+    /// GIF, every frame keeps its full colors.
+    pub fn render_apng_with_progress<F>(
+        &self,
+        path: impl AsRef<Path>,
+        fps: u32,
+        on_frame: F,
+    ) -> io::Result<()>
+    where
+        F: FnMut(u32, u32),
+    {
+        let frame = |index| Ok(self.render_frame(index));
+        encode_apng(path.as_ref(), (self.width, self.height), self.frames, fps, frame, on_frame)
+    }
+}
+
+/// Encodes frames `0..frames` of `width` x `height`, made by `frame`, as an animated PNG at
+/// `path` that loops forever.
+fn encode_apng<F, P>(
+    path: &Path,
+    (width, height): (u32, u32),
+    frames: u32,
+    fps: u32,
+    mut frame: F,
+    mut on_frame: P,
+) -> io::Result<()>
+where
+    F: FnMut(u32) -> io::Result<RgbImage>,
+    P: FnMut(u32, u32),
+{
+    let mut file = io::BufWriter::new(fs::File::create(path)?);
+    let mut encoder = png::Encoder::new(&mut file, width, height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_animated(frames, 0)?;
+    encoder.set_frame_delay(1, fps.clamp(1, u16::MAX as u32) as u16)?;
+    let mut writer = encoder.write_header()?;
+    for index in 0..frames {
+        writer.write_image_data(frame(index)?.as_raw())?;
+        on_frame(index + 1, frames);
+    }
+    writer.finish()?;
+    file.flush()
+}
+
+/// Path of frame `index` of a sequence of `frames` in `dir`.
+///
+/// # Examples
+/// ```
+/// use std::path::Path;
+///
+/// use mandelbrot::animation::frame_path;
+///
+/// assert_eq!(frame_path("out", 7, 120), Path::new("out/frame_0007.png"));
+/// assert_eq!(frame_path("out", 7, 20_000), Path::new("out/frame_00007.png"));
+/// ```
+pub fn frame_path(dir: impl AsRef<Path>, index: u32, frames: u32) -> PathBuf {
+    let digits = frames.saturating_sub(1).to_string().len().max(MIN_FRAME_DIGITS);
+    dir.as_ref().join(format!("frame_{index:0digits$}.png"))
+}
+
+/// Renders `frames` frames of a zoom from `start` to `end` at `width` x `height` into `dir`, see
+/// `ZoomSequence::render`.
+pub fn render_zoom_sequence(
+    start: Viewport,
+    end: Viewport,
+    frames: u32,
+    width: u32,
+    height: u32,
+    options: &RenderOptions,
+    dir: impl AsRef<Path>,
+) -> ImageResult<Vec<PathBuf>> {
+    ZoomSequence { start, end, frames, width, height, options: *options }.render(dir)
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dive::Keyframe;
+
+    #[test]
+    fn test_render_zoom_sequence_writes_numbered_frames() {
+        let dir = std::env::temp_dir().join(format!("mandelbrot-zoom-{}", std::process::id()));
+        let sequence = ZoomSequence {
+            start: Viewport::default(),
+            end: Viewport::new(-0.743643887037151, 0.131825904205330, 100.0),
+            frames: 3,
+            width: 32,
+            height: 24,
+            options: RenderOptions::new(100),
+        };
+        let mut reported = Vec::new();
+        let paths = sequence
+            .render_with_progress(&dir, |done, total| reported.push((done, total)))
+            .unwrap();
+
+        assert_eq!(paths, (0..3).map(|i| frame_path(&dir, i, 3)).collect::<Vec<_>>());
+        assert_eq!(reported, vec![(1, 3), (2, 3), (3, 3)]);
+        let first = image::open(&paths[0]).unwrap().into_rgb8();
+        let last = image::open(&paths[2]).unwrap().into_rgb8();
+        assert_eq!(first.dimensions(), (32, 24));
+        assert_ne!(first, last);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_dive_sequence_renders_every_keyframe() {
+        let dir = std::env::temp_dir().join(format!("mandelbrot-dive-{}", std::process::id()));
+        let mut dive = Dive::default();
+        dive.push(Keyframe { frames: 2, ..Keyframe::new(&Viewport::default(), 50) });
+        dive.push(Keyframe {
+            preset: Some("seahorse-valley".to_string()),
+            palette: Some("fire".to_string()),
+            ..Keyframe::new(&Viewport::default(), 200)
+        });
+        let sequence =
+            DiveSequence { dive: &dive, width: 24, height: 18, options: RenderOptions::new(1) };
+        let paths = sequence.render_with_progress(&dir, |_, _| {}).unwrap();
+        assert_eq!(paths.len(), 3);
+
+        let last = image::open(&paths[2]).unwrap().into_rgb8();
+        let seahorse = Viewport::preset("seahorse-valley").unwrap();
+        let fire = compose_with_palette(
+            24,
+            18,
+            &seahorse,
+            &RenderOptions::new(200),
+            &BuiltinPalette::Fire,
+        );
+        assert_eq!(last, fire);
+        assert_eq!(sequence.render_frame(3).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_color_cycle_writes_an_animated_png() {
+        let path =
+            std::env::temp_dir().join(format!("mandelbrot-cycle-{}.png", std::process::id()));
+        let opts = RenderOptions::new(100);
+        let escapes = crate::analysis::escape_viewport(32, 24, &Viewport::default(), &opts);
+        let palette = CyclicPalette::new(BuiltinPalette::Fire, 20.0, 100);
+        let cycle =
+            ColorCycle { width: 32, height: 24, escapes, options: opts, palette, frames: 5 };
+        let mut reported = 0;
+        cycle.render_apng_with_progress(&path, 10, |done, _| reported = done).unwrap();
+        assert_eq!(reported, 5);
+
+        let mut reader = png::Decoder::new(fs::File::open(&path).unwrap()).read_info().unwrap();
+        let control = reader.info().animation_control.unwrap();
+        assert_eq!((control.num_frames, control.num_plays), (5, 0));
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut pixels).unwrap();
+        reader.next_frame(&mut pixels).unwrap();
+        assert_eq!(pixels, cycle.render_frame(1).into_raw());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_video_formats_round_trip_and_odd_sizes_are_padded() {
+        for format in VideoFormat::ALL {
+            assert_eq!(VideoFormat::parse(&format.name().to_uppercase()), Some(format));
+        }
+        let args = VideoOptions::default().ffmpeg_args(321, 241, "zoom.mp4");
+        assert!(args.windows(2).any(|arg| arg == ["-c:v", "libx264"]));
+        assert!(args.windows(2).any(|arg| arg == ["-vf", "pad=ceil(iw/2)*2:ceil(ih/2)*2"]));
+        assert!(!args.contains(&"-b:v".to_string()));
+    }
+
+    #[cfg(feature = "gif")]
+    #[test]
+    fn test_render_gif_encodes_every_frame() {
+        let path = std::env::temp_dir().join(format!("mandelbrot-zoom-{}.gif", std::process::id()));
+        let sequence = ZoomSequence {
+            start: Viewport::default(),
+            end: Viewport::new(-0.743643887037151, 0.131825904205330, 100.0),
+            frames: 4,
+            width: 32,
+            height: 24,
+            options: RenderOptions::new(100),
+        };
+        let options = GifOptions { delay: 7, ..GifOptions::default() };
+        let mut reported = 0;
+        sequence.render_gif_with_progress(&path, &options, |done, _| reported = done).unwrap();
+        assert_eq!(reported, 4);
+
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::RGBA);
+        let mut decoder = options.read_info(fs::File::open(&path).unwrap()).unwrap();
+        assert_eq!((decoder.width(), decoder.height()), (32, 24));
+        let mut frames = 0;
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            assert_eq!(frame.delay, 7);
+            frames += 1;
+        }
+        assert_eq!(frames, 4);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(feature = "gif")]
+    #[test]
+    fn test_render_gif_rejects_oversized_frames() {
+        let sequence = ZoomSequence {
+            start: Viewport::default(),
+            end: Viewport::default(),
+            frames: 1,
+            width: 70_000,
+            height: 1,
+            options: RenderOptions::new(10),
+        };
+        let path = std::env::temp_dir().join("mandelbrot-oversized.gif");
+        let result = sequence.render_gif(&path, &GifOptions::default());
+        assert!(matches!(result, Err(ImageError::Limits(_))));
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,227 @@
+//! Colored ASCII art for terminals that understand ANSI escape sequences.
+//!
+//! Every cell keeps the character of `mandelbrot_ascii::canvas_from_counts` and gets a foreground
+//! color from its escape count, written as a 24-bit `38;2;r;g;b` sequence or, on terminals
+//! limited to 256 colors, as the nearest `38;5;n` entry of the xterm 6x6x6 color cube. Which of
+//! the two a terminal supports is guessed from the environment, see `ColorSupport::detect`.
+
+use std::{
+    env,
+    io::{self, BufWriter, IsTerminal, Write},
+};
+
+use crate::mandelbrot_ascii::canvas_from_counts;
+
+/// Color stops of the escape counts, from escaping at once to never escaping.
+const STOPS: [[u8; 3]; 5] =
+    [[0, 7, 100], [32, 107, 203], [237, 255, 255], [255, 170, 0], [0, 2, 0]];
+
+/// Resets the colors at the end of every row.
+const RESET: &str = "\x1b[0m";
+
+/// Which colors a terminal can show.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorSupport {
+    /// No escape sequences at all, the output of `mandelbrot_ascii::write_ascii`.
+    #[default]
+    Plain,
+    /// The 256 indexed colors of xterm.
+    Ansi256,
+    /// 24-bit RGB colors.
+    TrueColor,
+}
+
+impl ColorSupport {
+    pub const ALL: [Self; 3] = [Self::Plain, Self::Ansi256, Self::TrueColor];
+
+    /// Parses the names returned by `name`, ignoring case.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::ansi::ColorSupport;
+    ///
+    /// assert_eq!(ColorSupport::parse("TrueColor"), Some(ColorSupport::TrueColor));
+    /// assert_eq!(ColorSupport::parse("16"), None);
+    /// ```
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|support| support.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Plain => "none",
+            Self::Ansi256 => "256",
+            Self::TrueColor => "truecolor",
+        }
+    }
+
+    /// Guesses the colors of the terminal from the environment: none if standard output is not a
+    /// terminal, `NO_COLOR` is set or `TERM` is `dumb` or unset, 24-bit colors if `COLORTERM` is
+    /// `truecolor` or `24bit`, and 256 colors otherwise.
+    pub fn detect() -> Self {
+        Self::from_vars(
+            io::stdout().is_terminal(),
+            env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()),
+            env::var("TERM").ok().as_deref(),
+            env::var("COLORTERM").ok().as_deref(),
+        )
+    }
+
+    fn from_vars(
+        is_terminal: bool,
+        no_color: bool,
+        term: Option<&str>,
+        colorterm: Option<&str>,
+    ) -> Self {
+        match (term, colorterm) {
+            _ if !is_terminal || no_color => Self::Plain,
+            (None | Some("dumb"), _) => Self::Plain,
+            (_, Some("truecolor" | "24bit")) => Self::TrueColor,
+            _ => Self::Ansi256,
+        }
+    }
+
+    /// The escape sequence that sets the foreground to `color`, empty for `Plain`.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::ansi::ColorSupport;
+    ///
+    /// assert_eq!(ColorSupport::TrueColor.foreground([255, 128, 0]), "\x1b[38;2;255;128;0m");
+    /// assert_eq!(ColorSupport::Ansi256.foreground([255, 128, 0]), "\x1b[38;5;214m");
+    /// ```
+    pub fn foreground(&self, [r, g, b]: [u8; 3]) -> String {
+        match self {
+            Self::Plain => String::new(),
+            Self::Ansi256 => format!("\x1b[38;5;{}m", ansi256([r, g, b])),
+            Self::TrueColor => format!("\x1b[38;2;{r};{g};{b}m"),
+        }
+    }
+}
+
+/// The index of the xterm color cube entry nearest to `color`.
+pub fn ansi256(color: [u8; 3]) -> u8 {
+    let [r, g, b] = color.map(|channel| ((channel as u32 * 5 + 127) / 255) as u8);
+    16 + 36 * r + 6 * g + b
+}
+
+/// Color of an escape count out of `iterations`. The count is square-rooted first, so the few
+/// colors are not all spent on the slowly escaping points near the set.
+pub fn escape_color(value: u32, iterations: u32) -> [u8; 3] {
+    let t = (value as f64 / iterations.max(1) as f64).min(1.0).sqrt();
+    let position = t * (STOPS.len() - 1) as f64;
+    let index = (position.floor() as usize).min(STOPS.len() - 2);
+    let weight = position - index as f64;
+    let (from, to) = (STOPS[index], STOPS[index + 1]);
+    [0, 1, 2].map(|i| (from[i] as f64 + (to[i] as f64 - from[i] as f64) * weight).round() as u8)
+}
+
+/// Writes the `width` x `height` escape `counts` as ASCII art colored for `support`, one line per
+/// row. A color is only written where it changes, and every row ends with a reset.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::ansi::{write_colored, ColorSupport};
+///
+/// let mut out = Vec::new();
+/// write_colored(2, 1, &[100, 100], 100, ColorSupport::TrueColor, &mut out).unwrap();
+/// assert_eq!(String::from_utf8(out).unwrap(), "\x1b[38;2;0;2;0m@@\x1b[0m\n");
+/// ```
+pub fn write_colored<W: Write>(
+    width: u32,
+    height: u32,
+    counts: &[u32],
+    iterations: u32,
+    support: ColorSupport,
+    out: &mut W,
+) -> io::Result<()> {
+    let canvas = canvas_from_counts(width, height, counts, iterations);
+    let mut out = BufWriter::new(out);
+    let mut line = String::new();
+    for (row, values) in canvas.rows().zip(counts.chunks(width.max(1) as usize)) {
+        line.clear();
+        let mut previous = String::new();
+        for (&cell, &value) in row.iter().zip(values) {
+            let sequence = support.foreground(escape_color(value, iterations));
+            if sequence != previous {
+                line.push_str(&sequence);
+                previous = sequence;
+            }
+            line.push(cell);
+        }
+        if support != ColorSupport::Plain {
+            line.push_str(RESET);
+        }
+        line.push('\n');
+        out.write_all(line.as_bytes())?;
+    }
+    out.flush()
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{mandelbrot_ascii::write_ascii, renderer::Renderer};
+
+    #[test]
+    fn test_detects_support_from_environment() {
+        assert_eq!(
+            ColorSupport::from_vars(true, true, Some("xterm"), Some("truecolor")),
+            ColorSupport::Plain
+        );
+        assert_eq!(ColorSupport::from_vars(true, false, Some("dumb"), None), ColorSupport::Plain);
+        assert_eq!(ColorSupport::from_vars(true, false, None, None), ColorSupport::Plain);
+        assert_eq!(
+            ColorSupport::from_vars(true, false, Some("xterm-256color"), Some("24bit")),
+            ColorSupport::TrueColor
+        );
+        assert_eq!(
+            ColorSupport::from_vars(true, false, Some("xterm"), None),
+            ColorSupport::Ansi256
+        );
+        // Piped or redirected output gets no escape sequences, whatever the environment says.
+        assert_eq!(
+            ColorSupport::from_vars(false, false, Some("xterm-256color"), Some("truecolor")),
+            ColorSupport::Plain
+        );
+    }
+
+    #[test]
+    fn test_colored_art_keeps_the_characters() {
+        let renderer = Renderer::new().size(40, 20).iterations(100);
+        let counts = renderer.render_raw().into_counts();
+        let mut plain = Vec::new();
+        write_ascii(&renderer.render_ascii(), &mut plain).unwrap();
+
+        let mut colored = Vec::new();
+        write_colored(40, 20, &counts, 100, ColorSupport::Plain, &mut colored).unwrap();
+        assert_eq!(colored, plain);
+
+        for support in [ColorSupport::Ansi256, ColorSupport::TrueColor] {
+            let mut colored = Vec::new();
+            write_colored(40, 20, &counts, 100, support, &mut colored).unwrap();
+            let text = String::from_utf8(colored).unwrap();
+            let mut stripped = String::new();
+            let mut chars = text.chars();
+            while let Some(c) = chars.next() {
+                if c == '\x1b' {
+                    chars.by_ref().find(|&c| c == 'm');
+                } else {
+                    stripped.push(c);
+                }
+            }
+            assert_eq!(stripped.as_bytes(), plain);
+        }
+    }
+
+    #[test]
+    fn test_ansi256_corners_of_the_cube() {
+        assert_eq!(ansi256([0, 0, 0]), 16);
+        assert_eq!(ansi256([255, 255, 255]), 231);
+        assert_eq!(ansi256([255, 0, 0]), 196);
+        assert_eq!(escape_color(0, 100), STOPS[0]);
+        assert_eq!(escape_color(100, 100), STOPS[4]);
+    }
+}
@@ -0,0 +1,217 @@
+//! Average coloring, which colors a point by a statistic averaged over its orbit.
+//!
+//! Every step of the orbit adds a term to a running average: stripe average coloring adds
+//! `(1 + sin(STRIPE_DENSITY arg z)) / 2`, which draws stripes that follow the field lines, and
+//! the triangle inequality average (TIA) adds where `|z|` lies between the smallest and largest
+//! values the triangle inequality allows for `|z_prev^d| + |c|`. Averages taken with and without
+//! the last term are blended by the fractional part of the smooth iteration count, so the bands
+//! of whole iteration counts disappear. Averages are selected with
+//! `options::ColorMode::Average`.
+
+use std::sync::atomic::AtomicBool;
+
+use num_complex::Complex64;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    options::RenderOptions,
+    scheduler::{try_render_grid, RenderCancelled},
+    viewport::Viewport,
+};
+
+/// Number of stripes of `AverageColoring::Stripe` around a full turn of `z`.
+pub const STRIPE_DENSITY: f64 = 5.0;
+
+/// Smallest escape radius the averages are taken with. The blend of the last two averages is
+/// only smooth once the last step is dominated by `z^d`, which small radii do not ensure.
+pub const AVERAGE_BAILOUT: f64 = 1000.0;
+
+/// The statistic averaged over the orbit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AverageColoring {
+    /// Stripe average coloring, of the angle of `z`.
+    #[default]
+    Stripe,
+    /// Triangle inequality average, of the size of `z` against the bounds for its step.
+    TriangleInequality,
+}
+
+impl AverageColoring {
+    pub const ALL: [Self; 2] = [Self::Stripe, Self::TriangleInequality];
+
+    /// Parses the names returned by `name`, ignoring case.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::averages::AverageColoring;
+    ///
+    /// assert_eq!(AverageColoring::parse("TIA"), Some(AverageColoring::TriangleInequality));
+    /// assert_eq!(AverageColoring::parse("curvature"), None);
+    /// ```
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|coloring| coloring.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Stripe => "stripe",
+            Self::TriangleInequality => "tia",
+        }
+    }
+
+    /// The term, between `0.0` and `1.0`, that the step of the orbit of `c` landing on `z` adds
+    /// to the average.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::averages::AverageColoring;
+    /// use num_complex::Complex64;
+    ///
+    /// let (c, z_prev) = (Complex64::new(0.5, 0.0), Complex64::new(1.0, 0.0));
+    /// // `|z_prev^2 + c|` reaches the upper bound `|z_prev^2| + |c|` on the real axis.
+    /// let z = z_prev * z_prev + c;
+    /// assert_eq!(AverageColoring::TriangleInequality.term(z, c), 1.0);
+    /// assert_eq!(AverageColoring::Stripe.term(z, c), 0.5);
+    /// ```
+    pub fn term(&self, z: Complex64, c: Complex64) -> f64 {
+        match self {
+            Self::Stripe => 0.5 + 0.5 * (STRIPE_DENSITY * z.arg()).sin(),
+            Self::TriangleInequality => {
+                // `z - c` is `z_prev^d`, whatever the power.
+                let (step, offset) = ((z - c).norm(), c.norm());
+                let (low, high) = ((step - offset).abs(), step + offset);
+                if high > low {
+                    ((z.norm() - low) / (high - low)).clamp(0.0, 1.0)
+                } else {
+                    // With `z_prev` or `c` at zero the bounds meet, and say nothing.
+                    0.5
+                }
+            }
+        }
+    }
+}
+
+/// Average of `coloring` over the orbit of `c` under the iteration of `opts.kind`, between
+/// `0.0` and `1.0`, or `None` if the orbit does not escape within `opts.iterations`. The first
+/// step, which only ever lands on `c`, is left out.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::{
+///     averages::{orbit_average, AverageColoring},
+///     options::RenderOptions,
+/// };
+/// use num_complex::Complex64;
+///
+/// let opts = RenderOptions::new(500);
+/// let average = orbit_average(Complex64::new(-0.75, 0.1), &opts, AverageColoring::Stripe);
+/// assert!(average.is_some_and(|value| (0.0..=1.0).contains(&value)));
+/// let inside = orbit_average(Complex64::new(0.0, 0.0), &opts, AverageColoring::Stripe);
+/// assert_eq!(inside, None);
+/// ```
+pub fn orbit_average(c: Complex64, opts: &RenderOptions, coloring: AverageColoring) -> Option<f64> {
+    let bailout = opts.bailout.max(opts.kind.escape_radius()).max(AVERAGE_BAILOUT);
+    let bailout_sqr = bailout * bailout;
+    let power = opts.kind.power();
+    let (mut z, mut sum, mut last) = (Complex64::new(0.0, 0.0), 0.0, 0.0);
+    for i in 0..opts.iterations {
+        z = opts.kind.step(z, c);
+        if i > 0 {
+            last = coloring.term(z, c);
+            sum += last;
+        }
+        if z.norm_sqr() > bailout_sqr {
+            // Averages with and without the last term, blended by the fraction of a step left
+            // before the orbit crossed the bailout, see `mandelbrot_img::mandelbrot_smooth`.
+            let terms = i as f64;
+            if terms < 2.0 {
+                return Some(last);
+            }
+            let (average, previous) = (sum / terms, (sum - last) / (terms - 1.0));
+            let fraction = 1.0 + (bailout.ln() / z.norm().ln()).ln() / power.ln();
+            let fraction = fraction.clamp(0.0, 1.0);
+            return Some(fraction * average + (1.0 - fraction) * previous);
+        }
+    }
+    None
+}
+
+/// Runs `orbit_average` for every pixel of a `width` x `height` grid over `viewport`, in
+/// row-major order. Always uses `f64` arithmetic.
+pub fn average_viewport(
+    width: u32,
+    height: u32,
+    viewport: &Viewport,
+    opts: &RenderOptions,
+    coloring: AverageColoring,
+) -> Vec<Option<f64>> {
+    average_viewport_with_progress(width, height, viewport, opts, coloring, |_, _| {})
+}
+
+/// Like `average_viewport`, but calls `on_progress(done, total)` with the number of evaluated
+/// pixels after every finished row.
+pub fn average_viewport_with_progress<P>(
+    width: u32,
+    height: u32,
+    viewport: &Viewport,
+    opts: &RenderOptions,
+    coloring: AverageColoring,
+    on_progress: P,
+) -> Vec<Option<f64>>
+where
+    P: Fn(u64, u64) + Sync,
+{
+    let never = AtomicBool::new(false);
+    match try_average_viewport(width, height, viewport, opts, coloring, on_progress, &never) {
+        Ok(averages) => averages,
+        Err(RenderCancelled) => unreachable!("the render was never cancelled"),
+    }
+}
+
+/// Like `average_viewport_with_progress`, but stops once `cancel` is set, see
+/// `scheduler::try_render_grid`.
+///
+/// # Errors
+///
+/// Returns `RenderCancelled` if the render was cancelled.
+pub fn try_average_viewport<P>(
+    width: u32,
+    height: u32,
+    viewport: &Viewport,
+    opts: &RenderOptions,
+    coloring: AverageColoring,
+    on_progress: P,
+    cancel: &AtomicBool,
+) -> Result<Vec<Option<f64>>, RenderCancelled>
+where
+    P: Fn(u64, u64) + Sync,
+{
+    try_render_grid(
+        width,
+        height,
+        |x, y| orbit_average(viewport.point(x, y, width, height), opts, coloring),
+        on_progress,
+        cancel,
+    )
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_averages_have_no_bands() {
+        // Escape counts jump along this row, but the averages should change gradually.
+        let opts = RenderOptions::new(1000);
+        for coloring in AverageColoring::ALL {
+            let values: Vec<f64> = (0..=200)
+                .map(|i| Complex64::new(-2.0 + i as f64 * 1e-4, 0.6))
+                .map(|c| orbit_average(c, &opts, coloring).unwrap())
+                .collect();
+            let jump = values.windows(2).map(|pair| (pair[1] - pair[0]).abs()).fold(0.0, f64::max);
+            assert!(jump < 0.05, "{coloring:?} jumps by {jump}");
+        }
+    }
+}
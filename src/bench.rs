@@ -0,0 +1,157 @@
+//! Standard scenes and backends for measuring how fast the escape-time kernels render.
+//!
+//! The scenes cover the cases that stress a kernel differently: the default view, where most
+//! points escape within a few iterations, a deep zoom near the boundary, where every point takes
+//! long to decide, and a view of the boundary at a high iteration limit, where points inside the
+//! set run to the limit. Every backend that is compiled in renders every scene to escape counts,
+//! so their speeds compare directly. The `bench` subcommand of the binary prints the results, and
+//! the criterion benchmarks in `benches/` run the same scenes.
+
+use std::time::{Duration, Instant};
+
+use crate::{mandelbrot_img::mandelbrot, viewport::Viewport};
+
+/// A view rendered by the benchmarks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BenchScene {
+    pub name: &'static str,
+    pub viewport: Viewport,
+    pub iterations: u32,
+}
+
+impl BenchScene {
+    /// The default view at the default iteration limit of the binary.
+    pub fn default_view() -> Self {
+        Self { name: "default-view", viewport: Viewport::default(), iterations: 255 }
+    }
+
+    /// A spiral in seahorse valley at a zoom of one million, still within `f64` precision.
+    pub fn deep_zoom() -> Self {
+        let viewport = Viewport::new(-0.743643887037151, 0.13182590420533, 1e6);
+        Self { name: "deep-zoom", viewport, iterations: 5000 }
+    }
+
+    /// The neck between the main cardioid and the period-2 bulb, where much of the view is
+    /// inside the set and runs to the limit.
+    pub fn boundary() -> Self {
+        Self { name: "boundary", viewport: Viewport::new(-0.75, 0.0, 8.0), iterations: 10_000 }
+    }
+
+    /// The scenes `bench` renders, in order.
+    pub fn standard() -> [Self; 3] {
+        [Self::default_view(), Self::deep_zoom(), Self::boundary()]
+    }
+}
+
+/// A way of computing escape counts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// The scalar kernel, one pixel after the other on one thread.
+    Scalar,
+    /// The scalar kernel, with rows spread across threads.
+    #[cfg(feature = "parallel")]
+    Parallel,
+    /// The vectorized kernel of `simd`, with rows spread across threads if `parallel` is also
+    /// enabled.
+    #[cfg(feature = "simd")]
+    Simd,
+}
+
+impl Backend {
+    /// The backends compiled in.
+    pub const ALL: &'static [Self] = &[
+        Self::Scalar,
+        #[cfg(feature = "parallel")]
+        Self::Parallel,
+        #[cfg(feature = "simd")]
+        Self::Simd,
+    ];
+
+    /// Parses the names returned by `name`, ignoring case.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::bench::Backend;
+    ///
+    /// assert_eq!(Backend::parse("Scalar"), Some(Backend::Scalar));
+    /// assert_eq!(Backend::parse("gpu"), None);
+    /// ```
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|backend| backend.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Scalar => "scalar",
+            #[cfg(feature = "parallel")]
+            Self::Parallel => "parallel",
+            #[cfg(feature = "simd")]
+            Self::Simd => "simd",
+        }
+    }
+
+    /// Escape counts of every pixel of a `width` x `height` grid over `viewport`, in row-major
+    /// order. Every backend returns the same counts.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::{bench::Backend, viewport::Viewport};
+    ///
+    /// let counts: Vec<Vec<u32>> = Backend::ALL
+    ///     .iter()
+    ///     .map(|backend| backend.render(40, 30, &Viewport::default(), 100))
+    ///     .collect();
+    /// assert!(counts.iter().all(|backend_counts| *backend_counts == counts[0]));
+    /// ```
+    pub fn render(
+        &self,
+        width: u32,
+        height: u32,
+        viewport: &Viewport,
+        iterations: u32,
+    ) -> Vec<u32> {
+        let count = |x, y| mandelbrot(viewport.to_complex_num(x, y, width, height), iterations);
+        match self {
+            Self::Scalar => {
+                (0..height).flat_map(|y| (0..width).map(move |x| count(x, y))).collect()
+            }
+            #[cfg(feature = "parallel")]
+            Self::Parallel => crate::scheduler::render_grid(width, height, count),
+            #[cfg(feature = "simd")]
+            Self::Simd => crate::simd::render_counts(width, height, viewport, iterations),
+        }
+    }
+}
+
+/// The time one backend took to render one scene.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BenchResult {
+    pub scene: BenchScene,
+    pub backend: Backend,
+    pub width: u32,
+    pub height: u32,
+    /// The fastest of the timed renders.
+    pub elapsed: Duration,
+}
+
+impl BenchResult {
+    pub fn pixels_per_second(&self) -> f64 {
+        self.width as f64 * self.height as f64 / self.elapsed.as_secs_f64().max(f64::MIN_POSITIVE)
+    }
+}
+
+/// Renders `scene` at `width` x `height` with `backend` `runs` times, at least once, and keeps
+/// the fastest time, which is the one least disturbed by other work on the machine.
+pub fn run(scene: BenchScene, backend: Backend, width: u32, height: u32, runs: u32) -> BenchResult {
+    let elapsed = (0..runs.max(1))
+        .map(|_| {
+            let start = Instant::now();
+            let counts = backend.render(width, height, &scene.viewport, scene.iterations);
+            let elapsed = start.elapsed();
+            std::hint::black_box(counts);
+            elapsed
+        })
+        .min()
+        .expect("at least one run");
+    BenchResult { scene, backend, width, height, elapsed }
+}
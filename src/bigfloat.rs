@@ -0,0 +1,254 @@
+//! Arbitrary-precision coordinates for locations deeper than `f64` can address.
+//!
+//! Deep-zoom locations are shared as decimal strings with hundreds of digits. Going through `f64`
+//! keeps only about 16 of them, which moves a location at a zoom of `1e20` by thousands of
+//! screen widths. `parse_decimal` parses such strings losslessly into a `DBig`, a decimal float
+//! whose precision is the number of significant digits it was written with, so the location
+//! survives until it reaches a kernel that can use it.
+//!
+//! `escape_arbitrary` is that kernel: the escape-time iteration carried out with a fixed number
+//! of significant decimal digits. `render_arbitrary` uses it for whole grids, adding the small
+//! offset of each pixel to an arbitrary-precision center, and is what `Precision::Arbitrary`
+//! viewports render with. It is several hundred times slower than `f64`; `perturbation` gets
+//! the same pixels far faster and is what `Precision::Auto` switches to for deep zooms.
+
+use std::{fmt, str::FromStr};
+
+pub use dashu_float::DBig;
+use num_complex::Complex64;
+
+use crate::{
+    analysis::EscapeResult, options::RenderOptions, scheduler::render_grid, viewport::Viewport,
+};
+
+/// Error returned when a coordinate is not a decimal number.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseDecimalError {
+    input: String,
+}
+
+impl fmt::Display for ParseDecimalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} is not a decimal number", self.input)
+    }
+}
+
+impl std::error::Error for ParseDecimalError {}
+
+/// Parses a decimal number such as `-0.743643887037158704752191506114774` or `1.5e-30` without
+/// rounding.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::bigfloat::parse_decimal;
+///
+/// let digits = "-0.74364388703715870475219150611477416";
+/// let x = parse_decimal(digits).unwrap();
+/// assert_eq!(x.to_string(), digits);
+/// assert!(parse_decimal("0x1p3").is_err());
+/// ```
+pub fn parse_decimal(input: &str) -> Result<DBig, ParseDecimalError> {
+    let trimmed = input.trim();
+    let error = || ParseDecimalError { input: input.to_string() };
+    // `DBig` also accepts GMP style `@` exponents, which are not used in decimal coordinates.
+    if trimmed.is_empty() || trimmed.contains('@') {
+        return Err(error());
+    }
+    DBig::from_str(trimmed).map_err(|_| error())
+}
+
+/// Rounds `value` to the nearest `f64`.
+pub fn to_f64(value: &DBig) -> f64 {
+    value.to_f64().value()
+}
+
+/// A point of the complex plane with arbitrary-precision coordinates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PreciseCenter {
+    pub x: DBig,
+    pub y: DBig,
+}
+
+impl PreciseCenter {
+    /// Parses both coordinates with `parse_decimal`.
+    pub fn parse(x: &str, y: &str) -> Result<Self, ParseDecimalError> {
+        Ok(Self { x: parse_decimal(x)?, y: parse_decimal(y)? })
+    }
+
+    /// The point whose coordinates are the shortest decimals that round back to the same `f64`s,
+    /// such as `0.1` rather than the exact binary value just above it.
+    pub fn from_f64(x: f64, y: f64) -> Self {
+        Self { x: from_f64(x), y: from_f64(y) }
+    }
+
+    /// The point rounded to `f64` coordinates.
+    pub fn to_f64(&self) -> (f64, f64) {
+        (to_f64(&self.x), to_f64(&self.y))
+    }
+
+    /// Number of significant decimal digits of the less precise coordinate.
+    pub fn digits(&self) -> usize {
+        self.x.precision().min(self.y.precision())
+    }
+}
+
+impl FromStr for PreciseCenter {
+    type Err = ParseDecimalError;
+
+    /// Parses `x,y`, the format used on the command line.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (x, y) = s.split_once(',').ok_or_else(|| ParseDecimalError { input: s.to_string() })?;
+        Self::parse(x, y)
+    }
+}
+
+impl fmt::Display for PreciseCenter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{}", self.x, self.y)
+    }
+}
+
+/// Runs `c = x + yi` through the escape-time kernel with `digits` significant decimal digits.
+///
+/// The result matches `analysis::escape`, with `z` and `smooth` rounded to `f64` at the end.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::{
+///     bigfloat::{escape_arbitrary, parse_decimal},
+///     options::RenderOptions,
+/// };
+///
+/// let x = parse_decimal("0.25000000000000000000000000001").unwrap();
+/// let y = parse_decimal("0").unwrap();
+/// // Just right of the cusp of the cardioid: the orbit creeps away very slowly.
+/// let result = escape_arbitrary(&x, &y, &RenderOptions::new(50), 40);
+/// assert!(!result.escaped);
+/// ```
+pub fn escape_arbitrary(x: &DBig, y: &DBig, opts: &RenderOptions, digits: usize) -> EscapeResult {
+    let round = |value: &DBig| value.clone().with_precision(digits).value();
+    let (cx, cy) = (round(x), round(y));
+    let bailout_sqr = round(&from_f64(opts.bailout * opts.bailout));
+    let (mut zx, mut zy) = (round(&DBig::ZERO), round(&DBig::ZERO));
+    for i in 0..opts.iterations {
+        let (zx2, zy2) = (&zx * &zx, &zy * &zy);
+        let xy = &zx * &zy;
+        zy = &xy + &xy + &cy;
+        zx = zx2 - zy2 + &cx;
+        let norm = &zx * &zx + &zy * &zy;
+        if norm > bailout_sqr {
+            let z = Complex64::new(to_f64(&zx), to_f64(&zy));
+            let log_z = to_f64(&norm).ln() / 2.0;
+            let smooth = (i as f64 + 1.0 - log_z.log2()).max(0.0);
+            return EscapeResult { iterations: i, escaped: true, smooth, z };
+        }
+    }
+    let z = Complex64::new(to_f64(&zx), to_f64(&zy));
+    EscapeResult { iterations: opts.iterations, escaped: false, smooth: opts.iterations as f64, z }
+}
+
+/// Like `mandelbrot_img::mandelbrot`, but iterates with `digits` significant decimal digits.
+pub fn mandelbrot_arbitrary(x: &DBig, y: &DBig, iterations: u32, digits: usize) -> u32 {
+    escape_arbitrary(x, y, &RenderOptions::new(iterations), digits).iterations
+}
+
+/// Runs every pixel of a `width` x `height` grid through `escape_arbitrary`, in row-major order.
+///
+/// The grid spans `viewport` around `center`, which replaces the `f64` center of the viewport so
+/// locations given with more digits than `f64` holds render where they are.
+pub fn render_arbitrary(
+    width: u32,
+    height: u32,
+    viewport: &Viewport,
+    center: &PreciseCenter,
+    opts: &RenderOptions,
+    digits: usize,
+) -> Vec<EscapeResult> {
+    // The sum of two `DBig`s has the precision of the more precise one, so both terms are
+    // widened first; otherwise a short center would round the pixel offsets away.
+    let round = |value: DBig| value.with_precision(digits).value();
+    let (cx, cy) = (round(center.x.clone()), round(center.y.clone()));
+    let (span_x, span_y) = viewport.spans(width, height);
+    render_grid(width, height, |x, y| {
+        // Offsets from the center are small, so `f64` holds them to well below a pixel.
+        let dx = round(from_f64((x as f64 / width as f64 - 0.5) * span_x));
+        let dy = round(from_f64((y as f64 / height as f64 - 0.5) * span_y));
+        escape_arbitrary(&(&cx + dx), &(&cy + dy), opts, digits)
+    })
+}
+
+/// The shortest decimal that rounds back to `value`.
+fn from_f64(value: f64) -> DBig {
+    parse_decimal(&value.to_string()).unwrap_or_default()
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hundreds_of_digits_survive() {
+        let x = format!("-1.{}", "7".repeat(300));
+        let y = format!("0.{}1", "0".repeat(250));
+        let center = PreciseCenter::parse(&x, &y).unwrap();
+        assert_eq!(center.x.to_string(), x);
+        assert_eq!(center.y.to_string(), y);
+        assert!(center.digits() < 300);
+        assert_eq!(center.to_string().parse::<PreciseCenter>().unwrap(), center);
+
+        // Rounding to f64 only happens on request.
+        let (fx, fy) = center.to_f64();
+        assert_eq!(fx, -1.7777777777777777);
+        assert!(fy > 0.0 && fy < 1e-249);
+    }
+
+    #[test]
+    fn test_rejects_non_decimal_input() {
+        for input in ["", " ", "abc", "1.2.3", "1@5", "1;2"] {
+            assert!(parse_decimal(input).is_err(), "{input:?}");
+        }
+        assert!("1.5".parse::<PreciseCenter>().is_err());
+        assert_eq!(PreciseCenter::from_f64(-0.75, 0.1).to_f64(), (-0.75, 0.1));
+    }
+
+    #[test]
+    fn test_arbitrary_kernel_resolves_pixels_f64_merges() {
+        use crate::{
+            analysis::escape_viewport,
+            viewport::{FitMode, Precision},
+        };
+
+        // Left of the tip at -2 an orbit leaves radius 3 after about log4(1 / distance)
+        // iterations, while -2 itself stays on 2 forever. Eight pixels 1e-20 apart all round to
+        // -2 in f64.
+        let zoom = 3.5 / 8e-20;
+        // A single row sits half a pixel above the center; shift it onto the real axis.
+        let y = 1.0 / zoom;
+        let center = PreciseCenter::parse("-2.00000000000000000004", &y.to_string()).unwrap();
+        let viewport = Viewport::new(center.to_f64().0, y, zoom).with_fit(FitMode::Stretch);
+        let opts = RenderOptions::new(100).with_bailout(3.0);
+
+        let doubles = escape_viewport(8, 1, &viewport.with_precision(Precision::Double), &opts);
+        assert!(doubles.iter().all(|result| !result.escaped));
+
+        assert_eq!(viewport.resolved_precision(8, 1), Precision::Perturbation);
+        let digits = viewport.required_digits(8, 1);
+        let precise = render_arbitrary(8, 1, &viewport, &center, &opts, digits);
+        assert!(precise.iter().all(|result| result.escaped));
+        let counts: Vec<u32> = precise.iter().map(|result| result.iterations).collect();
+        assert!(counts.windows(2).all(|pair| pair[0] <= pair[1]), "{counts:?}");
+        assert!(counts[0] < counts[7], "{counts:?}");
+    }
+
+    #[test]
+    fn test_arbitrary_kernel_matches_f64_when_shallow() {
+        let opts = RenderOptions::new(200);
+        for (x, y) in [(-0.75, 0.1), (0.3, 0.5), (-1.25, 0.02), (0.0, 0.0)] {
+            let expected = crate::analysis::escape(Complex64::new(x, y), &opts);
+            let result = escape_arbitrary(&from_f64(x), &from_f64(y), &opts, 30);
+            assert_eq!(result.iterations, expected.iterations, "({x}, {y})");
+        }
+    }
+}
@@ -1,21 +1,95 @@
-use std::{env, path::Path, sync::RwLock};
+use std::{
+    env, fmt, fs,
+    io::{self, Write},
+    net::TcpListener,
+    path::{Path, PathBuf},
+    sync::RwLock,
+};
 
-use clap::{command, Arg, ArgMatches};
-use config::{builder::DefaultState, Config, ConfigBuilder};
+use clap::{command, value_parser, Arg, ArgAction, ArgMatches, Command};
+use config::{builder::DefaultState, Config, ConfigBuilder, Source};
 use console::Style;
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEventKind},
+    execute, queue, terminal,
+};
+use image::RgbImage;
 use indicatif::{ProgressBar, ProgressStyle};
-use log::{info, LevelFilter::Info};
-use mandelbrot::mandelbrot_ascii;
+use log::{info, warn, LevelFilter::Info};
+use mandelbrot::{
+    analysis::{
+        distance_viewport_with_progress, escape_viewport, escape_viewport_with_progress,
+        refine_iterations,
+    },
+    animation::{ColorCycle, DiveSequence, GifOptions, VideoFormat, VideoOptions, ZoomSequence},
+    ansi::{self, ColorSupport},
+    averages::average_viewport_with_progress,
+    bench::{self, Backend, BenchScene},
+    bigfloat::{render_arbitrary, PreciseCenter},
+    bookmarks::{BookmarkStore, DEFAULT_BOOKMARKS_FILE},
+    buffer::PixelInfoBuffer,
+    checkpoint::{self, checkpoint_path, Checkpoint, CHECKPOINT_INTERVAL},
+    contour::{self, default_levels},
+    dive::{Dive, DiveFormat},
+    engine::render_escape_time,
+    explorer::{bookmark_panel, Command as ExploreCommand, Explorer, PANEL_WIDTH},
+    export::{self, ChromaSubsampling, JpegOptions, OutputFormat, DEFAULT_JPEG_QUALITY},
+    formula::Formula,
+    html,
+    interior::{color_interior, InteriorColoring},
+    interop::{self, KfrParams},
+    lighting::{light_value, normal_viewport_with_progress, DEFAULT_AZIMUTH, DEFAULT_ELEVATION},
+    mandelbrot_ascii::{self, AsciiOptions, WriteMode},
+    mandelbrot_img::{
+        distance_value, shade_averages, shade_distances, shade_escapes, shade_escapes_f32,
+        shade_escapes_u16, shade_normals, shade_traps, trap_value,
+    },
+    metadata::{self, ImageMetadata},
+    newton::{self, NewtonOptions, Polynomial},
+    options::{ColorMode, RenderStrategy},
+    palette::{
+        colorize_escapes, colorize_values, BuiltinPalette, CyclicPalette, Gradient, Palette,
+    },
+    perturbation::render_perturbed,
+    playback::ZoomPlayback,
+    prelude::*,
+    projection::{compose_projected, Mobius, Projection},
+    pyramid::{self, PyramidOptions},
+    scene::Scene,
+    server::{self, Server},
+    session::{Session, DEFAULT_SESSION_FILE},
+    sixel,
+    stats::{self, RenderStats},
+    stereo::{compose_stereo, StereoLayout, StereoOptions},
+    stream::RowStream,
+    tiles::{self, render_tile, Tile, TileGrid},
+    traps::trap_viewport_with_progress,
+    viewport::{FitMode, Precision, PRESETS},
+};
 use once_cell::sync::Lazy;
 use pretty_env_logger::env_logger::Builder;
 use serde::{Deserialize, Serialize};
 
 const ITERATIONS: u32 = 255;
+/// Significant decimal digits an `f64` holds.
+const F64_DIGITS: usize = 17;
 const WIDTH: u32 = 800;
 const HEIGHT: u32 = 800;
+const ASCII_AREA: u64 = mandelbrot_ascii::WIDTH as u64 * mandelbrot_ascii::HEIGHT as u64;
 const DEFAULT_SETTINGS_FILE: &str = "settings.toml";
 const DEFAULT_IMAGE_PATH: &str = "mandelbrot.png";
 const DEFAULT_TEXT_PATH: &str = "mandelbrot.txt";
+const DEFAULT_HTML_PATH: &str = "mandelbrot.html";
+const DEFAULT_CUBEMAP_DIR: &str = "skybox";
+const DEFAULT_ANIMATION_DIR: &str = "zoom";
+const CUBEMAP_SIZE: u32 = 1024;
+const ANIMATION_FRAMES: u32 = 120;
+const COLOR_CYCLE_FPS: u32 = 25;
+/// Values of `--format` that write escape counts instead of an image.
+const DATA_FORMATS: [&str; 4] = ["raw", "csv", "exr", "svg"];
+#[cfg(feature = "wallpaper")]
+const DEFAULT_WALLPAPER_PATH: &str = "wallpaper.png";
 
 static CONFIG_BUILDER: Lazy<RwLock<ConfigBuilder<DefaultState>>> =
     Lazy::new(|| RwLock::new(Config::builder()));
@@ -38,74 +112,2034 @@ fn try_main() -> anyhow::Result<()> {
         .clone()
         .set_default("verbose", "1")? // This is not in the settings file.
         .add_source(config::File::with_name(&curr_path.to_string_lossy()))
-        .add_source(config::Environment::with_prefix("APP"))
+        .add_source(app_environment())
         .build_cloned()?;
-    let config_manager: ConfigManager = settings_new.try_deserialize()?;
+    let app_config = AppConfig::load(&settings_new, &curr_path)?;
 
     // Parse clap args.
-    let matches: ArgMatches = command!()
+    let matches: ArgMatches = wallpaper_args(command!())
         .arg(
             Arg::new("ascii")
                 .long("ascii")
+                .action(ArgAction::SetTrue)
                 .help("Generates the Mandelbrot set as ASCII art and print to terminal"),
         )
+        .arg(
+            Arg::new("braille")
+                .long("braille")
+                .action(ArgAction::SetTrue)
+                .help("Draws the ASCII art with braille dots, 2x4 per character"),
+        )
+        .arg(
+            Arg::new("charset")
+                .long("charset")
+                .value_name("CHARS")
+                .help("Draws the ASCII art with CHARS, from escaping at once to inside the set"),
+        )
+        .arg(
+            Arg::new("color")
+                .long("color")
+                .value_name("MODE")
+                .num_args(0..=1)
+                .default_missing_value("auto")
+                .value_parser(["auto", "none", "256", "truecolor"])
+                .help(
+                    "Colors the --ascii art, detecting the terminal's colors unless MODE is given",
+                ),
+        )
         .arg(
             Arg::new("text")
                 .long("text")
                 .help("Generates the Mandelbrot set as ASCII art and saves to text file"),
         )
+        .arg(
+            Arg::new("text-output")
+                .long("text-output")
+                .value_name("PATH")
+                .help("Saves the ASCII art to PATH instead of the file named in settings"),
+        )
+        .arg(
+            Arg::new("append")
+                .long("append")
+                .action(ArgAction::SetTrue)
+                .help("Appends the ASCII art to the text file instead of overwriting it"),
+        )
         .arg(
             Arg::new("image")
                 .long("image")
                 .help("Generates the Mandelbrot set as an image and saves to file"),
         )
+        .arg(
+            Arg::new("output")
+                .long("output")
+                .value_name("PATH")
+                .value_parser(parse_image_path)
+                .help("Saves the image to PATH instead of the file named in settings"),
+        )
+        .arg(Arg::new("stream").long("stream").action(ArgAction::SetTrue).help(
+            "Renders and writes the PNG a few rows at a time, so images larger than memory can be \
+             rendered",
+        ))
+        .arg(Arg::new("sidecar").long("sidecar").action(ArgAction::SetTrue).help(
+            "Also writes the render parameters to a JSON file next to the image, a scene file \
+             --scene renders again; PNG images always carry them",
+        ))
+        .arg(Arg::new("scene").long("scene").value_name("FILE").help(
+            "Renders the scene in FILE, a TOML or JSON file with the size, view, iterations, \
+             fractal and palette; other options override it",
+        ))
+        .arg(Arg::new("resume").long("resume").value_name("CHECKPOINT").help(
+            "Continues the interrupted render saved in CHECKPOINT, a .mbr-checkpoint file next to \
+             the image",
+        ))
+        .arg(
+            Arg::new("tiles")
+                .long("tiles")
+                .value_name("COLUMNSxROWS")
+                .value_parser(parse_tile_grid)
+                .help(
+                    "Renders the image as a grid of tiles saved next to it, skipping tiles that \
+                     are already saved for the same render, and stitches them",
+                ),
+        )
+        .arg(
+            Arg::new("tile-index")
+                .long("tile-index")
+                .value_name("COLUMN/ROW")
+                .value_parser(parse_tile_index)
+                .requires("tiles")
+                .help("Renders only the tile at COLUMN/ROW of --tiles, counting from 0"),
+        )
+        .arg(
+            Arg::new("width")
+                .long("width")
+                .value_parser(value_parser!(u32).range(1..))
+                .help("Width of the image or ASCII art, in pixels or characters"),
+        )
+        .arg(
+            Arg::new("height")
+                .long("height")
+                .value_parser(value_parser!(u32).range(1..))
+                .help("Height of the image or ASCII art, in pixels or lines"),
+        )
+        .arg(Arg::new("iterations").long("iterations").value_parser(parse_iterations).help(
+            "Maximum number of iterations per point, or auto to choose it from the zoom of the \
+             image",
+        ))
+        .arg(Arg::new("no-symmetry").long("no-symmetry").action(ArgAction::SetTrue).help(
+            "Computes every row of views centered on the real axis instead of mirroring the top \
+             half, which also keeps checkpoints of long renders",
+        ))
+        .arg(
+            Arg::new("strategy")
+                .long("strategy")
+                .value_parser(RenderStrategy::ALL.map(|strategy| strategy.name()))
+                .help(
+                    "How pixels are evaluated: per-pixel iterates all of them, boundary-trace \
+                     fills rectangles whose border shares an escape count, which is faster on \
+                     views with large flat areas but may miss tiny details",
+                ),
+        )
+        .arg(
+            Arg::new("refine-iterations")
+                .long("refine-iterations")
+                .action(ArgAction::SetTrue)
+                .help(
+                    "Renders a small preview first and doubles the iterations of the image while \
+                     that lets more of its pixels escape",
+                ),
+        )
+        .arg(
+            Arg::new("quality")
+                .long("quality")
+                .value_parser(value_parser!(u8).range(1..=100))
+                .help("JPEG quality from 1 to 100 when the image path ends in .jpg or .jpeg"),
+        )
+        .arg(
+            Arg::new("subsampling")
+                .long("subsampling")
+                .value_parser(["444", "422", "420"])
+                .help("JPEG chroma subsampling when the image path ends in .jpg or .jpeg"),
+        )
+        .arg(
+            Arg::new("projection")
+                .long("projection")
+                .value_parser(["planar", "sphere", "inverted", "log-polar"])
+                .help(
+                    "Maps the image onto the plane (default), the Riemann sphere, 1/c or log-polar",
+                ),
+        )
+        .arg(
+            Arg::new("mobius")
+                .long("mobius")
+                .value_name("A,B,C,D")
+                .value_parser(parse_mobius)
+                .conflicts_with("projection")
+                .help("Warps the image by the Möbius transformation (A w + B) / (C w + D)"),
+        )
+        .arg(
+            Arg::new("stereo")
+                .long("stereo")
+                .value_parser(["anaglyph", "side-by-side"])
+                .conflicts_with_all(["projection", "mobius"])
+                .help("Renders a red-cyan anaglyph or a side-by-side stereo pair"),
+        )
+        .arg(
+            Arg::new("parallax")
+                .long("parallax")
+                .value_parser(value_parser!(f64))
+                .help("Maximum shift between the eye views of --stereo, in pixels"),
+        )
+        .arg(
+            Arg::new("cubemap")
+                .long("cubemap")
+                .value_name("DIR")
+                .num_args(0..=1)
+                .default_missing_value(DEFAULT_CUBEMAP_DIR)
+                .help("Renders the six faces of a skybox cubemap into DIR"),
+        )
+        .arg(
+            Arg::new("center")
+                .long("center")
+                .value_name("RE,IM")
+                .value_parser(|s: &str| s.parse::<PreciseCenter>().map_err(|e| e.to_string()))
+                .allow_hyphen_values(true)
+                .help("Center of the image, as decimals of any length such as -0.75,0.1"),
+        )
+        .arg(
+            Arg::new("zoom")
+                .long("zoom")
+                .value_parser(parse_zoom)
+                .help("Magnification of the image, 1 shows the whole set"),
+        )
+        .arg(
+            Arg::new("palette")
+                .long("palette")
+                .value_parser(["grayscale", "fire", "ocean", "rainbow", "twilight"])
+                .help("Colors the image with a built-in palette"),
+        )
+        .arg(
+            Arg::new("palette-cycle")
+                .long("palette-cycle")
+                .value_name("ITERATIONS")
+                .value_parser(parse_period)
+                .help(
+                    "Repeats the palette every ITERATIONS iterations instead of spreading it over \
+                     the iteration limit, bringing out the boundary of deep zooms",
+                ),
+        )
+        .arg(
+            Arg::new("palette-offset")
+                .long("palette-offset")
+                .value_name("ITERATIONS")
+                .value_parser(value_parser!(f64))
+                .allow_negative_numbers(true)
+                .requires("palette-cycle")
+                .help("Shifts the colors of --palette-cycle by ITERATIONS iterations"),
+        )
+        .arg(Arg::new("color-cycle").long("color-cycle").action(ArgAction::SetTrue).help(
+            "Renders the image once and saves it as an animated PNG, or GIF for a .gif --output, \
+             whose palette turns through --palette-cycle iterations (default: the iteration \
+             limit) over --frames frames",
+        ))
+        .arg(
+            Arg::new("palette-file")
+                .long("palette-file")
+                .value_name("FILE")
+                .conflicts_with("palette")
+                .help(
+                    "Colors the image with the palette in FILE: a Fractint .map, GIMP .gpl or \
+                     Ultra Fractal .ugr file, or an image whose first row is the gradient",
+                ),
+        )
+        .arg(
+            Arg::new("color-mode")
+                .long("color-mode")
+                .value_parser(ColorMode::ALL.map(|mode| mode.name()))
+                .help(
+                    "Shades images by escape time, by the estimated distance to the set, by how \
+                     close orbits come to a trap shape, as a lit relief, or by the stripe or \
+                     triangle inequality average of the orbits",
+                ),
+        )
+        .arg(
+            Arg::new("light-azimuth")
+                .long("light-azimuth")
+                .value_name("DEGREES")
+                .value_parser(value_parser!(f64))
+                .allow_negative_numbers(true)
+                .help(format!(
+                    "Direction the light of --color-mode shaded comes from, counterclockwise from \
+                     the positive real axis [default: {DEFAULT_AZIMUTH}]"
+                )),
+        )
+        .arg(
+            Arg::new("light-elevation")
+                .long("light-elevation")
+                .value_name("DEGREES")
+                .value_parser(parse_elevation)
+                .help(format!(
+                    "Height of the light of --color-mode shaded above the plane, from 0 to 90 \
+                     [default: {DEFAULT_ELEVATION}]"
+                )),
+        )
+        .arg(
+            Arg::new("interior")
+                .long("interior")
+                .value_parser(InteriorColoring::ALL.map(|coloring| coloring.name()))
+                .help(
+                    "Colors the points inside the set apart from the rest: with a solid color, by \
+                     their distance to the boundary, or by how close their orbits come to a trap",
+                ),
+        )
+        .arg(
+            Arg::new("interior-color")
+                .long("interior-color")
+                .value_name("RRGGBB")
+                .value_parser(parse_color)
+                .help("Color of the points inside the set, implies --interior solid"),
+        )
+        .arg(
+            Arg::new("banded")
+                .long("banded")
+                .action(ArgAction::SetTrue)
+                .help("Shades the image by whole iteration counts instead of smooth gradients"),
+        )
+        .arg(
+            Arg::new("power")
+                .long("power")
+                .value_name("D")
+                .value_parser(parse_power)
+                .help("Renders the Multibrot set of z^D + c instead of the Mandelbrot set"),
+        )
+        .arg(
+            Arg::new("formula")
+                .long("formula")
+                .value_name("EXPR")
+                .value_parser(parse_formula)
+                .conflicts_with("power")
+                .help("Renders the escape-time fractal of z -> EXPR, such as \"z^3 + c*conj(z)\""),
+        )
+        .arg(
+            Arg::new("precision")
+                .long("precision")
+                .value_name("auto|double|perturbation|DIGITS")
+                .value_parser(parse_precision)
+                .help(
+                    "Arithmetic of the image; auto switches to arbitrary precision for deep zooms",
+                ),
+        )
+        .arg(
+            Arg::new("preset")
+                .long("preset")
+                .value_parser(PRESETS)
+                .help("Starts from a named view of the set instead of the default one"),
+        )
+        .arg(Arg::new("fit").long("fit").value_parser(FitMode::ALL.map(|fit| fit.name())).help(
+            "How the view fits images that are not 7:4: widened to show all of it (default), \
+             cropped to fill the image, or stretched",
+        ))
+        .arg(
+            Arg::new("params")
+                .long("params")
+                .value_name("PATH")
+                .help("Renders the location of a Kalles Fraktaler .kfr or Fractint .par file"),
+        )
+        .arg(
+            Arg::new("html")
+                .long("html")
+                .value_name("PATH")
+                .num_args(0..=1)
+                .default_missing_value(DEFAULT_HTML_PATH)
+                .help("Generates the Mandelbrot set as colored ASCII art and saves to HTML file"),
+        )
+        .arg(
+            Arg::new("play")
+                .long("play")
+                .action(ArgAction::SetTrue)
+                .help("Plays a zoom into the Mandelbrot set as ASCII animation in the terminal"),
+        )
+        .arg(
+            Arg::new("zoom-animate")
+                .long("zoom-animate")
+                .value_name("DIR")
+                .num_args(0..=1)
+                .default_missing_value(DEFAULT_ANIMATION_DIR)
+                .help(
+                    "Renders a zoom towards --center and --zoom, or the keyframes of --dive, as \
+                     numbered PNG frames in DIR, or as DIR.gif, DIR.mp4 or DIR.webm with --format \
+                     gif, mp4 or webm",
+                ),
+        )
+        .arg(Arg::new("dive").long("dive").value_name("FILE").requires("zoom-animate").help(
+            "Animates the keyframes of the dive FILE (TOML or JSON) with --zoom-animate, at the \
+             fps of the dive unless --fps is given",
+        ))
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .value_parser(["png", "gif", "mp4", "webm", "raw", "csv", "exr", "svg", "sixel"])
+                .help(
+                    "Output format: png, gif, mp4 or webm (encoded by ffmpeg) for --zoom-animate, \
+                     raw or csv to write the escape counts of --image and --output instead of an \
+                     image, exr to write their escape values as floats, svg to write their \
+                     iso-iteration contours, sixel to draw the image in the terminal",
+                ),
+        )
+        .arg(Arg::new("pixel-info").long("pixel-info").action(ArgAction::SetTrue).help(
+            "With --format raw or csv, writes the statistics of every orbit (escape count, smooth \
+             escape value, last and smallest |z|, derivative dz/dc) instead of the escape counts",
+        ))
+        .arg(Arg::new("bit-depth").long("bit-depth").value_parser(["8", "16"]).help(
+            "Bits per pixel of PNG images; 16 stores escape counts up to 65535 as gray levels",
+        ))
+        .arg(
+            Arg::new("frames")
+                .long("frames")
+                .value_parser(value_parser!(u32).range(1..))
+                .help("Number of frames rendered by --play, --zoom-animate and --color-cycle"),
+        )
+        .arg(
+            Arg::new("fps").long("fps").value_parser(value_parser!(u32).range(1..)).help(
+                "Frames per second used by --play, --color-cycle and --format gif, mp4 or webm",
+            ),
+        )
+        .arg(
+            Arg::new("bitrate")
+                .long("bitrate")
+                .value_name("KBPS")
+                .value_parser(value_parser!(u32).range(1..))
+                .help(
+                    "Video bitrate in kilobits per second for --format mp4 or webm [default: \
+                     constant quality]",
+                ),
+        )
+        .arg(
+            Arg::new("fresh")
+                .long("fresh")
+                .action(ArgAction::SetTrue)
+                .help("Ignores the saved session and starts from the default view"),
+        )
+        .arg(
+            Arg::new("backend")
+                .long("backend")
+                .value_parser(Backend::ALL.iter().map(Backend::name).collect::<Vec<_>>())
+                .help("Computes the images that explore saves with this backend"),
+        )
+        .subcommand(Command::new("explore").about(
+            "Explores the set interactively in the terminal: arrow keys pan, +/- zoom, i/I change \
+             the iterations, s saves the view as an image colored with --palette",
+        ))
+        .subcommand(
+            Command::new("bench")
+                .about(
+                    "Renders standard scenes with every backend compiled in and prints how many \
+                     pixels per second each renders",
+                )
+                .arg(
+                    Arg::new("width")
+                        .long("width")
+                        .value_parser(value_parser!(u32).range(1..))
+                        .default_value("400")
+                        .help("Width of the benchmark renders"),
+                )
+                .arg(
+                    Arg::new("height")
+                        .long("height")
+                        .value_parser(value_parser!(u32).range(1..))
+                        .default_value("300")
+                        .help("Height of the benchmark renders"),
+                )
+                .arg(
+                    Arg::new("runs")
+                        .long("runs")
+                        .value_parser(value_parser!(u32).range(1..))
+                        .default_value("3")
+                        .help("Renders per scene and backend; the fastest is reported"),
+                ),
+        )
+        .subcommand(
+            Command::new("serve")
+                .about(
+                    "Serves renders and map tiles over HTTP on localhost, with a map of the set \
+                     at /",
+                )
+                .arg(
+                    Arg::new("port")
+                        .long("port")
+                        .value_parser(value_parser!(u16))
+                        .default_value("8080")
+                        .help("Port to listen on"),
+                ),
+        )
+        .subcommand(
+            Command::new("tiles")
+                .about("Precomputes map tiles for static hosting")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("pyramid")
+                        .about(
+                            "Renders every map tile down to --max-zoom into DIR/{z}/{x}/{y}.png, \
+                             skipping the tiles that already exist",
+                        )
+                        .arg(
+                            Arg::new("max-zoom")
+                                .long("max-zoom")
+                                .value_parser(
+                                    value_parser!(u32).range(..=pyramid::MAX_TILE_ZOOM as i64),
+                                )
+                                .required(true)
+                                .help("Deepest zoom level to render; level z has 4^z tiles"),
+                        )
+                        .arg(
+                            Arg::new("output")
+                                .long("output")
+                                .value_name("DIR")
+                                .required(true)
+                                .help("Directory of the pyramid"),
+                        )
+                        .arg(
+                            Arg::new("iterations")
+                                .long("iterations")
+                                .value_parser(value_parser!(u32).range(1..))
+                                .help(
+                                    "Iteration limit of every tile [default: suggested per level]",
+                                ),
+                        )
+                        .arg(
+                            Arg::new("palette")
+                                .long("palette")
+                                .value_parser(BuiltinPalette::ALL.map(|palette| palette.name()))
+                                .help("Palette of the tiles [default: gray]"),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("newton")
+                .about(
+                    "Renders the Newton fractal of a polynomial, coloring every starting point by \
+                     the root Newton's method takes it to",
+                )
+                .arg(
+                    Arg::new("coefficients")
+                        .value_name("COEFFICIENTS")
+                        .value_parser(parse_polynomial)
+                        .allow_hyphen_values(true)
+                        .required(true)
+                        .help(
+                            "Coefficients of the polynomial from the highest degree down, such as \
+                             1,0,0,-1 for z^3 - 1; complex ones are written like 2+1i",
+                        ),
+                )
+                .arg(
+                    Arg::new("width")
+                        .long("width")
+                        .value_parser(value_parser!(u32).range(1..))
+                        .default_value("800")
+                        .help("Width of the image"),
+                )
+                .arg(
+                    Arg::new("height")
+                        .long("height")
+                        .value_parser(value_parser!(u32).range(1..))
+                        .default_value("800")
+                        .help("Height of the image"),
+                )
+                .arg(
+                    Arg::new("iterations")
+                        .long("iterations")
+                        .value_parser(value_parser!(u32).range(1..))
+                        .help(format!(
+                            "Steps of Newton's method before a point is given up [default: {}]",
+                            newton::DEFAULT_NEWTON_ITERATIONS
+                        )),
+                )
+                .arg(
+                    Arg::new("zoom").long("zoom").value_parser(parse_zoom).default_value("1").help(
+                        format!(
+                            "Magnification of the image, 1 shows {0} by {0} around the origin",
+                            newton::DEFAULT_SPAN
+                        ),
+                    ),
+                )
+                .arg(
+                    Arg::new("palette")
+                        .long("palette")
+                        .value_parser(BuiltinPalette::ALL.map(|palette| palette.name()))
+                        .default_value("rainbow")
+                        .help("Palette the roots take their colors from"),
+                )
+                .arg(
+                    Arg::new("output")
+                        .long("output")
+                        .value_name("PATH")
+                        .value_parser(|path: &str| output_format(path).map(|_| path.to_string()))
+                        .default_value("newton.png")
+                        .help("Saves the image to PATH"),
+                ),
+        )
+        .subcommand(
+            Command::new("stats")
+                .about(
+                    "Renders escape counts without an image and reports how they are spread, to \
+                     tune the iteration limit before a large render",
+                )
+                .arg(
+                    Arg::new("width")
+                        .long("width")
+                        .value_parser(value_parser!(u32).range(1..))
+                        .help(format!("Width of the render [default: {WIDTH}]")),
+                )
+                .arg(
+                    Arg::new("height")
+                        .long("height")
+                        .value_parser(value_parser!(u32).range(1..))
+                        .help(format!("Height of the render [default: {HEIGHT}]")),
+                )
+                .arg(
+                    Arg::new("iterations")
+                        .long("iterations")
+                        .value_parser(value_parser!(u32).range(1..))
+                        .help(format!("Maximum number of iterations [default: {ITERATIONS}]")),
+                )
+                .arg(
+                    Arg::new("center")
+                        .long("center")
+                        .value_name("RE,IM")
+                        .value_parser(|s: &str| {
+                            s.parse::<PreciseCenter>().map_err(|e| e.to_string())
+                        })
+                        .allow_hyphen_values(true)
+                        .help("Center of the render, such as -0.75,0.1"),
+                )
+                .arg(
+                    Arg::new("zoom")
+                        .long("zoom")
+                        .value_parser(parse_zoom)
+                        .help("Magnification of the render, 1 shows the whole set"),
+                )
+                .arg(
+                    Arg::new("preset")
+                        .long("preset")
+                        .value_parser(PRESETS)
+                        .help("Starts from a named view of the set instead of the default one"),
+                )
+                .arg(
+                    Arg::new("power")
+                        .long("power")
+                        .value_name("D")
+                        .value_parser(parse_power)
+                        .help("Renders the Multibrot set of z^D + c instead of the Mandelbrot set"),
+                )
+                .arg(
+                    Arg::new("bins").long("bins").value_parser(value_parser!(u32).range(1..)).help(
+                        format!(
+                            "Number of bins of the iteration histogram [default: {}]",
+                            stats::DEFAULT_BINS
+                        ),
+                    ),
+                )
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .action(ArgAction::SetTrue)
+                        .help("Prints the report as JSON instead of a table"),
+                ),
+        )
+        .subcommand(
+            Command::new("inspect")
+                .about(
+                    "Prints the render parameters stored in IMAGE, or in its JSON sidecar, as a \
+                     scene file",
+                )
+                .arg(Arg::new("image").value_name("IMAGE").required(true)),
+        )
         .after_help(
             "Longer explanation to appear after the options when displaying the help information \
              from --help or -h",
         )
         .get_matches();
 
-    if let Some(_ascii) = matches.get_one::<String>("ascii") {
+    if matches.subcommand_matches("explore").is_some() {
+        return explore(&matches);
+    }
+    if let Some(bench_matches) = matches.subcommand_matches("bench") {
+        let arg = |name| *bench_matches.get_one::<u32>(name).expect("the argument has a default");
+        return bench(arg("width"), arg("height"), arg("runs"));
+    }
+    if let Some(serve_matches) = matches.subcommand_matches("serve") {
+        return serve(*serve_matches.get_one::<u16>("port").expect("the argument has a default"));
+    }
+    if let Some(("pyramid", pyramid_matches)) =
+        matches.subcommand_matches("tiles").and_then(ArgMatches::subcommand)
+    {
+        let opts = PyramidOptions {
+            iterations: pyramid_matches.get_one::<u32>("iterations").copied(),
+            palette: pyramid_matches
+                .get_one::<String>("palette")
+                .and_then(|name| BuiltinPalette::parse(name)),
+        };
+        let max_zoom = *pyramid_matches.get_one::<u32>("max-zoom").expect("--max-zoom is required");
+        let dir = pyramid_matches.get_one::<String>("output").expect("--output is required");
+        return tile_pyramid(Path::new(dir), max_zoom, &opts);
+    }
+    if let Some(newton_matches) = matches.subcommand_matches("newton") {
+        return newton_fractal(newton_matches);
+    }
+    if let Some(stats_matches) = matches.subcommand_matches("stats") {
+        return render_stats(stats_matches);
+    }
+    if let Some(inspect_matches) = matches.subcommand_matches("inspect") {
+        let image = inspect_matches.get_one::<String>("image").expect("IMAGE is required");
+        return inspect(Path::new(image));
+    }
+    if let Some(path) = matches.get_one::<String>("resume") {
+        return resume(Path::new(path), &matches);
+    }
+
+    if matches.get_flag("ascii") {
         info!("Rendering image Mandelbrot set as {}", Style::new().bold().apply_to("ASCII"));
-        let pb = ProgressBar::new(WIDTH as u64 * HEIGHT as u64);
+        let support = match matches.get_one::<String>("color").map(String::as_str) {
+            None => ColorSupport::Plain,
+            Some("auto") => ColorSupport::detect(),
+            Some(name) => ColorSupport::parse(name).unwrap_or_default(),
+        };
+        let pb = ProgressBar::new(ASCII_AREA);
         style_progress_bar(&pb);
-        let image = mandelbrot_ascii::collect_ascii();
-        pb.finish();
-        mandelbrot_ascii::print_ascii(image);
+        if support == ColorSupport::Plain {
+            let image = render_ascii(&matches, &pb, true);
+            pb.finish();
+            mandelbrot_ascii::print_ascii(&image)?;
+        } else {
+            print_colored_ascii(&matches, support, &pb)?;
+        }
     }
 
-    if let Some(_text) = matches.get_one::<String>("text") {
+    let text_output = matches.get_one::<String>("text-output");
+    if matches.get_one::<String>("text").is_some() || text_output.is_some() {
         info!(
             "Rendering image Mandelbrot set as {} and saving to file",
             Style::new().bold().apply_to("ASCII")
         );
-        let pb = ProgressBar::new(WIDTH as u64 * HEIGHT as u64);
+        let pb = ProgressBar::new(ASCII_AREA);
         style_progress_bar(&pb);
-        let image = mandelbrot_ascii::collect_ascii();
-        mandelbrot_ascii::write_ascii_to_file(image, Path::new(&config_manager.text));
+        let image = render_ascii(&matches, &pb, false);
+        let path = text_output.unwrap_or(&app_config.text);
+        let mode =
+            if matches.get_flag("append") { WriteMode::Append } else { WriteMode::Overwrite };
+        mandelbrot_ascii::write_ascii_to_file(&image, path, mode)?;
         pb.finish_with_message("Wrote ascii to file");
     }
 
-    if let Some(_image) = matches.get_one::<String>("image") {
+    let output = matches.get_one::<String>("output");
+    let iterations_arg = iteration_limit(&matches);
+    let auto_iterations =
+        matches.get_one::<IterationLimit>("iterations") == Some(&IterationLimit::Auto);
+    let color_mode_arg = match matches
+        .get_one::<String>("color-mode")
+        .and_then(|name| ColorMode::parse(name))
+        .unwrap_or_default()
+    {
+        ColorMode::Shaded { azimuth, elevation } => ColorMode::Shaded {
+            azimuth: matches.get_one::<f64>("light-azimuth").copied().unwrap_or(azimuth),
+            elevation: matches.get_one::<f64>("light-elevation").copied().unwrap_or(elevation),
+        },
+        mode => mode,
+    };
+    let interior = match matches.get_one::<[u8; 3]>("interior-color") {
+        Some(&color) => InteriorColoring::Solid(color),
+        None => matches
+            .get_one::<String>("interior")
+            .and_then(|name| InteriorColoring::parse(name))
+            .unwrap_or_default(),
+    };
+    let sixel = matches.get_one::<String>("format").is_some_and(|format| format == "sixel");
+    let scene = match matches.get_one::<String>("scene") {
+        Some(path) => Some(Scene::load(path)?),
+        None => None,
+    };
+    if matches.get_one::<String>("image").is_some()
+        || output.is_some()
+        || sixel
+        || scene.is_some()
+        || matches.get_flag("color-cycle")
+    {
         info!(
             "Rendering image Mandelbrot set as {} and saving to file",
             Style::new().bold().apply_to("image")
         );
-        let pb = ProgressBar::new(WIDTH as u64 * HEIGHT as u64);
+        let width = matches
+            .get_one::<u32>("width")
+            .copied()
+            .or(scene.as_ref().map(|scene| scene.width))
+            .or(app_config.width)
+            .unwrap_or(WIDTH);
+        let height = matches
+            .get_one::<u32>("height")
+            .copied()
+            .or(scene.as_ref().map(|scene| scene.height))
+            .or(app_config.height)
+            .unwrap_or(HEIGHT);
+        let pb = ProgressBar::new(width as u64 * height as u64);
+        style_progress_bar(&pb);
+        let path = match (output, app_config.image.as_str()) {
+            (Some(output), _) => output.as_str(),
+            (None, "") => DEFAULT_IMAGE_PATH,
+            (None, path) => path,
+        };
+        let data = matches
+            .get_one::<String>("format")
+            .map(String::as_str)
+            .filter(|format| DATA_FORMATS.contains(format))
+            .or_else(|| data_format(path));
+        let format = match data {
+            Some(_) => None,
+            None if sixel => None,
+            None => Some(output_format(path).map_err(anyhow::Error::msg)?),
+        };
+        let projection = match matches.get_one::<Mobius>("mobius") {
+            Some(mobius) => Projection::Mobius(*mobius),
+            None => matches
+                .get_one::<String>("projection")
+                .and_then(|name| Projection::parse(name))
+                .unwrap_or_default(),
+        };
+        let stereo = matches.get_one::<String>("stereo").and_then(|name| StereoLayout::parse(name));
+        let mut stereo_opts = StereoOptions::default();
+        if let Some(parallax) = matches.get_one::<f64>("parallax") {
+            stereo_opts.parallax = *parallax;
+        }
+        let params = match matches.get_one::<String>("params") {
+            Some(path) => Some(load_params(Path::new(path))?),
+            None => None,
+        };
+        let (mut viewport, mut iterations) = params
+            .or_else(|| scene.as_ref().map(|scene| (scene.viewport, scene.iterations)))
+            .unwrap_or((
+                app_config.viewport.apply(projection.default_viewport()),
+                app_config.iterations.unwrap_or(ITERATIONS),
+            ));
+        if let Some(limit) = iterations_arg {
+            iterations = limit;
+        }
+        if let Some(preset) =
+            matches.get_one::<String>("preset").and_then(|name| Viewport::preset(name))
+        {
+            viewport = preset;
+        }
+        let center = matches.get_one::<PreciseCenter>("center");
+        if let Some(center) = center {
+            let planar = projection == Projection::Planar && stereo.is_none();
+            if center.digits() > F64_DIGITS && !planar {
+                warn!(
+                    "The center has {} significant digits, but projected images are rendered with \
+                     f64 precision",
+                    center.digits()
+                );
+            }
+            (viewport.center_x, viewport.center_y) = center.to_f64();
+        }
+        if let Some(zoom) = matches.get_one::<f64>("zoom") {
+            viewport.zoom = *zoom;
+        }
+        if let Some(precision) = matches.get_one::<Precision>("precision") {
+            viewport.precision = *precision;
+        }
+        if let Some(fit) = matches.get_one::<String>("fit").and_then(|name| FitMode::parse(name)) {
+            viewport.fit = fit;
+        }
+        let builtin_palette = matches
+            .get_one::<String>("palette")
+            .and_then(|name| BuiltinPalette::parse(name))
+            .or_else(|| scene.as_ref().and_then(Scene::palette))
+            .or_else(|| app_config.palette.as_deref().and_then(BuiltinPalette::parse));
+        let palette_file = matches.get_one::<String>("palette-file");
+        let gradient = match palette_file {
+            Some(file) => Some(Gradient::load(file)?),
+            None => builtin_palette.map(|palette| palette.gradient()),
+        };
+        let kind = matches
+            .get_one::<FractalKind>("power")
+            .copied()
+            .or(scene.as_ref().map(|scene| scene.kind))
+            .unwrap_or_default();
+        if kind != FractalKind::Mandelbrot && (projection != Projection::Planar || stereo.is_some())
+        {
+            warn!("--power only applies to planar renders, rendering the Mandelbrot set");
+        }
+        let formula = matches.get_one::<Formula>("formula");
+        if formula.is_some()
+            && (projection != Projection::Planar
+                || stereo.is_some()
+                || color_mode_arg != ColorMode::EscapeTime)
+        {
+            anyhow::bail!("--formula only renders planar escape-time images");
+        }
+        if formula.is_some()
+            && matches!(interior, InteriorColoring::Distance | InteriorColoring::OrbitTrap(_))
+        {
+            warn!("--interior {} follows the Mandelbrot set, not --formula", interior.name());
+        }
+        if auto_iterations {
+            iterations = viewport.suggested_iterations();
+        }
+        if matches.get_flag("refine-iterations") {
+            let opts = RenderOptions::new(iterations).with_kind(kind);
+            iterations = refine_iterations(width, height, &viewport, &opts);
+        }
+        if auto_iterations || matches.get_flag("refine-iterations") {
+            info!("Rendering with {iterations} iterations");
+        }
+        // Color cycling needs a palette; Twilight's ends meet, so it cycles without seams.
+        let cycle_gradient =
+            gradient.clone().unwrap_or_else(|| BuiltinPalette::Twilight.gradient());
+        let palette = gradient.map(|gradient| -> Box<dyn Palette> {
+            match matches.get_one::<f64>("palette-cycle") {
+                Some(&period) => {
+                    let offset = matches.get_one::<f64>("palette-offset").copied().unwrap_or(0.0);
+                    Box::new(CyclicPalette::new(gradient, period, iterations).with_offset(offset))
+                }
+                None => Box::new(gradient),
+            }
+        });
+        let palette = palette.as_deref();
+        let rendered_scene = Scene {
+            width,
+            height,
+            iterations,
+            // Scenes only name built-in palettes.
+            palette: builtin_palette
+                .filter(|_| palette_file.is_none())
+                .map(|palette| palette.name().to_string()),
+            kind,
+            viewport,
+        };
+        let render_opts = RenderOptions::new(iterations)
+            .with_smooth(!matches.get_flag("banded"))
+            .with_kind(kind)
+            .with_optimizations(true)
+            .with_color_mode(color_mode_arg)
+            .with_strategy(
+                matches
+                    .get_one::<String>("strategy")
+                    .and_then(|name| RenderStrategy::parse(name))
+                    .unwrap_or_default(),
+            )
+            .with_symmetry(!matches.get_flag("no-symmetry"));
+        // The arbitrary-precision kernels only iterate the Mandelbrot set.
+        let center = center.filter(|_| kind == FractalKind::Mandelbrot);
+        let escapes = || match (formula, center, viewport.resolved_precision(width, height)) {
+            (Some(formula), ..) => {
+                Ok(render_escape_time(width, height, &viewport, iterations, |z, c| {
+                    formula.eval(z, c)
+                }))
+            }
+            (_, Some(center), Precision::Arbitrary { digits }) => {
+                Ok(render_arbitrary(width, height, &viewport, center, &render_opts, digits))
+            }
+            (_, Some(center), Precision::Perturbation) => {
+                let digits = viewport.required_digits(width, height);
+                let (escapes, stats) =
+                    render_perturbed(width, height, &viewport, center, &render_opts, digits);
+                if stats.glitched > 0 {
+                    warn!("{} pixels are still glitched", stats.glitched);
+                }
+                Ok(escapes)
+            }
+            // Checkpoints store rows in the order they are rendered; subdivided renders finish
+            // tiles instead, and mirrored renders copy the bottom rows at the end.
+            _ if render_opts.strategy == RenderStrategy::BoundaryTrace
+                || (render_opts.symmetry && viewport.is_symmetric()) =>
+            {
+                Ok(escape_viewport_with_progress(
+                    width,
+                    height,
+                    &viewport,
+                    &render_opts,
+                    track_progress(&pb),
+                ))
+            }
+            _ => Checkpoint::new(width, height, viewport, render_opts).render_with_progress(
+                checkpoint_path(path),
+                CHECKPOINT_INTERVAL,
+                track_progress(&pb),
+            ),
+        };
+        if interior != InteriorColoring::Palette
+            && (projection != Projection::Planar || stereo.is_some())
+        {
+            warn!("--interior only applies to planar renders, ignoring it");
+        }
+        // Shading strategies without a palette shade in gray, like the rest of the image.
+        let interior_palette = palette.unwrap_or(&BuiltinPalette::Grayscale);
+        let with_interior = |mut image: RgbImage, inside: &[bool]| {
+            color_interior(&mut image, inside, &viewport, &render_opts, interior, interior_palette);
+            image
+        };
+        let render = || -> io::Result<RgbImage> {
+            Ok(match projection {
+                _ if stereo.is_some() => {
+                    let opts = StereoOptions { layout: stereo.unwrap_or_default(), ..stereo_opts };
+                    compose_stereo(width, height, iterations, &viewport, &opts)
+                }
+                Projection::Planar => match render_opts.color_mode {
+                    ColorMode::EscapeTime => {
+                        let escapes = escapes()?;
+                        let image = match palette {
+                            Some(palette) => {
+                                colorize_escapes(width, height, &escapes, &render_opts, palette)
+                            }
+                            None => shade_escapes(width, height, &escapes, &render_opts),
+                        };
+                        let inside: Vec<bool> = escapes.iter().map(|r| !r.escaped).collect();
+                        with_interior(image, &inside)
+                    }
+                    ColorMode::DistanceEstimate => {
+                        let distances = distance_viewport_with_progress(
+                            width,
+                            height,
+                            &viewport,
+                            iterations,
+                            track_progress(&pb),
+                        );
+                        let pixel_size = viewport.spans(width, height).0 / width as f64;
+                        let image = match palette {
+                            Some(palette) => {
+                                let values: Vec<f64> = distances
+                                    .iter()
+                                    .map(|&d| distance_value(d, pixel_size))
+                                    .collect();
+                                colorize_values(width, height, &values, palette)
+                            }
+                            None => shade_distances(width, height, &distances, pixel_size),
+                        };
+                        // Points that never escape have no exterior distance.
+                        let inside: Vec<bool> = distances.iter().map(|&d| d == 0.0).collect();
+                        with_interior(image, &inside)
+                    }
+                    ColorMode::OrbitTrap(shape) => {
+                        let distances = trap_viewport_with_progress(
+                            width,
+                            height,
+                            &viewport,
+                            &render_opts,
+                            shape,
+                            track_progress(&pb),
+                        );
+                        let image = match palette {
+                            Some(palette) => {
+                                let values: Vec<f64> =
+                                    distances.into_iter().map(trap_value).collect();
+                                colorize_values(width, height, &values, palette)
+                            }
+                            None => shade_traps(width, height, &distances),
+                        };
+                        if interior == InteriorColoring::Palette {
+                            image
+                        } else {
+                            // Traps do not record which orbits escaped.
+                            let escapes = escape_viewport(width, height, &viewport, &render_opts);
+                            let inside: Vec<bool> = escapes.iter().map(|r| !r.escaped).collect();
+                            with_interior(image, &inside)
+                        }
+                    }
+                    ColorMode::Shaded { azimuth, elevation } => {
+                        let normals = normal_viewport_with_progress(
+                            width,
+                            height,
+                            &viewport,
+                            iterations,
+                            track_progress(&pb),
+                        );
+                        let image = match palette {
+                            Some(palette) => {
+                                let values: Vec<f64> = normals
+                                    .iter()
+                                    .map(|&normal| light_value(normal, azimuth, elevation))
+                                    .collect();
+                                colorize_values(width, height, &values, palette)
+                            }
+                            None => shade_normals(width, height, &normals, azimuth, elevation),
+                        };
+                        let inside: Vec<bool> = normals.iter().map(Option::is_none).collect();
+                        with_interior(image, &inside)
+                    }
+                    ColorMode::Average(coloring) => {
+                        let averages = average_viewport_with_progress(
+                            width,
+                            height,
+                            &viewport,
+                            &render_opts,
+                            coloring,
+                            track_progress(&pb),
+                        );
+                        let image = match palette {
+                            Some(palette) => {
+                                let values: Vec<f64> =
+                                    averages.iter().map(|average| average.unwrap_or(0.0)).collect();
+                                colorize_values(width, height, &values, palette)
+                            }
+                            None => shade_averages(width, height, &averages),
+                        };
+                        let inside: Vec<bool> = averages.iter().map(Option::is_none).collect();
+                        with_interior(image, &inside)
+                    }
+                },
+                _ => compose_projected(width, height, iterations, &viewport, &projection),
+            })
+        };
+        if let Some(data) = data {
+            if projection != Projection::Planar || stereo.is_some() {
+                anyhow::bail!("--format {data} only supports the planar projection");
+            }
+            let path = Path::new(path).with_extension(data);
+            if matches.get_flag("pixel-info") {
+                if data != "raw" && data != "csv" {
+                    anyhow::bail!("--pixel-info only writes raw or csv files");
+                }
+                if formula.is_some() {
+                    anyhow::bail!("--pixel-info does not support --formula");
+                }
+                let buffer = PixelInfoBuffer::render_with_progress(
+                    width,
+                    height,
+                    &viewport,
+                    &render_opts,
+                    track_progress(&pb),
+                );
+                let mut file = io::BufWriter::new(fs::File::create(&path)?);
+                match data {
+                    "raw" => buffer.write_raw(&mut file)?,
+                    _ => buffer.write_csv(&mut file)?,
+                }
+                file.flush()?;
+            } else if data == "exr" {
+                shade_escapes_f32(width, height, &escapes()?, &render_opts).save(path)?;
+            } else {
+                let buffer =
+                    IterationBuffer::from_escapes(width, height, &escapes()?, &render_opts);
+                let mut file = io::BufWriter::new(fs::File::create(&path)?);
+                match data {
+                    "raw" => buffer.write_raw(&mut file)?,
+                    "svg" => contour::write_svg(&buffer, &default_levels(iterations), &mut file)?,
+                    _ => buffer.write_csv(&mut file)?,
+                }
+                file.flush()?;
+            }
+        } else if matches.get_flag("color-cycle") {
+            let planar = projection == Projection::Planar && stereo.is_none();
+            if !planar || render_opts.color_mode != ColorMode::EscapeTime {
+                anyhow::bail!("--color-cycle only animates planar escape-time images");
+            }
+            if !matches!(format, Some(OutputFormat::Png | OutputFormat::Gif)) {
+                anyhow::bail!("--color-cycle writes animated PNG or GIF images");
+            }
+            let period =
+                matches.get_one::<f64>("palette-cycle").copied().unwrap_or(iterations as f64);
+            let offset = matches.get_one::<f64>("palette-offset").copied().unwrap_or(0.0);
+            let cycle = ColorCycle {
+                width,
+                height,
+                escapes: escapes()?,
+                options: render_opts,
+                palette: CyclicPalette::new(cycle_gradient, period, iterations).with_offset(offset),
+                frames: matches.get_one::<u32>("frames").copied().unwrap_or(ANIMATION_FRAMES),
+            };
+            let fps = matches.get_one::<u32>("fps").copied().unwrap_or(COLOR_CYCLE_FPS);
+            pb.reset();
+            pb.set_length(cycle.frames as u64);
+            let on_frame = |done, _| pb.set_position(done as u64);
+            if format == Some(OutputFormat::Gif) {
+                cycle.render_gif_with_progress(path, &GifOptions::with_fps(fps), on_frame)?;
+            } else {
+                cycle.render_apng_with_progress(path, fps, on_frame)?;
+            }
+        } else if sixel {
+            let image = render()?;
+            pb.finish_and_clear();
+            let mut out = io::stdout().lock();
+            out.write_all(sixel::encode_sixel(&image).as_bytes())?;
+            writeln!(out)?;
+        } else if matches.get_flag("stream") {
+            let planar = projection == Projection::Planar && stereo.is_none();
+            if format != Some(OutputFormat::Png)
+                || !planar
+                || render_opts.color_mode != ColorMode::EscapeTime
+            {
+                anyhow::bail!("--stream only writes planar escape-time PNG images");
+            }
+            if matches!(
+                viewport.resolved_precision(width, height),
+                Precision::Arbitrary { .. } | Precision::Perturbation
+            ) {
+                warn!("Streamed rows are rendered with f64 precision");
+            }
+            let stream = RowStream::new(width, height, viewport, render_opts);
+            let file = io::BufWriter::new(fs::File::create(path)?);
+            match palette {
+                Some(palette) => stream.write_png_with_palette(file, palette)?,
+                None => stream.write_png(file)?,
+            }
+        } else if let Some(grid) = matches.get_one::<TileGrid>("tiles") {
+            let planar = projection == Projection::Planar && stereo.is_none();
+            if !planar || render_opts.color_mode != ColorMode::EscapeTime {
+                anyhow::bail!("--tiles only renders planar escape-time images");
+            }
+            if !grid.fits(width, height) {
+                anyhow::bail!(
+                    "--tiles {}x{} has more tiles than the {width}x{height} image has pixels",
+                    grid.columns,
+                    grid.rows
+                );
+            }
+            let tiles: Vec<Tile> = match matches.get_one::<(u32, u32)>("tile-index") {
+                Some(&(column, row)) => {
+                    let tile = grid.tile(column, row, width, height).ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "--tile-index {column}/{row} is outside the {}x{} grid",
+                            grid.columns,
+                            grid.rows
+                        )
+                    })?;
+                    vec![tile]
+                }
+                None => grid.tiles(width, height).collect(),
+            };
+            // Tiles saved by another render of the same file are rendered again.
+            let rendered = |tile: &Tile| {
+                ImageMetadata::load(tile.path(path))
+                    .is_ok_and(|saved| saved.tile == Some(*tile) && saved.scene == rendered_scene)
+            };
+            let missing: Vec<&Tile> = tiles.iter().filter(|tile| !rendered(tile)).collect();
+            pb.set_length(missing.iter().map(|tile| tile.width as u64 * tile.height as u64).sum());
+            for tile in missing {
+                let escapes = render_tile(width, height, &viewport, &render_opts, tile);
+                let image = match palette {
+                    Some(palette) => {
+                        colorize_escapes(tile.width, tile.height, &escapes, &render_opts, palette)
+                    }
+                    None => shade_escapes(tile.width, tile.height, &escapes, &render_opts),
+                };
+                let metadata = ImageMetadata::new(rendered_scene.clone()).with_tile(*tile);
+                metadata::save_png(&image, &metadata, tile.path(path))?;
+                pb.inc(tile.width as u64 * tile.height as u64);
+            }
+            if tiles.len() as u64 == grid.len() {
+                let image = tiles::stitch_files(width, height, grid, path)?;
+                if format.unwrap_or_default() == OutputFormat::Png {
+                    metadata::save_png(&image, &ImageMetadata::new(rendered_scene.clone()), path)?;
+                } else {
+                    image.save(path)?;
+                }
+                for tile in &tiles {
+                    fs::remove_file(tile.path(path))?;
+                }
+            }
+        } else if matches.get_one::<String>("bit-depth").is_some_and(|depth| depth == "16") {
+            let planar = projection == Projection::Planar && stereo.is_none();
+            if format != Some(OutputFormat::Png)
+                || !planar
+                || render_opts.color_mode != ColorMode::EscapeTime
+            {
+                anyhow::bail!("--bit-depth 16 only writes planar escape-time PNG images");
+            }
+            if palette.is_some() {
+                warn!("16-bit images store gray escape counts; the palette is ignored");
+            }
+            shade_escapes_u16(width, height, &escapes()?, &render_opts).save(path)?;
+        } else if format == Some(OutputFormat::Ico) {
+            export::save_ico(&export::compose_icon(iterations), path)?;
+        } else if format == Some(OutputFormat::Jpeg) {
+            let image = render()?;
+            let mut jpeg = JpegOptions::default();
+            if let Some(quality) = matches.get_one::<u8>("quality") {
+                jpeg.quality = *quality;
+            }
+            if let Some(subsampling) = matches.get_one::<String>("subsampling") {
+                jpeg.subsampling = ChromaSubsampling::parse(subsampling).unwrap_or_default();
+            }
+            export::save_jpeg(&image, path, &jpeg)?;
+        } else if format.unwrap_or_default() == OutputFormat::Png {
+            metadata::save_png(&render()?, &ImageMetadata::new(rendered_scene.clone()), path)?;
+        } else {
+            export::save_as(&render()?, path, format.unwrap_or_default(), DEFAULT_JPEG_QUALITY)?;
+        }
+        if matches.get_flag("sidecar") && data.is_none() && !sixel {
+            ImageMetadata::new(rendered_scene).save_sidecar(path)?;
+        }
+        if !sixel {
+            pb.finish_with_message("Saved image to file");
+        }
+    }
+
+    if let Some(dir) = matches.get_one::<String>("cubemap") {
+        info!(
+            "Rendering image Mandelbrot set as {} and saving to {dir}",
+            Style::new().bold().apply_to("cubemap")
+        );
+        let iterations = iterations_arg.unwrap_or(ITERATIONS);
+        export::save_cubemap(dir, CUBEMAP_SIZE, iterations, &Viewport::default())?;
+    }
+
+    if let Some(dir) = matches.get_one::<String>("zoom-animate") {
+        let format = matches.get_one::<String>("format").map(String::as_str);
+        let video = format.and_then(VideoFormat::parse);
+        let kind = match (format, video) {
+            (Some("gif"), _) => "GIF".to_string(),
+            (_, Some(video)) => video.name().to_uppercase(),
+            _ => "PNG frames".to_string(),
+        };
+        let dive = matches.get_one::<String>("dive").map(Dive::load).transpose()?;
+        info!(
+            "Rendering {} into the Mandelbrot set as {} in {dir}",
+            if dive.is_some() { "dive" } else { "zoom" },
+            Style::new().bold().apply_to(kind)
+        );
+        let mut end = ZoomPlayback::default().target;
+        if let Some(center) = matches.get_one::<PreciseCenter>("center") {
+            (end.center_x, end.center_y) = center.to_f64();
+        }
+        if let Some(zoom) = matches.get_one::<f64>("zoom") {
+            end.zoom = *zoom;
+        }
+        let sequence = ZoomSequence {
+            start: Viewport::default(),
+            end,
+            frames: matches.get_one::<u32>("frames").copied().unwrap_or(ANIMATION_FRAMES),
+            width: matches.get_one::<u32>("width").copied().unwrap_or(WIDTH),
+            height: matches.get_one::<u32>("height").copied().unwrap_or(HEIGHT),
+            options: RenderOptions::new(iterations_arg.unwrap_or(ITERATIONS))
+                .with_smooth(!matches.get_flag("banded"))
+                .with_kind(matches.get_one::<FractalKind>("power").copied().unwrap_or_default())
+                .with_optimizations(true),
+        };
+        // A dive brings its own path, length and speed.
+        let dive = dive.as_ref().map(|dive| DiveSequence {
+            dive,
+            width: sequence.width,
+            height: sequence.height,
+            options: sequence.options,
+        });
+        let frames = dive.map_or(sequence.frames, |dive| dive.dive.total_frames());
+        let fps = matches.get_one::<u32>("fps").copied().or(dive.map(|dive| dive.dive.fps));
+        let pb = ProgressBar::new(frames as u64);
         style_progress_bar(&pb);
-        if config_manager.image.is_empty() {
-            mandelbrot::mandelbrot_img::compose(WIDTH, HEIGHT, ITERATIONS)
-                .save(DEFAULT_IMAGE_PATH)?;
+        let on_frame = |done, _| pb.set_position(done as u64);
+        if let Some(format) = video {
+            let mut options = VideoOptions {
+                format,
+                bitrate: matches.get_one::<u32>("bitrate").copied(),
+                ..VideoOptions::default()
+            };
+            options.fps = fps.unwrap_or(options.fps);
+            let path = Path::new(dir).with_extension(format.name());
+            match dive {
+                Some(dive) => dive.render_video_with_progress(&path, &options, on_frame)?,
+                None => sequence.render_video_with_progress(&path, &options, on_frame)?,
+            }
+            pb.finish_with_message(format!("Saved {}", path.display()));
+        } else if format == Some("gif") {
+            let options = fps.map_or_else(GifOptions::default, GifOptions::with_fps);
+            let path = Path::new(dir).with_extension("gif");
+            match dive {
+                Some(dive) => dive.render_gif_with_progress(&path, &options, on_frame)?,
+                None => sequence.render_gif_with_progress(&path, &options, on_frame)?,
+            }
+            pb.finish_with_message(format!("Saved {}", path.display()));
         } else {
-            mandelbrot::mandelbrot_img::compose(WIDTH, HEIGHT, ITERATIONS)
-                .save(config_manager.image)?;
+            match dive {
+                Some(dive) => dive.render_with_progress(dir, on_frame)?,
+                None => sequence.render_with_progress(dir, on_frame)?,
+            };
+            pb.finish_with_message("Saved frames");
+        }
+    }
+
+    if let Some(path) = matches.get_one::<String>("html") {
+        info!(
+            "Rendering image Mandelbrot set as {} and saving to file",
+            Style::new().bold().apply_to("HTML")
+        );
+        let art = mandelbrot_ascii::render_frame(
+            &Viewport::default(),
+            matches.get_one::<u32>("width").copied().unwrap_or(mandelbrot_ascii::WIDTH),
+            matches.get_one::<u32>("height").copied().unwrap_or(mandelbrot_ascii::HEIGHT),
+            iterations_arg.unwrap_or(mandelbrot_ascii::ITERATIONS),
+        );
+        html::write_html_to_file(&art, "Mandelbrot set", Path::new(path))?;
+    }
+
+    #[cfg(feature = "wallpaper")]
+    if let Some(path) = matches.get_one::<String>("wallpaper") {
+        set_wallpaper(&matches, Path::new(path))?;
+    }
+
+    if matches.get_flag("play") {
+        info!("Playing zoom into the Mandelbrot set as {}", Style::new().bold().apply_to("ASCII"));
+        let mut playback = ZoomPlayback::default();
+        let session =
+            if matches.get_flag("fresh") { None } else { Session::load(DEFAULT_SESSION_FILE)? };
+        if let Some(session) = &session {
+            // Continue diving from where the last session stopped.
+            let depth = playback.target.zoom / playback.start.zoom;
+            playback.start = session.viewport;
+            playback.target = session.viewport.zoomed(depth);
+            playback.iterations = session.iterations;
+        }
+        // The ASCII frames have no colors, but the palette and backend carry over to `explore`.
+        let (palette, backend) = session_choices(&matches, session.as_ref());
+        if let Some(frames) = matches.get_one::<u32>("frames") {
+            playback.frames = *frames;
+        }
+        if let Some(fps) = matches.get_one::<u32>("fps") {
+            playback.fps = *fps;
+        }
+        playback.play(&mut io::stdout().lock())?;
+        Session { palette, backend, ..Session::new(playback.target, playback.iterations) }
+            .save(DEFAULT_SESSION_FILE)?;
+    }
+
+    Ok(())
+}
+
+/// Finishes the render saved in the checkpoint at `path` and saves its image, colored with
+/// `--palette` if one is given.
+fn resume(path: &Path, matches: &ArgMatches) -> anyhow::Result<()> {
+    let checkpoint = Checkpoint::load(path)?;
+    let image_path = checkpoint::image_path(path);
+    let (width, height, opts) = (checkpoint.width, checkpoint.height, checkpoint.opts);
+    info!(
+        "Resuming the render of {} from row {} of {height}",
+        image_path.display(),
+        checkpoint.completed_rows()
+    );
+    let pb = ProgressBar::new(width as u64 * height as u64);
+    style_progress_bar(&pb);
+    let escapes =
+        checkpoint.render_with_progress(path, CHECKPOINT_INTERVAL, track_progress(&pb))?;
+    let palette = matches.get_one::<String>("palette").and_then(|name| BuiltinPalette::parse(name));
+    let image = match palette {
+        Some(palette) => colorize_escapes(width, height, &escapes, &opts, &palette),
+        None => shade_escapes(width, height, &escapes, &opts),
+    };
+    image.save(&image_path)?;
+    pb.finish_with_message("Saved image to file");
+    Ok(())
+}
+
+/// Renders every standard scene with every backend and prints a table of the results.
+fn bench(width: u32, height: u32, runs: u32) -> anyhow::Result<()> {
+    println!("Rendering {width}x{height} pixels, fastest of {runs} runs");
+    println!("{:<14} {:<10} {:>12} {:>12}", "scene", "backend", "time", "Mpixels/s");
+    for scene in BenchScene::standard() {
+        for &backend in Backend::ALL {
+            let result = bench::run(scene, backend, width, height, runs);
+            println!(
+                "{:<14} {:<10} {:>9.1} ms {:>12.2}",
+                scene.name,
+                backend.name(),
+                result.elapsed.as_secs_f64() * 1000.0,
+                result.pixels_per_second() / 1e6
+            );
         }
-        pb.finish_with_message("Saved image to file");
     }
+    Ok(())
+}
+
+/// Serves renders and tiles on `port` of localhost until the process is stopped, with the
+/// defaults of `settings.toml`, which are reloaded when it changes.
+fn serve(port: u16) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    let server = Server::with_settings_file(DEFAULT_SETTINGS_FILE)?;
+    println!("Serving the Mandelbrot set on http://{}/", listener.local_addr()?);
+    server::serve(listener, server)?;
+    Ok(())
+}
+
+/// Renders the tile pyramid at `dir` down to `max_zoom`, resuming a previous run.
+fn tile_pyramid(dir: &Path, max_zoom: u32, opts: &PyramidOptions) -> anyhow::Result<()> {
+    let pb = ProgressBar::new(pyramid::tile_count(max_zoom));
+    style_progress_bar(&pb);
+    let summary = pyramid::render_pyramid(dir, max_zoom, opts, track_progress(&pb))?;
+    pb.finish();
+    println!(
+        "Rendered {} tiles into {}, skipped {} that already existed",
+        summary.rendered,
+        dir.display(),
+        summary.skipped
+    );
+    Ok(())
+}
+
+/// Renders the escape counts described by the arguments of the `stats` subcommand and prints
+/// their statistics.
+fn render_stats(matches: &ArgMatches) -> anyhow::Result<()> {
+    let arg = |name, default| matches.get_one::<u32>(name).copied().unwrap_or(default);
+    let (width, height) = (arg("width", WIDTH), arg("height", HEIGHT));
+    let iterations = arg("iterations", ITERATIONS);
+    let mut viewport = matches
+        .get_one::<String>("preset")
+        .and_then(|name| Viewport::preset(name))
+        .unwrap_or_default();
+    if let Some(center) = matches.get_one::<PreciseCenter>("center") {
+        (viewport.center_x, viewport.center_y) = center.to_f64();
+    }
+    if let Some(&zoom) = matches.get_one::<f64>("zoom") {
+        viewport.zoom = zoom;
+    }
+    let opts = RenderOptions::new(iterations)
+        .with_kind(matches.get_one::<FractalKind>("power").copied().unwrap_or_default())
+        .with_optimizations(true);
+
+    let pb = ProgressBar::new(width as u64 * height as u64);
+    style_progress_bar(&pb);
+    let escapes =
+        escape_viewport_with_progress(width, height, &viewport, &opts, track_progress(&pb));
+    pb.finish_and_clear();
+    let stats = RenderStats::from_escapes(
+        width,
+        height,
+        &escapes,
+        &viewport,
+        &opts,
+        arg("bins", stats::DEFAULT_BINS),
+    );
+
+    if matches.get_flag("json") {
+        println!("{}", serde_json::to_string_pretty(&stats)?);
+        return Ok(());
+    }
+    println!("Rendered {width}x{height} pixels with {iterations} iterations");
+    println!(
+        "{:<18} {:>6.2}% ({} pixels)",
+        "inside the set",
+        stats.inside_fraction() * 100.0,
+        stats.inside
+    );
+    println!("{:<18} {:>7.1}", "mean iterations", stats.mean_iterations);
+    println!("{:<18} {:>5}", "max iterations", stats.max_iterations);
+    println!();
+    println!("{:<13} {:>10} {:>7}", "iterations", "pixels", "share");
+    let largest = stats.histogram.iter().map(|bin| bin.count).max().unwrap_or(0).max(1);
+    for bin in &stats.histogram {
+        let share = bin.count as f64 / stats.pixels() as f64;
+        let bar = "#".repeat((bin.count * 30).div_ceil(largest) as usize);
+        println!(
+            "{:<13} {:>10} {:>6.2}% {bar}",
+            format!("{}-{}", bin.start, bin.end - 1),
+            bin.count,
+            share * 100.0
+        );
+    }
+    println!();
+    let suggested = stats.suggested;
+    println!("Suggested iterations:");
+    println!("{:<18} {:>10}", "for the zoom", suggested.zoom);
+    println!("{:<18} {:>10}", "after refining", suggested.refined);
+    println!(
+        "{:<18} {:>10}  (keeps {}% of the escaping pixels)",
+        "lowest",
+        suggested.lowest,
+        stats::KEPT_FRACTION * 100.0
+    );
+    Ok(())
+}
+
+/// Renders the Newton fractal described by the arguments of the `newton` subcommand.
+fn newton_fractal(matches: &ArgMatches) -> anyhow::Result<()> {
+    let arg = |name| *matches.get_one::<u32>(name).expect("the argument has a default");
+    let (width, height) = (arg("width"), arg("height"));
+    let polynomial = matches.get_one::<Polynomial>("coefficients").expect("required");
+    let iterations = matches.get_one::<u32>("iterations").copied();
+    let opts = NewtonOptions {
+        iterations: iterations.unwrap_or(newton::DEFAULT_NEWTON_ITERATIONS),
+        ..NewtonOptions::default()
+    };
+    let zoom = matches.get_one::<f64>("zoom").expect("the argument has a default");
+    let base = newton::default_viewport();
+    let viewport = Viewport::new(base.center_x, base.center_y, base.zoom * zoom);
+    let palette = matches
+        .get_one::<String>("palette")
+        .and_then(|name| BuiltinPalette::parse(name))
+        .unwrap_or_default();
+    let path = matches.get_one::<String>("output").expect("the argument has a default");
 
+    info!("Rendering the Newton fractal of {polynomial}");
+    let pb = ProgressBar::new(width as u64 * height as u64);
+    style_progress_bar(&pb);
+    let results = newton::newton_viewport_with_progress(
+        width,
+        height,
+        &viewport,
+        polynomial,
+        &opts,
+        track_progress(&pb),
+    );
+    let image =
+        newton::colorize_newton(width, height, &results, polynomial.degree(), &opts, &palette);
+    let format = output_format(path).map_err(anyhow::Error::msg)?;
+    export::save_as(&image, path, format, DEFAULT_JPEG_QUALITY)?;
+    pb.finish_with_message("Wrote the Newton fractal to file");
     Ok(())
 }
 
+/// Prints the render parameters of the image at `path` as a TOML scene file.
+fn inspect(path: &Path) -> anyhow::Result<()> {
+    let metadata = ImageMetadata::load(path)
+        .map_err(|e| anyhow::anyhow!("No render parameters found for {}: {e}", path.display()))?;
+    println!("# Rendered by mandelbrot {}", metadata.version);
+    print!("{}", metadata.scene.serialize(DiveFormat::Toml)?);
+    Ok(())
+}
+
+/// The names of the palette and backend given on the command line, or else those `session`
+/// saved.
+fn session_choices(
+    matches: &ArgMatches,
+    session: Option<&Session>,
+) -> (Option<String>, Option<String>) {
+    let choice = |name: &str, saved: Option<&Option<String>>| {
+        matches.get_one::<String>(name).cloned().or_else(|| saved.cloned().flatten())
+    };
+    (
+        choice("palette", session.map(|session| &session.palette)),
+        choice("backend", session.map(|session| &session.backend)),
+    )
+}
+
+/// Runs the terminal explorer, starting from the saved session unless `--fresh` is passed, and
+/// saves the session it ends with.
+fn explore(matches: &ArgMatches) -> anyhow::Result<()> {
+    let session =
+        if matches.get_flag("fresh") { None } else { Session::load(DEFAULT_SESSION_FILE)? };
+    let (palette, backend) = session_choices(matches, session.as_ref());
+    let mut explorer = match session {
+        Some(session) => Explorer::new(session.viewport, session.iterations),
+        None => Explorer::new(Viewport::default(), ITERATIONS),
+    };
+    let save = ExploreSave {
+        palette: palette.as_deref().and_then(BuiltinPalette::parse),
+        backend: backend.as_deref().and_then(Backend::parse),
+    };
+    let mut bookmarks = BookmarkStore::load(DEFAULT_BOOKMARKS_FILE)?;
+    let mut out = io::stdout();
+    terminal::enable_raw_mode()?;
+    execute!(out, terminal::EnterAlternateScreen, cursor::Hide)?;
+    let result = explore_loop(&mut explorer, &mut bookmarks, &save, &mut out);
+    execute!(out, cursor::Show, terminal::LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()?;
+    Session { palette, backend, ..Session::new(explorer.viewport, explorer.iterations) }
+        .save(DEFAULT_SESSION_FILE)?;
+    result
+}
+
+/// How the explorer saves its view as an image.
+struct ExploreSave {
+    palette: Option<BuiltinPalette>,
+    backend: Option<Backend>,
+}
+
+impl ExploreSave {
+    /// Saves the view of `explorer` to `path` as a `WIDTH` x `HEIGHT` image, with escape counts
+    /// from the backend and colors from the palette, grayscale if only the backend is set.
+    fn save(&self, explorer: &Explorer, path: &Path) -> anyhow::Result<()> {
+        let (viewport, iterations) = (&explorer.viewport, explorer.iterations);
+        let opts = RenderOptions::new(iterations).with_optimizations(true);
+        if self.palette.is_none() && self.backend.is_none() {
+            compose_with_options(WIDTH, HEIGHT, viewport, &opts).save(path)?;
+            return Ok(());
+        }
+        let counts = match self.backend {
+            Some(backend) => backend.render(WIDTH, HEIGHT, viewport, iterations),
+            None => escape_viewport(WIDTH, HEIGHT, viewport, &opts)
+                .iter()
+                .map(|result| result.iterations)
+                .collect(),
+        };
+        let values: Vec<f64> =
+            counts.iter().map(|&count| count as f64 / iterations.max(1) as f64).collect();
+        let palette = self.palette.unwrap_or(BuiltinPalette::Grayscale);
+        colorize_values(WIDTH, HEIGHT, &values, &palette).save(path)?;
+        Ok(())
+    }
+}
+
+/// Draws the explorer, with the bookmarks in a side panel when the terminal is wide enough, and
+/// applies key presses until `q` or Escape is pressed. Bookmarks are saved as they are added.
+fn explore_loop(
+    explorer: &mut Explorer,
+    bookmarks: &mut BookmarkStore,
+    save: &ExploreSave,
+    out: &mut io::Stdout,
+) -> anyhow::Result<()> {
+    let support = ColorSupport::detect();
+    let mut message = None;
+    let mut selected = None;
+    loop {
+        let (columns, rows) = terminal::size()?;
+        let panel = columns as u32 >= 2 * PANEL_WIDTH;
+        let art_columns = if panel { columns as u32 - PANEL_WIDTH } else { columns as u32 };
+        explorer.resize(art_columns, rows.saturating_sub(1) as u32);
+        explorer.render();
+        let mut art = Vec::new();
+        ansi::write_colored(
+            explorer.columns,
+            explorer.rows,
+            explorer.counts(),
+            explorer.iterations,
+            support,
+            &mut art,
+        )?;
+        queue!(out, cursor::MoveTo(0, 0))?;
+        // Raw mode does not return the cursor to the start of the line on a newline.
+        out.write_all(String::from_utf8_lossy(&art).replace('\n', "\r\n").as_bytes())?;
+        if panel {
+            for (row, line) in bookmark_panel(bookmarks, selected, explorer.rows).iter().enumerate()
+            {
+                queue!(out, cursor::MoveTo(art_columns as u16, row as u16))?;
+                write!(out, "{line}")?;
+            }
+            queue!(out, cursor::MoveTo(0, explorer.rows as u16))?;
+        }
+        let status = message.take().unwrap_or_else(|| explorer.status());
+        let status: String = status.chars().take(columns as usize).collect();
+        queue!(out, terminal::Clear(terminal::ClearType::CurrentLine))?;
+        write!(out, "{status}")?;
+        out.flush()?;
+
+        let command = match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => match key.code {
+                KeyCode::Left => ExploreCommand::PanLeft,
+                KeyCode::Right => ExploreCommand::PanRight,
+                KeyCode::Up => ExploreCommand::PanUp,
+                KeyCode::Down => ExploreCommand::PanDown,
+                KeyCode::Esc => ExploreCommand::Quit,
+                KeyCode::Char(key) => match ExploreCommand::from_key(key) {
+                    Some(command) => command,
+                    None => continue,
+                },
+                _ => continue,
+            },
+            _ => continue,
+        };
+        match command {
+            ExploreCommand::Quit => return Ok(()),
+            ExploreCommand::Save => {
+                let path = explore_image_path();
+                save.save(explorer, &path)?;
+                message = Some(format!("Saved {}", path.display()));
+            }
+            ExploreCommand::Bookmark => {
+                let bookmark = explorer.bookmark(bookmarks);
+                message =
+                    Some(format!("Bookmarked {} in {}", bookmark.name, DEFAULT_BOOKMARKS_FILE));
+                bookmarks.add(bookmark);
+                bookmarks.save()?;
+                selected = Some(bookmarks.len() - 1);
+            }
+            ExploreCommand::Jump => {
+                let next = selected.map_or(0, |index| (index + 1) % bookmarks.len().max(1));
+                match bookmarks.jump(next) {
+                    Some(bookmark) => {
+                        explorer.jump(bookmark);
+                        message = Some(format!("Jumped to {}", bookmark.name));
+                        selected = Some(next);
+                    }
+                    None => message = Some("No bookmarks yet, press b to add one".to_string()),
+                }
+            }
+            command => explorer.apply(command),
+        }
+    }
+}
+
+/// The first of `explore-001.png`, `explore-002.png` and so on that does not exist yet.
+fn explore_image_path() -> PathBuf {
+    (1..)
+        .map(|index| PathBuf::from(format!("explore-{index:03}.png")))
+        .find(|path| !path.exists())
+        .unwrap_or_default()
+}
+
+#[cfg(feature = "wallpaper")]
+fn wallpaper_args(command: Command) -> Command {
+    command
+        .arg(
+            Arg::new("wallpaper")
+                .long("wallpaper")
+                .value_name("PATH")
+                .num_args(0..=1)
+                .default_missing_value(DEFAULT_WALLPAPER_PATH)
+                .help("Renders at the display resolution, saves to PATH and sets it as wallpaper"),
+        )
+        .arg(
+            Arg::new("random-region")
+                .long("random-region")
+                .action(ArgAction::SetTrue)
+                .help("Picks a random interesting region for --wallpaper"),
+        )
+        .arg(
+            Arg::new("wallpaper-size")
+                .long("wallpaper-size")
+                .value_name("WIDTHxHEIGHT")
+                .value_parser(parse_size)
+                .help("Overrides the detected display resolution for --wallpaper"),
+        )
+}
+
+#[cfg(not(feature = "wallpaper"))]
+fn wallpaper_args(command: Command) -> Command {
+    command
+}
+
+#[cfg(feature = "wallpaper")]
+fn set_wallpaper(matches: &ArgMatches, path: &Path) -> anyhow::Result<()> {
+    use mandelbrot::desktop;
+
+    let (width, height) = match matches.get_one::<(u32, u32)>("wallpaper-size") {
+        Some(size) => *size,
+        None => desktop::primary_display_resolution().unwrap_or(desktop::DEFAULT_RESOLUTION),
+    };
+    let region = if matches.get_flag("random-region") {
+        desktop::random_region(desktop::time_seed())
+    } else {
+        desktop::interesting_regions().swap_remove(0)
+    };
+    info!(
+        "Rendering {} at {width}x{height} as {}",
+        region.name,
+        Style::new().bold().apply_to("wallpaper")
+    );
+    desktop::render_wallpaper(width, height, &region.viewport, region.iterations).save(path)?;
+    desktop::set_wallpaper(path).map_err(|e| anyhow::anyhow!("failed to set wallpaper: {e}"))
+}
+
+/// Parses a `WIDTHxHEIGHT` size such as `1920x1080`.
+#[cfg(feature = "wallpaper")]
+fn parse_size(size: &str) -> Result<(u32, u32), String> {
+    let (width, height) = size.split_once('x').ok_or("expected WIDTHxHEIGHT")?;
+    let width = width.parse::<u32>().map_err(|e| e.to_string())?;
+    let height = height.parse::<u32>().map_err(|e| e.to_string())?;
+    if width == 0 || height == 0 {
+        return Err("width and height must be positive".to_string());
+    }
+    Ok((width, height))
+}
+
+/// The `AsciiOptions` of the `--width`, `--height`, `--iterations` and `--charset` given on the
+/// command line. Without a size, art printed to a terminal is sized to fill it when `fit_terminal`
+/// is set, with the set corrected for the aspect of the characters.
+fn ascii_options(matches: &ArgMatches, fit_terminal: bool) -> AsciiOptions {
+    let width = matches.get_one::<u32>("width").copied();
+    let height = matches.get_one::<u32>("height").copied();
+    let mut opts = match terminal_size().filter(|_| fit_terminal) {
+        Some((columns, rows)) if width.is_none() && height.is_none() => {
+            AsciiOptions::new(columns, rows).with_aspect_correction(true)
+        }
+        _ => AsciiOptions::default(),
+    };
+    opts.width = width.unwrap_or(opts.width);
+    opts.height = height.unwrap_or(opts.height);
+    opts.iterations = iteration_limit(matches).unwrap_or(opts.iterations);
+    match matches.get_one::<String>("charset") {
+        Some(charset) => opts.with_charset(charset),
+        None => opts,
+    }
+}
+
+/// Columns and rows of the terminal on stdout, leaving a row for the prompt, or `None` if stdout
+/// is not a terminal.
+fn terminal_size() -> Option<(u32, u32)> {
+    let (rows, columns) = console::Term::stdout().size_checked()?;
+    Some((columns as u32, rows.saturating_sub(1).max(1) as u32))
+}
+
+/// Renders the ASCII art of `ascii_options`. With the default options the classic 80x40 art is
+/// rendered, which matches `mandelbrot.txt`. With `--braille` the size counts braille characters
+/// of 2x4 dots.
+fn render_ascii(matches: &ArgMatches, pb: &ProgressBar, fit_terminal: bool) -> AsciiCanvas {
+    let opts = ascii_options(matches, fit_terminal);
+    if matches.get_flag("braille") {
+        return mandelbrot_ascii::render_braille_with_progress(
+            &Viewport::default(),
+            opts.width,
+            opts.height,
+            opts.iterations,
+            track_progress(pb),
+        );
+    }
+    if opts == AsciiOptions::default() {
+        return mandelbrot_ascii::collect_ascii_with_progress(track_progress(pb));
+    }
+    mandelbrot_ascii::render_ascii_with_progress(&Viewport::default(), &opts, track_progress(pb))
+}
+
+/// Prints the ASCII art of `ascii_options` in the colors of `support`. Colored art always uses
+/// the current kernel, so at the default size it differs slightly from `mandelbrot.txt`.
+fn print_colored_ascii(
+    matches: &ArgMatches,
+    support: ColorSupport,
+    pb: &ProgressBar,
+) -> io::Result<()> {
+    let opts = ascii_options(matches, true);
+    let counts = mandelbrot_ascii::ascii_counts_with_progress(
+        &Viewport::default(),
+        &opts,
+        track_progress(pb),
+    );
+    pb.finish();
+    ansi::write_colored(
+        opts.width,
+        opts.height,
+        &counts,
+        opts.iterations,
+        support,
+        &mut io::stdout().lock(),
+    )
+}
+
+/// Accepts image paths whose extension names an `OutputFormat`, and paths of escape data files
+/// ending in `.raw`, `.csv` or `.exr`.
+fn parse_image_path(path: &str) -> Result<String, String> {
+    if data_format(path).is_some() {
+        return Ok(path.to_string());
+    }
+    output_format(path).map(|_| path.to_string())
+}
+
+/// The image format inferred from the extension of `path`.
+fn output_format(path: &str) -> Result<OutputFormat, String> {
+    OutputFormat::from_path(path).ok_or_else(|| {
+        let extensions: Vec<&str> =
+            OutputFormat::ALL.iter().flat_map(|format| format.extensions()).copied().collect();
+        format!("expected a file path ending in .{}", extensions.join(", ."))
+    })
+}
+
+/// The escape count format named by the extension of `path`, if any.
+fn data_format(path: &str) -> Option<&'static str> {
+    let extension = Path::new(path).extension()?.to_str()?;
+    DATA_FORMATS.into_iter().find(|format| format.eq_ignore_ascii_case(extension))
+}
+
+/// Reads the viewport and iteration count of a `.kfr` file, or of the first Mandelbrot entry of a
+/// `.par` file.
+fn load_params(path: &Path) -> anyhow::Result<(Viewport, u32)> {
+    let is_par = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("par"));
+    if is_par {
+        let entries = interop::load_par(path)?;
+        let entry = entries
+            .iter()
+            .find(|entry| entry.is_supported())
+            .ok_or_else(|| anyhow::anyhow!("{} has no type=mandel entry", path.display()))?;
+        info!("Rendering {} from {}", entry.name, path.display());
+        Ok((entry.viewport, entry.iterations))
+    } else {
+        let params = KfrParams::load(path)?;
+        Ok((params.viewport()?, params.iterations))
+    }
+}
+
+/// A value of `--iterations`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum IterationLimit {
+    Fixed(u32),
+    /// `Viewport::suggested_iterations` of the image; other renders keep their default.
+    Auto,
+}
+
+fn parse_iterations(limit: &str) -> Result<IterationLimit, String> {
+    if limit.eq_ignore_ascii_case("auto") {
+        return Ok(IterationLimit::Auto);
+    }
+    match limit.parse::<u32>() {
+        Ok(0) => Err("expected a positive number of iterations or auto".to_string()),
+        Ok(limit) => Ok(IterationLimit::Fixed(limit)),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// The fixed iteration limit given with `--iterations`, if any.
+fn iteration_limit(matches: &ArgMatches) -> Option<u32> {
+    match matches.get_one::<IterationLimit>("iterations") {
+        Some(IterationLimit::Fixed(limit)) => Some(*limit),
+        Some(IterationLimit::Auto) | None => None,
+    }
+}
+
+fn parse_power(power: &str) -> Result<FractalKind, String> {
+    let power = power.parse::<f64>().map_err(|e| e.to_string())?;
+    FractalKind::multibrot(power).ok_or_else(|| "expected a power greater than 1".to_string())
+}
+
+fn parse_formula(formula: &str) -> Result<Formula, String> {
+    Formula::parse(formula).map_err(|e| e.to_string())
+}
+
+fn parse_precision(name: &str) -> Result<Precision, String> {
+    Precision::parse(name).ok_or_else(|| {
+        "expected auto, double, perturbation or a positive number of digits".to_string()
+    })
+}
+
+fn parse_mobius(coefficients: &str) -> Result<Mobius, String> {
+    Mobius::parse(coefficients).ok_or_else(|| {
+        "expected four complex coefficients such as 1,0,0.5i,1 with A D - B C not zero".to_string()
+    })
+}
+
+fn parse_period(period: &str) -> Result<f64, String> {
+    match period.parse::<f64>() {
+        Ok(period) if period > 0.0 && period.is_finite() => Ok(period),
+        Ok(_) => Err("expected a positive number of iterations".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn parse_zoom(zoom: &str) -> Result<f64, String> {
+    match zoom.parse::<f64>() {
+        Ok(zoom) if zoom > 0.0 && zoom.is_finite() => Ok(zoom),
+        Ok(_) => Err("expected a finite zoom above 0".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn parse_elevation(elevation: &str) -> Result<f64, String> {
+    match elevation.parse::<f64>() {
+        Ok(elevation) if (0.0..=90.0).contains(&elevation) => Ok(elevation),
+        Ok(_) => Err("expected an angle from 0 to 90 degrees".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn parse_color(color: &str) -> Result<[u8; 3], String> {
+    let hex = color.strip_prefix('#').unwrap_or(color);
+    let channel = |i: usize| hex.get(i..i + 2).and_then(|h| u8::from_str_radix(h, 16).ok());
+    match (hex.len(), channel(0), channel(2), channel(4)) {
+        (6, Some(r), Some(g), Some(b)) => Ok([r, g, b]),
+        _ => Err("expected a hex color such as 1a2b3c".to_string()),
+    }
+}
+
+fn parse_polynomial(coefficients: &str) -> Result<Polynomial, String> {
+    Polynomial::parse(coefficients).ok_or_else(|| {
+        "expected the comma-separated coefficients of a polynomial of degree 2 or more, such as \
+         1,0,0,-1"
+            .to_string()
+    })
+}
+
+fn parse_tile_grid(grid: &str) -> Result<TileGrid, String> {
+    TileGrid::parse(grid).ok_or_else(|| "expected COLUMNSxROWS such as 4x4".to_string())
+}
+
+fn parse_tile_index(index: &str) -> Result<(u32, u32), String> {
+    let (column, row) = index.split_once('/').ok_or("expected COLUMN/ROW")?;
+    let column = column.parse::<u32>().map_err(|e| e.to_string())?;
+    let row = row.parse::<u32>().map_err(|e| e.to_string())?;
+    Ok((column, row))
+}
+
 fn style_progress_bar(pb: &ProgressBar) {
     pb.set_style(
         ProgressStyle::default_bar()
@@ -115,10 +2149,19 @@ fn style_progress_bar(pb: &ProgressBar) {
     );
 }
 
+/// Returns a progress callback for the renderers that moves `pb` to the reported position. The
+/// length is updated on every report since it depends on the size of the render.
+fn track_progress(pb: &ProgressBar) -> impl Fn(u64, u64) + Sync + '_ {
+    move |done, total| {
+        pb.set_length(total);
+        pb.set_position(done);
+    }
+}
+
 /// `build_config_settings` is a wrapper around `config` crate.
 ///
-/// * `build_cloned` method - Does not take ownership of `ConfigBuilder` to allow later reuse.
-/// let map_new = settings_new.try_deserialize::<HashMap<String, String>>()?;
+/// * `build_cloned` method - Does not take ownership of `ConfigBuilder` to allow later reuse. let
+///   map_new = settings_new.try_deserialize::<HashMap<String, String>>()?;
 // NOTE: Directly mutate `CONFIG_BUILDER` without assigning it.
 pub fn build_config_settings(path: &str) -> Result<Config, config::ConfigError> {
     let mut curr_path = env::current_dir().unwrap();
@@ -129,24 +2172,341 @@ pub fn build_config_settings(path: &str) -> Result<Config, config::ConfigError>
     // Eg.. `APP_DEBUG=1 ./target/app` would set the `debug` key
     Config::builder()
         .add_source(config::File::with_name(&curr_path.to_string_lossy()))
-        .add_source(config::Environment::with_prefix("APP"))
+        .add_source(app_environment())
         .build()
 }
 
+/// Typed settings read from `settings.toml` and `APP_*` environment variables.
+///
+/// Every value of an image render is taken from the first of these that sets it:
+///
+/// 1. the command line options, such as `--width` or `--output`,
+/// 2. the file given to `--scene` or `--params`,
+/// 3. `APP_*` environment variables, such as `APP_WIDTH=1920` or `APP_VIEWPORT__ZOOM=40`,
+/// 4. `settings.toml`,
+/// 5. the built-in defaults.
 #[derive(Debug, Deserialize, Serialize)]
-struct ConfigManager {
+struct AppConfig {
+    #[serde(default)]
+    debug: bool,
+    #[serde(default)]
+    priority: u8,
+    #[serde(default)]
+    key: String,
+    #[serde(default = "default_text_path")]
     text: String,
+    /// Path images are saved to.
+    #[serde(default = "default_image_path")]
     image: String,
+    verbose: u8,
+    #[serde(default)]
+    width: Option<u32>,
+    #[serde(default)]
+    height: Option<u32>,
+    #[serde(default)]
+    iterations: Option<u32>,
+    #[serde(default)]
+    palette: Option<String>,
+    #[serde(default)]
+    viewport: ViewportConfig,
+}
+
+/// The `[viewport]` table of the settings. Each entry overrides that of the default view on its
+/// own, so `APP_VIEWPORT__ZOOM=40` zooms into the default view without naming its center.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize, Serialize)]
+struct ViewportConfig {
+    #[serde(default)]
+    center_x: Option<f64>,
+    #[serde(default)]
+    center_y: Option<f64>,
+    #[serde(default)]
+    zoom: Option<f64>,
+    #[serde(default)]
+    precision: Option<Precision>,
+    #[serde(default)]
+    fit: Option<FitMode>,
+}
+
+impl ViewportConfig {
+    /// `viewport` with the entries that are set replaced.
+    fn apply(&self, viewport: Viewport) -> Viewport {
+        Viewport {
+            center_x: self.center_x.unwrap_or(viewport.center_x),
+            center_y: self.center_y.unwrap_or(viewport.center_y),
+            zoom: self.zoom.unwrap_or(viewport.zoom),
+            precision: self.precision.unwrap_or(viewport.precision),
+            fit: self.fit.unwrap_or(viewport.fit),
+        }
+    }
 }
 
-impl Default for ConfigManager {
+impl Default for AppConfig {
     fn default() -> Self {
         Self::new()
     }
 }
 
-impl ConfigManager {
+impl AppConfig {
+    const KEYS: [&'static str; 12] = [
+        "debug",
+        "priority",
+        "key",
+        "text",
+        "image",
+        "verbose",
+        "width",
+        "height",
+        "iterations",
+        "palette",
+        "viewport",
+        // Only read by `mandelbrot serve`, see `server::ServerSettings`.
+        "cache_size",
+    ];
+
     fn new() -> Self {
-        Self { text: DEFAULT_TEXT_PATH.to_string(), image: DEFAULT_IMAGE_PATH.to_string() }
+        Self {
+            debug: false,
+            priority: 0,
+            key: String::new(),
+            text: DEFAULT_TEXT_PATH.to_string(),
+            image: DEFAULT_IMAGE_PATH.to_string(),
+            verbose: 1,
+            width: None,
+            height: None,
+            iterations: None,
+            palette: None,
+            viewport: ViewportConfig::default(),
+        }
+    }
+
+    /// Deserializes and validates the settings in `config`, which was built from the settings
+    /// file at `path` and the environment.
+    ///
+    /// Type errors are reported by `config` together with the key and where the value came from;
+    /// range errors are reported as a `SettingsError`. Unknown keys are logged and ignored.
+    fn load(config: &Config, path: &Path) -> anyhow::Result<Self> {
+        let sources = SettingsSources::collect(path)?;
+        for key in config.collect()?.keys() {
+            if !Self::KEYS.contains(&key.as_str()) {
+                warn!("Ignoring unknown setting `{key}` from {}", sources.origin(key));
+            }
+        }
+        let settings: Self = config.clone().try_deserialize()?;
+        settings.validate(&sources)?;
+        Ok(settings)
+    }
+
+    fn validate(&self, sources: &SettingsSources) -> Result<(), SettingsError> {
+        if self.verbose > 3 {
+            return Err(SettingsError::new(
+                "verbose",
+                self.verbose,
+                "an integer in 0..=3",
+                sources,
+            ));
+        }
+        if self.text.trim().is_empty() {
+            return Err(SettingsError::new("text", &self.text, "a non-empty file path", sources));
+        }
+        for (key, size) in [("width", self.width), ("height", self.height)] {
+            if size == Some(0) {
+                return Err(SettingsError::new(key, 0, "a positive number of pixels", sources));
+            }
+        }
+        if self.iterations == Some(0) {
+            return Err(SettingsError::new("iterations", 0, "a positive integer", sources));
+        }
+        if let Some(palette) = self.palette.as_deref() {
+            if BuiltinPalette::parse(palette).is_none() {
+                let expected = "one of grayscale, fire, ocean, rainbow or twilight";
+                return Err(SettingsError::new("palette", palette, expected, sources));
+            }
+        }
+        if let Some(zoom) = self.viewport.zoom {
+            if !(zoom.is_finite() && zoom > 0.0) {
+                let expected = "a positive zoom";
+                return Err(SettingsError::new("viewport.zoom", zoom, expected, sources));
+            }
+        }
+        // An empty image path falls back to `DEFAULT_IMAGE_PATH`.
+        if !self.image.is_empty() && OutputFormat::from_path(&self.image).is_none() {
+            return Err(SettingsError::new(
+                "image",
+                &self.image,
+                "a file path with a supported image extension such as .png",
+                sources,
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn default_text_path() -> String {
+    DEFAULT_TEXT_PATH.to_string()
+}
+
+fn default_image_path() -> String {
+    DEFAULT_IMAGE_PATH.to_string()
+}
+
+/// The `APP_*` environment variables, where `__` separates the keys of tables such as
+/// `APP_VIEWPORT__ZOOM` for `viewport.zoom`.
+fn app_environment() -> config::Environment {
+    config::Environment::with_prefix("APP").prefix_separator("_").separator("__")
+}
+
+/// Keys provided by each settings source, used to tell where an invalid value came from.
+struct SettingsSources {
+    file: String,
+    file_keys: Vec<String>,
+    env_keys: Vec<String>,
+}
+
+impl SettingsSources {
+    fn collect(path: &Path) -> Result<Self, config::ConfigError> {
+        let file_keys =
+            config::File::with_name(&path.to_string_lossy()).required(false).collect()?;
+        // Named relative to the working directory, as `config` names the file in type errors.
+        let relative = env::current_dir().ok().and_then(|dir| path.strip_prefix(dir).ok());
+        let file = relative.unwrap_or(path).to_string_lossy().into_owned();
+        let env_keys = app_environment().collect()?;
+        Ok(Self { file, file_keys: dotted_keys(file_keys), env_keys: dotted_keys(env_keys) })
+    }
+
+    /// Describes the source that provided `key`, preferring the environment like `Config` does.
+    fn origin(&self, key: &str) -> String {
+        // Tables such as `viewport` are provided by the keys of their fields, `viewport.zoom`.
+        let provides =
+            |k: &String| k == key || k.strip_prefix(key).is_some_and(|k| k.starts_with('.'));
+        if self.env_keys.iter().any(provides) {
+            format!("environment variable APP_{}", key.to_uppercase().replace('.', "__"))
+        } else if self.file_keys.iter().any(provides) {
+            self.file.clone()
+        } else {
+            "the defaults".to_string()
+        }
+    }
+}
+
+/// The keys of the values in `map`, dotted like `viewport.zoom` for those of tables.
+fn dotted_keys(map: config::Map<String, config::Value>) -> Vec<String> {
+    map.into_iter()
+        .flat_map(|(key, value)| match value.kind {
+            config::ValueKind::Table(table) => {
+                dotted_keys(table).into_iter().map(|field| format!("{key}.{field}")).collect()
+            }
+            _ => vec![key],
+        })
+        .collect()
+}
+
+/// A setting whose value has the right type but is out of range.
+#[derive(Debug)]
+struct SettingsError {
+    key: &'static str,
+    found: String,
+    expected: &'static str,
+    origin: String,
+}
+
+impl SettingsError {
+    fn new(
+        key: &'static str,
+        found: impl fmt::Debug,
+        expected: &'static str,
+        sources: &SettingsSources,
+    ) -> Self {
+        Self { key, found: format!("{found:?}"), expected, origin: sources.origin(key) }
+    }
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid value {} for key `{}` in {}, expected {}",
+            self.found, self.key, self.origin, self.expected
+        )
+    }
+}
+
+impl std::error::Error for SettingsError {}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    /// The settings of `source` on top of the defaults `try_main` sets.
+    fn settings(source: impl Source + Send + Sync + 'static) -> AppConfig {
+        let builder = Config::builder().set_default("verbose", "1").unwrap();
+        builder.add_source(source).build().unwrap().try_deserialize().unwrap()
+    }
+
+    #[test]
+    fn test_single_viewport_key_overrides_the_default_view() {
+        let env = HashMap::from([("APP_VIEWPORT__ZOOM".to_string(), "40".to_string())]);
+        let viewport = settings(app_environment().source(Some(env))).viewport;
+        assert_eq!(
+            viewport.apply(Viewport::default()),
+            Viewport { zoom: 40.0, ..Viewport::default() }
+        );
+
+        let file = config::File::from_str("[viewport]\ncenter_y = 0.5\n", config::FileFormat::Toml);
+        let inverted = Viewport::new(1.5, 0.0, 0.5);
+        assert_eq!(settings(file).viewport.apply(inverted), Viewport::new(1.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn test_invalid_settings_name_their_key_and_source() {
+        let keys = |keys: &[&str]| keys.iter().map(|key| key.to_string()).collect();
+        let sources = SettingsSources {
+            file: "settings.toml".to_string(),
+            file_keys: keys(&["iterations", "viewport.zoom", "palette"]),
+            env_keys: keys(&["palette", "viewport.center_x"]),
+        };
+        let zoom = ViewportConfig { zoom: Some(-1.0), ..ViewportConfig::default() };
+        let cases = [
+            (AppConfig { iterations: Some(0), ..AppConfig::new() }, "iterations", "settings.toml"),
+            (AppConfig { viewport: zoom, ..AppConfig::new() }, "viewport.zoom", "settings.toml"),
+            (
+                AppConfig { palette: Some("sepia".to_string()), ..AppConfig::new() },
+                "palette",
+                "environment variable APP_PALETTE",
+            ),
+            (AppConfig { width: Some(0), ..AppConfig::new() }, "width", "the defaults"),
+            (AppConfig { verbose: 4, ..AppConfig::new() }, "verbose", "the defaults"),
+        ];
+        for (settings, key, origin) in cases {
+            let error = settings.validate(&sources).unwrap_err();
+            assert_eq!((error.key, error.origin.as_str()), (key, origin));
+        }
+        assert_eq!(
+            sources.origin("viewport.center_x"),
+            "environment variable APP_VIEWPORT__CENTER_X"
+        );
+
+        let file = config::File::from_str(
+            "iterations = 9\n[viewport]\nzoom = 2.0\n",
+            config::FileFormat::Toml,
+        );
+        let mut keys = dotted_keys(file.collect().unwrap());
+        keys.sort();
+        assert_eq!(keys, ["iterations", "viewport.zoom"]);
+
+        // The file is named as `config` names it in type errors.
+        let path = env::current_dir().unwrap().join(DEFAULT_SETTINGS_FILE);
+        assert_eq!(SettingsSources::collect(&path).unwrap().file, DEFAULT_SETTINGS_FILE);
+    }
+
+    #[test]
+    fn test_zoom_must_be_finite_and_positive() {
+        assert_eq!(parse_zoom("2.5"), Ok(2.5));
+        for zoom in ["0", "-3", "nan", "inf"] {
+            assert!(parse_zoom(zoom).is_err(), "{zoom} was accepted");
+        }
     }
 }
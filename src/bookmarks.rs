@@ -0,0 +1,211 @@
+//! A persistent store of named viewports, used to save interesting locations found while
+//! exploring and to jump back to them later.
+//!
+//! Bookmarks are kept in insertion order, which doubles as the jump list: the most recently
+//! saved bookmark is always last. The store is saved as a TOML file so it can be edited by hand.
+//!
+//! ```toml
+//! [[bookmarks]]
+//! name = "seahorse valley"
+//! iterations = 500
+//!
+//! [bookmarks.viewport]
+//! center_x = -0.743643887037151
+//! center_y = 0.13182590420533
+//! zoom = 10000.0
+//! ```
+//!
+//! Locations deeper than `f64` can represent keep their full decimal center in the optional
+//! `exact_center = ["<real>", "<imaginary>"]` entry; the `viewport` center is then its rounded
+//! value.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::viewport::Viewport;
+
+pub const DEFAULT_BOOKMARKS_FILE: &str = "bookmarks.toml";
+
+/// A named location in the complex plane together with the iteration count it was viewed with.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub name: String,
+    pub iterations: u32,
+    /// Center as decimal strings with more digits than `viewport` can hold.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exact_center: Option<[String; 2]>,
+    pub viewport: Viewport,
+}
+
+impl Bookmark {
+    pub fn new(name: impl Into<String>, viewport: Viewport, iterations: u32) -> Self {
+        Self { name: name.into(), iterations, viewport, exact_center: None }
+    }
+
+    /// Returns the bookmark with its center set to the decimal strings `x` and `y`, keeping
+    /// every digit in `exact_center` and the nearest `f64` values in `viewport`.
+    pub fn with_exact_center(
+        mut self,
+        x: &str,
+        y: &str,
+    ) -> Result<Self, std::num::ParseFloatError> {
+        self.viewport.center_x = x.trim().parse()?;
+        self.viewport.center_y = y.trim().parse()?;
+        self.exact_center = Some([x.trim().to_string(), y.trim().to_string()]);
+        Ok(self)
+    }
+
+    /// Center at full precision, from `exact_center` if present and from `viewport` otherwise.
+    #[cfg(feature = "bigfloat")]
+    pub fn precise_center(
+        &self,
+    ) -> Result<crate::bigfloat::PreciseCenter, crate::bigfloat::ParseDecimalError> {
+        use crate::bigfloat::PreciseCenter;
+
+        match &self.exact_center {
+            Some([x, y]) => PreciseCenter::parse(x, y),
+            None => Ok(PreciseCenter::from_f64(self.viewport.center_x, self.viewport.center_y)),
+        }
+    }
+}
+
+/// Ordered collection of bookmarks backed by a TOML file.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BookmarkStore {
+    #[serde(skip)]
+    path: PathBuf,
+    #[serde(default)]
+    bookmarks: Vec<Bookmark>,
+}
+
+impl BookmarkStore {
+    /// Creates an empty store that will be saved to `path`.
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self { path: path.as_ref().to_path_buf(), bookmarks: Vec::new() }
+    }
+
+    /// Loads the store from `path`, returning an empty store if the file does not exist yet.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::new(path)),
+            Err(e) => return Err(e),
+        };
+        let mut store: Self =
+            toml::from_str(&contents).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        store.path = path.to_path_buf();
+        Ok(store)
+    }
+
+    /// Writes the store to the file it was loaded from or created for.
+    pub fn save(&self) -> io::Result<()> {
+        let contents =
+            toml::to_string(self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(&self.path, contents)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends `bookmark` to the end of the jump list, replacing an existing bookmark with the
+    /// same name.
+    pub fn add(&mut self, bookmark: Bookmark) {
+        self.bookmarks.retain(|b| b.name != bookmark.name);
+        self.bookmarks.push(bookmark);
+    }
+
+    /// Removes the bookmark called `name`, returning it if it existed.
+    pub fn remove(&mut self, name: &str) -> Option<Bookmark> {
+        let index = self.bookmarks.iter().position(|b| b.name == name)?;
+        Some(self.bookmarks.remove(index))
+    }
+
+    /// Looks up a bookmark by name.
+    pub fn get(&self, name: &str) -> Option<&Bookmark> {
+        self.bookmarks.iter().find(|b| b.name == name)
+    }
+
+    /// Bookmark at position `index` of the jump list.
+    pub fn jump(&self, index: usize) -> Option<&Bookmark> {
+        self.bookmarks.get(index)
+    }
+
+    /// Most recently saved bookmark.
+    pub fn last(&self) -> Option<&Bookmark> {
+        self.bookmarks.last()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Bookmark> {
+        self.bookmarks.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.bookmarks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bookmarks.is_empty()
+    }
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    #[test]
+    fn test_add_replaces_and_moves_to_end() {
+        let mut store = BookmarkStore::default();
+        store.add(Bookmark::new("a", Viewport::default(), 100));
+        store.add(Bookmark::new("b", Viewport::default(), 100));
+        store.add(Bookmark::new("a", Viewport::new(0.0, 0.0, 2.0), 200));
+
+        assert_eq!(store.len(), 2);
+        assert_eq!(store.jump(0).unwrap().name, "b");
+        assert_eq!(store.last().unwrap().iterations, 200);
+        assert_eq!(store.remove("b").unwrap().name, "b");
+        assert!(store.get("b").is_none());
+    }
+
+    #[test]
+    fn test_exact_center_keeps_digits() {
+        let x = "-0.7436438870371587047521915061147741600012";
+        let bookmark = Bookmark::new("deep", Viewport::default(), 5000)
+            .with_exact_center(x, "0.1318259042053119")
+            .unwrap();
+        assert_eq!(bookmark.viewport.center_x, -0.7436438870371587);
+        let toml = toml::to_string(&bookmark).unwrap();
+        let loaded: Bookmark = toml::from_str(&toml).unwrap();
+        assert_eq!(loaded.exact_center.as_ref().unwrap()[0], x);
+        #[cfg(feature = "bigfloat")]
+        assert_eq!(loaded.precise_center().unwrap().x.to_string(), x);
+    }
+
+    #[test]
+    fn test_save_and_load_round_trip() {
+        let path =
+            env::temp_dir().join(format!("mandelbrot-bookmarks-{}.toml", std::process::id()));
+        let mut store = BookmarkStore::new(&path);
+        store.add(Bookmark::new("seahorse", Viewport::new(-0.7436, 0.1318, 1e4), 500));
+        store.save().unwrap();
+
+        let loaded = BookmarkStore::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, store);
+    }
+
+    #[test]
+    fn test_load_missing_file_is_empty() {
+        let store = BookmarkStore::load("does-not-exist/bookmarks.toml").unwrap();
+        assert!(store.is_empty());
+    }
+}
@@ -0,0 +1,266 @@
+//! Renders the Buddhabrot, the density of escaping orbits, instead of escape times.
+//!
+//! Random points `c` are drawn from the disk of radius 2. The orbit of every point that escapes
+//! after at least `min_iterations` and fewer than `max_iterations` iterations is replayed, and
+//! each orbit point that falls into the viewport adds a hit to its pixel. The resulting
+//! `DensityMap` is tone-mapped into an image by scaling the hits to the busiest pixel and applying
+//! a gamma curve, since a few pixels near the real axis collect far more hits than the rest.
+//!
+//! Low iteration limits show the ghostly "Buddha" figure, high minimums bring out the fine
+//! filaments traced by slowly escaping orbits. Points of the main cardioid and the period-2 bulb
+//! never escape and are rejected without iterating. The samples are split into `BATCHES` batches
+//! with their own random streams, so the result only depends on the seed, not on the number of
+//! threads.
+
+#[cfg(feature = "image")]
+use image::{ImageBuffer, Rgb, RgbImage};
+
+use crate::{
+    analysis::{in_main_cardioid, in_period_two_bulb},
+    scheduler::map_slice,
+    viewport::Viewport,
+};
+
+/// Number of independently seeded batches the samples are split into.
+pub const BATCHES: u64 = 16;
+
+/// Gamma used by `compose_buddhabrot`; `2.0` takes the square root of the normalized density.
+pub const DEFAULT_GAMMA: f64 = 2.0;
+
+/// How many points are sampled and which orbits are accumulated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BuddhabrotOptions {
+    /// Number of points `c` drawn from the disk of radius 2.
+    pub samples: u64,
+    /// Orbits escaping after fewer iterations are discarded.
+    pub min_iterations: u32,
+    /// Orbits that have not escaped after this many iterations are discarded.
+    pub max_iterations: u32,
+    /// Seed of the random points; the same seed gives the same density.
+    pub seed: u64,
+}
+
+impl Default for BuddhabrotOptions {
+    fn default() -> Self {
+        Self { samples: 1_000_000, min_iterations: 20, max_iterations: 1000, seed: 0 }
+    }
+}
+
+/// Orbit hits per pixel of a `width` x `height` grid, in row-major order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DensityMap {
+    width: u32,
+    height: u32,
+    hits: Vec<u32>,
+}
+
+impl DensityMap {
+    /// Samples `opts.samples` points and accumulates the orbits that pass through `viewport`.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::{
+    ///     buddhabrot::{BuddhabrotOptions, DensityMap},
+    ///     viewport::Viewport,
+    /// };
+    ///
+    /// let opts = BuddhabrotOptions { samples: 10_000, ..BuddhabrotOptions::default() };
+    /// let density = DensityMap::accumulate(64, 48, &Viewport::default(), &opts);
+    /// assert_eq!(density.hits().len(), 64 * 48);
+    /// assert!(density.max() > 0);
+    /// ```
+    pub fn accumulate(
+        width: u32,
+        height: u32,
+        viewport: &Viewport,
+        opts: &BuddhabrotOptions,
+    ) -> Self {
+        let batches: Vec<u64> = (0..BATCHES).collect();
+        let partial = map_slice(&batches, |&batch| {
+            let mut hits = vec![0; width as usize * height as usize];
+            let mut state = opts.seed ^ batch.wrapping_mul(0xA24B_AED4_963E_E407);
+            let mut orbit = Vec::new();
+            // The first batches take the remainder, so the total is exactly `samples`.
+            let samples = opts.samples / BATCHES + u64::from(batch < opts.samples % BATCHES);
+            for _ in 0..samples {
+                let c = (4.0 * unit(&mut state) - 2.0, 4.0 * unit(&mut state) - 2.0);
+                if !trace_orbit(c, opts, &mut orbit) {
+                    continue;
+                }
+                for &z in &orbit {
+                    if let Some(index) = pixel_index(viewport, z, width, height) {
+                        hits[index] += 1;
+                    }
+                }
+            }
+            hits
+        });
+        let mut hits = vec![0u32; width as usize * height as usize];
+        for batch in partial {
+            for (total, count) in hits.iter_mut().zip(batch) {
+                *total = total.saturating_add(count);
+            }
+        }
+        Self { width, height, hits }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Hits of all pixels in row-major order.
+    pub fn hits(&self) -> &[u32] {
+        &self.hits
+    }
+
+    /// Hits of pixel `(x, y)`.
+    pub fn hit(&self, x: u32, y: u32) -> u32 {
+        self.hits[(y * self.width + x) as usize]
+    }
+
+    /// Hits of the busiest pixel.
+    pub fn max(&self) -> u32 {
+        self.hits.iter().copied().max().unwrap_or(0)
+    }
+
+    /// Brightness of every pixel between `0.0` and `1.0`: the hits divided by `max` and raised to
+    /// `1 / gamma`, so a `gamma` above `1.0` lifts the faint parts of the image.
+    pub fn tone_map(&self, gamma: f64) -> Vec<f64> {
+        let max = self.max().max(1) as f64;
+        self.hits.iter().map(|&hits| (hits as f64 / max).powf(1.0 / gamma)).collect()
+    }
+
+    /// The tone-mapped density as a grayscale image.
+    #[cfg(feature = "image")]
+    pub fn to_image(&self, gamma: f64) -> RgbImage {
+        let brightness = self.tone_map(gamma);
+        ImageBuffer::from_fn(self.width, self.height, |x, y| {
+            let value = (brightness[(y * self.width + x) as usize] * 255.0).round() as u8;
+            Rgb([value, value, value])
+        })
+    }
+}
+
+/// Renders the Buddhabrot of `viewport` at `width` x `height`, tone-mapped with `DEFAULT_GAMMA`.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::{
+///     buddhabrot::{compose_buddhabrot, BuddhabrotOptions},
+///     viewport::Viewport,
+/// };
+///
+/// let opts = BuddhabrotOptions { samples: 10_000, ..BuddhabrotOptions::default() };
+/// let image = compose_buddhabrot(64, 48, &Viewport::default(), &opts);
+/// assert_eq!(image.dimensions(), (64, 48));
+/// ```
+#[cfg(feature = "image")]
+pub fn compose_buddhabrot(
+    width: u32,
+    height: u32,
+    viewport: &Viewport,
+    opts: &BuddhabrotOptions,
+) -> RgbImage {
+    DensityMap::accumulate(width, height, viewport, opts).to_image(DEFAULT_GAMMA)
+}
+
+/// Iterates `c` and stores its orbit in `orbit`. Returns `true` if the orbit escaped within the
+/// iteration range of `opts` and should be accumulated.
+fn trace_orbit(c: (f64, f64), opts: &BuddhabrotOptions, orbit: &mut Vec<(f64, f64)>) -> bool {
+    orbit.clear();
+    let (cx, cy) = c;
+    if cx * cx + cy * cy > 4.0 || in_main_cardioid(c) || in_period_two_bulb(c) {
+        return false;
+    }
+    let (mut x, mut y) = (0.0_f64, 0.0_f64);
+    for i in 0..opts.max_iterations {
+        let x_temp = x * x - y * y + cx;
+        y = 2.0 * x * y + cy;
+        x = x_temp;
+        if x * x + y * y > 4.0 {
+            return i >= opts.min_iterations;
+        }
+        orbit.push((x, y));
+    }
+    false
+}
+
+/// Index of the pixel of a `width` x `height` grid over `viewport` that contains `z`, the
+/// inverse of `Viewport::to_complex_num`.
+fn pixel_index(viewport: &Viewport, z: (f64, f64), width: u32, height: u32) -> Option<usize> {
+    let (x, y) = viewport.pixel(z, width, height);
+    if x < 0.0 || y < 0.0 || x >= width as f64 || y >= height as f64 {
+        return None;
+    }
+    Some(y as usize * width as usize + x as usize)
+}
+
+/// Advances the SplitMix64 generator in `state` and returns its next output.
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Next uniformly distributed value in `[0.0, 1.0)`.
+fn unit(state: &mut u64) -> f64 {
+    (splitmix64(state) >> 11) as f64 / (1u64 << 53) as f64
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pixel_index_inverts_viewport_mapping() {
+        let viewport = Viewport::new(-0.5, 0.25, 3.0);
+        for (x, y) in [(0, 0), (17, 5), (63, 47)] {
+            let (re, im) = viewport.to_complex_num(x, y, 64, 48);
+            // Nudge into the pixel, whose top-left corner `to_complex_num` returns.
+            let z = (re + 1e-9, im + 1e-9);
+            assert_eq!(pixel_index(&viewport, z, 64, 48), Some(y as usize * 64 + x as usize));
+        }
+        assert_eq!(pixel_index(&viewport, (10.0, 0.0), 64, 48), None);
+    }
+
+    #[test]
+    fn test_density_is_deterministic_and_symmetric() {
+        let opts =
+            BuddhabrotOptions { samples: 200_000, max_iterations: 200, ..Default::default() };
+        let (width, height) = (70, 40);
+        let density = DensityMap::accumulate(width, height, &Viewport::default(), &opts);
+        assert_eq!(density, DensityMap::accumulate(width, height, &Viewport::default(), &opts));
+
+        // Conjugate points have conjugate orbits, so the top and bottom halves nearly match.
+        let half = |rows: std::ops::Range<u32>| {
+            rows.flat_map(|y| (0..width).map(move |x| (x, y)))
+                .map(|(x, y)| density.hit(x, y) as f64)
+                .sum::<f64>()
+        };
+        let (top, bottom) = (half(0..height / 2), half(height / 2..height));
+        assert!((top - bottom).abs() / (top + bottom) < 0.05, "{top} vs {bottom}");
+    }
+
+    #[test]
+    fn test_min_iterations_discards_short_orbits() {
+        let viewport = Viewport::default();
+        let all = BuddhabrotOptions { samples: 50_000, min_iterations: 0, ..Default::default() };
+        let long = BuddhabrotOptions { min_iterations: 100, ..all };
+        let total =
+            |opts| DensityMap::accumulate(60, 40, &viewport, &opts).hits().iter().sum::<u32>();
+        assert!(total(long) < total(all));
+
+        let mut orbit = Vec::new();
+        assert!(!trace_orbit((0.0, 0.0), &all, &mut orbit));
+        assert!(trace_orbit((0.5, 0.5), &all, &mut orbit));
+        assert_eq!(orbit.len(), crate::mandelbrot_img::mandelbrot((0.5, 0.5), 1000) as usize);
+    }
+}
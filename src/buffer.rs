@@ -0,0 +1,341 @@
+//! Per-pixel results of a render, kept separate from how they are colored.
+//!
+//! An `IterationBuffer` stores the escape count of every pixel and, when
+//! `RenderOptions::escape_angle` is set, the argument of `z` at the moment the orbit escaped as a
+//! second channel. The angle is what binary decomposition, field lines and normal-map shading are
+//! built from, so storing it lets colorings use it without iterating the kernel again.
+//!
+//! The escape angle is most useful with a large bailout radius (for example
+//! `RenderOptions::with_bailout(1000.0)`), which makes decomposition cells line up between
+//! neighbouring iteration bands.
+//!
+//! The counts can be written out as a matrix for analysis elsewhere: `write_raw` writes them as
+//! little-endian `u32`s in row-major order, without a header, and `write_csv` writes one line of
+//! comma-separated counts per row.
+//!
+//! A `PixelInfoBuffer` stores an `analysis::PixelInfo` per pixel instead, the escape count
+//! together with the smooth escape value, the last and smallest `|z|` and the derivative
+//! `dz/dc`, so colorings can be written elsewhere without changing the kernel. `write_raw` writes
+//! them as records of `PIXEL_INFO_SIZE` little-endian bytes, and `write_csv` as one line per
+//! pixel under a header.
+
+use std::io::{self, Write};
+
+use num_complex::Complex64;
+
+use crate::{
+    analysis::{escape, pixel_info, EscapeResult, PixelInfo},
+    options::RenderOptions,
+    scheduler::{render_grid, render_grid_with_progress},
+    viewport::Viewport,
+};
+
+/// Escape counts and optional escape angles of a `width` x `height` grid, in row-major order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IterationBuffer {
+    width: u32,
+    height: u32,
+    iterations: u32,
+    counts: Vec<u32>,
+    /// Argument of `z` at escape, `NaN` for pixels that did not escape.
+    angles: Option<Vec<f64>>,
+}
+
+impl IterationBuffer {
+    /// Renders `viewport` at `width` x `height` with `opts`.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::{buffer::IterationBuffer, options::RenderOptions, viewport::Viewport};
+    ///
+    /// let opts = RenderOptions::new(100).with_escape_angle(true);
+    /// let buffer = IterationBuffer::render(80, 40, &Viewport::classic(), &opts);
+    /// assert_eq!(buffer.count(0, 0), 0);
+    /// assert!(buffer.angle(0, 0).is_some());
+    /// assert_eq!(buffer.angle(60, 20), None);
+    /// ```
+    pub fn render(width: u32, height: u32, viewport: &Viewport, opts: &RenderOptions) -> Self {
+        let results = render_grid(width, height, |x, y| {
+            let (re, im) = viewport.to_complex_num(x, y, width, height);
+            escape(Complex64::new(re, im), opts)
+        });
+        Self::from_escapes(width, height, &results, opts)
+    }
+
+    /// Collects the escape results of a `width` x `height` grid, in row-major order, such as
+    /// those of `analysis::escape_viewport`.
+    pub fn from_escapes(
+        width: u32,
+        height: u32,
+        results: &[EscapeResult],
+        opts: &RenderOptions,
+    ) -> Self {
+        assert_eq!(results.len(), width as usize * height as usize, "one result per pixel");
+        let angles = opts.escape_angle.then(|| {
+            results.iter().map(|r| if r.escaped { r.z.arg() } else { f64::NAN }).collect()
+        });
+        let counts = results.iter().map(|r| r.iterations).collect();
+        Self { width, height, iterations: opts.iterations, counts, angles }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Iteration limit the buffer was rendered with.
+    pub fn iterations(&self) -> u32 {
+        self.iterations
+    }
+
+    /// Escape counts of all pixels in row-major order.
+    pub fn counts(&self) -> &[u32] {
+        &self.counts
+    }
+
+    /// Escape count of pixel `(x, y)`, equal to `iterations` for points that did not escape.
+    pub fn count(&self, x: u32, y: u32) -> u32 {
+        self.counts[self.index(x, y)]
+    }
+
+    /// Consumes the buffer, returning the escape counts in row-major order.
+    pub fn into_counts(self) -> Vec<u32> {
+        self.counts
+    }
+
+    /// Writes the escape counts as little-endian `u32`s in row-major order.
+    pub fn write_raw<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let bytes: Vec<u8> = self.counts.iter().flat_map(|count| count.to_le_bytes()).collect();
+        out.write_all(&bytes)
+    }
+
+    /// Writes the escape counts as CSV, one line per row.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::{buffer::IterationBuffer, options::RenderOptions, viewport::Viewport};
+    ///
+    /// let buffer = IterationBuffer::render(3, 2, &Viewport::classic(), &RenderOptions::new(50));
+    /// let mut csv = Vec::new();
+    /// buffer.write_csv(&mut csv).unwrap();
+    /// assert_eq!(String::from_utf8(csv).unwrap(), "0,1,3\n0,50,50\n");
+    /// ```
+    pub fn write_csv<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        for row in self.counts.chunks(self.width.max(1) as usize) {
+            let line: Vec<String> = row.iter().map(u32::to_string).collect();
+            writeln!(out, "{}", line.join(","))?;
+        }
+        Ok(())
+    }
+
+    /// Returns `true` if the escape angle channel was recorded.
+    pub fn has_angles(&self) -> bool {
+        self.angles.is_some()
+    }
+
+    /// Argument of `z` in `(-PI, PI]` when the orbit of pixel `(x, y)` escaped, or `None` if it
+    /// did not escape or the channel was not recorded.
+    pub fn angle(&self, x: u32, y: u32) -> Option<f64> {
+        let angle = self.angles.as_ref()?[self.index(x, y)];
+        (!angle.is_nan()).then_some(angle)
+    }
+
+    /// Binary decomposition of pixel `(x, y)`: `Some(true)` if `z` escaped through the upper half
+    /// plane, `Some(false)` through the lower half, and `None` without an escape angle.
+    pub fn decomposition(&self, x: u32, y: u32) -> Option<bool> {
+        self.angle(x, y).map(|angle| angle >= 0.0)
+    }
+
+    fn index(&self, x: u32, y: u32) -> usize {
+        (y * self.width + x) as usize
+    }
+}
+
+/// Size of a record of `PixelInfoBuffer::write_raw`: `iters` as a `u32`, then `smooth`,
+/// `final_mag`, `min_mag` and the real and imaginary parts of `dz` as `f64`s.
+pub const PIXEL_INFO_SIZE: usize = 4 + 5 * 8;
+
+/// The `analysis::PixelInfo` of every pixel of a `width` x `height` grid, in row-major order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PixelInfoBuffer {
+    width: u32,
+    height: u32,
+    pixels: Vec<PixelInfo>,
+}
+
+impl PixelInfoBuffer {
+    /// Renders `viewport` at `width` x `height` with `opts`.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::{buffer::PixelInfoBuffer, options::RenderOptions, viewport::Viewport};
+    ///
+    /// let buffer = PixelInfoBuffer::render(80, 40, &Viewport::classic(), &RenderOptions::new(100));
+    /// assert_eq!(buffer.pixel(0, 0).iters, 0);
+    /// assert!(buffer.pixel(60, 20).min_mag < 2.0);
+    /// ```
+    pub fn render(width: u32, height: u32, viewport: &Viewport, opts: &RenderOptions) -> Self {
+        Self::render_with_progress(width, height, viewport, opts, |_, _| {})
+    }
+
+    /// Like `render`, but calls `on_progress(done, total)` with the number of evaluated pixels
+    /// after every finished row.
+    pub fn render_with_progress<P>(
+        width: u32,
+        height: u32,
+        viewport: &Viewport,
+        opts: &RenderOptions,
+        on_progress: P,
+    ) -> Self
+    where
+        P: Fn(u64, u64) + Sync,
+    {
+        let pixels = render_grid_with_progress(
+            width,
+            height,
+            |x, y| pixel_info(viewport.point(x, y, width, height), opts),
+            on_progress,
+        );
+        Self { width, height, pixels }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Statistics of all pixels in row-major order.
+    pub fn pixels(&self) -> &[PixelInfo] {
+        &self.pixels
+    }
+
+    /// Statistics of pixel `(x, y)`.
+    pub fn pixel(&self, x: u32, y: u32) -> PixelInfo {
+        self.pixels[(y * self.width + x) as usize]
+    }
+
+    /// Writes the statistics as records of `PIXEL_INFO_SIZE` little-endian bytes in row-major
+    /// order.
+    pub fn write_raw<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let mut bytes = Vec::with_capacity(self.pixels.len() * PIXEL_INFO_SIZE);
+        for info in &self.pixels {
+            bytes.extend_from_slice(&info.iters.to_le_bytes());
+            for value in [info.smooth, info.final_mag, info.min_mag, info.dz.re, info.dz.im] {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        out.write_all(&bytes)
+    }
+
+    /// Writes the statistics as CSV, one line per pixel in row-major order after a header that
+    /// names the columns.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::{buffer::PixelInfoBuffer, options::RenderOptions, viewport::Viewport};
+    ///
+    /// let buffer = PixelInfoBuffer::render(3, 2, &Viewport::classic(), &RenderOptions::new(50));
+    /// let mut csv = Vec::new();
+    /// buffer.write_csv(&mut csv).unwrap();
+    /// let csv = String::from_utf8(csv).unwrap();
+    /// assert_eq!(csv.lines().next(), Some("x,y,iters,smooth,final_mag,min_mag,dz_re,dz_im"));
+    /// assert_eq!(csv.lines().count(), 1 + 3 * 2);
+    /// ```
+    pub fn write_csv<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "x,y,iters,smooth,final_mag,min_mag,dz_re,dz_im")?;
+        for (i, info) in self.pixels.iter().enumerate() {
+            let (x, y) = (i as u32 % self.width, i as u32 / self.width);
+            writeln!(
+                out,
+                "{x},{y},{},{},{},{},{},{}",
+                info.iters, info.smooth, info.final_mag, info.min_mag, info.dz.re, info.dz.im
+            )?;
+        }
+        Ok(())
+    }
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mandelbrot_img::mandelbrot;
+
+    #[test]
+    fn test_counts_match_kernel_and_angles_are_optional() {
+        let viewport = Viewport::default();
+        let opts = RenderOptions::new(200);
+        let buffer = IterationBuffer::render(64, 32, &viewport, &opts);
+        assert!(!buffer.has_angles());
+        assert_eq!(buffer.decomposition(0, 0), None);
+        for (x, y) in [(0, 0), (20, 10), (48, 16), (63, 31)] {
+            let c = viewport.to_complex_num(x, y, 64, 32);
+            assert_eq!(buffer.count(x, y), mandelbrot(c, 200));
+        }
+
+        let with_angles = IterationBuffer::render(64, 32, &viewport, &opts.with_escape_angle(true));
+        assert_eq!(with_angles.counts(), buffer.counts());
+    }
+
+    #[test]
+    fn test_raw_and_csv_hold_the_same_counts() {
+        let buffer = IterationBuffer::render(16, 9, &Viewport::default(), &RenderOptions::new(80));
+        let mut raw = Vec::new();
+        buffer.write_raw(&mut raw).unwrap();
+        let from_raw: Vec<u32> =
+            raw.chunks(4).map(|b| u32::from_le_bytes(b.try_into().unwrap())).collect();
+        assert_eq!(from_raw, buffer.counts());
+
+        let mut csv = Vec::new();
+        buffer.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(csv.lines().count(), 9);
+        let from_csv: Vec<u32> =
+            csv.lines().flat_map(|line| line.split(',')).map(|n| n.parse().unwrap()).collect();
+        assert_eq!(from_csv, buffer.into_counts());
+    }
+
+    #[test]
+    fn test_pixel_info_agrees_with_the_counts() {
+        let (viewport, opts) = (Viewport::default(), RenderOptions::new(200));
+        let counts = IterationBuffer::render(32, 24, &viewport, &opts);
+        let infos = PixelInfoBuffer::render(32, 24, &viewport, &opts);
+        let iters: Vec<u32> = infos.pixels().iter().map(|info| info.iters).collect();
+        assert_eq!(iters, counts.counts());
+
+        let mut raw = Vec::new();
+        infos.write_raw(&mut raw).unwrap();
+        assert_eq!(raw.len(), 32 * 24 * PIXEL_INFO_SIZE);
+        let record = &raw[(5 * 32 + 7) * PIXEL_INFO_SIZE..];
+        let f64_at =
+            |i: usize| f64::from_le_bytes(record[4 + 8 * i..12 + 8 * i].try_into().unwrap());
+        let info = infos.pixel(7, 5);
+        assert_eq!(u32::from_le_bytes(record[..4].try_into().unwrap()), info.iters);
+        assert_eq!([f64_at(0), f64_at(2), f64_at(4)], [info.smooth, info.min_mag, info.dz.im]);
+    }
+
+    #[test]
+    fn test_conjugate_points_escape_with_opposite_angles() {
+        let viewport = Viewport::default();
+        let opts = RenderOptions::new(100).with_bailout(1000.0).with_escape_angle(true);
+        let buffer = IterationBuffer::render(40, 30, &viewport, &opts);
+        for x in 0..40 {
+            for y in 0..30 {
+                let Some(angle) = buffer.angle(x, y) else { continue };
+                let (re, im) = viewport.to_complex_num(x, y, 40, 30);
+                let mirrored = escape(Complex64::new(re, -im), &opts);
+                assert_eq!(mirrored.iterations, buffer.count(x, y));
+                if angle.abs() < std::f64::consts::PI {
+                    assert!((mirrored.z.arg() + angle).abs() < 1e-9);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,267 @@
+//! Checkpoints that let an interrupted render continue where it stopped.
+//!
+//! Deep zooms can take hours per frame. While a `Checkpoint` renders, the rows it has finished
+//! are written to a `.mbr-checkpoint` file next to the image, so a render that is killed or
+//! crashes can be loaded again with `Checkpoint::load` and only renders the rows that are missing.
+//! Renders that finish within the first checkpoint interval never write the file, and the file is
+//! removed once the render is complete.
+//!
+//! A checkpoint file starts with one line of JSON holding the size, viewport and options of the
+//! render, followed by the escape results of the finished rows in row-major order, each as
+//! `RECORD_SIZE` little-endian bytes: the iteration count as a `u32`, whether the orbit escaped
+//! as a byte, and the smooth value and both parts of `z` as `f64`s. Rows are appended as the
+//! render goes on, and a row cut short by a crash is dropped when the file is loaded.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use num_complex::Complex64;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    analysis::EscapeResult, options::RenderOptions, stream::RowStream, viewport::Viewport,
+};
+
+/// Extension added to the path of an image to name its checkpoint.
+pub const CHECKPOINT_EXTENSION: &str = "mbr-checkpoint";
+
+/// How often the binary writes finished rows to the checkpoint.
+pub const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Bytes of one escape result in a checkpoint file.
+pub const RECORD_SIZE: usize = 4 + 1 + 8 + 8 + 8;
+
+/// The parameters of a render and the escape results of the rows finished so far.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub width: u32,
+    pub height: u32,
+    pub viewport: Viewport,
+    pub opts: RenderOptions,
+    /// Escape results of the finished rows, in row-major order.
+    #[serde(skip)]
+    pub escapes: Vec<EscapeResult>,
+}
+
+impl Checkpoint {
+    /// A checkpoint of a render that has not started yet.
+    pub fn new(width: u32, height: u32, viewport: Viewport, opts: RenderOptions) -> Self {
+        Self { width, height, viewport, opts, escapes: Vec::new() }
+    }
+
+    /// Number of rows finished so far.
+    pub fn completed_rows(&self) -> u32 {
+        (self.escapes.len() / self.width.max(1) as usize) as u32
+    }
+
+    /// Loads the checkpoint saved at `path`, dropping a last row that was only partly written.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not a checkpoint.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let contents = fs::read(path)?;
+        let newline = contents
+            .iter()
+            .position(|&byte| byte == b'\n')
+            .ok_or_else(|| invalid_data("the checkpoint has no header"))?;
+        let mut checkpoint: Self =
+            serde_json::from_slice(&contents[..newline]).map_err(invalid_data)?;
+        let records = &contents[newline + 1..];
+        let row_size = RECORD_SIZE * checkpoint.width.max(1) as usize;
+        let rows = (records.len() / row_size).min(checkpoint.height as usize);
+        checkpoint.escapes =
+            records[..rows * row_size].chunks_exact(RECORD_SIZE).map(decode_record).collect();
+        Ok(checkpoint)
+    }
+
+    /// Saves the checkpoint to `path`, replacing any earlier checkpoint only once the new one
+    /// has been written in full.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let partial = path.with_extension(format!("{CHECKPOINT_EXTENSION}.partial"));
+        let mut out = BufWriter::new(File::create(&partial)?);
+        serde_json::to_writer(&mut out, self).map_err(invalid_data)?;
+        out.write_all(b"\n")?;
+        write_records(&mut out, &self.escapes)?;
+        out.into_inner().map_err(io::IntoInnerError::into_error)?.sync_all()?;
+        fs::rename(partial, path)
+    }
+
+    /// Renders the rows that are still missing, see `render_with_progress`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the checkpoint cannot be written or removed.
+    pub fn render(
+        self,
+        path: impl AsRef<Path>,
+        interval: Duration,
+    ) -> io::Result<Vec<EscapeResult>> {
+        self.render_with_progress(path, interval, |_, _| {})
+    }
+
+    /// Renders the rows that are still missing and returns the escape results of the whole
+    /// render. The finished rows are saved to `path` once `interval` has passed and appended
+    /// every `interval` after that; the file is removed when the render is complete.
+    /// `on_progress` is called with the number of pixels done and the total after every row.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the checkpoint cannot be written or removed.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// use mandelbrot::{analysis::escape_viewport, checkpoint::Checkpoint, prelude::*};
+    ///
+    /// let (viewport, opts) = (Viewport::default(), RenderOptions::new(100));
+    /// let checkpoint = Checkpoint::new(64, 40, viewport, opts);
+    /// let escapes = checkpoint.render("unused.mbr-checkpoint", Duration::from_secs(60)).unwrap();
+    /// assert_eq!(escapes, escape_viewport(64, 40, &viewport, &opts));
+    /// ```
+    pub fn render_with_progress<P>(
+        mut self,
+        path: impl AsRef<Path>,
+        interval: Duration,
+        on_progress: P,
+    ) -> io::Result<Vec<EscapeResult>>
+    where
+        P: Fn(u64, u64),
+    {
+        let path = path.as_ref();
+        let total = self.width as u64 * self.height as u64;
+        let stream = RowStream::new(self.width, self.height, self.viewport, self.opts)
+            .with_first_row(self.completed_rows());
+        // The checkpoint file and the number of escape results written to it so far.
+        let mut file: Option<(BufWriter<File>, usize)> = None;
+        let mut last_save = Instant::now();
+        on_progress(self.escapes.len() as u64, total);
+        for row in stream {
+            self.escapes.extend(row);
+            on_progress(self.escapes.len() as u64, total);
+            if last_save.elapsed() < interval {
+                continue;
+            }
+            match &mut file {
+                Some((out, saved)) => {
+                    write_records(out, &self.escapes[*saved..])?;
+                    out.flush()?;
+                    out.get_ref().sync_data()?;
+                    *saved = self.escapes.len();
+                }
+                None => {
+                    self.save(path)?;
+                    let out = BufWriter::new(OpenOptions::new().append(true).open(path)?);
+                    file = Some((out, self.escapes.len()));
+                }
+            }
+            last_save = Instant::now();
+        }
+        drop(file);
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+        Ok(self.escapes)
+    }
+}
+
+/// The checkpoint of the image at `path`, the path with `.mbr-checkpoint` appended.
+///
+/// # Examples
+/// ```
+/// use std::path::PathBuf;
+///
+/// use mandelbrot::checkpoint::{checkpoint_path, image_path};
+///
+/// let checkpoint = checkpoint_path("frames/0042.png");
+/// assert_eq!(checkpoint, PathBuf::from("frames/0042.png.mbr-checkpoint"));
+/// assert_eq!(image_path(&checkpoint), PathBuf::from("frames/0042.png"));
+/// ```
+pub fn checkpoint_path(path: impl AsRef<Path>) -> PathBuf {
+    let mut path = path.as_ref().as_os_str().to_owned();
+    path.push(format!(".{CHECKPOINT_EXTENSION}"));
+    PathBuf::from(path)
+}
+
+/// The image a checkpoint at `path` belongs to, the path without `.mbr-checkpoint`.
+pub fn image_path(path: impl AsRef<Path>) -> PathBuf {
+    let path = path.as_ref();
+    match path.extension() {
+        Some(extension) if extension == CHECKPOINT_EXTENSION => path.with_extension(""),
+        _ => path.to_path_buf(),
+    }
+}
+
+fn write_records<W: Write>(out: &mut W, escapes: &[EscapeResult]) -> io::Result<()> {
+    for result in escapes {
+        let mut record = [0; RECORD_SIZE];
+        record[..4].copy_from_slice(&result.iterations.to_le_bytes());
+        record[4] = result.escaped as u8;
+        record[5..13].copy_from_slice(&result.smooth.to_le_bytes());
+        record[13..21].copy_from_slice(&result.z.re.to_le_bytes());
+        record[21..].copy_from_slice(&result.z.im.to_le_bytes());
+        out.write_all(&record)?;
+    }
+    Ok(())
+}
+
+fn decode_record(record: &[u8]) -> EscapeResult {
+    let f64_at = |at: usize| f64::from_le_bytes(record[at..at + 8].try_into().unwrap());
+    EscapeResult {
+        iterations: u32::from_le_bytes(record[..4].try_into().unwrap()),
+        escaped: record[4] != 0,
+        smooth: f64_at(5),
+        z: Complex64::new(f64_at(13), f64_at(21)),
+    }
+}
+
+fn invalid_data(e: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+    use crate::analysis::escape_viewport;
+
+    #[test]
+    fn test_resumes_an_interrupted_render() {
+        let path =
+            env::temp_dir().join(format!("mandelbrot-{}.mbr-checkpoint", std::process::id()));
+        let (width, height) = (30, 40);
+        let viewport = Viewport::new(-0.745, 0.11, 30.0);
+        let opts = RenderOptions::new(200).with_escape_angle(true);
+        let whole = escape_viewport(width, height, &viewport, &opts);
+
+        // A render killed after 25 rows, in the middle of writing the 26th.
+        let mut checkpoint = Checkpoint::new(width, height, viewport, opts);
+        checkpoint.escapes = whole[..25 * width as usize].to_vec();
+        checkpoint.save(&path).unwrap();
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        write_records(&mut file, &whole[25 * width as usize..25 * width as usize + 7]).unwrap();
+        drop(file);
+
+        let loaded = Checkpoint::load(&path).unwrap();
+        assert_eq!(loaded, checkpoint);
+        assert_eq!(loaded.completed_rows(), 25);
+
+        let escapes = loaded.render(&path, Duration::ZERO).unwrap();
+        assert_eq!(escapes, whole);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,59 @@
+//! Complex numbers as the functions of this crate accept them.
+//!
+//! The kernels work on `num_complex::Complex64`, but the older functions such as
+//! `mandelbrot_img::mandelbrot` and `analysis::classify` were written for `(re, im)` tuples. They
+//! take any `IntoComplex` instead, so they accept a `Complex64`, a tuple or a `[re, im]` array
+//! alike, and a plain `f64` as a point on the real axis.
+//!
+//! ```
+//! use mandelbrot::{mandelbrot_img::mandelbrot, prelude::*};
+//!
+//! let c = Complex64::new(-0.75, 0.1);
+//! assert_eq!(mandelbrot(c, 100), mandelbrot((-0.75, 0.1), 100));
+//! assert_eq!(mandelbrot(-1.0, 100), 100);
+//! ```
+
+use num_complex::Complex64;
+
+/// A value that stands for a complex number.
+pub trait IntoComplex {
+    fn into_complex(self) -> Complex64;
+}
+
+impl IntoComplex for Complex64 {
+    fn into_complex(self) -> Complex64 {
+        self
+    }
+}
+
+impl IntoComplex for &Complex64 {
+    fn into_complex(self) -> Complex64 {
+        *self
+    }
+}
+
+/// `(re, im)`.
+impl IntoComplex for (f64, f64) {
+    fn into_complex(self) -> Complex64 {
+        Complex64::new(self.0, self.1)
+    }
+}
+
+/// `[re, im]`.
+impl IntoComplex for [f64; 2] {
+    fn into_complex(self) -> Complex64 {
+        Complex64::new(self[0], self[1])
+    }
+}
+
+/// A real number.
+impl IntoComplex for f64 {
+    fn into_complex(self) -> Complex64 {
+        Complex64::new(self, 0.0)
+    }
+}
+
+/// `c` as an `(re, im)` tuple.
+pub fn to_tuple(c: Complex64) -> (f64, f64) {
+    (c.re, c.im)
+}
@@ -0,0 +1,187 @@
+//! Contours of equal escape count, traced as lines for vector output.
+//!
+//! `trace` runs marching squares over a grid of escape counts: every square of four neighbouring
+//! pixels whose counts lie on both sides of a level contributes a segment, placed between the
+//! pixels by linear interpolation, and the segments are joined into polylines. The contour at the
+//! iteration limit is the outline of the set; lower levels are the edges of the escape-time
+//! bands around it. `write_svg` writes the contours of several levels as the paths of an SVG
+//! file, for poster printers and pen plotters that want vector output instead of pixels.
+
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+};
+
+use crate::buffer::IterationBuffer;
+
+/// One end of a contour segment: the edge between two neighbouring pixels that it crosses, as
+/// the pixel at the top or left of the edge and whether the edge is horizontal.
+type Edge = (u32, u32, bool);
+
+/// Traces the contour between the pixels of a `width` x `height` grid of `counts`, in row-major
+/// order, that escape before `level` and those that do not. Points are in pixel units with
+/// `(0.5, 0.5)` at the center of the first pixel. A closed line ends with its first point.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::contour::trace;
+///
+/// #[rustfmt::skip]
+/// let counts = [
+///     0, 0, 0,
+///     0, 9, 0,
+///     0, 0, 0,
+/// ];
+/// let lines = trace(3, 3, &counts, 5);
+/// assert_eq!(lines.len(), 1);
+/// assert_eq!(lines[0].len(), 5);
+/// assert_eq!(lines[0].first(), lines[0].last());
+/// ```
+pub fn trace(width: u32, height: u32, counts: &[u32], level: u32) -> Vec<Vec<(f64, f64)>> {
+    let iso = level as f64 - 0.5;
+    let value = |x: u32, y: u32| counts[(y * width + x) as usize] as f64;
+    let point = |(x, y, horizontal): Edge| {
+        let (to_x, to_y) = if horizontal { (x + 1, y) } else { (x, y + 1) };
+        let (from, to) = (value(x, y), value(to_x, to_y));
+        let t = if from == to { 0.5 } else { ((iso - from) / (to - from)).clamp(0.0, 1.0) };
+        let (dx, dy) = if horizontal { (t, 0.0) } else { (0.0, t) };
+        (x as f64 + dx + 0.5, y as f64 + dy + 0.5)
+    };
+
+    let mut segments: Vec<(Edge, Edge)> = Vec::new();
+    for y in 0..height.saturating_sub(1) {
+        for x in 0..width.saturating_sub(1) {
+            let corners = [value(x, y), value(x + 1, y), value(x + 1, y + 1), value(x, y + 1)];
+            let case = corners.iter().fold(0, |case, &v| case << 1 | (v >= iso) as u8);
+            let (top, right) = ((x, y, true), (x + 1, y, false));
+            let (bottom, left) = ((x, y + 1, true), (x, y, false));
+            // Whether the two inside corners of a saddle are connected through the middle.
+            let joined = || corners.iter().sum::<f64>() / 4.0 >= iso;
+            match case {
+                1 | 14 => segments.push((left, bottom)),
+                2 | 13 => segments.push((bottom, right)),
+                3 | 12 => segments.push((left, right)),
+                4 | 11 => segments.push((top, right)),
+                6 | 9 => segments.push((top, bottom)),
+                7 | 8 => segments.push((left, top)),
+                5 if joined() => segments.extend([(left, top), (bottom, right)]),
+                10 if !joined() => segments.extend([(left, top), (bottom, right)]),
+                5 | 10 => segments.extend([(left, bottom), (top, right)]),
+                _ => {}
+            }
+        }
+    }
+
+    let mut ends: BTreeMap<Edge, Vec<usize>> = BTreeMap::new();
+    for (i, &(a, b)) in segments.iter().enumerate() {
+        ends.entry(a).or_default().push(i);
+        ends.entry(b).or_default().push(i);
+    }
+    let mut used = vec![false; segments.len()];
+    let mut lines = Vec::new();
+    // Lines that end at the border of the grid first, so they are walked from one end.
+    let open = ends.iter().filter(|(_, at)| at.len() == 1).map(|(&edge, at)| (edge, at[0]));
+    let starts: Vec<(Edge, usize)> =
+        open.chain(segments.iter().enumerate().map(|(i, &(a, _))| (a, i))).collect();
+    for (mut edge, mut segment) in starts {
+        if used[segment] {
+            continue;
+        }
+        let mut line = vec![point(edge)];
+        loop {
+            used[segment] = true;
+            let (a, b) = segments[segment];
+            edge = if a == edge { b } else { a };
+            line.push(point(edge));
+            match ends[&edge].iter().find(|&&next| !used[next]) {
+                Some(&next) => segment = next,
+                None => break,
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// Contour levels for renders with an iteration limit of `iterations`: every power of two below
+/// it, which spaces the bands evenly towards the set, and the limit itself, the outline of the
+/// set.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::contour::default_levels;
+///
+/// assert_eq!(default_levels(100), [2, 4, 8, 16, 32, 64, 100]);
+/// ```
+pub fn default_levels(iterations: u32) -> Vec<u32> {
+    let mut levels: Vec<u32> =
+        (1..32).map(|power| 1 << power).take_while(|&level| level < iterations).collect();
+    levels.push(iterations);
+    levels
+}
+
+/// Writes the contours of `buffer` at every level of `levels` as an SVG image of the size of the
+/// buffer, one `path` per level. The path of a level has the level in its `data-level`
+/// attribute.
+pub fn write_svg<W: Write>(
+    buffer: &IterationBuffer,
+    levels: &[u32],
+    out: &mut W,
+) -> io::Result<()> {
+    let (width, height) = (buffer.width(), buffer.height());
+    writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+    )?;
+    for &level in levels {
+        let lines = trace(width, height, buffer.counts(), level);
+        if lines.is_empty() {
+            continue;
+        }
+        let mut path = String::new();
+        for line in &lines {
+            for (i, (x, y)) in line.iter().enumerate() {
+                let command = if i == 0 { 'M' } else { 'L' };
+                path.push_str(&format!("{command}{x:.2} {y:.2} "));
+            }
+            if line.len() > 2 && line.first() == line.last() {
+                path.push('Z');
+            }
+        }
+        writeln!(
+            out,
+            r#"  <path data-level="{level}" fill="none" stroke="black" stroke-width="0.5" d="{}"/>"#,
+            path.trim_end()
+        )?;
+    }
+    writeln!(out, "</svg>")
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{options::RenderOptions, viewport::Viewport};
+
+    #[test]
+    fn test_outline_of_the_set_is_closed() {
+        let (width, height) = (120, 90);
+        let buffer =
+            IterationBuffer::render(width, height, &Viewport::default(), &RenderOptions::new(100));
+        let lines = trace(width, height, buffer.counts(), 100);
+        let longest = lines.iter().max_by_key(|line| line.len()).unwrap();
+        assert_eq!(longest.first(), longest.last());
+        // Every point lies between two pixels on opposite sides of the level.
+        for &(x, y) in lines.iter().flatten() {
+            assert!(x >= 0.5 && y >= 0.5 && x <= width as f64 - 0.5 && y <= height as f64 - 0.5);
+        }
+        assert!(trace(width, height, buffer.counts(), 101).is_empty());
+
+        let mut svg = Vec::new();
+        write_svg(&buffer, &default_levels(100), &mut svg).unwrap();
+        let svg = String::from_utf8(svg).unwrap();
+        assert!(svg.starts_with("<svg") && svg.trim_end().ends_with("</svg>"));
+        assert!(svg.contains(r#"data-level="100""#));
+    }
+}
@@ -0,0 +1,160 @@
+//! Renders the Mandelbrot set as a desktop wallpaper and sets it as the background.
+//!
+//! The render matches the resolution of the primary display, which is detected by asking the
+//! platform's own tools (`xrandr`, `system_profiler` or `wmic`) and falls back to
+//! `DEFAULT_RESOLUTION`. Setting the background is delegated to the `wallpaper` crate, which
+//! knows the per-desktop-environment details.
+//!
+//! Every invocation can pick a different region from `interesting_regions`, so running the binary
+//! from a login script gives a new wallpaper each session.
+
+use std::{
+    error::Error,
+    path::Path,
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use image::RgbImage;
+
+use crate::{bookmarks::Bookmark, mandelbrot_img, viewport::Viewport};
+
+/// Resolution used when the primary display cannot be queried.
+pub const DEFAULT_RESOLUTION: (u32, u32) = (1920, 1080);
+
+/// Well-known regions of the set that make good wallpapers.
+pub fn interesting_regions() -> Vec<Bookmark> {
+    vec![
+        Bookmark::new("whole set", Viewport::default(), 255),
+        Bookmark::new(
+            "seahorse valley",
+            Viewport::new(-0.743643887037151, 0.13182590420533, 200.0),
+            1000,
+        ),
+        Bookmark::new("elephant valley", Viewport::new(0.2925, 0.0155, 120.0), 800),
+        Bookmark::new("triple spiral", Viewport::new(-0.088, 0.654, 60.0), 800),
+        Bookmark::new("mini mandelbrot", Viewport::new(-1.7687788, 0.0017389, 2000.0), 1500),
+        Bookmark::new("scepter valley", Viewport::new(-1.36, 0.0, 25.0), 600),
+    ]
+}
+
+/// Picks one of `interesting_regions` based on `seed` and varies its zoom by up to a factor of
+/// two in either direction, so repeated picks of the same region still differ.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::desktop::random_region;
+///
+/// assert_eq!(random_region(7), random_region(7));
+/// ```
+pub fn random_region(seed: u64) -> Bookmark {
+    let regions = interesting_regions();
+    let first = splitmix64(seed);
+    let second = splitmix64(first);
+    let mut region = regions[(first % regions.len() as u64) as usize].clone();
+    // Map the second draw onto [0.5, 2.0) logarithmically.
+    let t = (second >> 11) as f64 / (1u64 << 53) as f64;
+    region.viewport = region.viewport.zoomed(2f64.powf(2.0 * t - 1.0));
+    region
+}
+
+/// Seed derived from the current time, for a different region on every invocation.
+pub fn time_seed() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos() as u64).unwrap_or_default()
+}
+
+/// Resolution of the primary display, or `None` if it cannot be determined on this platform.
+pub fn primary_display_resolution() -> Option<(u32, u32)> {
+    if cfg!(target_os = "macos") {
+        let output = Command::new("system_profiler").arg("SPDisplaysDataType").output().ok()?;
+        parse_system_profiler(&String::from_utf8_lossy(&output.stdout))
+    } else if cfg!(windows) {
+        let output = Command::new("wmic")
+            .args([
+                "path",
+                "Win32_VideoController",
+                "get",
+                "CurrentHorizontalResolution,CurrentVerticalResolution",
+            ])
+            .output()
+            .ok()?;
+        parse_wmic(&String::from_utf8_lossy(&output.stdout))
+    } else {
+        let output = Command::new("xrandr").arg("--current").output().ok()?;
+        parse_xrandr(&String::from_utf8_lossy(&output.stdout))
+    }
+}
+
+/// Renders `viewport` at `width` x `height` in the same grayscale as `compose`.
+pub fn render_wallpaper(width: u32, height: u32, viewport: &Viewport, iterations: u32) -> RgbImage {
+    mandelbrot_img::compose_with_viewport(width, height, iterations, viewport)
+}
+
+/// Sets the image at `path` as the desktop background.
+pub fn set_wallpaper(path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+    let path = path.as_ref().canonicalize()?;
+    ::wallpaper::set_from_path(&path.to_string_lossy())
+}
+
+/// Parses `current 1920 x 1080` from the first line of `xrandr --current`.
+fn parse_xrandr(output: &str) -> Option<(u32, u32)> {
+    let current = output.lines().next()?.split("current ").nth(1)?;
+    let mut parts = current.split(',').next()?.split(" x ");
+    Some((parts.next()?.trim().parse().ok()?, parts.next()?.trim().parse().ok()?))
+}
+
+/// Parses the first `Resolution: 2560 x 1600` line of `system_profiler SPDisplaysDataType`.
+fn parse_system_profiler(output: &str) -> Option<(u32, u32)> {
+    let line = output.lines().find_map(|line| line.trim().strip_prefix("Resolution: "))?;
+    let mut parts = line.split_whitespace();
+    let width = parts.next()?.parse().ok()?;
+    parts.next()?; // "x"
+    Some((width, parts.next()?.parse().ok()?))
+}
+
+/// Parses the first row of numbers printed by `wmic`.
+fn parse_wmic(output: &str) -> Option<(u32, u32)> {
+    output.lines().skip(1).find_map(|line| {
+        let mut parts = line.split_whitespace();
+        Some((parts.next()?.parse().ok()?, parts.next()?.parse().ok()?))
+    })
+}
+
+fn splitmix64(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_display_tools() {
+        let xrandr = "Screen 0: minimum 8 x 8, current 2560 x 1440, maximum 32767 x 32767\n";
+        assert_eq!(parse_xrandr(xrandr), Some((2560, 1440)));
+
+        let profiler = "Graphics/Displays:\n    Display:\n      Resolution: 3024 x 1964 Retina\n";
+        assert_eq!(parse_system_profiler(profiler), Some((3024, 1964)));
+
+        let wmic = "CurrentHorizontalResolution  CurrentVerticalResolution\n1920  1080\n";
+        assert_eq!(parse_wmic(wmic), Some((1920, 1080)));
+
+        assert_eq!(parse_xrandr(""), None);
+    }
+
+    #[test]
+    fn test_random_region_zoom_stays_near_preset() {
+        let presets = interesting_regions();
+        for seed in 0..100 {
+            let region = random_region(seed);
+            let preset = presets.iter().find(|p| p.name == region.name).unwrap();
+            let ratio = region.viewport.zoom / preset.viewport.zoom;
+            assert!((0.5..=2.0).contains(&ratio));
+        }
+    }
+}
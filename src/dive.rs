@@ -0,0 +1,416 @@
+//! A file format for sharing zoom paths ("dives") through the Mandelbrot set.
+//!
+//! A dive is a list of keyframes. Consecutive keyframes form a segment which is animated with
+//! `Viewport::interpolate` over the number of frames stored in the first keyframe of the segment,
+//! paced by that keyframe's `Easing` and in its palette, while the iteration limit moves from
+//! one keyframe's to the next. Because a dive stores no resolution, the same file can be
+//! re-rendered at any size, for instance by `animation::DiveSequence`.
+//!
+//! A keyframe at a named location gives the name of a `Viewport::preset` instead of a center and
+//! zoom, so a tour of the well-known places reads like an itinerary.
+//!
+//! Centers are stored as decimal strings so deep locations keep every digit they were written
+//! with, even if the current renderer only uses `f64` precision. Dives are saved as TOML or JSON,
+//! depending on the file extension:
+//!
+//! ```toml
+//! fps = 30
+//!
+//! [[keyframes]]
+//! center_x = "-0.75"
+//! center_y = "0"
+//! zoom = 1.0
+//! iterations = 255
+//! frames = 120
+//! easing = "ease-in-out"
+//!
+//! [[keyframes]]
+//! center_x = "-0.743643887037151"
+//! center_y = "0.13182590420533"
+//! zoom = 10000.0
+//! iterations = 1000
+//! palette = "fire"
+//!
+//! [[keyframes]]
+//! preset = "elephant-valley"
+//! iterations = 500
+//! ```
+
+use std::{fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::viewport::Viewport;
+
+/// Frames per segment for keyframes that do not specify their own.
+pub const DEFAULT_SEGMENT_FRAMES: u32 = 60;
+
+/// Playback speed of dives that do not specify their own.
+pub const DEFAULT_DIVE_FPS: u32 = 30;
+
+/// Serialization format of a dive file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiveFormat {
+    Toml,
+    Json,
+}
+
+impl DiveFormat {
+    /// Picks the format from the extension of `path`, defaulting to TOML.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::dive::DiveFormat;
+    ///
+    /// assert_eq!(DiveFormat::from_path("seahorse.json"), DiveFormat::Json);
+    /// assert_eq!(DiveFormat::from_path("seahorse.dive"), DiveFormat::Toml);
+    /// ```
+    pub fn from_path(path: impl AsRef<Path>) -> Self {
+        match path.as_ref().extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("json") => Self::Json,
+            _ => Self::Toml,
+        }
+    }
+}
+
+/// How the frames of a segment are spread between its keyframes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Easing {
+    /// At a steady pace.
+    #[default]
+    Linear,
+    /// Starting slowly and speeding up.
+    EaseIn,
+    /// Slowing down towards the end.
+    EaseOut,
+    /// Starting and ending slowly, so consecutive segments join without a jolt.
+    EaseInOut,
+}
+
+impl Easing {
+    pub const ALL: [Self; 4] = [Self::Linear, Self::EaseIn, Self::EaseOut, Self::EaseInOut];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Linear => "linear",
+            Self::EaseIn => "ease-in",
+            Self::EaseOut => "ease-out",
+            Self::EaseInOut => "ease-in-out",
+        }
+    }
+
+    /// The easing called `name`, ignoring case.
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|easing| easing.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Progress through a segment after a fraction `t` of its frames, from `0` to `1`.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::dive::Easing;
+    ///
+    /// for easing in Easing::ALL {
+    ///     assert_eq!((easing.apply(0.0), easing.apply(1.0)), (0.0, 1.0));
+    /// }
+    /// assert!(Easing::EaseIn.apply(0.5) < 0.5 && Easing::EaseOut.apply(0.5) > 0.5);
+    /// assert_eq!(Easing::EaseInOut.apply(0.5), 0.5);
+    /// ```
+    pub fn apply(self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Self::Linear => t,
+            Self::EaseIn => t * t,
+            Self::EaseOut => t * (2.0 - t),
+            Self::EaseInOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+/// A location of the dive and the settings of the segment that starts at it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Keyframe {
+    /// Real part of the center, as a decimal string.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub center_x: String,
+    /// Imaginary part of the center, as a decimal string.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub center_y: String,
+    #[serde(default = "default_keyframe_zoom")]
+    pub zoom: f64,
+    /// Name of a `Viewport::preset` whose center and zoom replace the ones above.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preset: Option<String>,
+    pub iterations: u32,
+    /// Frames from this keyframe to the next one. Unused for the last keyframe.
+    #[serde(default = "default_segment_frames")]
+    pub frames: u32,
+    /// Pace of the segment starting at this keyframe.
+    #[serde(default, skip_serializing_if = "is_linear")]
+    pub easing: Easing,
+    /// Name of the palette used for the segment starting at this keyframe.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub palette: Option<String>,
+}
+
+fn default_keyframe_zoom() -> f64 {
+    1.0
+}
+
+fn is_linear(easing: &Easing) -> bool {
+    *easing == Easing::Linear
+}
+
+fn default_segment_frames() -> u32 {
+    DEFAULT_SEGMENT_FRAMES
+}
+
+impl Keyframe {
+    /// Creates a keyframe at `viewport`, with the default segment length and no palette.
+    pub fn new(viewport: &Viewport, iterations: u32) -> Self {
+        Self {
+            center_x: viewport.center_x.to_string(),
+            center_y: viewport.center_y.to_string(),
+            zoom: viewport.zoom,
+            preset: None,
+            iterations,
+            frames: DEFAULT_SEGMENT_FRAMES,
+            easing: Easing::Linear,
+            palette: None,
+        }
+    }
+
+    /// Viewport of this keyframe, that of its preset if it names one, with the center rounded
+    /// to `f64`.
+    pub fn viewport(&self) -> io::Result<Viewport> {
+        if let Some(name) = &self.preset {
+            return Viewport::preset(name).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("unknown preset {name:?}"))
+            });
+        }
+        let parse = |s: &str| {
+            s.trim().parse::<f64>().map_err(|e| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("invalid center {s:?}: {e}"))
+            })
+        };
+        Ok(Viewport::new(parse(&self.center_x)?, parse(&self.center_y)?, self.zoom))
+    }
+
+    /// Center of this keyframe with every digit it was written with.
+    #[cfg(feature = "bigfloat")]
+    pub fn precise_center(&self) -> io::Result<crate::bigfloat::PreciseCenter> {
+        crate::bigfloat::PreciseCenter::parse(&self.center_x, &self.center_y)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+/// A shareable zoom path.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Dive {
+    #[serde(default = "default_dive_fps")]
+    pub fps: u32,
+    #[serde(default)]
+    pub keyframes: Vec<Keyframe>,
+}
+
+fn default_dive_fps() -> u32 {
+    DEFAULT_DIVE_FPS
+}
+
+impl Default for Dive {
+    fn default() -> Self {
+        Self { fps: DEFAULT_DIVE_FPS, keyframes: Vec::new() }
+    }
+}
+
+/// One frame of a dive: where to look and how to render it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiveFrame<'a> {
+    pub viewport: Viewport,
+    pub iterations: u32,
+    pub palette: Option<&'a str>,
+}
+
+impl Dive {
+    /// Loads a dive from `path`, as JSON if the extension is `.json` and as TOML otherwise.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let contents = fs::read_to_string(&path)?;
+        Self::parse(&contents, DiveFormat::from_path(&path))
+    }
+
+    /// Saves the dive to `path`, as JSON if the extension is `.json` and as TOML otherwise.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(&path, self.serialize(DiveFormat::from_path(&path))?)
+    }
+
+    /// Parses a dive from `contents` in the given format.
+    pub fn parse(contents: &str, format: DiveFormat) -> io::Result<Self> {
+        match format {
+            DiveFormat::Toml => toml::from_str(contents).map_err(invalid_data),
+            DiveFormat::Json => serde_json::from_str(contents).map_err(invalid_data),
+        }
+    }
+
+    /// Serializes the dive in the given format.
+    pub fn serialize(&self, format: DiveFormat) -> io::Result<String> {
+        match format {
+            DiveFormat::Toml => toml::to_string(self).map_err(invalid_data),
+            DiveFormat::Json => serde_json::to_string_pretty(self).map_err(invalid_data),
+        }
+    }
+
+    /// Appends `keyframe` to the end of the path.
+    pub fn push(&mut self, keyframe: Keyframe) {
+        self.keyframes.push(keyframe);
+    }
+
+    /// Number of frames of the whole dive, including the final keyframe.
+    pub fn total_frames(&self) -> u32 {
+        match self.keyframes.split_last() {
+            None => 0,
+            Some((_, segments)) => segments.iter().map(|k| k.frames).sum::<u32>() + 1,
+        }
+    }
+
+    /// Viewport, iteration count and palette of frame `index`, or `None` past the last frame.
+    /// Within a segment the iteration count moves linearly, at the pace of the easing, from the
+    /// count of its first keyframe to that of its last.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::{
+    ///     dive::{Dive, Keyframe},
+    ///     viewport::Viewport,
+    /// };
+    ///
+    /// let mut dive = Dive::default();
+    /// dive.push(Keyframe { frames: 10, ..Keyframe::new(&Viewport::default(), 100) });
+    /// dive.push(Keyframe::new(&Viewport::new(-0.75, 0.1, 100.0), 500));
+    ///
+    /// assert_eq!(dive.total_frames(), 11);
+    /// assert_eq!(dive.frame(10).unwrap().unwrap().viewport.zoom, 100.0);
+    /// assert!(dive.frame(11).unwrap().is_none());
+    /// ```
+    pub fn frame(&self, index: u32) -> io::Result<Option<DiveFrame<'_>>> {
+        let mut start = 0;
+        for pair in self.keyframes.windows(2) {
+            let (from, to) = (&pair[0], &pair[1]);
+            if index < start + from.frames {
+                let t = from.easing.apply((index - start) as f64 / from.frames as f64);
+                let iterations =
+                    from.iterations as f64 + (to.iterations as f64 - from.iterations as f64) * t;
+                return Ok(Some(DiveFrame {
+                    viewport: from.viewport()?.interpolate(&to.viewport()?, t),
+                    iterations: iterations.round() as u32,
+                    palette: from.palette.as_deref(),
+                }));
+            }
+            start += from.frames;
+        }
+        match self.keyframes.last() {
+            Some(last) if index == start => Ok(Some(DiveFrame {
+                viewport: last.viewport()?,
+                iterations: last.iterations,
+                palette: last.palette.as_deref(),
+            })),
+            _ => Ok(None),
+        }
+    }
+}
+
+fn invalid_data(e: impl std::error::Error + Send + Sync + 'static) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    fn seahorse_dive() -> Dive {
+        let mut dive = Dive::default();
+        dive.push(Keyframe { frames: 4, ..Keyframe::new(&Viewport::default(), 255) });
+        dive.push(Keyframe {
+            center_x: "-0.7436438870371587167000".to_string(),
+            center_y: "0.1318259042053119".to_string(),
+            palette: Some("fire".to_string()),
+            ..Keyframe::new(&Viewport::new(0.0, 0.0, 1e4), 1000)
+        });
+        dive
+    }
+
+    #[test]
+    fn test_round_trip_keeps_center_digits() {
+        let dive = seahorse_dive();
+        for format in [DiveFormat::Toml, DiveFormat::Json] {
+            let text = dive.serialize(format).unwrap();
+            assert!(text.contains("-0.7436438870371587167000"));
+            assert_eq!(Dive::parse(&text, format).unwrap(), dive);
+        }
+        #[cfg(feature = "bigfloat")]
+        assert_eq!(
+            dive.keyframes[1].precise_center().unwrap().x.to_string(),
+            "-0.7436438870371587167"
+        );
+
+        let path = env::temp_dir().join(format!("mandelbrot-dive-{}.json", std::process::id()));
+        dive.save(&path).unwrap();
+        assert!(fs::read_to_string(&path).unwrap().trim_start().starts_with('{'));
+        assert_eq!(Dive::load(&path).unwrap(), dive);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_frames_follow_segments() {
+        let dive = seahorse_dive();
+        assert_eq!(dive.total_frames(), 5);
+
+        let first = dive.frame(0).unwrap().unwrap();
+        assert_eq!(first.viewport, Viewport::default());
+        assert_eq!((first.iterations, first.palette), (255, None));
+
+        let last = dive.frame(4).unwrap().unwrap();
+        assert_eq!(last.viewport.zoom, 1e4);
+        assert_eq!((last.iterations, last.palette), (1000, Some("fire")));
+
+        let zooms: Vec<f64> =
+            (0..5).map(|i| dive.frame(i).unwrap().unwrap().viewport.zoom).collect();
+        assert!(zooms.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn test_eased_segments_reach_presets() {
+        let text = "[[keyframes]]\npreset = \"classic\"\niterations = 100\nframes = 4\neasing = \
+                    \"ease-in\"\n\n[[keyframes]]\npreset = \"seahorse-valley\"\niterations = 500\n";
+        let dive = Dive::parse(text, DiveFormat::Toml).unwrap();
+        assert_eq!(dive.keyframes[0].easing, Easing::EaseIn);
+        assert_eq!(
+            Dive::parse(&dive.serialize(DiveFormat::Json).unwrap(), DiveFormat::Json).unwrap(),
+            dive
+        );
+
+        let middle = dive.frame(2).unwrap().unwrap();
+        let linear =
+            Viewport::classic().interpolate(&Viewport::preset("seahorse-valley").unwrap(), 0.5);
+        assert!(middle.viewport.zoom < linear.zoom);
+        assert_eq!(middle.iterations, 200);
+        assert_eq!(
+            dive.frame(4).unwrap().unwrap().viewport,
+            Viewport::preset("seahorse-valley").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_invalid_center_is_reported() {
+        let text =
+            "[[keyframes]]\ncenter_x = \"abc\"\ncenter_y = \"0\"\nzoom = 1.0\niterations = 10\n";
+        let dive = Dive::parse(text, DiveFormat::Toml).unwrap();
+        assert_eq!(dive.fps, DEFAULT_DIVE_FPS);
+        assert_eq!(dive.keyframes[0].frames, DEFAULT_SEGMENT_FRAMES);
+        assert_eq!(dive.frame(0).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+}
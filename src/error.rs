@@ -0,0 +1,119 @@
+//! The error type of the fallible functions of this crate.
+//!
+//! Older functions return `io::Result` and keep doing so, but functions that read or write files
+//! the user named, or take sizes from them, return `Error`: it says which file failed, and tells
+//! unusable settings such as a zero-sized image apart from I/O failures, so callers can report
+//! them instead of passing on an opaque message from the encoder.
+
+use std::{
+    error, fmt, io,
+    path::{Path, PathBuf},
+};
+
+use crate::scheduler::RenderCancelled;
+
+/// Largest iteration limit `check_iterations` accepts. Renders never need more; a larger limit is
+/// almost always a typo or an overflowed computation, and would keep every pixel inside the set
+/// busy for minutes.
+pub const MAX_ITERATIONS: u32 = 1_000_000_000;
+
+/// Largest number of pixels `check_dimensions` accepts, so that pixel indices fit in a `u32`.
+pub const MAX_PIXELS: u64 = u32::MAX as u64;
+
+/// `Result` with this crate's `Error`.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Why a render or one of its files failed.
+#[derive(Debug)]
+pub enum Error {
+    /// A width or height of zero, or more than `MAX_PIXELS` pixels.
+    InvalidDimensions { width: u32, height: u32 },
+    /// An iteration limit above `MAX_ITERATIONS`.
+    IterationOverflow { iterations: u32 },
+    /// Reading or writing a file failed, at `path` if known.
+    IoError { path: Option<PathBuf>, source: io::Error },
+    /// A scene or settings file that cannot be parsed or serialized.
+    ConfigError(String),
+    /// The render was stopped through its cancellation flag.
+    Cancelled,
+}
+
+impl Error {
+    /// An I/O error on the file at `path`.
+    pub fn io(path: impl AsRef<Path>, source: io::Error) -> Self {
+        Self::IoError { path: Some(path.as_ref().to_path_buf()), source }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidDimensions { width, height } => write!(
+                f,
+                "cannot render {width}x{height} pixels: both sides must be positive and the image \
+                 at most {MAX_PIXELS} pixels"
+            ),
+            Self::IterationOverflow { iterations } => {
+                write!(f, "{iterations} iterations exceed the limit of {MAX_ITERATIONS}")
+            }
+            Self::IoError { path: Some(path), source } => write!(f, "{}: {source}", path.display()),
+            Self::IoError { path: None, source } => source.fmt(f),
+            Self::ConfigError(message) => f.write_str(message),
+            Self::Cancelled => RenderCancelled.fmt(f),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::IoError { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(source: io::Error) -> Self {
+        Self::IoError { path: None, source }
+    }
+}
+
+impl From<RenderCancelled> for Error {
+    fn from(_: RenderCancelled) -> Self {
+        Self::Cancelled
+    }
+}
+
+/// Checks that a `width` x `height` grid can be rendered.
+///
+/// # Errors
+///
+/// Returns `Error::InvalidDimensions` if a side is zero or the grid has more than `MAX_PIXELS`
+/// pixels.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::error::{check_dimensions, Error};
+///
+/// assert!(check_dimensions(1920, 1080).is_ok());
+/// assert!(matches!(check_dimensions(0, 1080), Err(Error::InvalidDimensions { .. })));
+/// ```
+pub fn check_dimensions(width: u32, height: u32) -> Result<()> {
+    if width == 0 || height == 0 || width as u64 * height as u64 > MAX_PIXELS {
+        return Err(Error::InvalidDimensions { width, height });
+    }
+    Ok(())
+}
+
+/// Checks that `iterations` is an iteration limit renders accept.
+///
+/// # Errors
+///
+/// Returns `Error::IterationOverflow` if it is above `MAX_ITERATIONS`.
+pub fn check_iterations(iterations: u32) -> Result<()> {
+    if iterations > MAX_ITERATIONS {
+        return Err(Error::IterationOverflow { iterations });
+    }
+    Ok(())
+}
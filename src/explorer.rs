@@ -0,0 +1,355 @@
+//! State of the interactive terminal explorer, `mandelbrot explore`.
+//!
+//! The explorer shows the current viewport as ASCII art that fills the terminal, corrected for
+//! the aspect of the characters, and changes it one `Command` at a time. Reading keys and drawing
+//! to the terminal is left to the binary, so the navigation itself can be driven and tested
+//! without a terminal.
+//!
+//! Frames are rendered with `xaos::XaosRenderer`, so a pan or zoom only iterates the columns and
+//! rows of characters it brings into view. Views can be bookmarked into a
+//! `bookmarks::BookmarkStore` and jumped back to, and `bookmark_panel` lays out the list of them
+//! for a side panel next to the art.
+
+use crate::{
+    bookmarks::{Bookmark, BookmarkStore},
+    mandelbrot_ascii::AsciiOptions,
+    viewport::Viewport,
+    xaos::{FrameStats, XaosRenderer},
+};
+
+/// Fraction of the view a pan moves it by.
+pub const PAN_STEP: f64 = 0.1;
+/// Factor a zoom step magnifies or shrinks the view by.
+pub const ZOOM_STEP: f64 = 1.5;
+/// Factor an iteration step raises or lowers the iteration limit by.
+pub const ITERATION_STEP: f64 = 1.5;
+/// Lowest iteration limit `Command::FewerIterations` goes down to.
+pub const MIN_ITERATIONS: u32 = 10;
+/// Columns of the bookmark panel, including its border.
+pub const PANEL_WIDTH: u32 = 32;
+
+/// A change the explorer makes to the view, or an action the binary takes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    PanLeft,
+    PanRight,
+    PanUp,
+    PanDown,
+    ZoomIn,
+    ZoomOut,
+    MoreIterations,
+    FewerIterations,
+    /// Returns to the default view.
+    Reset,
+    /// Saves the view as an image.
+    Save,
+    /// Saves the view as a bookmark.
+    Bookmark,
+    /// Jumps to the next bookmark.
+    Jump,
+    Quit,
+}
+
+impl Command {
+    /// The command of a character key: `h`, `j`, `k` and `l` pan like the arrow keys, `+` (or
+    /// `=`) and `-` zoom, `i` and `I` raise and lower the iteration limit, `r` resets, `s` saves,
+    /// `b` bookmarks, `g` jumps to a bookmark and `q` quits.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::explorer::Command;
+    ///
+    /// assert_eq!(Command::from_key('+'), Some(Command::ZoomIn));
+    /// assert_eq!(Command::from_key('x'), None);
+    /// ```
+    pub fn from_key(key: char) -> Option<Self> {
+        match key {
+            'h' => Some(Self::PanLeft),
+            'l' => Some(Self::PanRight),
+            'k' => Some(Self::PanUp),
+            'j' => Some(Self::PanDown),
+            '+' | '=' => Some(Self::ZoomIn),
+            '-' | '_' => Some(Self::ZoomOut),
+            'i' => Some(Self::MoreIterations),
+            'I' => Some(Self::FewerIterations),
+            'r' => Some(Self::Reset),
+            's' => Some(Self::Save),
+            'b' => Some(Self::Bookmark),
+            'g' => Some(Self::Jump),
+            'q' => Some(Self::Quit),
+            _ => None,
+        }
+    }
+}
+
+/// The view of an exploration session and the size of the terminal it is drawn in.
+#[derive(Debug, Clone)]
+pub struct Explorer {
+    pub viewport: Viewport,
+    pub iterations: u32,
+    /// Characters per row of the art.
+    pub columns: u32,
+    /// Rows of the art.
+    pub rows: u32,
+    renderer: XaosRenderer,
+}
+
+impl Explorer {
+    pub fn new(viewport: Viewport, iterations: u32) -> Self {
+        let (columns, rows) = (crate::mandelbrot_ascii::WIDTH, crate::mandelbrot_ascii::HEIGHT);
+        let renderer = XaosRenderer::new(columns, rows, iterations);
+        Self { viewport, iterations, columns, rows, renderer }
+    }
+
+    /// Fits the art to `columns` x `rows` characters. A new size starts the next frame from
+    /// scratch.
+    pub fn resize(&mut self, columns: u32, rows: u32) {
+        (self.columns, self.rows) = (columns.max(1), rows.max(1));
+        self.renderer.resize(self.columns, self.rows);
+    }
+
+    /// Applies `command` to the view. `Save`, `Bookmark`, `Jump` and `Quit` leave it as it is;
+    /// bookmarks go through `bookmark` and `jump`.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::{
+    ///     explorer::{Command, Explorer},
+    ///     viewport::Viewport,
+    /// };
+    ///
+    /// let mut explorer = Explorer::new(Viewport::classic(), 100);
+    /// explorer.apply(Command::ZoomIn);
+    /// explorer.apply(Command::MoreIterations);
+    /// assert_eq!((explorer.viewport.zoom, explorer.iterations), (1.5, 150));
+    /// ```
+    pub fn apply(&mut self, command: Command) {
+        let viewport = &mut self.viewport;
+        let (step_x, step_y) = (viewport.width() * PAN_STEP, viewport.height() * PAN_STEP);
+        match command {
+            Command::PanLeft => viewport.center_x -= step_x,
+            Command::PanRight => viewport.center_x += step_x,
+            Command::PanUp => viewport.center_y -= step_y,
+            Command::PanDown => viewport.center_y += step_y,
+            Command::ZoomIn => *viewport = viewport.zoomed(ZOOM_STEP),
+            Command::ZoomOut => *viewport = viewport.zoomed(1.0 / ZOOM_STEP),
+            Command::MoreIterations => {
+                self.iterations = (self.iterations as f64 * ITERATION_STEP).ceil() as u32;
+            }
+            Command::FewerIterations => {
+                self.iterations =
+                    ((self.iterations as f64 / ITERATION_STEP) as u32).max(MIN_ITERATIONS);
+            }
+            Command::Reset => *viewport = Viewport::default(),
+            Command::Save | Command::Bookmark | Command::Jump | Command::Quit => {}
+        }
+    }
+
+    /// A bookmark of the view, named `bookmark N` with the first `N` not yet taken in `store`.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::{bookmarks::BookmarkStore, explorer::Explorer, viewport::Viewport};
+    ///
+    /// let mut store = BookmarkStore::default();
+    /// let explorer = Explorer::new(Viewport::new(-0.75, 0.1, 40.0), 300);
+    /// store.add(explorer.bookmark(&store));
+    /// assert_eq!(explorer.bookmark(&store).name, "bookmark 2");
+    /// ```
+    pub fn bookmark(&self, store: &BookmarkStore) -> Bookmark {
+        let name = (1..)
+            .map(|index| format!("bookmark {index}"))
+            .find(|name| store.get(name).is_none())
+            .unwrap_or_default();
+        Bookmark::new(name, self.viewport, self.iterations)
+    }
+
+    /// Moves to the view of `bookmark`, with its iteration limit.
+    pub fn jump(&mut self, bookmark: &Bookmark) {
+        (self.viewport, self.iterations) = (bookmark.viewport, bookmark.iterations);
+    }
+
+    /// Options of the art, which fills `columns` x `rows` with aspect correction.
+    pub fn ascii_options(&self) -> AsciiOptions {
+        AsciiOptions::new(self.columns, self.rows)
+            .with_iterations(self.iterations)
+            .with_aspect_correction(true)
+    }
+
+    /// Renders the art of the view, reusing the characters of the previous frame that the view
+    /// still shows to within half a character.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::{
+    ///     explorer::{Command, Explorer},
+    ///     viewport::Viewport,
+    /// };
+    ///
+    /// let mut explorer = Explorer::new(Viewport::default(), 100);
+    /// explorer.resize(80, 24);
+    /// assert_eq!(explorer.render().computed, 80 * 24);
+    /// explorer.apply(Command::PanRight);
+    /// let stats = explorer.render();
+    /// assert!(stats.reused > stats.computed);
+    /// ```
+    pub fn render(&mut self) -> FrameStats {
+        let opts = self.ascii_options();
+        self.renderer.resize(self.columns, self.rows);
+        self.renderer.set_iterations(self.iterations);
+        let columns = (0..self.columns).map(|x| opts.to_complex_num(&self.viewport, x, 0).0);
+        let rows = (0..self.rows).map(|y| opts.to_complex_num(&self.viewport, 0, y).1);
+        self.renderer.render_coordinates(columns.collect(), rows.collect())
+    }
+
+    /// Escape counts of the art of the last `render` in row-major order, empty before the
+    /// first.
+    pub fn counts(&self) -> &[u32] {
+        self.renderer.counts()
+    }
+
+    /// One line describing the view and the keys.
+    pub fn status(&self) -> String {
+        let Viewport { center_x, center_y, zoom, .. } = self.viewport;
+        format!(
+            "{center_x:.15} {center_y:+.15}i  zoom {zoom:.3e}  iterations {}  [arrows] pan [+/-] \
+             zoom [i/I] iterations [r] reset [s] save [b] bookmark [g] jump [q] quit",
+            self.iterations
+        )
+    }
+}
+
+/// The `rows` lines of the bookmark panel, each `PANEL_WIDTH` characters wide with a border on
+/// the left: a title, then the bookmarks of `store` in jump order with their zoom, the one at
+/// `selected` marked. Bookmarks that do not fit are left out, keeping the selected one in view.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::{
+///     bookmarks::{Bookmark, BookmarkStore},
+///     explorer::{bookmark_panel, PANEL_WIDTH},
+///     viewport::Viewport,
+/// };
+///
+/// let mut store = BookmarkStore::default();
+/// store.add(Bookmark::new("seahorse", Viewport::new(-0.7436, 0.1318, 1e4), 500));
+/// let panel = bookmark_panel(&store, Some(0), 4);
+/// assert_eq!(panel.len(), 4);
+/// assert!(panel[1].contains("> seahorse"));
+/// assert!(panel.iter().all(|line| line.chars().count() == PANEL_WIDTH as usize));
+/// ```
+pub fn bookmark_panel(store: &BookmarkStore, selected: Option<usize>, rows: u32) -> Vec<String> {
+    let width = PANEL_WIDTH as usize - 2;
+    let fit = |text: String| {
+        let text: String = text.chars().take(width).collect();
+        format!("\u{2502} {text:<width$}")
+    };
+    let mut lines = vec![fit(format!("Bookmarks ({})", store.len()))];
+    let visible = (rows as usize).saturating_sub(1);
+    let first = selected.map_or(0, |selected| (selected + 1).saturating_sub(visible));
+    for (index, bookmark) in store.iter().enumerate().skip(first).take(visible) {
+        let marker = if selected == Some(index) { '>' } else { ' ' };
+        lines.push(fit(format!("{marker} {} {:.1e}", bookmark.name, bookmark.viewport.zoom)));
+    }
+    if store.is_empty() && visible > 0 {
+        lines.push(fit("  [b] to add one".to_string()));
+    }
+    lines.resize(rows as usize, fit(String::new()));
+    lines
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mandelbrot_ascii::ascii_counts;
+
+    #[test]
+    fn test_commands_undo_each_other() {
+        let start = Explorer::new(Viewport::new(-0.5, 0.25, 4.0), 200);
+        let mut explorer = start.clone();
+        let pairs = [
+            (Command::PanLeft, Command::PanRight),
+            (Command::PanUp, Command::PanDown),
+            (Command::ZoomIn, Command::ZoomOut),
+        ];
+        for (command, inverse) in pairs {
+            explorer.apply(command);
+            assert_ne!(explorer.viewport, start.viewport);
+            explorer.apply(inverse);
+        }
+        assert!((explorer.viewport.center_x - start.viewport.center_x).abs() < 1e-12);
+        assert!((explorer.viewport.center_y - start.viewport.center_y).abs() < 1e-12);
+        assert!((explorer.viewport.zoom - start.viewport.zoom).abs() < 1e-12);
+
+        for _ in 0..20 {
+            explorer.apply(Command::FewerIterations);
+        }
+        assert_eq!(explorer.iterations, MIN_ITERATIONS);
+        explorer.apply(Command::Reset);
+        assert_eq!(explorer.viewport, Viewport::default());
+    }
+
+    #[test]
+    fn test_jump_returns_to_a_bookmark() {
+        let mut store = BookmarkStore::default();
+        let mut explorer = Explorer::new(Viewport::new(-0.5, 0.25, 4.0), 200);
+        let start = explorer.viewport;
+        store.add(explorer.bookmark(&store));
+        explorer.apply(Command::ZoomIn);
+        explorer.apply(Command::MoreIterations);
+        store.add(explorer.bookmark(&store));
+        explorer.apply(Command::Reset);
+
+        explorer.jump(store.jump(0).unwrap());
+        assert_eq!((explorer.viewport, explorer.iterations), (start, 200));
+        assert_eq!(store.jump(1).unwrap().iterations, 300);
+
+        // The selected bookmark stays in view when the list is longer than the panel.
+        for _ in 0..5 {
+            store.add(explorer.bookmark(&store));
+        }
+        let panel = bookmark_panel(&store, Some(6), 3);
+        assert_eq!(panel.len(), 3);
+        assert!(panel[2].contains("> bookmark 7"), "{panel:?}");
+    }
+
+    #[test]
+    fn test_counts_fill_the_terminal() {
+        let mut explorer = Explorer::new(Viewport::default(), 50);
+        explorer.resize(120, 30);
+        explorer.render();
+        let counts = explorer.counts();
+        assert_eq!(counts.len(), 120 * 30);
+        // The center of the default view is inside the set.
+        assert_eq!(counts[15 * 120 + 60], 50);
+    }
+
+    #[test]
+    fn test_pans_and_zooms_reuse_the_previous_frame() {
+        let (columns, rows, iterations) = (120, 40, 100);
+        let mut explorer = Explorer::new(Viewport::new(-0.75, 0.1, 2.0), iterations);
+        explorer.resize(columns, rows);
+        explorer.render();
+        for command in [Command::PanLeft, Command::PanDown, Command::ZoomIn, Command::ZoomOut] {
+            explorer.apply(command);
+            let stats = explorer.render();
+            // A zoom step of 1.5 still keeps two thirds of the columns and of the rows.
+            assert!(stats.reused * 3 > columns as u64 * rows as u64, "{command:?}: {stats:?}");
+            // Reused characters are off by at most half a character, which keeps the shape.
+            let inside = |counts: &[u32]| counts.iter().filter(|&&c| c == iterations).count();
+            let exact = inside(&ascii_counts(&explorer.viewport, &explorer.ascii_options()));
+            let approximate = inside(explorer.counts());
+            assert!(exact.abs_diff(approximate) * 20 < exact, "{command:?}: {approximate}");
+        }
+
+        // New sizes and iteration limits cannot reuse counts of the old ones.
+        explorer.resize(100, 40);
+        assert_eq!(explorer.render().reused, 0);
+        explorer.apply(Command::MoreIterations);
+        assert_eq!(explorer.render().reused, 0);
+        let exact = ascii_counts(&explorer.viewport, &explorer.ascii_options());
+        assert_eq!(explorer.counts(), exact);
+    }
+}
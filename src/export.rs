@@ -0,0 +1,427 @@
+//! Encoders for output formats that need more control than `ImageBuffer::save` offers.
+//!
+//! `OutputFormat` lists the formats images can be saved in and is inferred from the extension of
+//! the output path; `save_as` writes an image in any of them. Most go through the encoders of the
+//! `image` crate. WebP is written losslessly.
+//!
+//! JPEG output goes through `jpeg-encoder` rather than the encoder of the `image` crate, because
+//! the latter cannot choose the chroma subsampling. Renders of the Mandelbrot set have large
+//! smooth gradients next to fine filaments, and full-resolution chroma (4:4:4) keeps colored
+//! filaments sharp at the cost of a somewhat larger file.
+//!
+//! ICO output renders the set once per icon size, so every size of an application icon is drawn
+//! at its native resolution instead of being downscaled from a single large render. BMP output
+//! needs no special handling and goes through `ImageBuffer::save`.
+//!
+//! Cubemaps are written as six square images named after their faces (`px.png`, `nx.png`, ...),
+//! the layout game engines import as a skybox.
+
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Cursor, Seek, Write},
+    path::Path,
+};
+
+use image::{
+    codecs::{
+        ico::{IcoEncoder, IcoFrame},
+        pnm::{PnmSubtype, SampleEncoding},
+    },
+    error::{EncodingError, ImageFormatHint, LimitError, LimitErrorKind},
+    ColorType, DynamicImage, ImageEncoder, ImageError, ImageFormat, ImageOutputFormat, ImageResult,
+    RgbImage,
+};
+
+use crate::{
+    mandelbrot_img::compose,
+    projection::{compose_projected, CubeFace, Projection},
+    viewport::Viewport,
+};
+
+/// Sizes rendered into an icon by `compose_icon`, in pixels.
+pub const ICON_SIZES: [u32; 4] = [16, 32, 64, 256];
+
+/// Default JPEG quality, high enough that banding in gradients is not visible.
+pub const DEFAULT_JPEG_QUALITY: u8 = 90;
+
+/// A format images are saved in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Png,
+    /// Lossy, with the quality given to `save_as` and full-resolution chroma.
+    Jpeg,
+    Bmp,
+    Tiff,
+    /// Lossless WebP.
+    WebP,
+    /// Binary PPM, the uncompressed RGB format of Netpbm.
+    Ppm,
+    /// A single-frame GIF, quantized to 256 colors.
+    Gif,
+    /// An icon with the image as its only entry; see `save_ico` for icons with several sizes.
+    Ico,
+}
+
+impl OutputFormat {
+    pub const ALL: [Self; 8] =
+        [Self::Png, Self::Jpeg, Self::Bmp, Self::Tiff, Self::WebP, Self::Ppm, Self::Gif, Self::Ico];
+
+    /// Parses the names returned by `name`, or any of `extensions`, ignoring case.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::export::OutputFormat;
+    ///
+    /// assert_eq!(OutputFormat::parse("WebP"), Some(OutputFormat::WebP));
+    /// assert_eq!(OutputFormat::parse("jpg"), Some(OutputFormat::Jpeg));
+    /// assert_eq!(OutputFormat::parse("svg"), None);
+    /// ```
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|format| {
+            format.name().eq_ignore_ascii_case(name)
+                || format.extensions().iter().any(|extension| extension.eq_ignore_ascii_case(name))
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Jpeg => "jpeg",
+            Self::Bmp => "bmp",
+            Self::Tiff => "tiff",
+            Self::WebP => "webp",
+            Self::Ppm => "ppm",
+            Self::Gif => "gif",
+            Self::Ico => "ico",
+        }
+    }
+
+    /// File extensions of the format, the usual one first.
+    pub fn extensions(&self) -> &'static [&'static str] {
+        match self {
+            Self::Png => &["png"],
+            Self::Jpeg => &["jpg", "jpeg"],
+            Self::Bmp => &["bmp"],
+            Self::Tiff => &["tif", "tiff"],
+            Self::WebP => &["webp"],
+            Self::Ppm => &["ppm"],
+            Self::Gif => &["gif"],
+            Self::Ico => &["ico"],
+        }
+    }
+
+    /// The format named by the extension of `path`, if it is one of `extensions`.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::export::OutputFormat;
+    ///
+    /// assert_eq!(OutputFormat::from_path("out/poster.TIF"), Some(OutputFormat::Tiff));
+    /// assert_eq!(OutputFormat::from_path("poster"), None);
+    /// ```
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?;
+        Self::ALL.into_iter().find(|format| {
+            format.extensions().iter().any(|known| known.eq_ignore_ascii_case(extension))
+        })
+    }
+}
+
+/// Resolution of the chroma channels relative to the luma channel.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChromaSubsampling {
+    /// Full chroma resolution.
+    #[default]
+    Yuv444,
+    /// Half horizontal chroma resolution.
+    Yuv422,
+    /// Half horizontal and vertical chroma resolution, the smallest files.
+    Yuv420,
+}
+
+impl ChromaSubsampling {
+    /// Parses the usual short names `444`, `422` and `420`, with or without a `yuv` prefix.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::export::ChromaSubsampling;
+    ///
+    /// assert_eq!(ChromaSubsampling::parse("420"), Some(ChromaSubsampling::Yuv420));
+    /// assert_eq!(ChromaSubsampling::parse("YUV444"), Some(ChromaSubsampling::Yuv444));
+    /// assert_eq!(ChromaSubsampling::parse("411"), None);
+    /// ```
+    pub fn parse(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+        match name.strip_prefix("yuv").unwrap_or(&name) {
+            "444" => Some(Self::Yuv444),
+            "422" => Some(Self::Yuv422),
+            "420" => Some(Self::Yuv420),
+            _ => None,
+        }
+    }
+
+    fn sampling_factor(self) -> jpeg_encoder::SamplingFactor {
+        match self {
+            Self::Yuv444 => jpeg_encoder::SamplingFactor::R_4_4_4,
+            Self::Yuv422 => jpeg_encoder::SamplingFactor::R_4_2_2,
+            Self::Yuv420 => jpeg_encoder::SamplingFactor::R_4_2_0,
+        }
+    }
+}
+
+/// Settings for JPEG output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JpegOptions {
+    /// Quality from 1 (smallest) to 100 (best).
+    pub quality: u8,
+    pub subsampling: ChromaSubsampling,
+}
+
+impl Default for JpegOptions {
+    fn default() -> Self {
+        Self { quality: DEFAULT_JPEG_QUALITY, subsampling: ChromaSubsampling::default() }
+    }
+}
+
+/// Encodes `image` as JPEG into an in-memory buffer.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::{
+///     export::{encode_jpeg, JpegOptions},
+///     mandelbrot_img::compose,
+/// };
+///
+/// let jpeg = encode_jpeg(&compose(64, 64, 100), &JpegOptions::default()).unwrap();
+/// assert_eq!(&jpeg[..2], &[0xFF, 0xD8]);
+/// ```
+pub fn encode_jpeg(image: &RgbImage, opts: &JpegOptions) -> ImageResult<Vec<u8>> {
+    let mut bytes = Vec::new();
+    write_jpeg(image, opts, &mut bytes)?;
+    Ok(bytes)
+}
+
+/// Saves `image` as a JPEG file at `path`.
+pub fn save_jpeg(image: &RgbImage, path: impl AsRef<Path>, opts: &JpegOptions) -> ImageResult<()> {
+    let file = BufWriter::new(File::create(path)?);
+    write_jpeg(image, opts, file)
+}
+
+fn write_jpeg<W: jpeg_encoder::JfifWrite>(
+    image: &RgbImage,
+    opts: &JpegOptions,
+    out: W,
+) -> ImageResult<()> {
+    let (width, height) = match (u16::try_from(image.width()), u16::try_from(image.height())) {
+        (Ok(width), Ok(height)) => (width, height),
+        _ => return Err(ImageError::Limits(LimitError::from_kind(LimitErrorKind::DimensionError))),
+    };
+    let mut encoder = jpeg_encoder::Encoder::new(out, opts.quality.clamp(1, 100));
+    encoder.set_sampling_factor(opts.subsampling.sampling_factor());
+    encoder.encode(image.as_raw(), width, height, jpeg_encoder::ColorType::Rgb).map_err(|e| {
+        ImageError::Encoding(EncodingError::new(ImageFormatHint::Exact(ImageFormat::Jpeg), e))
+    })
+}
+
+/// Encodes `image` in `format` into an in-memory buffer. `quality`, from 1 to 100, is only used
+/// by JPEG.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::{
+///     export::{encode_as, OutputFormat},
+///     mandelbrot_img::compose,
+/// };
+///
+/// let ppm = encode_as(&compose(64, 48, 100), OutputFormat::Ppm, 90).unwrap();
+/// assert!(ppm.starts_with(b"P6"));
+/// ```
+pub fn encode_as(image: &RgbImage, format: OutputFormat, quality: u8) -> ImageResult<Vec<u8>> {
+    let mut bytes = Cursor::new(Vec::new());
+    write_as(image, format, quality, &mut bytes)?;
+    Ok(bytes.into_inner())
+}
+
+/// Saves `image` at `path` in `format`, whatever the extension of `path`. `quality`, from 1 to
+/// 100, is only used by JPEG.
+pub fn save_as(
+    image: &RgbImage,
+    path: impl AsRef<Path>,
+    format: OutputFormat,
+    quality: u8,
+) -> ImageResult<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    write_as(image, format, quality, &mut file)?;
+    file.flush()?;
+    Ok(())
+}
+
+fn write_as<W: Write + Seek>(
+    image: &RgbImage,
+    format: OutputFormat,
+    quality: u8,
+    out: &mut W,
+) -> ImageResult<()> {
+    let format = match format {
+        OutputFormat::Jpeg => {
+            let opts = JpegOptions { quality, ..JpegOptions::default() };
+            return write_jpeg(image, &opts, out);
+        }
+        OutputFormat::Ico => return write_ico(std::slice::from_ref(image), out),
+        OutputFormat::Png => ImageOutputFormat::Png,
+        OutputFormat::Bmp => ImageOutputFormat::Bmp,
+        OutputFormat::Tiff => ImageOutputFormat::Tiff,
+        OutputFormat::WebP => ImageOutputFormat::WebP,
+        OutputFormat::Ppm => ImageOutputFormat::Pnm(PnmSubtype::Pixmap(SampleEncoding::Binary)),
+        OutputFormat::Gif => ImageOutputFormat::Gif,
+    };
+    image.write_to(out, format)
+}
+
+/// Renders the set once for every size in `ICON_SIZES`.
+pub fn compose_icon(iterations: u32) -> Vec<RgbImage> {
+    ICON_SIZES.iter().map(|&size| compose(size, size, iterations)).collect()
+}
+
+/// Encodes `frames` as a multi-resolution ICO file into an in-memory buffer. Each frame must be
+/// at most 256 pixels wide and high.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::export::{compose_icon, encode_ico};
+///
+/// let ico = encode_ico(&compose_icon(100)).unwrap();
+/// assert_eq!(&ico[..4], &[0, 0, 1, 0]);
+/// ```
+pub fn encode_ico(frames: &[RgbImage]) -> ImageResult<Vec<u8>> {
+    let mut bytes = Vec::new();
+    write_ico(frames, &mut bytes)?;
+    Ok(bytes)
+}
+
+/// Saves `frames` as a multi-resolution ICO file at `path`.
+pub fn save_ico(frames: &[RgbImage], path: impl AsRef<Path>) -> ImageResult<()> {
+    write_ico(frames, BufWriter::new(File::create(path)?))
+}
+
+fn write_ico<W: std::io::Write>(frames: &[RgbImage], out: W) -> ImageResult<()> {
+    // PNG entries of an icon must be RGBA to be readable by common decoders.
+    let frames = frames
+        .iter()
+        .map(|frame| {
+            let rgba = DynamicImage::ImageRgb8(frame.clone()).into_rgba8();
+            IcoFrame::as_png(rgba.as_raw(), rgba.width(), rgba.height(), ColorType::Rgba8)
+        })
+        .collect::<ImageResult<Vec<_>>>()?;
+    IcoEncoder::new(out).encode_images(&frames)
+}
+
+/// Encodes `image` as an uncompressed BMP file into an in-memory buffer.
+pub fn encode_bmp(image: &RgbImage) -> ImageResult<Vec<u8>> {
+    let mut bytes = Vec::new();
+    image::codecs::bmp::BmpEncoder::new(&mut bytes).write_image(
+        image.as_raw(),
+        image.width(),
+        image.height(),
+        ColorType::Rgb8,
+    )?;
+    Ok(bytes)
+}
+
+/// Renders the six faces of a cubemap around the Riemann sphere, `size` pixels square, with the
+/// center of `viewport` in the middle of the front face.
+pub fn compose_cubemap(
+    size: u32,
+    iterations: u32,
+    viewport: &Viewport,
+) -> Vec<(CubeFace, RgbImage)> {
+    CubeFace::ALL
+        .iter()
+        .map(|&face| {
+            let projection = Projection::CubeFace(face);
+            (face, compose_projected(size, size, iterations, viewport, &projection))
+        })
+        .collect()
+}
+
+/// Saves the faces of `compose_cubemap` as `px.png`, `nx.png`, `py.png`, `ny.png`, `pz.png` and
+/// `nz.png` in the directory `dir`, creating it if needed.
+pub fn save_cubemap(
+    dir: impl AsRef<Path>,
+    size: u32,
+    iterations: u32,
+    viewport: &Viewport,
+) -> ImageResult<()> {
+    let dir = dir.as_ref();
+    fs::create_dir_all(dir)?;
+    for (face, image) in compose_cubemap(size, iterations, viewport) {
+        image.save(dir.join(format!("{}.png", face.name())))?;
+    }
+    Ok(())
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quality_and_subsampling_shrink_output() {
+        let image = compose(128, 128, 255);
+        let best = JpegOptions { quality: 100, subsampling: ChromaSubsampling::Yuv444 };
+        let small = JpegOptions { quality: 50, subsampling: ChromaSubsampling::Yuv420 };
+        let best = encode_jpeg(&image, &best).unwrap();
+        let small = encode_jpeg(&image, &small).unwrap();
+        assert!(small.len() < best.len());
+
+        let decoded = image::load_from_memory_with_format(&best, ImageFormat::Jpeg).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (128, 128));
+    }
+
+    #[test]
+    fn test_ico_contains_every_size() {
+        let ico = encode_ico(&compose_icon(50)).unwrap();
+        let decoded = image::load_from_memory_with_format(&ico, ImageFormat::Ico).unwrap();
+        // The decoder picks the largest entry.
+        assert_eq!(decoded.width(), 256);
+        // The ICONDIR header stores the number of images.
+        assert_eq!(u16::from_le_bytes([ico[4], ico[5]]), ICON_SIZES.len() as u16);
+    }
+
+    #[test]
+    fn test_bmp_round_trip() {
+        let image = compose(40, 30, 100);
+        let bmp = encode_bmp(&image).unwrap();
+        let decoded = image::load_from_memory_with_format(&bmp, ImageFormat::Bmp).unwrap();
+        assert_eq!(decoded.to_rgb8(), image);
+    }
+
+    #[test]
+    fn test_every_output_format_decodes() {
+        let image = compose(40, 30, 100);
+        for format in OutputFormat::ALL {
+            let bytes = encode_as(&image, format, 100).unwrap();
+            let path = format!("image.{}", format.extensions()[0]);
+            let decoded =
+                image::load_from_memory_with_format(&bytes, ImageFormat::from_path(&path).unwrap())
+                    .unwrap();
+            assert_eq!((decoded.width(), decoded.height()), (40, 30), "{format:?}");
+            if !matches!(format, OutputFormat::Jpeg | OutputFormat::Gif) {
+                assert_eq!(decoded.to_rgb8(), image, "{format:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_save_cubemap_writes_every_face() {
+        let dir = std::env::temp_dir().join(format!("mandelbrot-cubemap-{}", std::process::id()));
+        save_cubemap(&dir, 16, 50, &Viewport::default()).unwrap();
+        for face in CubeFace::ALL {
+            let image = image::open(dir.join(format!("{}.png", face.name()))).unwrap();
+            assert_eq!((image.width(), image.height()), (16, 16));
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,388 @@
+//! C bindings for embedding the renderer in programs written in other languages.
+//!
+//! The library, built as a `cdylib`, exports the functions below under their own names, and
+//! `include/mandelbrot.h`, generated from this module with `cbindgen`, declares them for C and
+//! C++:
+//!
+//! ```c
+//! #include "mandelbrot.h"
+//!
+//! MandelbrotParams params;
+//! mandelbrot_init_params(&params, sizeof params);
+//! params.width = 640;
+//! params.height = 480;
+//! size_t len = mandelbrot_rgba_len(&params);
+//! uint8_t *pixels = malloc(len);
+//! if (mandelbrot_render(&params, pixels, len) != MANDELBROT_STATUS_OK) { ... }
+//! ```
+//!
+//! The caller owns every buffer: the functions only write into the memory they are given and
+//! never keep a pointer after they return, so buffers are allocated and freed with the caller's
+//! own allocator, and nothing has to be released through this library.
+//!
+//! `MandelbrotParams` only grows at the end and starts with its own size, which the caller sets
+//! to `sizeof` the struct of the header it was compiled against, `mandelbrot_init_params` doing
+//! it along with the defaults. The library reads only that many bytes, gives the fields the
+//! caller's version lacks their defaults and ignores those it does not know itself, so a program
+//! keeps working with both older and newer builds of the library. A panic never unwinds into
+//! the caller: the functions catch it and report `MANDELBROT_STATUS_PANICKED`.
+
+use std::{
+    mem::{offset_of, size_of},
+    panic::{self, AssertUnwindSafe},
+    ptr, slice,
+};
+
+use crate::{
+    analysis::escape_viewport,
+    error::{check_dimensions, check_iterations, Error},
+    mandelbrot_img::render_rgba8,
+    options::RenderOptions,
+    viewport::Viewport,
+};
+
+/// Outcome of a render, returned by the functions that render.
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MandelbrotStatus {
+    /// The render succeeded.
+    Ok = 0,
+    /// A pointer argument was null.
+    NullPointer = 1,
+    /// The width or height is zero, or the image has too many pixels.
+    InvalidDimensions = 2,
+    /// The iteration limit is above `error::MAX_ITERATIONS`.
+    IterationOverflow = 3,
+    /// The output buffer is smaller than the render.
+    BufferTooSmall = 4,
+    /// The zoom is not a finite number above zero, or the center is not finite.
+    InvalidViewport = 5,
+    /// `struct_size` is smaller than the first version of `MandelbrotParams`.
+    UnsupportedVersion = 6,
+    /// The library panicked; nothing was written.
+    Panicked = 7,
+}
+
+/// End of `smooth`, the last field of the first version of `MandelbrotParams`, and so the
+/// smallest `struct_size` accepted.
+const MIN_PARAMS_SIZE: usize = offset_of!(MandelbrotParams, smooth) + size_of::<u8>();
+
+/// What to render, passed by pointer.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MandelbrotParams {
+    /// Size in bytes of the struct as the caller knows it, `sizeof(MandelbrotParams)`.
+    pub struct_size: usize,
+    pub width: u32,
+    pub height: u32,
+    pub iterations: u32,
+    /// Real part of the center of the view.
+    pub center_x: f64,
+    /// Imaginary part of the center of the view.
+    pub center_y: f64,
+    /// Zoom of the view, see `viewport::Viewport`.
+    pub zoom: f64,
+    /// Whether to use smooth escape values, see `options::RenderOptions::smooth`; `0` is false.
+    pub smooth: u8,
+}
+
+impl Default for MandelbrotParams {
+    fn default() -> Self {
+        let viewport = Viewport::default();
+        let opts = RenderOptions::default();
+        Self {
+            struct_size: size_of::<Self>(),
+            width: 800,
+            height: 600,
+            iterations: opts.iterations,
+            center_x: viewport.center_x,
+            center_y: viewport.center_y,
+            zoom: viewport.zoom,
+            smooth: opts.smooth as u8,
+        }
+    }
+}
+
+impl MandelbrotParams {
+    /// Copies the parameters at `params`, as many bytes as its `struct_size` says, with the
+    /// defaults for the fields the caller's version lacks.
+    ///
+    /// # Safety
+    ///
+    /// `params` must be null or point to `struct_size` readable bytes.
+    unsafe fn read(params: *const Self) -> Result<Self, MandelbrotStatus> {
+        if params.is_null() {
+            return Err(MandelbrotStatus::NullPointer);
+        }
+        // SAFETY: every version starts with `struct_size`.
+        let size = unsafe { ptr::addr_of!((*params).struct_size).read_unaligned() };
+        if size < MIN_PARAMS_SIZE {
+            return Err(MandelbrotStatus::UnsupportedVersion);
+        }
+        let mut copy = Self::default();
+        // SAFETY: the caller guarantees `size` readable bytes, and no more bytes are copied than
+        // either version of the struct has.
+        unsafe {
+            ptr::copy_nonoverlapping(
+                params.cast::<u8>(),
+                ptr::addr_of_mut!(copy).cast::<u8>(),
+                size.min(size_of::<Self>()),
+            );
+        }
+        Ok(copy)
+    }
+
+    fn viewport(&self) -> Viewport {
+        Viewport::new(self.center_x, self.center_y, self.zoom)
+    }
+
+    fn options(&self) -> RenderOptions {
+        RenderOptions::new(self.iterations).with_smooth(self.smooth != 0)
+    }
+
+    fn pixels(&self) -> usize {
+        self.width as usize * self.height as usize
+    }
+
+    /// The first problem that keeps the parameters from being rendered, if any.
+    fn check(&self) -> MandelbrotStatus {
+        let view = [self.center_x, self.center_y, self.zoom];
+        if !view.iter().all(|value| value.is_finite()) || self.zoom <= 0.0 {
+            return MandelbrotStatus::InvalidViewport;
+        }
+        match check_dimensions(self.width, self.height).and(check_iterations(self.iterations)) {
+            Ok(()) => MandelbrotStatus::Ok,
+            Err(Error::IterationOverflow { .. }) => MandelbrotStatus::IterationOverflow,
+            Err(_) => MandelbrotStatus::InvalidDimensions,
+        }
+    }
+}
+
+/// Runs the body of an exported function, reporting a panic as `Panicked` instead of letting it
+/// reach the caller.
+fn catch_panic(body: impl FnOnce() -> MandelbrotStatus) -> MandelbrotStatus {
+    // Nothing is written to the caller's memory until the render is done, so a panic leaves
+    // nothing half updated.
+    panic::catch_unwind(AssertUnwindSafe(body)).unwrap_or(MandelbrotStatus::Panicked)
+}
+
+/// Fills the `size` bytes at `params` with the parameters of the default view at 800 x 600
+/// pixels, `struct_size` set to `size`, which is `sizeof(MandelbrotParams)` of the caller's
+/// header. Fields newer than this library are zeroed.
+///
+/// # Safety
+///
+/// `params` must be null or point to `size` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn mandelbrot_init_params(
+    params: *mut MandelbrotParams,
+    size: usize,
+) -> MandelbrotStatus {
+    catch_panic(|| {
+        if params.is_null() {
+            return MandelbrotStatus::NullPointer;
+        }
+        if size < MIN_PARAMS_SIZE {
+            return MandelbrotStatus::UnsupportedVersion;
+        }
+        let defaults = MandelbrotParams { struct_size: size, ..MandelbrotParams::default() };
+        let known = size.min(size_of::<MandelbrotParams>());
+        // SAFETY: the caller guarantees `size` writable bytes, and `known` is at most the size
+        // of `defaults`.
+        unsafe {
+            let out = params.cast::<u8>();
+            ptr::copy_nonoverlapping(ptr::addr_of!(defaults).cast::<u8>(), out, known);
+            ptr::write_bytes(out.add(known), 0, size - known);
+        }
+        MandelbrotStatus::Ok
+    })
+}
+
+/// Length in bytes of the buffer `mandelbrot_render` needs for `params`, or `0` if `params` is
+/// null or of an unsupported version.
+///
+/// # Safety
+///
+/// `params` must be null or point to a `MandelbrotParams` of `struct_size` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn mandelbrot_rgba_len(params: *const MandelbrotParams) -> usize {
+    panic::catch_unwind(|| {
+        // SAFETY: the caller passes null or a valid pointer.
+        let params = unsafe { MandelbrotParams::read(params) };
+        params.map_or(0, |params| params.pixels() * 4)
+    })
+    .unwrap_or(0)
+}
+
+/// Renders `params` into `out` as opaque RGBA rows, four bytes per pixel starting at the top
+/// left, see `mandelbrot_img::render_rgba8`, and returns `Ok` or the problem. Nothing is
+/// written unless the render succeeds.
+///
+/// # Safety
+///
+/// `params` must be null or point to a `MandelbrotParams` of `struct_size` readable bytes, and
+/// `out` must be null or point to `out_len` writable bytes that no other thread accesses
+/// during the call.
+#[no_mangle]
+pub unsafe extern "C" fn mandelbrot_render(
+    params: *const MandelbrotParams,
+    out: *mut u8,
+    out_len: usize,
+) -> MandelbrotStatus {
+    catch_panic(|| {
+        // SAFETY: the caller passes null or a valid pointer.
+        let params = match unsafe { MandelbrotParams::read(params) } {
+            Ok(params) => params,
+            Err(status) => return status,
+        };
+        if out.is_null() {
+            return MandelbrotStatus::NullPointer;
+        }
+        let status = params.check();
+        if status != MandelbrotStatus::Ok {
+            return status;
+        }
+        if out_len < params.pixels() * 4 {
+            return MandelbrotStatus::BufferTooSmall;
+        }
+        let pixels =
+            render_rgba8(params.width, params.height, &params.viewport(), &params.options());
+        // SAFETY: `out` is not null and the caller guarantees `out_len` writable bytes.
+        let out = unsafe { slice::from_raw_parts_mut(out, out_len) };
+        out[..pixels.len()].copy_from_slice(&pixels);
+        MandelbrotStatus::Ok
+    })
+}
+
+/// Renders the escape counts of `params` into `out`, one per pixel row by row, the iteration
+/// limit for points inside the set, and returns `Ok` or the problem. `out_len` counts elements,
+/// not bytes. Nothing is written unless the render succeeds.
+///
+/// # Safety
+///
+/// `params` must be null or point to a `MandelbrotParams` of `struct_size` readable bytes, and
+/// `out` must be null or point to `out_len` writable, aligned `u32`s that no other thread
+/// accesses during the call.
+#[no_mangle]
+pub unsafe extern "C" fn mandelbrot_render_counts(
+    params: *const MandelbrotParams,
+    out: *mut u32,
+    out_len: usize,
+) -> MandelbrotStatus {
+    catch_panic(|| {
+        // SAFETY: the caller passes null or a valid pointer.
+        let params = match unsafe { MandelbrotParams::read(params) } {
+            Ok(params) => params,
+            Err(status) => return status,
+        };
+        if out.is_null() {
+            return MandelbrotStatus::NullPointer;
+        }
+        let status = params.check();
+        if status != MandelbrotStatus::Ok {
+            return status;
+        }
+        if out_len < params.pixels() {
+            return MandelbrotStatus::BufferTooSmall;
+        }
+        let escapes =
+            escape_viewport(params.width, params.height, &params.viewport(), &params.options());
+        // SAFETY: `out` is not null and the caller guarantees `out_len` writable `u32`s.
+        let out = unsafe { slice::from_raw_parts_mut(out, out_len) };
+        for (count, result) in out.iter_mut().zip(&escapes) {
+            *count = result.iterations;
+        }
+        MandelbrotStatus::Ok
+    })
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::mem::MaybeUninit;
+
+    use super::*;
+
+    fn default_params() -> MandelbrotParams {
+        let mut params = MaybeUninit::<MandelbrotParams>::uninit();
+        let size = size_of::<MandelbrotParams>();
+        let status = unsafe { mandelbrot_init_params(params.as_mut_ptr(), size) };
+        assert_eq!(status, MandelbrotStatus::Ok);
+        unsafe { params.assume_init() }
+    }
+
+    #[test]
+    fn test_render_checks_its_arguments() {
+        let params = MandelbrotParams { width: 40, height: 30, ..default_params() };
+        let mut pixels = vec![0; unsafe { mandelbrot_rgba_len(&params) }];
+        let render = |params: &MandelbrotParams, out: &mut [u8]| unsafe {
+            mandelbrot_render(params, out.as_mut_ptr(), out.len())
+        };
+        assert_eq!(render(&params, &mut pixels), MandelbrotStatus::Ok);
+        assert_eq!(pixels, render_rgba8(40, 30, &params.viewport(), &params.options()));
+
+        let mut short = vec![0; pixels.len() - 1];
+        assert_eq!(render(&params, &mut short), MandelbrotStatus::BufferTooSmall);
+        assert!(short.iter().all(|&byte| byte == 0));
+        let empty = MandelbrotParams { width: 0, ..params };
+        assert_eq!(render(&empty, &mut pixels), MandelbrotStatus::InvalidDimensions);
+        let endless = MandelbrotParams { iterations: u32::MAX, ..params };
+        assert_eq!(render(&endless, &mut pixels), MandelbrotStatus::IterationOverflow);
+        for zoom in [0.0, -2.0, f64::NAN, f64::INFINITY] {
+            let view = MandelbrotParams { zoom, ..params };
+            assert_eq!(render(&view, &mut pixels), MandelbrotStatus::InvalidViewport);
+        }
+        let nowhere = MandelbrotParams { center_x: f64::NAN, ..params };
+        assert_eq!(render(&nowhere, &mut pixels), MandelbrotStatus::InvalidViewport);
+        unsafe {
+            assert_eq!(
+                mandelbrot_render(ptr::null(), pixels.as_mut_ptr(), 1),
+                MandelbrotStatus::NullPointer
+            );
+            assert_eq!(
+                mandelbrot_render(&params, ptr::null_mut(), 0),
+                MandelbrotStatus::NullPointer
+            );
+        }
+
+        let mut counts = vec![0; 40 * 30];
+        let status =
+            unsafe { mandelbrot_render_counts(&params, counts.as_mut_ptr(), counts.len()) };
+        assert_eq!(status, MandelbrotStatus::Ok);
+        assert_eq!(counts[15 * 40 + 25], params.iterations);
+    }
+
+    #[test]
+    fn test_params_of_other_versions_are_read_by_their_size() {
+        // A newer caller's struct, with a field this library does not know.
+        #[repr(C)]
+        struct Newer {
+            params: MandelbrotParams,
+            extra: u64,
+        }
+        let mut newer = Newer { params: default_params(), extra: 7 };
+        let size = size_of::<Newer>();
+        let status = unsafe { mandelbrot_init_params(ptr::addr_of_mut!(newer).cast(), size) };
+        assert_eq!(status, MandelbrotStatus::Ok);
+        assert_eq!((newer.params.struct_size, newer.extra), (size, 0));
+        newer.params.width = 40;
+        newer.params.height = 30;
+        let mut pixels = vec![0; 40 * 30 * 4];
+        let status = unsafe {
+            mandelbrot_render(ptr::addr_of!(newer).cast(), pixels.as_mut_ptr(), pixels.len())
+        };
+        assert_eq!(status, MandelbrotStatus::Ok);
+
+        // The first version ends with `smooth`, without the padding after it.
+        let params = MandelbrotParams { struct_size: MIN_PARAMS_SIZE, ..newer.params };
+        assert_eq!(unsafe { mandelbrot_rgba_len(&params) }, pixels.len());
+        let older = MandelbrotParams { struct_size: MIN_PARAMS_SIZE - 1, ..params };
+        assert_eq!(unsafe { mandelbrot_rgba_len(&older) }, 0);
+        let status = unsafe { mandelbrot_render(&older, pixels.as_mut_ptr(), pixels.len()) };
+        assert_eq!(status, MandelbrotStatus::UnsupportedVersion);
+        let mut short = MaybeUninit::<MandelbrotParams>::uninit();
+        let status = unsafe { mandelbrot_init_params(short.as_mut_ptr(), 8) };
+        assert_eq!(status, MandelbrotStatus::UnsupportedVersion);
+    }
+}
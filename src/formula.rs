@@ -0,0 +1,621 @@
+//! Escape-time formulas parsed at runtime, like `z^2 + c` or `z^3 + c*conj(z)`.
+//!
+//! `Formula::parse` reads an expression of the orbit point `z` and the pixel `c` and compiles it
+//! to a short list of stack instructions, which `Formula::eval` runs once per step of the orbit.
+//! The closure `|z, c| formula.eval(z, c)` renders with `engine::render_escape_time`, so
+//! fractals can be tried out from the command line without recompiling anything.
+//!
+//! Expressions are made of numbers, the variables `z` and `c`, the constants `i`, `pi` and `e`,
+//! the operators `+ - * / ^` and calls of the functions in `Function::ALL`. `^` binds tighter
+//! than a leading minus and groups to the right, and a product can be written without `*`, as
+//! in `2z` or `c(z + 1)`. Parts that only involve constants are computed once while compiling,
+//! and whole powers become repeated multiplication, which is both faster and more accurate.
+
+use std::{error::Error, f64::consts, fmt, str::FromStr};
+
+use num_complex::Complex64;
+
+/// Most values a formula may need at once while it is evaluated. Formulas that nest deeper are
+/// rejected by `Formula::parse`.
+pub const MAX_STACK: usize = 32;
+
+/// Most parentheses, signs and exponents a formula may nest inside one another. The parser
+/// descends once per level, so deeper formulas are rejected before they can exhaust its stack.
+pub const MAX_NESTING: usize = 64;
+
+/// Largest whole exponent computed by repeated multiplication rather than through logarithms.
+const MAX_INT_POWER: f64 = 64.0;
+
+/// Error of a formula that could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormulaError {
+    /// Byte offset into the formula at which the problem was found.
+    pub position: usize,
+    /// What is wrong there.
+    pub message: String,
+}
+
+impl FormulaError {
+    fn new(position: usize, message: impl Into<String>) -> Self {
+        Self { position, message: message.into() }
+    }
+}
+
+impl fmt::Display for FormulaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at column {}", self.message, self.position + 1)
+    }
+}
+
+impl Error for FormulaError {}
+
+/// A function that can be called in a formula.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Function {
+    /// Complex conjugate.
+    Conj,
+    /// Absolute value, as a real number.
+    Abs,
+    /// Real part.
+    Re,
+    /// Imaginary part, as a real number.
+    Im,
+    Exp,
+    /// Principal natural logarithm.
+    Log,
+    /// Principal square root.
+    Sqrt,
+    Sin,
+    Cos,
+    Tan,
+    Sinh,
+    Cosh,
+}
+
+impl Function {
+    pub const ALL: [Self; 12] = [
+        Self::Conj,
+        Self::Abs,
+        Self::Re,
+        Self::Im,
+        Self::Exp,
+        Self::Log,
+        Self::Sqrt,
+        Self::Sin,
+        Self::Cos,
+        Self::Tan,
+        Self::Sinh,
+        Self::Cosh,
+    ];
+
+    /// Parses the names returned by `name`, ignoring case. `ln` is accepted for `Log` too.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::formula::Function;
+    ///
+    /// assert_eq!(Function::parse("Conj"), Some(Function::Conj));
+    /// assert_eq!(Function::parse("ln"), Some(Function::Log));
+    /// assert_eq!(Function::parse("gamma"), None);
+    /// ```
+    pub fn parse(name: &str) -> Option<Self> {
+        if name.eq_ignore_ascii_case("ln") {
+            return Some(Self::Log);
+        }
+        Self::ALL.into_iter().find(|function| function.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Conj => "conj",
+            Self::Abs => "abs",
+            Self::Re => "re",
+            Self::Im => "im",
+            Self::Exp => "exp",
+            Self::Log => "log",
+            Self::Sqrt => "sqrt",
+            Self::Sin => "sin",
+            Self::Cos => "cos",
+            Self::Tan => "tan",
+            Self::Sinh => "sinh",
+            Self::Cosh => "cosh",
+        }
+    }
+
+    /// Applies the function to `z`.
+    pub fn apply(&self, z: Complex64) -> Complex64 {
+        match self {
+            Self::Conj => z.conj(),
+            Self::Abs => Complex64::new(z.norm(), 0.0),
+            Self::Re => Complex64::new(z.re, 0.0),
+            Self::Im => Complex64::new(z.im, 0.0),
+            Self::Exp => z.exp(),
+            Self::Log => z.ln(),
+            Self::Sqrt => z.sqrt(),
+            Self::Sin => z.sin(),
+            Self::Cos => z.cos(),
+            Self::Tan => z.tan(),
+            Self::Sinh => z.sinh(),
+            Self::Cosh => z.cosh(),
+        }
+    }
+}
+
+/// `base^exponent`, with `0^w` taken as `0` for exponents with a positive real part rather than
+/// the NaN of the logarithm of `0`, since every orbit starts there.
+fn pow(base: Complex64, exponent: Complex64) -> Complex64 {
+    if base == Complex64::new(0.0, 0.0) && exponent.re > 0.0 {
+        base
+    } else {
+        base.powc(exponent)
+    }
+}
+
+/// One instruction of a compiled formula, run against a stack of values.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Z,
+    C,
+    Const(Complex64),
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+    PowInt(i32),
+    Neg,
+    Call(Function),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Token {
+    Number(f64),
+    Name,
+    Symbol(char),
+    End,
+}
+
+/// Splits `source` into tokens, each with its byte offset and, for names, its text.
+fn tokenize(source: &str) -> Result<Vec<(usize, Token, &str)>, FormulaError> {
+    let bytes = source.as_bytes();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let start = pos;
+        let byte = bytes[pos];
+        if byte.is_ascii_whitespace() {
+            pos += 1;
+        } else if byte.is_ascii_digit() || byte == b'.' {
+            while pos < bytes.len() && (bytes[pos].is_ascii_digit() || bytes[pos] == b'.') {
+                pos += 1;
+            }
+            // An exponent only when digits follow, so `2e` stays a product with `e`.
+            if pos < bytes.len() && matches!(bytes[pos], b'e' | b'E') {
+                let mut end = pos + 1;
+                if end < bytes.len() && matches!(bytes[end], b'+' | b'-') {
+                    end += 1;
+                }
+                if end < bytes.len() && bytes[end].is_ascii_digit() {
+                    pos = end;
+                    while pos < bytes.len() && bytes[pos].is_ascii_digit() {
+                        pos += 1;
+                    }
+                }
+            }
+            let text = &source[start..pos];
+            let value = text
+                .parse()
+                .map_err(|_| FormulaError::new(start, format!("invalid number '{text}'")))?;
+            tokens.push((start, Token::Number(value), text));
+        } else if byte.is_ascii_alphabetic() || byte == b'_' {
+            while pos < bytes.len() && (bytes[pos].is_ascii_alphanumeric() || bytes[pos] == b'_') {
+                pos += 1;
+            }
+            tokens.push((start, Token::Name, &source[start..pos]));
+        } else if b"+-*/^()".contains(&byte) {
+            pos += 1;
+            tokens.push((start, Token::Symbol(byte as char), &source[start..pos]));
+        } else {
+            let symbol = source[start..].chars().next().unwrap_or_default();
+            return Err(FormulaError::new(start, format!("unexpected '{symbol}'")));
+        }
+    }
+    tokens.push((source.len(), Token::End, ""));
+    Ok(tokens)
+}
+
+/// Recursive descent parser that emits the instructions of each part as soon as it is read.
+struct Parser<'a> {
+    tokens: Vec<(usize, Token, &'a str)>,
+    next: usize,
+    ops: Vec<Op>,
+    /// Number of `unary` calls in progress, which every level of nesting goes through.
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Token {
+        self.tokens[self.next].1
+    }
+
+    fn position(&self) -> usize {
+        self.tokens[self.next].0
+    }
+
+    fn eat(&mut self, symbol: char) -> bool {
+        let found = self.peek() == Token::Symbol(symbol);
+        if found {
+            self.next += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, symbol: char) -> Result<(), FormulaError> {
+        if self.eat(symbol) {
+            Ok(())
+        } else {
+            Err(FormulaError::new(self.position(), format!("expected '{symbol}'")))
+        }
+    }
+
+    /// Adds `op`, computing it right away if its operands are constants.
+    fn emit(&mut self, op: Op) {
+        let len = self.ops.len();
+        let folded = match (op, &self.ops[..]) {
+            (Op::Neg, [.., Op::Const(a)]) => Some((1, -a)),
+            (Op::Call(function), [.., Op::Const(a)]) => Some((1, function.apply(*a))),
+            (Op::PowInt(n), [.., Op::Const(a)]) => Some((1, a.powi(n))),
+            (Op::Add, [.., Op::Const(a), Op::Const(b)]) => Some((2, a + b)),
+            (Op::Sub, [.., Op::Const(a), Op::Const(b)]) => Some((2, a - b)),
+            (Op::Mul, [.., Op::Const(a), Op::Const(b)]) => Some((2, a * b)),
+            (Op::Div, [.., Op::Const(a), Op::Const(b)]) => Some((2, a / b)),
+            (Op::Pow, [.., Op::Const(a), Op::Const(b)]) => Some((2, pow(*a, *b))),
+            _ => None,
+        };
+        match folded {
+            Some((operands, value)) => {
+                self.ops.truncate(len - operands);
+                self.ops.push(Op::Const(value));
+            }
+            None => self.ops.push(op),
+        }
+    }
+
+    /// `expr = term (('+' | '-') term)*`
+    fn expr(&mut self) -> Result<(), FormulaError> {
+        self.term()?;
+        loop {
+            if self.eat('+') {
+                self.term()?;
+                self.emit(Op::Add);
+            } else if self.eat('-') {
+                self.term()?;
+                self.emit(Op::Sub);
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    /// `term = unary (('*' | '/')? unary)*`, where a missing operator multiplies.
+    fn term(&mut self) -> Result<(), FormulaError> {
+        self.unary()?;
+        loop {
+            if self.eat('*') {
+                self.unary()?;
+                self.emit(Op::Mul);
+            } else if self.eat('/') {
+                self.unary()?;
+                self.emit(Op::Div);
+            } else if matches!(self.peek(), Token::Number(_) | Token::Name | Token::Symbol('(')) {
+                self.unary()?;
+                self.emit(Op::Mul);
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    /// `unary = ('-' | '+') unary | power`
+    fn unary(&mut self) -> Result<(), FormulaError> {
+        if self.depth == MAX_NESTING {
+            return Err(FormulaError::new(self.position(), "formula is nested too deeply"));
+        }
+        self.depth += 1;
+        let result = if self.eat('-') {
+            self.unary().map(|()| self.emit(Op::Neg))
+        } else if self.eat('+') {
+            self.unary()
+        } else {
+            self.power()
+        };
+        self.depth -= 1;
+        result
+    }
+
+    /// `power = atom ('^' unary)?`, so `-z^2` is `-(z^2)` and `z^-2` is `z^(-2)`.
+    fn power(&mut self) -> Result<(), FormulaError> {
+        self.atom()?;
+        if self.eat('^') {
+            self.unary()?;
+            match self.ops.last() {
+                Some(&Op::Const(n))
+                    if n.im == 0.0 && n.re.fract() == 0.0 && n.re.abs() <= MAX_INT_POWER =>
+                {
+                    self.ops.pop();
+                    self.emit(Op::PowInt(n.re as i32));
+                }
+                _ => self.emit(Op::Pow),
+            }
+        }
+        Ok(())
+    }
+
+    /// `atom = number | name | function '(' expr ')' | '(' expr ')'`
+    fn atom(&mut self) -> Result<(), FormulaError> {
+        let (position, token, text) = self.tokens[self.next];
+        match token {
+            Token::Number(value) => {
+                self.next += 1;
+                self.emit(Op::Const(Complex64::new(value, 0.0)));
+            }
+            Token::Name => {
+                self.next += 1;
+                let op = match text.to_ascii_lowercase().as_str() {
+                    "z" => Op::Z,
+                    "c" => Op::C,
+                    "i" => Op::Const(Complex64::i()),
+                    "pi" => Op::Const(Complex64::new(consts::PI, 0.0)),
+                    "e" => Op::Const(Complex64::new(consts::E, 0.0)),
+                    _ => {
+                        let function = Function::parse(text).ok_or_else(|| {
+                            FormulaError::new(position, format!("unknown name '{text}'"))
+                        })?;
+                        self.expect('(')?;
+                        self.expr()?;
+                        self.expect(')')?;
+                        Op::Call(function)
+                    }
+                };
+                self.emit(op);
+            }
+            Token::Symbol('(') => {
+                self.next += 1;
+                self.expr()?;
+                self.expect(')')?;
+            }
+            Token::End => return Err(FormulaError::new(position, "unexpected end of formula")),
+            Token::Symbol(symbol) => {
+                return Err(FormulaError::new(position, format!("unexpected '{symbol}'")));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A formula `f(z, c)` compiled by `Formula::parse`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Formula {
+    source: String,
+    ops: Vec<Op>,
+}
+
+impl Formula {
+    /// Parses and compiles `source`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `FormulaError` pointing at the first problem if `source` is not a formula of
+    /// `z` and `c`, if it nests deeper than `MAX_NESTING`, or if evaluating it needs more than
+    /// `MAX_STACK` values at once.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::formula::Formula;
+    ///
+    /// assert!(Formula::parse("z^3 + c*conj(z)").is_ok());
+    /// let error = Formula::parse("z^2 + q").unwrap_err();
+    /// assert_eq!(error.to_string(), "unknown name 'q' at column 7");
+    /// ```
+    pub fn parse(source: &str) -> Result<Self, FormulaError> {
+        let mut parser = Parser { tokens: tokenize(source)?, next: 0, ops: Vec::new(), depth: 0 };
+        parser.expr()?;
+        if parser.peek() != Token::End {
+            let (position, _, text) = parser.tokens[parser.next];
+            return Err(FormulaError::new(position, format!("unexpected '{text}'")));
+        }
+        let mut depth: usize = 0;
+        for op in &parser.ops {
+            match op {
+                Op::Z | Op::C | Op::Const(_) => depth += 1,
+                Op::Add | Op::Sub | Op::Mul | Op::Div | Op::Pow => depth -= 1,
+                Op::PowInt(_) | Op::Neg | Op::Call(_) => {}
+            }
+            if depth > MAX_STACK {
+                return Err(FormulaError::new(0, "formula is nested too deeply"));
+            }
+        }
+        Ok(Self { source: source.trim().to_string(), ops: parser.ops })
+    }
+
+    /// The formula as it was written.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Value of the formula for the orbit point `z` and the pixel `c`.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::formula::Formula;
+    /// use num_complex::Complex64;
+    ///
+    /// let formula = Formula::parse("z^2 + c").unwrap();
+    /// let (z, c) = (Complex64::new(1.0, 2.0), Complex64::new(0.5, -1.0));
+    /// assert_eq!(formula.eval(z, c), z * z + c);
+    /// ```
+    pub fn eval(&self, z: Complex64, c: Complex64) -> Complex64 {
+        let mut stack = [Complex64::new(0.0, 0.0); MAX_STACK];
+        let mut top = 0;
+        for op in &self.ops {
+            match *op {
+                Op::Z | Op::C | Op::Const(_) => {
+                    stack[top] = match *op {
+                        Op::Z => z,
+                        Op::C => c,
+                        Op::Const(value) => value,
+                        _ => unreachable!(),
+                    };
+                    top += 1;
+                }
+                Op::Add | Op::Sub | Op::Mul | Op::Div | Op::Pow => {
+                    top -= 1;
+                    let (a, b) = (stack[top - 1], stack[top]);
+                    stack[top - 1] = match op {
+                        Op::Add => a + b,
+                        Op::Sub => a - b,
+                        Op::Mul => a * b,
+                        Op::Div => a / b,
+                        _ => pow(a, b),
+                    };
+                }
+                Op::PowInt(n) => stack[top - 1] = stack[top - 1].powi(n),
+                Op::Neg => stack[top - 1] = -stack[top - 1],
+                Op::Call(function) => stack[top - 1] = function.apply(stack[top - 1]),
+            }
+        }
+        stack[0]
+    }
+}
+
+impl FromStr for Formula {
+    type Err = FormulaError;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        Self::parse(source)
+    }
+}
+
+impl fmt::Display for Formula {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        analysis::escape_viewport, engine::render_escape_time, options::RenderOptions,
+        viewport::Viewport,
+    };
+
+    fn ops(source: &str) -> Vec<Op> {
+        Formula::parse(source).unwrap().ops
+    }
+
+    #[test]
+    fn test_tokenizer_reads_numbers_names_and_symbols() {
+        assert_eq!(
+            tokenize(" 1.5e-3z^2 + 2e").unwrap(),
+            vec![
+                (1, Token::Number(1.5e-3), "1.5e-3"),
+                (7, Token::Name, "z"),
+                (8, Token::Symbol('^'), "^"),
+                (9, Token::Number(2.0), "2"),
+                (11, Token::Symbol('+'), "+"),
+                // Without digits after it, `e` is the constant.
+                (13, Token::Number(2.0), "2"),
+                (14, Token::Name, "e"),
+                (15, Token::End, ""),
+            ]
+        );
+        assert_eq!(tokenize("conj_2(z)").unwrap()[0], (0, Token::Name, "conj_2"));
+        assert_eq!(tokenize("1..2").unwrap_err().position, 0);
+        assert_eq!(tokenize("z # c").unwrap_err().position, 2);
+    }
+
+    #[test]
+    fn test_operators_follow_precedence() {
+        let (z, c) = (Complex64::new(0.3, -1.2), Complex64::new(-0.7, 0.4));
+        let cases: [(&str, Complex64); 10] = [
+            ("z^2 + c", z * z + c),
+            ("-z^2", -(z * z)),
+            ("z - c - z", z - c - z),
+            ("z / c / z", z / c / z),
+            ("z^-1 - c/2*z", z.inv() - c / 2.0 * z),
+            ("2z(c + 1)", 2.0 * z * (c + 1.0)),
+            ("z^3 + c*conj(z)", z * z * z + c * z.conj()),
+            ("--z + +c", z + c),
+            ("z^c^2", z.powc(c * c)),
+            ("Sqrt(z)^2.5 - ln(c)", z.sqrt().powc(Complex64::new(2.5, 0.0)) - c.ln()),
+        ];
+        for (source, expected) in cases {
+            let value = Formula::parse(source).unwrap().eval(z, c);
+            assert!((value - expected).norm() < 1e-12, "{source}: {value} != {expected}");
+        }
+    }
+
+    #[test]
+    fn test_constants_are_folded_while_compiling() {
+        assert_eq!(ops("2^3^2"), vec![Op::Const(Complex64::new(512.0, 0.0))]);
+        assert_eq!(ops("-(1 + 2i) * 2"), vec![Op::Const(Complex64::new(-2.0, -4.0))]);
+        let [Op::Const(value)] = ops("exp(i pi) + 1.5e1")[..] else {
+            panic!("exp(i pi) + 1.5e1 was not folded");
+        };
+        assert!((value - Complex64::new(14.0, 0.0)).norm() < 1e-12);
+        // Whole exponents, also negative ones, become repeated multiplication.
+        assert_eq!(ops("z^-2"), vec![Op::Z, Op::PowInt(-2)]);
+        assert_eq!(ops("z^(1 + 1) + c"), vec![Op::Z, Op::PowInt(2), Op::C, Op::Add]);
+        assert_eq!(ops("z^2.5"), vec![Op::Z, Op::Const(Complex64::new(2.5, 0.0)), Op::Pow]);
+        assert_eq!(ops("z^100"), vec![Op::Z, Op::Const(Complex64::new(100.0, 0.0)), Op::Pow]);
+    }
+
+    #[test]
+    fn test_errors_point_at_the_problem() {
+        for (source, position) in [
+            ("z^2 +", 5),
+            ("z ** 2", 3),
+            ("(z + c", 6),
+            ("sin z", 4),
+            ("z $ c", 2),
+            ("z)", 1),
+            ("q + z", 0),
+            ("", 0),
+        ] {
+            assert_eq!(Formula::parse(source).unwrap_err().position, position, "{source}");
+        }
+        let stacked = format!("{}z{}", "(z + ".repeat(MAX_STACK), ")".repeat(MAX_STACK));
+        assert!(Formula::parse(&stacked).is_err());
+    }
+
+    #[test]
+    fn test_deep_nesting_is_rejected_without_overflowing() {
+        let parens = |depth| format!("{}z{}", "(".repeat(depth), ")".repeat(depth));
+        assert!(Formula::parse(&parens(MAX_NESTING - 1)).is_ok());
+        for source in [
+            parens(MAX_NESTING),
+            parens(5000),
+            format!("{}z", "-".repeat(5000)),
+            format!("{}z", "sin(".repeat(5000)),
+            "z^".repeat(5000) + "2",
+        ] {
+            let error = Formula::parse(&source).unwrap_err();
+            assert_eq!(error.message, "formula is nested too deeply");
+        }
+    }
+
+    #[test]
+    fn test_parsed_formula_matches_the_builtin_kernel() {
+        let viewport = Viewport::new(-0.745, 0.11, 40.0);
+        let builtin = escape_viewport(48, 36, &viewport, &RenderOptions::new(300));
+        let formula = Formula::parse("z^2 + c").unwrap();
+        let parsed = render_escape_time(48, 36, &viewport, 300, |z, c| formula.eval(z, c));
+        for (builtin, parsed) in builtin.iter().zip(&parsed) {
+            assert_eq!(builtin.iterations, parsed.iterations);
+            assert!((builtin.smooth - parsed.smooth).abs() < 1e-9);
+        }
+    }
+}
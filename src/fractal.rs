@@ -0,0 +1,97 @@
+//! The families of escape-time fractals the kernels can iterate.
+//!
+//! The Mandelbrot set iterates `z^2 + c`. Multibrot sets raise `z` to another power `d` instead,
+//! which gives the set `d - 1`-fold rotational symmetry. Integer powers are computed by repeated
+//! multiplication; real powers use the principal branch of `z^d`, whose branch cut along the
+//! negative real axis shows up as seams in the picture.
+//!
+//! For `d >= 2` every orbit that leaves the disk of radius 2 escapes, like for the Mandelbrot
+//! set. Below that the disk has to grow to `2^(1 / (d - 1))`, see `FractalKind::escape_radius`.
+
+use num_complex::Complex64;
+use serde::{Deserialize, Serialize};
+
+/// Which iteration the kernels run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "name", rename_all = "lowercase")]
+pub enum FractalKind {
+    /// `z^2 + c`.
+    #[default]
+    Mandelbrot,
+    /// `z^power + c`, for a `power` greater than `1`.
+    Multibrot { power: f64 },
+}
+
+impl FractalKind {
+    /// The Multibrot set of `power`, or `None` unless `power` is a finite number greater than `1`.
+    /// A power of `2` gives the Mandelbrot set.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::fractal::FractalKind;
+    ///
+    /// assert_eq!(FractalKind::multibrot(2.0), Some(FractalKind::Mandelbrot));
+    /// assert_eq!(FractalKind::multibrot(3.0), Some(FractalKind::Multibrot { power: 3.0 }));
+    /// assert_eq!(FractalKind::multibrot(1.0), None);
+    /// ```
+    pub fn multibrot(power: f64) -> Option<Self> {
+        if !power.is_finite() || power <= 1.0 {
+            return None;
+        }
+        Some(if power == 2.0 { Self::Mandelbrot } else { Self::Multibrot { power } })
+    }
+
+    /// The exponent of `z`.
+    pub fn power(&self) -> f64 {
+        match self {
+            Self::Mandelbrot => 2.0,
+            Self::Multibrot { power } => *power,
+        }
+    }
+
+    /// Smallest radius past which every orbit is known to escape: `2` for powers of at least
+    /// `2`, and `2^(1 / (power - 1))` below.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::fractal::FractalKind;
+    ///
+    /// assert_eq!(FractalKind::Multibrot { power: 5.0 }.escape_radius(), 2.0);
+    /// assert_eq!(FractalKind::Multibrot { power: 1.5 }.escape_radius(), 4.0);
+    /// ```
+    pub fn escape_radius(&self) -> f64 {
+        2f64.powf(1.0 / (self.power() - 1.0)).max(2.0)
+    }
+
+    /// One step of the iteration, `z^power + c`.
+    pub fn step(&self, z: Complex64, c: Complex64) -> Complex64 {
+        match self {
+            Self::Mandelbrot => z * z + c,
+            Self::Multibrot { power } if power.fract() == 0.0 && *power <= u32::MAX as f64 => {
+                z.powu(*power as u32) + c
+            }
+            // `powf` of zero is NaN, but the orbit starts there.
+            Self::Multibrot { .. } if z == Complex64::new(0.0, 0.0) => c,
+            Self::Multibrot { power } => z.powf(*power) + c,
+        }
+    }
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_step_matches_squaring_and_real_powers() {
+        let (z, c) = (Complex64::new(0.3, -0.4), Complex64::new(-0.1, 0.2));
+        assert_eq!(FractalKind::Mandelbrot.step(z, c), z * z + c);
+        let cubic = FractalKind::Multibrot { power: 3.0 }.step(z, c);
+        assert!((cubic - (z * z * z + c)).norm() < 1e-15);
+        let real = FractalKind::Multibrot { power: 2.5 };
+        assert_eq!(real.step(Complex64::new(0.0, 0.0), c), c);
+        let expected = Complex64::from_polar(z.norm().powf(2.5), z.arg() * 2.5) + c;
+        assert!((real.step(z, c) - expected).norm() < 1e-12);
+    }
+}
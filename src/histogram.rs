@@ -0,0 +1,169 @@
+//! Histogram equalization of escape values.
+//!
+//! Mapping escape counts linearly onto a palette wastes most of its colors: in a typical render
+//! nearly all pixels escape within a few iterations, and the deeper the zoom, the narrower and
+//! higher the band of counts actually present. Equalization instead colors every pixel by the
+//! fraction of escaping pixels that escaped before it, the cumulative distribution of the
+//! counts, so each color covers roughly the same area of the image at any zoom.
+//!
+//! This takes two passes over a render: one to count how many pixels escaped after each number of
+//! iterations, and one to look every pixel up in the resulting distribution. Smooth escape values
+//! are interpolated between their neighbouring whole counts, which keeps gradients continuous.
+
+#[cfg(feature = "image")]
+use image::RgbImage;
+
+#[cfg(feature = "image")]
+use crate::{
+    analysis::escape_viewport,
+    palette::{colorize_values, Palette},
+    viewport::Viewport,
+};
+use crate::{analysis::EscapeResult, options::RenderOptions};
+
+/// Number of escaping pixels per iteration count.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Histogram {
+    counts: Vec<u64>,
+    /// `cumulative[n]` is the number of escaping pixels with fewer than `n` iterations.
+    cumulative: Vec<u64>,
+}
+
+impl Histogram {
+    /// Counts the escaping pixels of `escapes` per iteration count, up to `iterations`.
+    pub fn from_escapes(escapes: &[EscapeResult], iterations: u32) -> Self {
+        let bins = iterations.max(1) as usize;
+        let mut counts = vec![0; bins];
+        for result in escapes.iter().filter(|result| result.escaped) {
+            counts[(result.iterations as usize).min(bins - 1)] += 1;
+        }
+        let cumulative = counts
+            .iter()
+            .scan(0, |total, &count| {
+                let before = *total;
+                *total += count;
+                Some(before)
+            })
+            .collect();
+        Self { counts, cumulative }
+    }
+
+    /// Escaping pixels per iteration count.
+    pub fn counts(&self) -> &[u64] {
+        &self.counts
+    }
+
+    /// Total number of escaping pixels.
+    pub fn total(&self) -> u64 {
+        self.cumulative.last().copied().unwrap_or(0) + self.counts.last().copied().unwrap_or(0)
+    }
+
+    /// Fraction of escaping pixels that escaped before `value`, between `0.0` and `1.0`. The
+    /// fractional part of `value` interpolates within its whole count.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::{analysis::escape, histogram::Histogram, options::RenderOptions};
+    /// use num_complex::Complex64;
+    ///
+    /// let opts = RenderOptions::new(100);
+    /// let escapes: Vec<_> = [2.0, 1.0, 0.0].map(|re| escape(Complex64::new(re, 0.0), &opts)).to_vec();
+    /// let histogram = Histogram::from_escapes(&escapes, 100);
+    /// assert_eq!(histogram.total(), 2);
+    /// assert_eq!(histogram.cdf(0.0), 0.0);
+    /// assert_eq!(histogram.cdf(100.0), 1.0);
+    /// ```
+    pub fn cdf(&self, value: f64) -> f64 {
+        let total = self.total();
+        if total == 0 {
+            return 0.0;
+        }
+        let last = self.counts.len() - 1;
+        let value = value.clamp(0.0, self.counts.len() as f64);
+        let bin = (value.floor() as usize).min(last);
+        let below = self.cumulative[bin] as f64 + (value - bin as f64) * self.counts[bin] as f64;
+        below / total as f64
+    }
+
+    /// Equalized value of every result, in the same order: the `cdf` of its escape value, smooth
+    /// or whole depending on `opts.smooth`, and `1.0` for points that did not escape.
+    pub fn equalize(&self, escapes: &[EscapeResult], opts: &RenderOptions) -> Vec<f64> {
+        escapes
+            .iter()
+            .map(|result| match (result.escaped, opts.smooth) {
+                (false, _) => 1.0,
+                (true, true) => self.cdf(result.smooth),
+                (true, false) => self.cdf(result.iterations as f64),
+            })
+            .collect()
+    }
+}
+
+/// Renders `viewport` with the colors of `palette` spread by histogram equalization, so the
+/// palette is used evenly at any zoom. Points inside the set get `palette.color(1.0)`.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::{histogram::compose_histogram, palette::BuiltinPalette, prelude::*};
+///
+/// let opts = RenderOptions::new(200);
+/// let viewport = Viewport::new(-0.745, 0.11, 100.0);
+/// let image = compose_histogram(64, 48, &viewport, &opts, &BuiltinPalette::Ocean);
+/// assert_eq!(image.dimensions(), (64, 48));
+/// ```
+#[cfg(feature = "image")]
+pub fn compose_histogram<P: Palette + ?Sized>(
+    width: u32,
+    height: u32,
+    viewport: &Viewport,
+    opts: &RenderOptions,
+    palette: &P,
+) -> RgbImage {
+    let escapes = escape_viewport(width, height, viewport, opts);
+    let values = Histogram::from_escapes(&escapes, opts.iterations).equalize(&escapes, opts);
+    colorize_values(width, height, &values, palette)
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{analysis::escape_viewport, viewport::Viewport};
+
+    #[test]
+    fn test_equalized_values_are_spread_evenly() {
+        let opts = RenderOptions::new(500).with_smooth(false);
+        let viewport = Viewport::new(-0.743643887037151, 0.131825904205330, 200.0);
+        let escapes = escape_viewport(80, 60, &viewport, &opts);
+        let histogram = Histogram::from_escapes(&escapes, opts.iterations);
+        let values = histogram.equalize(&escapes, &opts);
+
+        // A quarter of the escaping pixels lands in each quarter of the range, give or take the
+        // pixels that share a count across a boundary.
+        let escaped: Vec<f64> =
+            values.iter().zip(&escapes).filter(|(_, r)| r.escaped).map(|(v, _)| *v).collect();
+        for quarter in 0..4 {
+            let (low, high) = (quarter as f64 / 4.0, (quarter + 1) as f64 / 4.0);
+            let share = escaped.iter().filter(|&&v| v >= low && v < high).count() as f64
+                / escaped.len() as f64;
+            assert!((share - 0.25).abs() < 0.1, "quarter {quarter}: {share}");
+        }
+        assert!(values.iter().zip(&escapes).all(|(v, r)| r.escaped || *v == 1.0));
+    }
+
+    #[test]
+    fn test_cdf_is_monotonic_with_smooth_values() {
+        let opts = RenderOptions::new(100);
+        let escapes = escape_viewport(40, 30, &Viewport::default(), &opts);
+        let histogram = Histogram::from_escapes(&escapes, opts.iterations);
+        let mut previous = 0.0;
+        for step in 0..=400 {
+            let value = histogram.cdf(step as f64 / 4.0);
+            assert!(value >= previous);
+            previous = value;
+        }
+        assert_eq!(previous, 1.0);
+        assert_eq!(Histogram::from_escapes(&[], 10).cdf(3.0), 0.0);
+    }
+}
@@ -0,0 +1,111 @@
+//! Exports character-art renders as a standalone HTML page.
+//!
+//! Every character of the ramp used by `mandelbrot_ascii::to_ascii_char` is given a color, and
+//! runs of equally colored characters are wrapped in a single `<span>` with an inline style inside
+//! a `<pre>` block. The page has no external stylesheets or scripts, so it can be embedded in a
+//! blog post or opened directly in a browser.
+
+use std::{fmt::Write as _, fs, io, path::Path};
+
+/// Background color of the exported page.
+pub const BACKGROUND: &str = "#000000";
+
+/// Color used for a character of the ASCII ramp, from the sparse outside (`.`) to the inside of
+/// the set (`@`).
+pub fn char_color(c: char) -> &'static str {
+    match c {
+        '.' => "#1b2a49",
+        '*' => "#2e4a7d",
+        ':' => "#3f72af",
+        'o' => "#5fa8d3",
+        '&' => "#9bd1e5",
+        '8' => "#f6d186",
+        '#' => "#f89a4b",
+        '@' => "#ffffff",
+        _ => "#cccccc",
+    }
+}
+
+/// Renders `art`, newline separated rows of characters, as a standalone HTML page.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::html::to_html;
+///
+/// let page = to_html("..@@\n", "mandelbrot");
+/// assert!(page.starts_with("<!DOCTYPE html>"));
+/// assert!(page.contains(r#"<span style="color:#1b2a49">..</span>"#));
+/// assert!(page.contains(r#"<span style="color:#ffffff">@@</span>"#));
+/// ```
+pub fn to_html(art: &str, title: &str) -> String {
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    let _ = writeln!(html, "<title>{}</title>", escape(title));
+    html.push_str("</head>\n");
+    let _ = writeln!(html, "<body style=\"margin:0;background:{BACKGROUND}\">");
+    html.push_str("<pre style=\"font-family:monospace;line-height:1.0\">");
+    for line in art.lines() {
+        push_spans(&mut html, line);
+        html.push('\n');
+    }
+    html.push_str("</pre>\n</body>\n</html>\n");
+    html
+}
+
+/// Writes `art` as a standalone HTML page to `path`.
+pub fn write_html_to_file(art: &str, title: &str, path: &Path) -> io::Result<()> {
+    fs::write(path, to_html(art, title))
+}
+
+/// Appends `line` to `html`, grouping consecutive characters of the same color into one span.
+fn push_spans(html: &mut String, line: &str) {
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        let color = char_color(c);
+        let mut run = String::new();
+        run.push(c);
+        while let Some(&next) = chars.peek() {
+            if char_color(next) != color {
+                break;
+            }
+            run.push(next);
+            chars.next();
+        }
+        let _ = write!(html, "<span style=\"color:{color}\">{}</span>", escape(&run));
+    }
+}
+
+/// Escapes the characters with special meaning in HTML text.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escapes_ramp_characters() {
+        let page = to_html("&&", "a < b");
+        assert!(page.contains("<title>a &lt; b</title>"));
+        assert!(page.contains(r#"<span style="color:#9bd1e5">&amp;&amp;</span>"#));
+    }
+
+    #[test]
+    fn test_one_span_per_color_run() {
+        let page = to_html(".*.\n", "");
+        assert_eq!(page.matches("<span").count(), 3);
+    }
+}
@@ -0,0 +1,237 @@
+//! Coloring of the points inside the set, separately from those outside.
+//!
+//! Escape-time coloring has nothing to say about points that never escape: they all reach the
+//! iteration limit and get the same color. `InteriorColoring` picks another strategy for them,
+//! a solid color, their distance to the boundary, or how close their orbit comes to a trap,
+//! while the exterior keeps whatever `options::ColorMode` it was rendered with.
+//!
+//! The distance to the boundary is the interior distance estimate: the orbit of an interior
+//! point settles on an attracting cycle, and the derivatives of the cycle give the distance up
+//! to a factor of 4, the way `analysis::distance_estimate` does outside.
+
+#[cfg(feature = "image")]
+use image::{Rgb, RgbImage};
+use num_complex::Complex64;
+
+use crate::traps::TrapShape;
+#[cfg(feature = "image")]
+use crate::{
+    fractal::FractalKind, mandelbrot_img::trap_value, options::RenderOptions, palette::Palette,
+    scheduler::map_slice, traps::trap_distance, viewport::Viewport,
+};
+
+/// Longest attracting cycle `interior_distance` looks for.
+pub const MAX_PERIOD: u32 = 4096;
+
+/// Distance to the boundary, in pixels, over which `InteriorColoring::Distance` fades from the
+/// color at the boundary to that deep inside.
+pub const INTERIOR_FALLOFF: f64 = 64.0;
+
+/// How points inside the set are colored.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InteriorColoring {
+    /// As the palette colors points that never escape, `Palette::color(1.0)`.
+    #[default]
+    Palette,
+    /// A single color.
+    Solid([u8; 3]),
+    /// By the distance to the boundary, with the palette going from `1.0` at the boundary to
+    /// `0.0` deep inside, see `INTERIOR_FALLOFF`. Only for the Mandelbrot set; other fractals
+    /// keep the palette's color.
+    Distance,
+    /// By how close the orbit comes to a trap, see `traps::trap_distance`.
+    OrbitTrap(TrapShape),
+}
+
+impl InteriorColoring {
+    /// Every strategy, with black as the solid color.
+    pub const ALL: [Self; 7] = [
+        Self::Palette,
+        Self::Solid([0, 0, 0]),
+        Self::Distance,
+        Self::OrbitTrap(TrapShape::Point),
+        Self::OrbitTrap(TrapShape::Line),
+        Self::OrbitTrap(TrapShape::Circle),
+        Self::OrbitTrap(TrapShape::Cross),
+    ];
+
+    /// Parses the names returned by `name`, ignoring case. `"solid"` is black.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::{interior::InteriorColoring, traps::TrapShape};
+    ///
+    /// assert_eq!(InteriorColoring::parse("Distance"), Some(InteriorColoring::Distance));
+    /// assert_eq!(
+    ///     InteriorColoring::parse("trap-circle"),
+    ///     Some(InteriorColoring::OrbitTrap(TrapShape::Circle))
+    /// );
+    /// assert_eq!(InteriorColoring::parse("escape-time"), None);
+    /// ```
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|coloring| coloring.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Palette => "palette",
+            Self::Solid(_) => "solid",
+            Self::Distance => "distance",
+            Self::OrbitTrap(TrapShape::Point) => "trap-point",
+            Self::OrbitTrap(TrapShape::Line) => "trap-line",
+            Self::OrbitTrap(TrapShape::Circle) => "trap-circle",
+            Self::OrbitTrap(TrapShape::Cross) => "trap-cross",
+        }
+    }
+}
+
+/// Estimated distance from `c` to the boundary of the Mandelbrot set, for `c` inside it, or
+/// `None` if the orbit escapes or does not settle on a cycle within `iterations`.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::interior::interior_distance;
+/// use num_complex::Complex64;
+///
+/// // The period-2 bulb is the disk of radius 0.25 around -1.
+/// let d = interior_distance(Complex64::new(-1.0, 0.0), 1000).unwrap();
+/// assert!(d > 0.25 / 4.0 && d < 0.25 * 4.0);
+/// assert_eq!(interior_distance(Complex64::new(1.0, 1.0), 1000), None);
+/// ```
+pub fn interior_distance(c: Complex64, iterations: u32) -> Option<f64> {
+    // Let the orbit settle on its cycle.
+    let mut z = Complex64::new(0.0, 0.0);
+    for _ in 0..iterations {
+        z = z * z + c;
+        if z.norm_sqr() > 4.0 {
+            return None;
+        }
+    }
+    let period = cycle_period(z, c)?;
+    // Newton's method on f^p(z) = z pulls the point onto the cycle.
+    for _ in 0..8 {
+        let (w, dz) =
+            (0..period).fold((z, Complex64::new(1.0, 0.0)), |(w, dz), _| (w * w + c, 2.0 * w * dz));
+        let step = (w - z) / (dz - 1.0);
+        z -= step;
+        if !step.is_finite() || step.norm_sqr() < 1e-30 {
+            break;
+        }
+    }
+
+    let one = Complex64::new(1.0, 0.0);
+    let (mut dz, mut dc, mut dzdz, mut dcdz) =
+        (one, Complex64::default(), Complex64::default(), Complex64::default());
+    for _ in 0..period {
+        dcdz = 2.0 * (z * dcdz + dz * dc);
+        dzdz = 2.0 * (dz * dz + z * dzdz);
+        dc = 2.0 * z * dc + 1.0;
+        dz = 2.0 * z * dz;
+        z = z * z + c;
+    }
+    if dz.norm_sqr() >= 1.0 {
+        return None;
+    }
+    let distance = (1.0 - dz.norm_sqr()) / (dcdz + dzdz * dc / (one - dz)).norm();
+    distance.is_finite().then_some(distance)
+}
+
+/// Length of the cycle that `z`, a point on the settled orbit of `c`, lies on: the first return
+/// of the orbit close to `z`, or the closest return if there is none.
+fn cycle_period(z: Complex64, c: Complex64) -> Option<u32> {
+    let tolerance = 1e-9 * z.norm_sqr().max(1e-6);
+    let (mut w, mut closest) = (z, None::<(u32, f64)>);
+    for period in 1..=MAX_PERIOD {
+        w = w * w + c;
+        let distance = (w - z).norm_sqr();
+        if distance < tolerance {
+            return Some(period);
+        }
+        if closest.is_none_or(|(_, nearest)| distance < nearest) {
+            closest = Some((period, distance));
+        }
+    }
+    closest.map(|(period, _)| period)
+}
+
+/// Recolors the pixels of `image`, rendered from `viewport`, whose entry in `inside` is `true`
+/// with `coloring`, taking colors from `palette` for the strategies that shade. Leaves the image
+/// alone for `InteriorColoring::Palette`.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::{
+///     analysis::escape_viewport,
+///     interior::{color_interior, InteriorColoring},
+///     palette::{colorize_escapes, BuiltinPalette},
+///     prelude::*,
+/// };
+///
+/// let (viewport, opts) = (Viewport::default(), RenderOptions::new(100));
+/// let escapes = escape_viewport(40, 30, &viewport, &opts);
+/// let mut image = colorize_escapes(40, 30, &escapes, &opts, &BuiltinPalette::Fire);
+/// let inside: Vec<bool> = escapes.iter().map(|result| !result.escaped).collect();
+/// let blue = InteriorColoring::Solid([0, 0, 255]);
+/// color_interior(&mut image, &inside, &viewport, &opts, blue, &BuiltinPalette::Fire);
+/// assert_eq!(image.get_pixel(25, 15).0, [0, 0, 255]);
+/// ```
+#[cfg(feature = "image")]
+pub fn color_interior<P: Palette + ?Sized>(
+    image: &mut RgbImage,
+    inside: &[bool],
+    viewport: &Viewport,
+    opts: &RenderOptions,
+    coloring: InteriorColoring,
+    palette: &P,
+) {
+    if coloring == InteriorColoring::Palette {
+        return;
+    }
+    let (width, height) = image.dimensions();
+    let pixel_size = viewport.spans(width, height).0 / width as f64;
+    let pixels: Vec<u32> = (0..width * height).filter(|&i| inside[i as usize]).collect();
+    // The palette need not be `Sync`, so only the values are computed in parallel.
+    let values = map_slice(&pixels, |&i| {
+        let c = viewport.point(i % width, i / width, width, height);
+        match coloring {
+            InteriorColoring::Palette | InteriorColoring::Solid(_) => 1.0,
+            InteriorColoring::Distance if opts.kind == FractalKind::Mandelbrot => {
+                match interior_distance(c, opts.iterations) {
+                    Some(distance) => {
+                        let depth = (distance / (INTERIOR_FALLOFF * pixel_size)).min(1.0);
+                        1.0 - depth.sqrt()
+                    }
+                    None => 1.0,
+                }
+            }
+            InteriorColoring::Distance => 1.0,
+            InteriorColoring::OrbitTrap(shape) => trap_value(trap_distance(c, opts, shape)),
+        }
+    });
+    for (i, value) in pixels.into_iter().zip(values) {
+        let color = match coloring {
+            InteriorColoring::Solid(color) => Rgb(color),
+            _ => palette.color(value),
+        };
+        image.put_pixel(i % width, i / width, color);
+    }
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interior_distance_shrinks_towards_the_boundary() {
+        // Along the real axis the main cardioid ends at 0.25 and the period-2 bulb at -1.25.
+        let distances: Vec<f64> = [-0.2, 0.0, 0.2, 0.24]
+            .iter()
+            .map(|&x| interior_distance(Complex64::new(x, 0.0), 10_000).unwrap())
+            .collect();
+        assert!(distances.windows(2).all(|pair| pair[0] > pair[1]), "{distances:?}");
+        let near_edge = interior_distance(Complex64::new(-1.24, 0.0), 10_000).unwrap();
+        assert!(near_edge < 0.01 * 4.0 && near_edge > 0.01 / 4.0, "{near_edge}");
+    }
+}
@@ -0,0 +1,343 @@
+//! Reads parameter files written by other fractal programs, so locations shared by their users
+//! can be re-rendered with this crate.
+//!
+//! Kalles Fraktaler saves locations as `.kfr` files and palettes as `.kfp` files. Both are plain
+//! text with one `Key: value` pair per line, and Mandel Machine reads and writes the same format:
+//!
+//! ```text
+//! Re: -0.743643887037158704752191506114774
+//! Im: 0.131825904205311970493132056385139
+//! Zoom: 1E20
+//! Iterations: 10000
+//! Colors: 255,255,255,128,0,64,160,0,0,
+//! ```
+//!
+//! Unknown keys are ignored. A Kalles Fraktaler zoom of `1` shows the plane from `-2` to `2`
+//! vertically, which is a zoom of `0.5` in `Viewport` terms.
+//!
+//! Fractint `.par` files hold any number of named entries of `key=value` settings:
+//!
+//! ```text
+//! Seahorse { ; a comment
+//!   reset=2004 type=mandel center-mag=-0.7435/0.1314/250 maxiter=1000
+//!   }
+//! ```
+//!
+//! The location is read from `center-mag` (a magnification of `1` spans `2` vertically, like a
+//! `Viewport` zoom of `1`) or from `corners=xmin/xmax/ymin/ymax`. Entries of other fractal types
+//! are read too, but only `type=mandel` is rendered the same way by this crate.
+//!
+//! Ultra Fractal `.upr` files can be written for a viewport, using the standard `Mandelbrot`
+//! formula. An Ultra Fractal magnification of `1` spans `3` vertically.
+
+use std::{fs, io, path::Path};
+
+use crate::{
+    bookmarks::Bookmark,
+    dive::Keyframe,
+    viewport::{Viewport, BASE_HEIGHT},
+};
+
+/// Iteration count used when a parameter file does not specify one.
+pub const KFR_DEFAULT_ITERATIONS: u32 = 200;
+
+/// Iteration count Fractint uses when a `.par` entry does not specify `maxiter`.
+pub const PAR_DEFAULT_ITERATIONS: u32 = 150;
+
+/// Vertical span of an Ultra Fractal view at a magnification of `1`.
+const UPR_BASE_HEIGHT: f64 = 3.0;
+
+/// Location, iteration count and palette read from a `.kfr` or `.kfp` file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KfrParams {
+    /// Real part of the center as written in the file.
+    pub re: String,
+    /// Imaginary part of the center as written in the file.
+    pub im: String,
+    /// Zoom as written in the file, which may exceed the range of `f64`.
+    pub zoom: String,
+    pub iterations: u32,
+    /// Palette colors as `[r, g, b]` triples, empty if the file has none.
+    pub colors: Vec<[u8; 3]>,
+}
+
+impl KfrParams {
+    /// Loads a `.kfr` or `.kfp` file.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Parses the contents of a `.kfr` or `.kfp` file. Keys missing from the file, such as the
+    /// location in palette-only `.kfp` files, get the values of a whole-set view at zoom `1`.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::interop::KfrParams;
+    ///
+    /// let params =
+    ///     KfrParams::parse("Re: -0.75\r\nIm: 0.1\r\nZoom: 200\r\nIterations: 500\r\n").unwrap();
+    /// let viewport = params.viewport().unwrap();
+    /// assert_eq!((viewport.center_x, viewport.center_y, viewport.zoom), (-0.75, 0.1, 100.0));
+    /// assert_eq!(params.iterations, 500);
+    /// ```
+    pub fn parse(contents: &str) -> io::Result<Self> {
+        let mut params = Self {
+            re: "-0.75".to_string(),
+            im: "0".to_string(),
+            zoom: "1".to_string(),
+            iterations: KFR_DEFAULT_ITERATIONS,
+            colors: Vec::new(),
+        };
+        for line in contents.lines() {
+            let Some((key, value)) = line.split_once(':') else { continue };
+            let value = value.trim();
+            match key.trim() {
+                "Re" => params.re = value.to_string(),
+                "Im" => params.im = value.to_string(),
+                "Zoom" => params.zoom = value.to_string(),
+                "Iterations" => params.iterations = value.parse().map_err(invalid_data)?,
+                "Colors" => params.colors = parse_colors(value)?,
+                _ => {}
+            }
+        }
+        Ok(params)
+    }
+
+    /// The location as a viewport, with the center rounded to `f64`. Zooms beyond the range of
+    /// `f64` become infinite.
+    pub fn viewport(&self) -> io::Result<Viewport> {
+        let number = |s: &str| s.parse::<f64>().map_err(invalid_data);
+        Ok(Viewport::new(number(&self.re)?, number(&self.im)?, number(&self.zoom)? / 2.0))
+    }
+
+    /// The location as a bookmark called `name`, keeping every digit of the center.
+    pub fn bookmark(&self, name: impl Into<String>) -> io::Result<Bookmark> {
+        Bookmark::new(name, self.viewport()?, self.iterations)
+            .with_exact_center(&self.re, &self.im)
+            .map_err(invalid_data)
+    }
+
+    /// The location as a dive keyframe, keeping every digit of the center.
+    pub fn keyframe(&self) -> io::Result<Keyframe> {
+        Ok(Keyframe {
+            center_x: self.re.clone(),
+            center_y: self.im.clone(),
+            ..Keyframe::new(&self.viewport()?, self.iterations)
+        })
+    }
+}
+
+/// A named entry of a Fractint `.par` file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParEntry {
+    pub name: String,
+    /// Fractint fractal type, such as `mandel` or `julia`.
+    pub fractal_type: String,
+    pub viewport: Viewport,
+    pub iterations: u32,
+}
+
+impl ParEntry {
+    /// Returns `true` if this crate renders the entry's fractal type.
+    pub fn is_supported(&self) -> bool {
+        self.fractal_type.eq_ignore_ascii_case("mandel")
+    }
+}
+
+/// Loads every entry of a Fractint `.par` file.
+pub fn load_par(path: impl AsRef<Path>) -> io::Result<Vec<ParEntry>> {
+    parse_par(&fs::read_to_string(path)?)
+}
+
+/// Parses every entry of a Fractint `.par` file.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::interop::parse_par;
+///
+/// let entries = parse_par("Whole { type=mandel corners=-2.5/1/-1/1 maxiter=255 }").unwrap();
+/// assert_eq!(entries[0].viewport.center_x, -0.75);
+/// assert_eq!(entries[0].viewport.zoom, 1.0);
+/// assert!(entries[0].is_supported());
+/// ```
+pub fn parse_par(contents: &str) -> io::Result<Vec<ParEntry>> {
+    // Comments run from `;` to the end of the line.
+    let contents: String = contents
+        .lines()
+        .map(|line| line.split(';').next().unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join("\n");
+    let mut entries = Vec::new();
+    let mut rest = contents.as_str();
+    while let Some(open) = rest.find('{') {
+        let name = rest[..open].trim().to_string();
+        let close = rest[open..].find('}').ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("entry {name:?} is not closed"))
+        })?;
+        entries.push(parse_par_entry(name, &rest[open + 1..open + close])?);
+        rest = &rest[open + close + 1..];
+    }
+    Ok(entries)
+}
+
+fn parse_par_entry(name: String, body: &str) -> io::Result<ParEntry> {
+    let mut entry = ParEntry {
+        name,
+        fractal_type: "mandel".to_string(),
+        viewport: Viewport::new(-0.5, 0.0, 2.0 / 3.0),
+        iterations: PAR_DEFAULT_ITERATIONS,
+    };
+    let numbers = |value: &str| {
+        value
+            .split('/')
+            .map(|n| n.trim().parse::<f64>().map_err(invalid_data))
+            .collect::<io::Result<Vec<_>>>()
+    };
+    let invalid = |key: &str| io::Error::new(io::ErrorKind::InvalidData, format!("invalid {key}"));
+    for setting in body.split_whitespace() {
+        let Some((key, value)) = setting.split_once('=') else { continue };
+        match key.to_ascii_lowercase().as_str() {
+            "type" => entry.fractal_type = value.to_string(),
+            "maxiter" => entry.iterations = value.parse().map_err(invalid_data)?,
+            "center-mag" => match numbers(value)?[..] {
+                [x, y, mag, ..] if mag > 0.0 => entry.viewport = Viewport::new(x, y, mag),
+                _ => return Err(invalid(key)),
+            },
+            "corners" => match numbers(value)?[..] {
+                [x_min, x_max, y_min, y_max, ..] if y_max > y_min => {
+                    entry.viewport = Viewport::new(
+                        (x_min + x_max) / 2.0,
+                        (y_min + y_max) / 2.0,
+                        BASE_HEIGHT / (y_max - y_min),
+                    )
+                }
+                _ => return Err(invalid(key)),
+            },
+            _ => {}
+        }
+    }
+    Ok(entry)
+}
+
+/// Formats `viewport` as an Ultra Fractal `.upr` parameter set called `name` for a
+/// `width` x `height` image.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::{interop::to_upr, viewport::Viewport};
+///
+/// let upr = to_upr("Whole set", &Viewport::classic(), 500, 800, 600);
+/// assert!(upr.starts_with("Whole set {"));
+/// assert!(upr.contains("center=-0.75/0 magn=1.5"));
+/// assert!(upr.contains("maxiter=500"));
+/// ```
+pub fn to_upr(name: &str, viewport: &Viewport, iterations: u32, width: u32, height: u32) -> String {
+    let magn = UPR_BASE_HEIGHT / viewport.height();
+    format!(
+        "{name} {{\n\
+         fractal:\n  title=\"{name}\" width={width} height={height} layers=1\n\
+         layer:\n  caption=\"Background\" opacity=100\n\
+         mapping:\n  center={}/{} magn={magn}\n\
+         formula:\n  maxiter={iterations} filename=\"Standard.ufm\" entry=\"Mandelbrot\"\n\
+         inside:\n  transfer=none\n\
+         outside:\n  transfer=linear\n\
+         }}\n",
+        viewport.center_x, viewport.center_y,
+    )
+}
+
+/// Saves `viewport` as an Ultra Fractal `.upr` parameter file, see `to_upr`.
+pub fn save_upr(
+    path: impl AsRef<Path>,
+    name: &str,
+    viewport: &Viewport,
+    iterations: u32,
+    width: u32,
+    height: u32,
+) -> io::Result<()> {
+    fs::write(path, to_upr(name, viewport, iterations, width, height))
+}
+
+/// Parses a comma-separated list of `r,g,b` components, which may end with a trailing comma.
+fn parse_colors(value: &str) -> io::Result<Vec<[u8; 3]>> {
+    let components = value
+        .split(',')
+        .map(str::trim)
+        .filter(|component| !component.is_empty())
+        .map(|component| component.parse::<u8>().map_err(invalid_data))
+        .collect::<io::Result<Vec<_>>>()?;
+    if components.len() % 3 != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "palette colors must be r,g,b triples",
+        ));
+    }
+    Ok(components.chunks(3).map(|rgb| [rgb[0], rgb[1], rgb[2]]).collect())
+}
+
+fn invalid_data(e: impl std::error::Error + Send + Sync + 'static) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEAHORSE_KFR: &str = "\
+Re: -0.743643887037158704752191506114774
+Im: 0.131825904205311970493132056385139
+Zoom: 1E20
+Iterations: 10000
+IterDiv: 1.000000
+SmoothMethod: 0
+Colors: 255,255,255,128,0,64,160,0,0,
+";
+
+    #[test]
+    fn test_parse_kfr_keeps_location() {
+        let params = KfrParams::parse(SEAHORSE_KFR).unwrap();
+        assert_eq!(params.iterations, 10000);
+        assert_eq!(params.colors, vec![[255, 255, 255], [128, 0, 64], [160, 0, 0]]);
+        assert_eq!(params.viewport().unwrap().zoom, 5e19);
+
+        let bookmark = params.bookmark("seahorse").unwrap();
+        assert_eq!(bookmark.exact_center.unwrap()[0], "-0.743643887037158704752191506114774");
+        assert_eq!(params.keyframe().unwrap().center_y, "0.131825904205311970493132056385139");
+    }
+
+    #[test]
+    fn test_palette_only_and_invalid_files() {
+        let palette = KfrParams::parse("Colors: 0,0,0,255,255,255\n").unwrap();
+        assert_eq!(palette.viewport().unwrap(), Viewport::new(-0.75, 0.0, 0.5));
+        assert_eq!(palette.colors.len(), 2);
+
+        assert!(KfrParams::parse("Colors: 1,2\n").is_err());
+        assert!(KfrParams::parse("Iterations: many\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_par_entries() {
+        let par = "\
+; Saved by Fractint
+Seahorse { ; deep in the valley
+  reset=2004 type=mandel center-mag=-0.7435/0.1314/250 maxiter=1000
+  colors=000<30>fff
+  }
+Dragon {
+  reset=2004 type=julia params=-0.8/0.156
+  }
+";
+        let entries = parse_par(par).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].name, "Seahorse");
+        assert_eq!(entries[0].viewport, Viewport::new(-0.7435, 0.1314, 250.0));
+        assert_eq!(entries[0].iterations, 1000);
+        assert!(!entries[1].is_supported());
+        assert_eq!(entries[1].iterations, PAR_DEFAULT_ITERATIONS);
+
+        assert!(parse_par("Broken { corners=1/2 }").is_err());
+        assert!(parse_par("Open { type=mandel").is_err());
+    }
+}
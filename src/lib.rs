@@ -20,6 +20,8 @@
 //! boundaries of these sequences. The Mandelbrot set is a beautiful and fascinating
 //! mathematical object that has captured the imagination of mathematicians, artists, and
 //! computer scientists for decades.
+//!
+//! The most commonly used items are re-exported from the `prelude` module.
 
 pub mod mandelbrot_img {
     //! The code plots the Mandelbrot set, a fractal, by calculating the number of iterations it
@@ -38,8 +40,35 @@ pub mod mandelbrot_img {
     //! calculates the number of iterations it takes for a complex number to escape to infinity or
     //! stay within a given radius. The code also includes documentation comments that explain the
     //! purpose of the code and provide some background on the Mandelbrot set.
+    //!
+    //! `compose` requires the `image` feature; the kernel functions are always available, and so
+    //! are `compose_raw` and `render_rgba8` for callers that want escape counts or RGBA bytes
+    //! without the `image` crate.
+
+    #[cfg(feature = "image")]
+    use std::{io::Cursor, sync::atomic::AtomicBool};
+
+    #[cfg(feature = "image")]
+    use image::{DynamicImage, ImageBuffer, ImageFormat, ImageResult, Luma, Rgb};
+    use num_complex::Complex64;
+
+    use crate::complex::IntoComplex;
+    #[cfg(feature = "image")]
+    use crate::{
+        analysis::{escape_viewport, try_distance_viewport, try_escape_viewport, EscapeResult},
+        averages::try_average_viewport,
+        lighting::{light_value, try_normal_viewport},
+        options::{ColorMode, RenderOptions},
+        scheduler::RenderCancelled,
+        traps::try_trap_viewport,
+        viewport::Viewport,
+    };
+
+    /// Distance to the set, in pixels, at which `distance_value` reaches black.
+    pub const DISTANCE_FALLOFF: f64 = 4.0;
 
-    use image::{ImageBuffer, Rgb};
+    /// Rate at which `trap_value` darkens with the distance of an orbit to the trap.
+    pub const TRAP_FALLOFF: f64 = 8.0;
 
     /// Composes an image of the Mandelbrot set with a specified `width`, `height`, and
     /// `iterations`.
@@ -53,17 +82,553 @@ pub mod mandelbrot_img {
     /// assert_eq!(image.width(), 800);
     /// assert_eq!(image.height(), 800);
     /// ```
+    #[cfg(feature = "image")]
     pub fn compose(width: u32, height: u32, iterations: u32) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
-        let mut image = ImageBuffer::new(width, height);
-        for (x, y, pixel) in image.enumerate_pixels_mut() {
-            let c = to_complex_num(x, y, width, height);
-            let i = mandelbrot(c, iterations);
-            *pixel = Rgb([i as u8, i as u8, i as u8]);
+        compose_with_viewport(width, height, iterations, &Viewport::default())
+    }
+
+    /// Like `compose`, but renders the region of the complex plane described by `viewport`
+    /// instead of the whole set of `Viewport::default_set()`, fitted with `FitMode::Contain`.
+    /// `Viewport::classic()` renders the `[-2.5, 1.0] x [-1.0, 1.0]` region `compose` used to.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::{mandelbrot_img::compose_with_viewport, viewport::Viewport};
+    ///
+    /// // Seahorse Valley, a hundred times closer than the default view.
+    /// let viewport = Viewport::new(-0.745, 0.11, 100.0);
+    /// let image = compose_with_viewport(320, 200, 500, &viewport);
+    /// assert_eq!(image.dimensions(), (320, 200));
+    /// ```
+    #[cfg(feature = "image")]
+    pub fn compose_with_viewport(
+        width: u32,
+        height: u32,
+        iterations: u32,
+        viewport: &Viewport,
+    ) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+        compose_with_options(width, height, viewport, &RenderOptions::new(iterations))
+    }
+
+    /// Like `compose_with_viewport`, with the iteration limit, escape radius and shading taken
+    /// from `opts`.
+    ///
+    /// With `RenderOptions::smooth` (the default) pixels are shaded by the continuous escape
+    /// value of `mandelbrot_smooth`, so gradients have no visible steps; without it every
+    /// iteration count is its own band of gray. A larger bailout, such as
+    /// `RenderOptions::with_bailout(256.0)`, makes the smooth gradient even more regular. With
+    /// `ColorMode::DistanceEstimate` pixels are shaded by `shade_distances` instead, with
+    /// `ColorMode::OrbitTrap` by `shade_traps`, with `ColorMode::Shaded` by `shade_normals`, and
+    /// with `ColorMode::Average` by `shade_averages`.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::{mandelbrot_img::compose_with_options, prelude::*};
+    ///
+    /// let banded = RenderOptions::new(100).with_smooth(false);
+    /// let image = compose_with_options(64, 64, &Viewport::default(), &banded);
+    /// assert_eq!(image.dimensions(), (64, 64));
+    /// ```
+    #[cfg(feature = "image")]
+    pub fn compose_with_options(
+        width: u32,
+        height: u32,
+        viewport: &Viewport,
+        opts: &RenderOptions,
+    ) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+        compose_with_progress(width, height, viewport, opts, |_, _| {})
+    }
+
+    /// Like `compose_with_options`, but calls `on_progress(done, total)` with the number of
+    /// rendered pixels after every finished row, to drive progress bars or estimate the time
+    /// left. With the `parallel` feature it may be called from several threads.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::sync::atomic::{AtomicU64, Ordering};
+    ///
+    /// use mandelbrot::{mandelbrot_img::compose_with_progress, prelude::*};
+    ///
+    /// let done = AtomicU64::new(0);
+    /// let opts = RenderOptions::new(100);
+    /// compose_with_progress(64, 48, &Viewport::default(), &opts, |rendered, total| {
+    ///     assert_eq!(total, 64 * 48);
+    ///     done.fetch_max(rendered, Ordering::Relaxed);
+    /// });
+    /// assert_eq!(done.into_inner(), 64 * 48);
+    /// ```
+    #[cfg(feature = "image")]
+    pub fn compose_with_progress<P>(
+        width: u32,
+        height: u32,
+        viewport: &Viewport,
+        opts: &RenderOptions,
+        on_progress: P,
+    ) -> ImageBuffer<Rgb<u8>, Vec<u8>>
+    where
+        P: Fn(u64, u64) + Sync,
+    {
+        let never = AtomicBool::new(false);
+        match try_compose(width, height, viewport, opts, on_progress, &never) {
+            Ok(image) => image,
+            Err(RenderCancelled) => unreachable!("the render was never cancelled"),
+        }
+    }
+
+    /// Like `compose_with_progress`, but stops once `cancel` is set, so frontends can abort deep
+    /// renders from another thread. The flag is checked before every row.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RenderCancelled` if the render was cancelled.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::sync::{atomic::AtomicBool, Arc};
+    ///
+    /// use mandelbrot::{mandelbrot_img::try_compose, prelude::*, scheduler::RenderCancelled};
+    ///
+    /// let cancel = Arc::new(AtomicBool::new(true));
+    /// let opts = RenderOptions::new(100);
+    /// let result = try_compose(64, 48, &Viewport::default(), &opts, |_, _| {}, &cancel);
+    /// assert_eq!(result, Err(RenderCancelled));
+    /// ```
+    #[cfg(feature = "image")]
+    pub fn try_compose<P>(
+        width: u32,
+        height: u32,
+        viewport: &Viewport,
+        opts: &RenderOptions,
+        on_progress: P,
+        cancel: &AtomicBool,
+    ) -> Result<ImageBuffer<Rgb<u8>, Vec<u8>>, RenderCancelled>
+    where
+        P: Fn(u64, u64) + Sync,
+    {
+        match opts.color_mode {
+            ColorMode::EscapeTime => {
+                let escapes =
+                    try_escape_viewport(width, height, viewport, opts, on_progress, cancel)?;
+                Ok(shade_escapes(width, height, &escapes, opts))
+            }
+            ColorMode::DistanceEstimate => {
+                let distances = try_distance_viewport(
+                    width,
+                    height,
+                    viewport,
+                    opts.iterations,
+                    on_progress,
+                    cancel,
+                )?;
+                Ok(shade_distances(
+                    width,
+                    height,
+                    &distances,
+                    viewport.spans(width, height).0 / width as f64,
+                ))
+            }
+            ColorMode::OrbitTrap(shape) => {
+                let distances =
+                    try_trap_viewport(width, height, viewport, opts, shape, on_progress, cancel)?;
+                Ok(shade_traps(width, height, &distances))
+            }
+            ColorMode::Shaded { azimuth, elevation } => {
+                let normals = try_normal_viewport(
+                    width,
+                    height,
+                    viewport,
+                    opts.iterations,
+                    on_progress,
+                    cancel,
+                )?;
+                Ok(shade_normals(width, height, &normals, azimuth, elevation))
+            }
+            ColorMode::Average(coloring) => {
+                let averages = try_average_viewport(
+                    width,
+                    height,
+                    viewport,
+                    opts,
+                    coloring,
+                    on_progress,
+                    cancel,
+                )?;
+                Ok(shade_averages(width, height, &averages))
+            }
+        }
+    }
+
+    /// Shades the estimated distances of a `width` x `height` grid, in row-major order, with
+    /// the brightness of `distance_value`, so the set and its filaments are white.
+    #[cfg(feature = "image")]
+    pub fn shade_distances(
+        width: u32,
+        height: u32,
+        distances: &[f64],
+        pixel_size: f64,
+    ) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+        ImageBuffer::from_fn(width, height, |x, y| {
+            let distance = distances[(y * width + x) as usize];
+            let i = (distance_value(distance, pixel_size) * 255.0).round() as u8;
+            Rgb([i, i, i])
+        })
+    }
+
+    /// Brightness between `0.0` and `1.0` of a pixel of `pixel_size` whose center is `distance`
+    /// away from the set: `1.0` on the set, fading to `0.0` at `DISTANCE_FALLOFF` pixels.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::mandelbrot_img::{distance_value, DISTANCE_FALLOFF};
+    ///
+    /// assert_eq!(distance_value(0.0, 0.01), 1.0);
+    /// assert_eq!(distance_value(DISTANCE_FALLOFF * 0.01, 0.01), 0.0);
+    /// ```
+    pub fn distance_value(distance: f64, pixel_size: f64) -> f64 {
+        1.0 - (distance / (DISTANCE_FALLOFF * pixel_size)).min(1.0).sqrt()
+    }
+
+    /// Shades the orbit trap distances of a `width` x `height` grid, in row-major order, with the
+    /// brightness of `trap_value`, so orbits that come close to the trap are white.
+    #[cfg(feature = "image")]
+    pub fn shade_traps(
+        width: u32,
+        height: u32,
+        distances: &[f64],
+    ) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+        ImageBuffer::from_fn(width, height, |x, y| {
+            let i = (trap_value(distances[(y * width + x) as usize]) * 255.0).round() as u8;
+            Rgb([i, i, i])
+        })
+    }
+
+    /// Shades the surface normals of a `width` x `height` grid, in row-major order, with the
+    /// brightness of `lighting::light_value` for a light from `azimuth` and `elevation`, so the
+    /// set is black and its surroundings stand out in relief.
+    #[cfg(feature = "image")]
+    pub fn shade_normals(
+        width: u32,
+        height: u32,
+        normals: &[Option<Complex64>],
+        azimuth: f64,
+        elevation: f64,
+    ) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+        ImageBuffer::from_fn(width, height, |x, y| {
+            let normal = normals[(y * width + x) as usize];
+            let i = (light_value(normal, azimuth, elevation) * 255.0).round() as u8;
+            Rgb([i, i, i])
+        })
+    }
+
+    /// Shades the orbit averages of a `width` x `height` grid, in row-major order, from black to
+    /// white, with the set, `None`, black.
+    #[cfg(feature = "image")]
+    pub fn shade_averages(
+        width: u32,
+        height: u32,
+        averages: &[Option<f64>],
+    ) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+        ImageBuffer::from_fn(width, height, |x, y| {
+            let average = averages[(y * width + x) as usize].unwrap_or(0.0);
+            let i = (average * 255.0).round() as u8;
+            Rgb([i, i, i])
+        })
+    }
+
+    /// Brightness between `0.0` and `1.0` of a pixel whose orbit came `distance` close to the
+    /// trap: `1.0` on the trap, falling off exponentially at the rate `TRAP_FALLOFF`.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::mandelbrot_img::{trap_value, TRAP_FALLOFF};
+    ///
+    /// assert_eq!(trap_value(0.0), 1.0);
+    /// assert_eq!(trap_value(1.0 / TRAP_FALLOFF), (-1.0_f64).exp());
+    /// ```
+    pub fn trap_value(distance: f64) -> f64 {
+        (-distance * TRAP_FALLOFF).exp()
+    }
+
+    /// Escape counts of `viewport` rendered at `width` x `height`, in row-major order, for callers
+    /// that want the raw data instead of a colored image. Points that do not escape within
+    /// `iterations` get `iterations`. See `buffer::IterationBuffer` for the counts together with
+    /// their dimensions.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::{mandelbrot_img::compose_raw, viewport::Viewport};
+    ///
+    /// let counts = compose_raw(80, 60, 100, &Viewport::default());
+    /// assert_eq!(counts.len(), 80 * 60);
+    /// assert_eq!(counts[30 * 80 + 50], 100);
+    /// ```
+    pub fn compose_raw(
+        width: u32,
+        height: u32,
+        iterations: u32,
+        viewport: &crate::viewport::Viewport,
+    ) -> Vec<u32> {
+        let opts = crate::options::RenderOptions::new(iterations);
+        crate::analysis::escape_viewport(width, height, viewport, &opts)
+            .iter()
+            .map(|result| result.iterations)
+            .collect()
+    }
+
+    /// Renders the escape times of a `width` x `height` grid over `viewport` as tightly packed
+    /// RGBA rows, four bytes per pixel starting at the top left, without the `image` crate. The
+    /// pixels are opaque and have the grays of `compose_with_options` with
+    /// `ColorMode::EscapeTime`, whatever the color mode of `opts`, so they can be uploaded to a
+    /// texture or canvas as they are.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::{mandelbrot_img::render_rgba8, prelude::*};
+    ///
+    /// let pixels = render_rgba8(80, 60, &Viewport::default(), &RenderOptions::new(100));
+    /// assert_eq!(pixels.len(), 80 * 60 * 4);
+    /// assert_eq!(pixels[(30 * 80 + 50) * 4..][..4], [100, 100, 100, 255]);
+    /// ```
+    pub fn render_rgba8(
+        width: u32,
+        height: u32,
+        viewport: &crate::viewport::Viewport,
+        opts: &crate::options::RenderOptions,
+    ) -> Vec<u8> {
+        crate::analysis::escape_viewport(width, height, viewport, opts)
+            .iter()
+            .flat_map(|result| {
+                let i = gray_level(result, opts);
+                [i, i, i, u8::MAX]
+            })
+            .collect()
+    }
+
+    /// Shades the escape results of a `width` x `height` grid, in row-major order, in the gray
+    /// ramp of `compose_with_options`. Escape values wrap around every 256 iterations; see
+    /// `shade_escapes_u16` and `shade_escapes_f32` to keep larger counts.
+    #[cfg(feature = "image")]
+    pub fn shade_escapes(
+        width: u32,
+        height: u32,
+        escapes: &[EscapeResult],
+        opts: &RenderOptions,
+    ) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+        ImageBuffer::from_fn(width, height, |x, y| {
+            let i = gray_level(&escapes[(y * width + x) as usize], opts);
+            Rgb([i, i, i])
+        })
+    }
+
+    /// The gray of an escape result in the ramp of `shade_escapes`.
+    fn gray_level(
+        result: &crate::analysis::EscapeResult,
+        opts: &crate::options::RenderOptions,
+    ) -> u8 {
+        // Smooth values wrap around like the whole counts do, so both share one gray ramp.
+        escape_value(result, opts).round().rem_euclid(256.0) as u8
+    }
+
+    /// Like `compose_with_options`, but with 16-bit gray pixels that hold escape values up to
+    /// 65535, see `shade_escapes_u16`.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::{mandelbrot_img::compose_u16, prelude::*};
+    ///
+    /// let image = compose_u16(64, 48, &Viewport::default(), &RenderOptions::new(1000));
+    /// assert_eq!(image.get_pixel(32, 24).0, [1000]);
+    /// ```
+    #[cfg(feature = "image")]
+    pub fn compose_u16(
+        width: u32,
+        height: u32,
+        viewport: &Viewport,
+        opts: &RenderOptions,
+    ) -> ImageBuffer<Luma<u16>, Vec<u16>> {
+        let escapes = escape_viewport(width, height, viewport, opts);
+        shade_escapes_u16(width, height, &escapes, opts)
+    }
+
+    /// Like `shade_escapes`, but stores the escape value itself in a 16-bit gray pixel instead
+    /// of wrapping it every 256 iterations, so the data survives for tone mapping in other
+    /// tools. Values wrap around every 65536 iterations.
+    #[cfg(feature = "image")]
+    pub fn shade_escapes_u16(
+        width: u32,
+        height: u32,
+        escapes: &[EscapeResult],
+        opts: &RenderOptions,
+    ) -> ImageBuffer<Luma<u16>, Vec<u16>> {
+        ImageBuffer::from_fn(width, height, |x, y| {
+            let value = escape_value(&escapes[(y * width + x) as usize], opts);
+            Luma([value.round().rem_euclid(65536.0) as u16])
+        })
+    }
+
+    /// Like `compose_with_options`, but with 32-bit float pixels that hold the escape value
+    /// unchanged, see `shade_escapes_f32`.
+    #[cfg(feature = "image")]
+    pub fn compose_f32(
+        width: u32,
+        height: u32,
+        viewport: &Viewport,
+        opts: &RenderOptions,
+    ) -> ImageBuffer<Rgb<f32>, Vec<f32>> {
+        let escapes = escape_viewport(width, height, viewport, opts);
+        shade_escapes_f32(width, height, &escapes, opts)
+    }
+
+    /// Stores the escape value of every pixel in all three channels of a float image, the pixel
+    /// type OpenEXR files are written from. Smooth values keep their fraction, and points inside
+    /// the set get the iteration limit.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::{analysis::escape_viewport, mandelbrot_img::shade_escapes_f32, prelude::*};
+    ///
+    /// let opts = RenderOptions::new(100_000);
+    /// let escapes = escape_viewport(8, 8, &Viewport::default(), &opts);
+    /// let image = shade_escapes_f32(8, 8, &escapes, &opts);
+    /// assert_eq!(image.get_pixel(4, 4).0, [100_000.0; 3]);
+    /// ```
+    #[cfg(feature = "image")]
+    pub fn shade_escapes_f32(
+        width: u32,
+        height: u32,
+        escapes: &[EscapeResult],
+        opts: &RenderOptions,
+    ) -> ImageBuffer<Rgb<f32>, Vec<f32>> {
+        ImageBuffer::from_fn(width, height, |x, y| {
+            let value = escape_value(&escapes[(y * width + x) as usize], opts) as f32;
+            Rgb([value; 3])
+        })
+    }
+
+    /// The escape value shaded by `shade_escapes`, before it is wrapped into a pixel: the
+    /// iteration limit inside the set, and the smooth or whole count outside.
+    fn escape_value(
+        result: &crate::analysis::EscapeResult,
+        opts: &crate::options::RenderOptions,
+    ) -> f64 {
+        if !result.escaped {
+            opts.iterations as f64
+        } else if opts.smooth {
+            result.smooth
+        } else {
+            result.iterations as f64
         }
-        image
     }
 
-    /// Maps pixel coordinates to complex plane coordinates.
+    /// Like `compose`, but returns a `DynamicImage`, which can be converted to other color types
+    /// or encoded without knowing the pixel format.
+    #[cfg(feature = "image")]
+    pub fn compose_dynamic(width: u32, height: u32, iterations: u32) -> DynamicImage {
+        DynamicImage::ImageRgb8(compose(width, height, iterations))
+    }
+
+    /// Encodes `image` in `format` into an in-memory buffer, so it can be served or passed on
+    /// without touching the filesystem.
+    ///
+    /// # Examples
+    /// ```
+    /// use image::ImageFormat;
+    /// use mandelbrot::mandelbrot_img::{compose_dynamic, encode_to_vec};
+    ///
+    /// let image = compose_dynamic(64, 64, 100);
+    /// let png = encode_to_vec(&image, ImageFormat::Png).unwrap();
+    /// assert_eq!(&png[1..4], b"PNG");
+    /// ```
+    #[cfg(feature = "image")]
+    pub fn encode_to_vec(image: &DynamicImage, format: ImageFormat) -> ImageResult<Vec<u8>> {
+        let mut bytes = Cursor::new(Vec::new());
+        image.write_to(&mut bytes, format)?;
+        Ok(bytes.into_inner())
+    }
+
+    /// Draws `orbit`, such as one returned by `analysis::orbit`, onto `image` as a polyline of
+    /// `color`, with the image covering `viewport`. Segments are clipped to the image, so orbits
+    /// that leave the viewport or escape to large values are drawn up to its edge.
+    ///
+    /// # Examples
+    /// ```
+    /// use image::Rgb;
+    /// use mandelbrot::{
+    ///     analysis::orbit,
+    ///     mandelbrot_img::{compose_with_viewport, draw_orbit},
+    ///     viewport::Viewport,
+    /// };
+    ///
+    /// let viewport = Viewport::default();
+    /// let mut image = compose_with_viewport(350, 200, 100, &viewport);
+    /// draw_orbit(&mut image, &viewport, &orbit((-0.5, 0.5), 100), Rgb([255, 0, 0]));
+    /// // The orbit starts at c itself.
+    /// let (x, y) = viewport.pixel((-0.5, 0.5), 350, 200);
+    /// assert_eq!(image.get_pixel(x as u32, y as u32), &Rgb([255, 0, 0]));
+    /// ```
+    #[cfg(feature = "image")]
+    pub fn draw_orbit(
+        image: &mut ImageBuffer<Rgb<u8>, Vec<u8>>,
+        viewport: &Viewport,
+        orbit: &[(f64, f64)],
+        color: Rgb<u8>,
+    ) {
+        let (width, height) = image.dimensions();
+        let pixels: Vec<(f64, f64)> =
+            orbit.iter().map(|&z| viewport.pixel(z, width, height)).collect();
+        if let [only] = pixels[..] {
+            draw_segment(image, only, only, color);
+        }
+        for segment in pixels.windows(2) {
+            draw_segment(image, segment[0], segment[1], color);
+        }
+    }
+
+    /// Draws the line from `start` to `end`, in fractional pixel positions, one pixel per step
+    /// along its longer axis, after clipping it to the image.
+    #[cfg(feature = "image")]
+    fn draw_segment(
+        image: &mut ImageBuffer<Rgb<u8>, Vec<u8>>,
+        start: (f64, f64),
+        end: (f64, f64),
+        color: Rgb<u8>,
+    ) {
+        let (width, height) = image.dimensions();
+        let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+        // Liang-Barsky clipping of the segment `start + t * (dx, dy)` for `t` in `0..=1`.
+        let (mut t0, mut t1) = (0.0_f64, 1.0_f64);
+        for (p, q) in [
+            (-dx, start.0),
+            (dx, width as f64 - start.0),
+            (-dy, start.1),
+            (dy, height as f64 - start.1),
+        ] {
+            if p == 0.0 {
+                if q < 0.0 {
+                    return;
+                }
+            } else if p < 0.0 {
+                t0 = t0.max(q / p);
+            } else {
+                t1 = t1.min(q / p);
+            }
+        }
+        if t0 > t1 {
+            return;
+        }
+        let steps = ((t1 - t0) * dx.abs().max(dy.abs())).ceil().max(1.0) as u32;
+        for i in 0..=steps {
+            let t = t0 + (t1 - t0) * i as f64 / steps as f64;
+            let (x, y) = (start.0 + t * dx, start.1 + t * dy);
+            if x >= 0.0 && y >= 0.0 && x < width as f64 && y < height as f64 {
+                image.put_pixel(x as u32, y as u32, color);
+            }
+        }
+    }
+
+    /// Maps pixel coordinates to complex plane coordinates, scaling each axis onto the fixed
+    /// `[-2.5, 1.0] x [-1.0, 1.0]` region on its own like `viewport::FitMode::Stretch`, so grids
+    /// that are not 7:4 stretch the set. `Viewport::to_complex_num` fits the region to the grid.
     ///
     /// # Examples
     /// ```
@@ -86,8 +651,8 @@ pub mod mandelbrot_img {
     /// let i = mandelbrot((0.0, 0.0), 100);
     /// assert_eq!(i, 100);
     /// ```
-    pub fn mandelbrot(c: (f64, f64), iterations: u32) -> u32 {
-        let (cx, cy) = c;
+    pub fn mandelbrot(c: impl IntoComplex, iterations: u32) -> u32 {
+        let Complex64 { re: cx, im: cy } = c.into_complex();
         let mut x = 0.0;
         let mut y = 0.0;
         let mut i = 0;
@@ -102,6 +667,67 @@ pub mod mandelbrot_img {
         }
         i
     }
+
+    /// Like `mandelbrot`, but iterates `z^power + c`, the Multibrot set of `power`, with the
+    /// escape radius of `fractal::FractalKind::escape_radius`. `power` must be greater than `1`.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::mandelbrot_img::{mandelbrot, multibrot};
+    ///
+    /// assert_eq!(multibrot((0.3, 0.5), 2.0, 100), mandelbrot((0.3, 0.5), 100));
+    /// // -1 is inside the Mandelbrot set but escapes the cubic Multibrot set.
+    /// assert_eq!(multibrot((-1.0, 0.0), 3.0, 100), 2);
+    /// ```
+    pub fn multibrot(c: impl IntoComplex, power: f64, iterations: u32) -> u32 {
+        let kind = crate::fractal::FractalKind::Multibrot { power };
+        let radius_sqr = kind.escape_radius().powi(2);
+        let c = c.into_complex();
+        let mut z = Complex64::new(0.0, 0.0);
+        for i in 0..iterations {
+            z = kind.step(z, c);
+            if z.norm_sqr() > radius_sqr {
+                return i;
+            }
+        }
+        iterations
+    }
+
+    /// Calculates the continuous (fractional) escape value of a complex number.
+    ///
+    /// Unlike `mandelbrot`, which returns a whole number of iterations, this returns
+    /// `n + 1 - log2(ln |z|)` where `n` is the escape iteration and `z` the first orbit point
+    /// outside `bailout`. Neighbouring points therefore get close values instead of jumping
+    /// between integers, which removes banding from gradients. A larger `bailout` (such as
+    /// `256.0`) gives smoother results. Points that do not escape within `max_iter` iterations
+    /// return `max_iter`.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::mandelbrot_img::mandelbrot_smooth;
+    ///
+    /// assert_eq!(mandelbrot_smooth((0.0, 0.0), 100, 2.0), 100.0);
+    /// let v = mandelbrot_smooth((0.5, 0.5), 100, 256.0);
+    /// assert!(v > 0.0 && v < 100.0);
+    /// ```
+    pub fn mandelbrot_smooth(c: impl IntoComplex, max_iter: u32, bailout: f64) -> f64 {
+        let Complex64 { re: cx, im: cy } = c.into_complex();
+        let bailout_sqr = bailout * bailout;
+        let mut x = 0.0_f64;
+        let mut y = 0.0_f64;
+        for i in 0..max_iter {
+            let x_temp = x * x - y * y + cx;
+            y = 2.0 * x * y + cy;
+            x = x_temp;
+            let mag_sqr = x * x + y * y;
+            if mag_sqr > bailout_sqr {
+                // ln |z| = ln(|z|^2) / 2
+                let log_z = mag_sqr.ln() / 2.0;
+                return (i as f64 + 1.0 - log_z.log2()).max(0.0);
+            }
+        }
+        max_iter as f64
+    }
 }
 
 pub mod mandelbrot_ascii {
@@ -123,17 +749,45 @@ pub mod mandelbrot_ascii {
     //! * mandelbrot: Calculates the value of a point in the Mandelbrot set given a complex number.
     //! * calculate_pixel_index: Calculates the pixel index from the x and y coordinate of a pixel.
     //! * collect_ascii: Calculates the ASCII representation of the Mandelbrot set and returns it as
-    //!   a HashMap where the keys are the pixel indices and the values are the corresponding ASCII
-    //!   characters.
+    //!   an `AsciiCanvas`, a grid of characters stored row by row.
+    //! * write_ascii: Writes an `AsciiCanvas` to any `io::Write`, such as a file, socket or
+    //!   in-memory buffer.
     //! * print_ascii: Prints the ASCII representation of the Mandelbrot set. The representation is
-    //!   passed as a HashMap.
+    //!   passed as an `AsciiCanvas`.
+    //! * render_canvas: Renders the region of a `Viewport` into an `AsciiCanvas`.
+    //! * render_frame: Renders the region of a `Viewport` as newline separated rows of ASCII.
+    //! * collect_ascii_with: Renders the ASCII art at the size and with the characters of an
+    //!   `AsciiOptions`.
+    //! * render_braille: Renders the region of a `Viewport` as braille characters with 2 x 4 dots
+    //!   each.
+
+    use std::{
+        collections::HashMap,
+        fmt::{self, Write as _},
+        fs::OpenOptions,
+        io::{self, BufWriter, Write},
+        path::Path,
+    };
 
-    use std::{collections::HashMap, fs::File, io::Write, path::Path};
+    use crate::{
+        error::{self, Error},
+        mandelbrot_img,
+        scheduler::render_grid_with_progress,
+        viewport::Viewport,
+    };
 
     pub const WIDTH: u32 = 80;
     pub const HEIGHT: u32 = 40;
     pub const ITERATIONS: u32 = 100;
     pub const ESCAPE_RADIUS: f64 = 2.0;
+    /// Height of a terminal character relative to its width.
+    pub const CELL_ASPECT: f64 = 2.0;
+    /// Dots per braille character, across and down.
+    pub const BRAILLE_DOTS: (u32, u32) = (2, 4);
+    /// The braille character without any dots raised.
+    const BRAILLE_BLANK: u32 = 0x2800;
+    /// The bit of every dot of a braille character, by row and column.
+    const BRAILLE_BITS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
 
     /// Calculates the Mandelbrot set value for a given complex number
     ///
@@ -145,6 +799,7 @@ pub mod mandelbrot_ascii {
     ///
     /// The number of iterations it takes for the given complex number to escape the Mandelbrot set
     /// or the maximum number of iterations if it does not escape
+    #[deprecated(note = "use `renderer::Renderer` or `mandelbrot_img::mandelbrot`")]
     pub fn mandelbrot(c: (f64, f64)) -> u32 {
         let (cx, cy) = c;
         let mut x = 0.0;
@@ -161,49 +816,483 @@ pub mod mandelbrot_ascii {
         iterations
     }
 
-    /// Collects ASCII characters to represent the intensity of the Mandelbrot set values
-    /// and stores the values in a hash map where the key is the pixel index and the value
-    /// is the ASCII character.
-    pub fn collect_ascii() -> HashMap<usize, char> {
-        let _image_area = WIDTH * HEIGHT;
-        let mut image = HashMap::new();
+    /// Size, iteration limit and characters of ASCII art.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct AsciiOptions {
+        /// Characters per row.
+        pub width: u32,
+        /// Number of rows.
+        pub height: u32,
+        pub iterations: u32,
+        /// Characters from points that escape at once to points inside the set, spread evenly
+        /// over the escape counts. `None`, or an empty ramp, uses `to_ascii_char`.
+        pub charset: Option<Vec<char>>,
+        /// Whether the imaginary axis is scaled by `CELL_ASPECT`, so the set keeps its shape in
+        /// a terminal instead of filling the canvas. The view is widened along one axis to keep
+        /// all of the viewport visible.
+        pub aspect_correction: bool,
+    }
 
-        for x in 0..WIDTH {
-            for y in 0..HEIGHT {
-                let c = to_complex_num(x, y, WIDTH, HEIGHT);
-                let value = mandelbrot(c);
-                let ascii_char = to_ascii_char(value);
-                let pixel_index = calculate_pixel_index(x, y, WIDTH);
-                image.insert(pixel_index, ascii_char);
+    impl Default for AsciiOptions {
+        fn default() -> Self {
+            Self {
+                width: WIDTH,
+                height: HEIGHT,
+                iterations: ITERATIONS,
+                charset: None,
+                aspect_correction: false,
             }
         }
-        image
     }
 
-    /// Prints the ASCII image generated by the `collect_ascii` function
-    pub fn print_ascii(image: HashMap<usize, char>) {
-        for y in 0..HEIGHT {
-            for x in 0..WIDTH {
-                let pixel_index = calculate_pixel_index(x, y, WIDTH);
-                print!("{}", image[&pixel_index]);
+    impl AsciiOptions {
+        pub fn new(width: u32, height: u32) -> Self {
+            Self { width, height, ..Default::default() }
+        }
+
+        /// Returns a copy of the options with the iteration limit set to `iterations`.
+        pub fn with_iterations(self, iterations: u32) -> Self {
+            Self { iterations, ..self }
+        }
+
+        /// Returns a copy of the options that draws with the characters of `charset`.
+        pub fn with_charset(self, charset: &str) -> Self {
+            Self { charset: Some(charset.chars().collect()), ..self }
+        }
+
+        /// Returns a copy of the options that corrects for the aspect of terminal characters if
+        /// `aspect_correction` is set.
+        pub fn with_aspect_correction(self, aspect_correction: bool) -> Self {
+            Self { aspect_correction, ..self }
+        }
+
+        /// The character of an escape count.
+        pub fn to_char(&self, value: u32) -> char {
+            match self.charset.as_deref() {
+                None | Some([]) => scaled_ascii_char(value, self.iterations),
+                Some(charset) => {
+                    let last = charset.len() - 1;
+                    let iterations = self.iterations.max(1) as usize;
+                    charset[(value as usize).min(iterations) * last / iterations]
+                }
             }
-            println!();
+        }
+
+        /// The point of the complex plane drawn by the character at `(x, y)`.
+        pub fn to_complex_num(&self, viewport: &Viewport, x: u32, y: u32) -> (f64, f64) {
+            if !self.aspect_correction {
+                return viewport.to_complex_num(x, y, self.width, self.height);
+            }
+            let (width, height) = (self.width as f64, self.height as f64);
+            let cell = (viewport.width() / width).max(viewport.height() / (height * CELL_ASPECT));
+            let cx = viewport.center_x + (x as f64 - width / 2.0) * cell;
+            let cy = viewport.center_y + (y as f64 - height / 2.0) * cell * CELL_ASPECT;
+            (cx, cy)
         }
     }
 
-    /// Prints the ASCII image generated by the `collect_ascii` function
-    pub fn write_ascii_to_file(image: HashMap<usize, char>, path: &Path) {
-        let mut buffer = File::create(path).unwrap(); // if !path.exists() { }
-
-        for y in 0..HEIGHT {
-            let mut line = String::new();
-            for x in 0..WIDTH {
-                let pixel_index = calculate_pixel_index(x, y, WIDTH);
-                line.push(image[&pixel_index]);
+    /// A grid of ASCII characters stored row by row.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::mandelbrot_ascii::AsciiCanvas;
+    ///
+    /// let mut canvas = AsciiCanvas::new(3, 2);
+    /// canvas.set(1, 1, '@');
+    /// assert_eq!(canvas.get(1, 1), Some('@'));
+    /// assert_eq!(canvas.to_string(), "...\n.@.\n");
+    /// ```
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct AsciiCanvas {
+        width: u32,
+        height: u32,
+        cells: Vec<char>,
+    }
+
+    impl AsciiCanvas {
+        /// Creates a `width` x `height` canvas filled with the character of an escape count of
+        /// zero.
+        pub fn new(width: u32, height: u32) -> Self {
+            Self { width, height, cells: vec![to_ascii_char(0); cell_count(width, height)] }
+        }
+
+        /// Creates a canvas from `cells` stored row by row.
+        ///
+        /// # Panics
+        ///
+        /// Panics if `cells` does not hold exactly `width * height` characters.
+        pub fn from_cells(width: u32, height: u32, cells: Vec<char>) -> Self {
+            assert_eq!(cells.len(), cell_count(width, height), "cell count must match dimensions");
+            Self { width, height, cells }
+        }
+
+        pub fn width(&self) -> u32 {
+            self.width
+        }
+
+        pub fn height(&self) -> u32 {
+            self.height
+        }
+
+        /// Character at `(x, y)`, or `None` if the coordinate is outside the canvas.
+        pub fn get(&self, x: u32, y: u32) -> Option<char> {
+            if x >= self.width || y >= self.height {
+                return None;
             }
+            Some(self.cells[calculate_pixel_index(x, y, self.width)])
+        }
+
+        /// Sets the character at `(x, y)`.
+        ///
+        /// # Panics
+        ///
+        /// Panics if the coordinate is outside the canvas.
+        pub fn set(&mut self, x: u32, y: u32, c: char) {
+            assert!(x < self.width && y < self.height, "({x}, {y}) is outside the canvas");
+            self.cells[calculate_pixel_index(x, y, self.width)] = c;
+        }
+
+        /// Iterates over the rows of the canvas from top to bottom.
+        pub fn rows(&self) -> impl Iterator<Item = &[char]> {
+            self.cells.chunks(self.width.max(1) as usize)
+        }
+
+        /// Converts the canvas to the map of pixel index to character returned by
+        /// `collect_ascii` before `AsciiCanvas` existed.
+        #[deprecated(note = "use the `AsciiCanvas` accessors instead")]
+        pub fn to_hash_map(&self) -> HashMap<usize, char> {
+            self.cells.iter().copied().enumerate().collect()
+        }
+    }
+
+    /// Number of cells of a `width` x `height` canvas, multiplied without overflowing `u32`.
+    fn cell_count(width: u32, height: u32) -> usize {
+        (width as usize).checked_mul(height as usize).expect("canvas has too many cells to address")
+    }
+
+    impl fmt::Display for AsciiCanvas {
+        /// Writes every row followed by a newline.
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            for row in self.rows() {
+                for c in row {
+                    f.write_char(*c)?;
+                }
+                f.write_char('\n')?;
+            }
+            Ok(())
+        }
+    }
+
+    /// Collects ASCII characters to represent the intensity of the Mandelbrot set values
+    /// into a `WIDTH` x `HEIGHT` canvas.
+    pub fn collect_ascii() -> AsciiCanvas {
+        collect_ascii_with_progress(|_, _| {})
+    }
+
+    /// Like `collect_ascii`, but calls `on_progress(done, total)` with the number of rendered
+    /// cells after every finished row.
+    #[allow(deprecated)]
+    pub fn collect_ascii_with_progress<P>(on_progress: P) -> AsciiCanvas
+    where
+        P: Fn(u64, u64) + Sync,
+    {
+        let cells = render_grid_with_progress(
+            WIDTH,
+            HEIGHT,
+            |x, y| {
+                let c = to_complex_num(x, y, WIDTH, HEIGHT);
+                to_ascii_char(mandelbrot(c))
+            },
+            on_progress,
+        );
+        AsciiCanvas::from_cells(WIDTH, HEIGHT, cells)
+    }
+
+    /// Collects the ASCII representation as a hash map where the key is the pixel index and the
+    /// value is the ASCII character.
+    #[deprecated(note = "use `collect_ascii`, which returns an `AsciiCanvas`")]
+    #[allow(deprecated)]
+    pub fn collect_ascii_map() -> HashMap<usize, char> {
+        collect_ascii().to_hash_map()
+    }
+
+    /// Writes `canvas` to `out`, one line per row.
+    ///
+    /// Rows are written through a `BufWriter`, so unbuffered writers such as files and sockets
+    /// are not hit with a system call per character.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::mandelbrot_ascii::{collect_ascii, write_ascii};
+    ///
+    /// let canvas = collect_ascii();
+    /// let mut buffer = Vec::new();
+    /// write_ascii(&canvas, &mut buffer).unwrap();
+    /// assert_eq!(String::from_utf8(buffer).unwrap(), canvas.to_string());
+    /// ```
+    pub fn write_ascii<W: Write>(canvas: &AsciiCanvas, out: &mut W) -> io::Result<()> {
+        let mut out = BufWriter::new(out);
+        let mut line = String::with_capacity(canvas.width() as usize + 1);
+        for row in canvas.rows() {
+            line.clear();
+            line.extend(row);
             line.push('\n');
-            buffer.write(line.as_bytes()).unwrap();
+            out.write_all(line.as_bytes())?;
         }
+        out.flush()
+    }
+
+    /// Prints the ASCII image generated by the `collect_ascii` function
+    pub fn print_ascii(canvas: &AsciiCanvas) -> io::Result<()> {
+        write_ascii(canvas, &mut io::stdout().lock())
+    }
+
+    /// How `write_ascii_to_file` treats an existing file.
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    pub enum WriteMode {
+        /// Truncates the file before writing.
+        #[default]
+        Overwrite,
+        /// Writes after the existing contents of the file.
+        Append,
+    }
+
+    /// Writes the ASCII image generated by the `collect_ascii` function to `path`, creating the
+    /// file if it does not exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::IoError` with the path if the file cannot be opened or written to.
+    pub fn write_ascii_to_file(
+        canvas: &AsciiCanvas,
+        path: impl AsRef<Path>,
+        mode: WriteMode,
+    ) -> error::Result<()> {
+        let path = path.as_ref();
+        let mut file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(mode == WriteMode::Overwrite)
+            .append(mode == WriteMode::Append)
+            .open(path)
+            .map_err(|e| Error::io(path, e))?;
+        write_ascii(canvas, &mut file).map_err(|e| Error::io(path, e))
+    }
+
+    /// Renders the region described by `viewport` into a `width` x `height` canvas.
+    ///
+    /// Escape counts are scaled from `iterations` onto the `ITERATIONS` range understood by
+    /// `to_ascii_char`, so deeper zooms can raise the iteration budget without saturating the
+    /// character ramp.
+    pub fn render_canvas(
+        viewport: &Viewport,
+        width: u32,
+        height: u32,
+        iterations: u32,
+    ) -> AsciiCanvas {
+        render_canvas_with_progress(viewport, width, height, iterations, |_, _| {})
+    }
+
+    /// Like `render_canvas`, but calls `on_progress(done, total)` with the number of rendered
+    /// cells after every finished row.
+    pub fn render_canvas_with_progress<P>(
+        viewport: &Viewport,
+        width: u32,
+        height: u32,
+        iterations: u32,
+        on_progress: P,
+    ) -> AsciiCanvas
+    where
+        P: Fn(u64, u64) + Sync,
+    {
+        let opts = AsciiOptions { width, height, iterations, ..Default::default() };
+        render_ascii_with_progress(viewport, &opts, on_progress)
+    }
+
+    /// Renders the whole set with the size, iteration limit and characters of `opts`.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::mandelbrot_ascii::{collect_ascii_with, AsciiOptions};
+    ///
+    /// let opts = AsciiOptions::new(120, 30).with_charset(" .-=+#");
+    /// let canvas = collect_ascii_with(&opts);
+    /// assert_eq!((canvas.width(), canvas.height()), (120, 30));
+    /// assert!(canvas.rows().flatten().all(|c| " .-=+#".contains(*c)));
+    /// ```
+    pub fn collect_ascii_with(opts: &AsciiOptions) -> AsciiCanvas {
+        render_ascii_with_progress(&Viewport::default(), opts, |_, _| {})
+    }
+
+    /// Escape counts of the characters of `opts` over `viewport`, in row-major order, such as
+    /// for coloring them with `ansi::write_colored`.
+    pub fn ascii_counts(viewport: &Viewport, opts: &AsciiOptions) -> Vec<u32> {
+        ascii_counts_with_progress(viewport, opts, |_, _| {})
+    }
+
+    /// Like `ascii_counts`, but calls `on_progress(done, total)` with the number of rendered
+    /// cells after every finished row.
+    pub fn ascii_counts_with_progress<P>(
+        viewport: &Viewport,
+        opts: &AsciiOptions,
+        on_progress: P,
+    ) -> Vec<u32>
+    where
+        P: Fn(u64, u64) + Sync,
+    {
+        render_grid_with_progress(
+            opts.width,
+            opts.height,
+            |x, y| mandelbrot_img::mandelbrot(opts.to_complex_num(viewport, x, y), opts.iterations),
+            on_progress,
+        )
+    }
+
+    /// Renders the region described by `viewport` with the settings of `opts`, calling
+    /// `on_progress(done, total)` with the number of rendered cells after every finished row.
+    pub fn render_ascii_with_progress<P>(
+        viewport: &Viewport,
+        opts: &AsciiOptions,
+        on_progress: P,
+    ) -> AsciiCanvas
+    where
+        P: Fn(u64, u64) + Sync,
+    {
+        let cells = render_grid_with_progress(
+            opts.width,
+            opts.height,
+            |x, y| {
+                let c = opts.to_complex_num(viewport, x, y);
+                opts.to_char(mandelbrot_img::mandelbrot(c, opts.iterations))
+            },
+            on_progress,
+        );
+        AsciiCanvas::from_cells(opts.width, opts.height, cells)
+    }
+
+    /// Builds a `width` x `height` canvas from escape counts in row-major order, such as those
+    /// of `xaos::XaosRenderer::counts`, scaled from `iterations` like `render_canvas` does.
+    pub fn canvas_from_counts(
+        width: u32,
+        height: u32,
+        counts: &[u32],
+        iterations: u32,
+    ) -> AsciiCanvas {
+        let cells = counts.iter().map(|&value| scaled_ascii_char(value, iterations)).collect();
+        AsciiCanvas::from_cells(width, height, cells)
+    }
+
+    /// Renders the region described by `viewport` as braille characters, `columns` x `rows`
+    /// cells of 2 x 4 dots each, for eight times the resolution of `render_canvas`. A dot is
+    /// raised where the point does not escape within `iterations`.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::{mandelbrot_ascii::render_braille, viewport::Viewport};
+    ///
+    /// let canvas = render_braille(&Viewport::default(), 40, 20, 100);
+    /// assert_eq!((canvas.width(), canvas.height()), (40, 20));
+    /// assert!(canvas.rows().flatten().any(|&c| c == '\u{28ff}'));
+    /// ```
+    pub fn render_braille(
+        viewport: &Viewport,
+        columns: u32,
+        rows: u32,
+        iterations: u32,
+    ) -> AsciiCanvas {
+        render_braille_with_progress(viewport, columns, rows, iterations, |_, _| {})
+    }
+
+    /// Like `render_braille`, but calls `on_progress(done, total)` with the number of rendered
+    /// dots after every finished row of dots.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the dots of `columns` or `rows` do not fit in a `u32`.
+    pub fn render_braille_with_progress<P>(
+        viewport: &Viewport,
+        columns: u32,
+        rows: u32,
+        iterations: u32,
+        on_progress: P,
+    ) -> AsciiCanvas
+    where
+        P: Fn(u64, u64) + Sync,
+    {
+        let dots = |cells: u32, per_cell: u32| {
+            cells.checked_mul(per_cell).expect("braille canvas has too many dots to address")
+        };
+        let (width, height) = (dots(columns, BRAILLE_DOTS.0), dots(rows, BRAILLE_DOTS.1));
+        let counts = render_grid_with_progress(
+            width,
+            height,
+            |x, y| {
+                mandelbrot_img::mandelbrot(viewport.to_complex_num(x, y, width, height), iterations)
+            },
+            on_progress,
+        );
+        braille_from_counts(width, height, &counts, iterations)
+    }
+
+    /// Packs a `width` x `height` grid of escape counts in row-major order into braille
+    /// characters, raising the dot of every count of at least `threshold`. Cells at the right and
+    /// bottom edges that are only partly covered by the grid get their missing dots lowered.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::mandelbrot_ascii::braille_from_counts;
+    ///
+    /// // The left column of dots set, the right column not.
+    /// let canvas = braille_from_counts(2, 4, &[9, 0, 9, 0, 9, 0, 9, 0], 9);
+    /// assert_eq!(canvas.to_string(), "\u{2847}\n");
+    /// ```
+    pub fn braille_from_counts(
+        width: u32,
+        height: u32,
+        counts: &[u32],
+        threshold: u32,
+    ) -> AsciiCanvas {
+        let columns = width.div_ceil(BRAILLE_DOTS.0);
+        let rows = height.div_ceil(BRAILLE_DOTS.1);
+        let mut canvas = AsciiCanvas::new(columns, rows);
+        for row in 0..rows {
+            for column in 0..columns {
+                let mut bits = 0;
+                for (dy, bit_row) in BRAILLE_BITS.iter().enumerate() {
+                    for (dx, bit) in bit_row.iter().enumerate() {
+                        let x = column * BRAILLE_DOTS.0 + dx as u32;
+                        let y = row * BRAILLE_DOTS.1 + dy as u32;
+                        if x < width
+                            && y < height
+                            && counts[calculate_pixel_index(x, y, width)] >= threshold
+                        {
+                            bits |= bit;
+                        }
+                    }
+                }
+                canvas.set(column, row, char::from_u32(BRAILLE_BLANK + bits).unwrap_or(' '));
+            }
+        }
+        canvas
+    }
+
+    fn scaled_ascii_char(value: u32, iterations: u32) -> char {
+        to_ascii_char((value as u64 * ITERATIONS as u64 / iterations.max(1) as u64) as u32)
+    }
+
+    /// Renders the region described by `viewport` as `height` rows of `width` ASCII characters,
+    /// each row terminated by a newline.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::{mandelbrot_ascii::render_frame, viewport::Viewport};
+    ///
+    /// let frame = render_frame(&Viewport::default(), 80, 40, 100);
+    /// assert_eq!(frame.lines().count(), 40);
+    /// assert!(frame.lines().all(|line| line.chars().count() == 80));
+    /// ```
+    pub fn render_frame(viewport: &Viewport, width: u32, height: u32, iterations: u32) -> String {
+        render_canvas(viewport, width, height, iterations).to_string()
     }
 
     /// Converts a value representing the Mandelbrot set to an ASCII character
@@ -240,18 +1329,85 @@ pub mod mandelbrot_ascii {
     /// # Returns
     ///
     /// A complex number represented as a tuple of two floating point numbers
+    #[deprecated(note = "use `renderer::Renderer` or `viewport::Viewport::to_complex_num`")]
     pub fn to_complex_num(x: u32, y: u32, width: u32, height: u32) -> (f64, f64) {
-        let cx = x as f64 / width as f64 * 3.5 - 2.5;
-        let cy = y as f64 / height as f64 * 2.0 - 1.0;
-        (cx, cy)
+        mandelbrot_img::to_complex_num(x, y, width, height)
     }
 
     // Calculates the pixel index from the x and y coordinate
     pub fn calculate_pixel_index(x: u32, y: u32, width: u32) -> usize {
-        (y * width + x) as usize
+        y as usize * width as usize + x as usize
     }
 }
 
+pub mod analysis;
+#[cfg(feature = "image")]
+pub mod animation;
+pub mod ansi;
+pub mod averages;
+pub mod bench;
+#[cfg(feature = "bigfloat")]
+pub mod bigfloat;
+pub mod bookmarks;
+pub mod buddhabrot;
+pub mod buffer;
+pub mod checkpoint;
+pub mod complex;
+pub mod contour;
+#[cfg(feature = "wallpaper")]
+pub mod desktop;
+pub mod dive;
+pub mod engine;
+pub mod error;
+pub mod explorer;
+#[cfg(feature = "image")]
+pub mod export;
+pub mod ffi;
+pub mod formula;
+pub mod fractal;
+pub mod histogram;
+pub mod html;
+pub mod interior;
+pub mod interop;
+pub mod lighting;
+#[cfg(feature = "image")]
+pub mod metadata;
+pub mod newton;
+pub mod options;
+#[cfg(feature = "image")]
+pub mod palette;
+#[cfg(feature = "bigfloat")]
+pub mod perturbation;
+pub mod playback;
+pub mod prelude;
+pub mod projection;
+#[cfg(feature = "image")]
+pub mod pyramid;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod renderer;
+pub mod scene;
+pub mod scheduler;
+#[cfg(feature = "image")]
+pub mod server;
+pub mod session;
+#[cfg(feature = "simd")]
+pub mod simd;
+#[cfg(feature = "image")]
+pub mod sixel;
+pub mod stats;
+#[cfg(feature = "image")]
+pub mod stereo;
+pub mod stream;
+pub mod subdivision;
+pub mod tiles;
+pub mod traps;
+pub mod viewport;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod watch;
+pub mod xaos;
+
 //------------------------------------------------------------------------------
 
 #[cfg(test)]
@@ -259,19 +1415,18 @@ mod tests {
     use std::collections::HashMap;
 
     use super::*;
-    use crate::mandelbrot_img::mandelbrot;
+    use crate::{
+        mandelbrot_img::mandelbrot,
+        viewport::{FitMode, Viewport},
+    };
 
     const WIDTH: u32 = 800;
     const HEIGHT: u32 = 800;
 
-    // Note that we used the round function to round the f64 values to the nearest u32.
-    // This way, we ensure that we don't lose precision (in the context of `to_complex_num` casting)
-    // during the casting from f64 to u32.
+    // `mandelbrot_img::to_complex_num` maps the grid onto the classic region, stretched.
     fn from_complex_num(c: (f64, f64), width: u32, height: u32) -> (u32, u32) {
-        let (cx, cy) = c;
-        let x = (cx + 2.5) / 3.5 * (width as f64 * 1.0);
-        let y = (cy + 1.0) / 2.0 * (height as f64 * 1.0);
-        (x.round() as u32, y.round() as u32)
+        let mapper = Viewport::classic().with_fit(FitMode::Stretch).mapper(width, height);
+        mapper.nearest_pixel(c).expect("the point lies inside the grid")
     }
 
     #[test]
@@ -385,4 +1540,181 @@ mod tests {
         // Verify that the results of the enumeration and the loop are the same
         assert_eq!(hash_enum, hash_loops);
     }
+
+    #[test]
+    fn test_ascii_canvas_matches_saved_text() {
+        let canvas = mandelbrot_ascii::collect_ascii();
+        assert_eq!(canvas.rows().count(), mandelbrot_ascii::HEIGHT as usize);
+        assert_eq!(canvas.to_string(), include_str!("../mandelbrot.txt"));
+    }
+
+    #[test]
+    #[should_panic(expected = "cell count must match dimensions")]
+    fn test_huge_canvas_sizes_do_not_overflow() {
+        // 65536 x 65537 cells overflow `u32`, where they would wrap around to 65536.
+        mandelbrot_ascii::AsciiCanvas::from_cells(65_536, 65_537, vec!['.'; 65_536]);
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_ascii_canvas_hash_map_adapter() {
+        let canvas = mandelbrot_ascii::collect_ascii();
+        let map = mandelbrot_ascii::collect_ascii_map();
+        for y in 0..mandelbrot_ascii::HEIGHT {
+            for x in 0..mandelbrot_ascii::WIDTH {
+                let pixel_index =
+                    mandelbrot_ascii::calculate_pixel_index(x, y, mandelbrot_ascii::WIDTH);
+                assert_eq!(canvas.get(x, y), Some(map[&pixel_index]));
+            }
+        }
+    }
+
+    #[test]
+    fn test_ascii_options_charset_and_aspect() {
+        use mandelbrot_ascii::{render_canvas, AsciiOptions, CELL_ASPECT};
+
+        let viewport = viewport::Viewport::default();
+        let classic = AsciiOptions::default();
+        assert_eq!(
+            mandelbrot_ascii::collect_ascii_with(&classic),
+            render_canvas(&viewport, 80, 40, 100)
+        );
+
+        let opts = AsciiOptions::new(60, 20).with_charset("ab");
+        assert_eq!((opts.to_char(0), opts.to_char(99), opts.to_char(100)), ('a', 'a', 'b'));
+        assert_eq!(opts.with_charset("").to_char(100), '@');
+
+        // Cells are twice as tall as wide, and the whole viewport stays in view.
+        let opts = AsciiOptions::new(60, 20).with_aspect_correction(true);
+        let (x0, y0) = opts.to_complex_num(&viewport, 30, 10);
+        let (x1, y1) = opts.to_complex_num(&viewport, 31, 11);
+        assert_eq!((x0, y0), (viewport.center_x, viewport.center_y));
+        assert!(((y1 - y0) - (x1 - x0) * CELL_ASPECT).abs() < 1e-12);
+        let (left, top) = opts.to_complex_num(&viewport, 0, 0);
+        let (min_x, _, min_y, _) = viewport.bounds();
+        assert!(left <= min_x + 1e-12 && top <= min_y + 1e-12);
+    }
+
+    #[test]
+    fn test_braille_packs_every_dot_once() {
+        use mandelbrot_ascii::braille_from_counts;
+
+        // Every dot of a 3 x 5 grid on its own: the partial cells keep only the covered dots.
+        let mut raised = Vec::new();
+        for index in 0..15 {
+            let mut counts = [0; 15];
+            counts[index] = 1;
+            let canvas = braille_from_counts(3, 5, &counts, 1);
+            assert_eq!((canvas.width(), canvas.height()), (2, 2));
+            let cells: Vec<u32> = canvas.rows().flatten().map(|&c| c as u32 - 0x2800).collect();
+            assert_eq!(cells.iter().map(|bits| bits.count_ones()).sum::<u32>(), 1);
+            raised.push(cells);
+        }
+        raised.dedup();
+        assert_eq!(raised.len(), 15);
+        assert_eq!(
+            braille_from_counts(3, 5, &[1; 15], 1).to_string(),
+            "\u{28ff}\u{2847}\n\u{2809}\u{2801}\n"
+        );
+
+        let done = std::sync::atomic::AtomicU64::new(0);
+        let viewport = crate::viewport::Viewport::default();
+        let on_progress = |rendered, total| {
+            assert_eq!(total, 40 * 2 * 20 * 4);
+            done.fetch_max(rendered, std::sync::atomic::Ordering::Relaxed);
+        };
+        let canvas =
+            mandelbrot_ascii::render_braille_with_progress(&viewport, 40, 20, 100, on_progress);
+        assert_eq!(canvas, mandelbrot_ascii::render_braille(&viewport, 40, 20, 100));
+        assert_eq!(done.into_inner(), 40 * 2 * 20 * 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "braille canvas has too many dots")]
+    fn test_huge_braille_canvas_does_not_overflow() {
+        mandelbrot_ascii::render_braille(&crate::viewport::Viewport::default(), 1, u32::MAX / 2, 1);
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn test_rgba8_matches_composed_image() {
+        use crate::{options::RenderOptions, viewport::Viewport};
+
+        let (viewport, opts) = (Viewport::seahorse_valley(), RenderOptions::new(300));
+        let image = mandelbrot_img::compose_with_options(64, 48, &viewport, &opts);
+        let rgba = image::DynamicImage::ImageRgb8(image).into_rgba8();
+        assert_eq!(mandelbrot_img::render_rgba8(64, 48, &viewport, &opts), rgba.into_raw());
+    }
+
+    #[test]
+    fn test_write_ascii_to_file_modes() {
+        use mandelbrot_ascii::{write_ascii_to_file, AsciiCanvas, WriteMode};
+
+        let path =
+            std::env::temp_dir().join(format!("mandelbrot-ascii-{}.txt", std::process::id()));
+        let canvas = AsciiCanvas::new(4, 2);
+        write_ascii_to_file(&canvas, &path, WriteMode::Overwrite).unwrap();
+        write_ascii_to_file(&canvas, &path, WriteMode::Append).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), canvas.to_string().repeat(2));
+
+        write_ascii_to_file(&canvas, &path, WriteMode::Overwrite).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), canvas.to_string());
+        std::fs::remove_file(&path).unwrap();
+
+        let missing_dir = std::env::temp_dir().join("mandelbrot-missing-dir").join("out.txt");
+        assert!(write_ascii_to_file(&canvas, missing_dir, WriteMode::Overwrite).is_err());
+    }
+
+    #[test]
+    fn test_mandelbrot_smooth_tracks_escape_count() {
+        const ITERATIONS: u32 = 255;
+        for x in (0..WIDTH).step_by(13) {
+            for y in (0..HEIGHT).step_by(17) {
+                let c = mandelbrot_img::to_complex_num(x, y, WIDTH, HEIGHT);
+                let count = mandelbrot(c, ITERATIONS);
+                let smooth = mandelbrot_img::mandelbrot_smooth(c, ITERATIONS, 2.0);
+                if count == ITERATIONS {
+                    assert_eq!(smooth, ITERATIONS as f64);
+                } else {
+                    // A large overshoot past the bailout can pull the value below the count.
+                    assert!((0.0..=count as f64 + 2.0).contains(&smooth), "{c:?}: {smooth}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "image")]
+    fn test_compose_smoothing_only_shifts_exterior_shades() {
+        use crate::{options::RenderOptions, viewport::Viewport};
+
+        let viewport = Viewport::default();
+        let opts = RenderOptions::new(100);
+        let smooth = mandelbrot_img::compose_with_options(80, 60, &viewport, &opts);
+        let banded =
+            mandelbrot_img::compose_with_options(80, 60, &viewport, &opts.with_smooth(false));
+        for (x, y, pixel) in banded.enumerate_pixels() {
+            let count = mandelbrot(viewport.to_complex_num(x, y, 80, 60), 100);
+            assert_eq!(pixel[0], count as u8);
+            let shift = smooth.get_pixel(x, y)[0] as i32 - pixel[0] as i32;
+            assert!((-1..=2).contains(&shift), "({x}, {y}): {shift}");
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "image")]
+    fn test_deep_shades_keep_counts_above_255() {
+        use crate::{options::RenderOptions, viewport::Viewport};
+
+        let viewport = Viewport::seahorse_valley();
+        let opts = RenderOptions::new(2000);
+        let gray = mandelbrot_img::compose_with_options(80, 60, &viewport, &opts);
+        let deep = mandelbrot_img::compose_u16(80, 60, &viewport, &opts);
+        let float = mandelbrot_img::compose_f32(80, 60, &viewport, &opts);
+        assert!(deep.pixels().any(|pixel| pixel[0] > 255));
+        for (x, y, pixel) in deep.enumerate_pixels() {
+            assert_eq!(pixel[0] % 256, gray.get_pixel(x, y)[0] as u16);
+            assert_eq!(pixel[0], float.get_pixel(x, y)[0].round() as u16);
+        }
+    }
 }
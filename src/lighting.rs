@@ -0,0 +1,172 @@
+//! Slope shading, which lights the set as if it were a relief.
+//!
+//! Outside the set the potential rises smoothly away from the boundary, so it can be read as the
+//! height of a surface. The direction of its slope at `c` is that of `z / (dz/dc)` once the orbit
+//! has escaped, the same derivative `analysis::distance_estimate` follows. Tilting that direction
+//! up gives a surface normal, which `light_value` lights with a distant light from
+//! `azimuth` and `elevation`: Lambert's diffuse term for the relief and a Phong highlight for the
+//! ridges. Shading is selected with `options::ColorMode::Shaded`.
+
+use std::sync::atomic::AtomicBool;
+
+use num_complex::Complex64;
+
+use crate::{
+    analysis::DISTANCE_RADIUS,
+    scheduler::{try_render_grid, RenderCancelled},
+    viewport::Viewport,
+};
+
+/// Direction the light comes from in the complex plane, in degrees counterclockwise from the
+/// positive real axis, unless another one is given.
+pub const DEFAULT_AZIMUTH: f64 = 45.0;
+
+/// Height of the light above the plane, in degrees, unless another one is given.
+pub const DEFAULT_ELEVATION: f64 = 45.0;
+
+/// Brightness of surfaces that face away from the light.
+pub const AMBIENT: f64 = 0.2;
+
+/// Share of the brightness that comes from the highlight rather than the diffuse term.
+pub const SPECULAR: f64 = 0.3;
+
+/// Exponent of the highlight; larger ones make it smaller and sharper.
+pub const SHININESS: f64 = 20.0;
+
+/// Direction in which the potential at `c` rises fastest, as a unit vector in the complex
+/// plane, which the normal of the relief leans towards, or `None` if `c` does not leave
+/// `DISTANCE_RADIUS` within `iterations` iterations and is treated as part of the set.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::lighting::surface_normal;
+/// use num_complex::Complex64;
+///
+/// // Far out on the real axis the potential rises away from the set, to the right.
+/// let normal = surface_normal(Complex64::new(3.0, 0.0), 100).unwrap();
+/// assert!((normal.re - 1.0).abs() < 1e-9 && normal.im.abs() < 1e-9);
+/// assert_eq!(surface_normal(Complex64::new(0.0, 0.0), 100), None);
+/// ```
+pub fn surface_normal(c: Complex64, iterations: u32) -> Option<Complex64> {
+    let radius_sqr = DISTANCE_RADIUS * DISTANCE_RADIUS;
+    let mut z = Complex64::new(0.0, 0.0);
+    let mut dz = Complex64::new(0.0, 0.0);
+    for _ in 0..iterations {
+        dz = 2.0 * z * dz + 1.0;
+        z = z * z + c;
+        if z.norm_sqr() > radius_sqr {
+            let u = z / dz;
+            return (u.norm() > 0.0).then(|| u / u.norm()).filter(|u| u.is_finite());
+        }
+    }
+    None
+}
+
+/// Brightness between `0.0` and `1.0` of a surface whose normal leans towards `normal`, see
+/// `surface_normal`, lit from `azimuth` and `elevation` in degrees. The set itself, `None`, is
+/// `0.0`.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::lighting::{light_value, AMBIENT};
+/// use num_complex::Complex64;
+///
+/// let east = Some(Complex64::new(1.0, 0.0));
+/// assert!(light_value(east, 0.0, 45.0) > light_value(east, 180.0, 45.0));
+/// assert_eq!(light_value(east, 180.0, 0.0), AMBIENT);
+/// assert_eq!(light_value(None, 0.0, 45.0), 0.0);
+/// ```
+pub fn light_value(normal: Option<Complex64>, azimuth: f64, elevation: f64) -> f64 {
+    let Some(normal) = normal else {
+        return 0.0;
+    };
+    let (azimuth, elevation) = (azimuth.to_radians(), elevation.to_radians());
+    let light = [elevation.cos() * azimuth.cos(), elevation.cos() * azimuth.sin(), elevation.sin()];
+    // Slopes of 45 degrees: the normal tilts halfway between the plane and the vertical.
+    let n = [normal.re / 2.0_f64.sqrt(), normal.im / 2.0_f64.sqrt(), 1.0 / 2.0_f64.sqrt()];
+    let diffuse = (n[0] * light[0] + n[1] * light[1] + n[2] * light[2]).max(0.0);
+    // The light reflected towards a viewer looking straight down.
+    let reflected = (2.0 * diffuse * n[2] - light[2]).max(0.0);
+    let highlight = if diffuse > 0.0 { reflected.powf(SHININESS) } else { 0.0 };
+    let lit = (1.0 - SPECULAR) * diffuse + SPECULAR * highlight;
+    (AMBIENT + (1.0 - AMBIENT) * lit).clamp(0.0, 1.0)
+}
+
+/// Runs `surface_normal` for every pixel of a `width` x `height` grid over `viewport`, in
+/// row-major order. Always uses `f64` arithmetic.
+pub fn normal_viewport(
+    width: u32,
+    height: u32,
+    viewport: &Viewport,
+    iterations: u32,
+) -> Vec<Option<Complex64>> {
+    normal_viewport_with_progress(width, height, viewport, iterations, |_, _| {})
+}
+
+/// Like `normal_viewport`, but calls `on_progress(done, total)` with the number of evaluated
+/// pixels after every finished row.
+pub fn normal_viewport_with_progress<P>(
+    width: u32,
+    height: u32,
+    viewport: &Viewport,
+    iterations: u32,
+    on_progress: P,
+) -> Vec<Option<Complex64>>
+where
+    P: Fn(u64, u64) + Sync,
+{
+    let never = AtomicBool::new(false);
+    match try_normal_viewport(width, height, viewport, iterations, on_progress, &never) {
+        Ok(normals) => normals,
+        Err(RenderCancelled) => unreachable!("the render was never cancelled"),
+    }
+}
+
+/// Like `normal_viewport_with_progress`, but stops once `cancel` is set, see
+/// `scheduler::try_render_grid`.
+///
+/// # Errors
+///
+/// Returns `RenderCancelled` if the render was cancelled.
+pub fn try_normal_viewport<P>(
+    width: u32,
+    height: u32,
+    viewport: &Viewport,
+    iterations: u32,
+    on_progress: P,
+    cancel: &AtomicBool,
+) -> Result<Vec<Option<Complex64>>, RenderCancelled>
+where
+    P: Fn(u64, u64) + Sync,
+{
+    try_render_grid(
+        width,
+        height,
+        |x, y| surface_normal(viewport.point(x, y, width, height), iterations),
+        on_progress,
+        cancel,
+    )
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_light_from_above_shows_no_relief() {
+        // Every slope faces a light at the zenith equally, whichever way it falls.
+        let values: Vec<f64> = [0.0, 1.0, 2.0, 4.0]
+            .iter()
+            .map(|&angle: &f64| light_value(Some(Complex64::from_polar(1.0, angle)), 0.0, 90.0))
+            .collect();
+        assert!(values.iter().all(|&value| (value - values[0]).abs() < 1e-12), "{values:?}");
+
+        // A slope facing the light is brighter than one facing away from it.
+        let normal = surface_normal(Complex64::new(-0.745, 0.2), 1000).unwrap();
+        let towards = normal.arg().to_degrees();
+        let away = towards + 180.0;
+        assert!(light_value(Some(normal), towards, 30.0) > light_value(Some(normal), away, 30.0));
+    }
+}
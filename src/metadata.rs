@@ -0,0 +1,261 @@
+//! Render parameters stored with the images they were rendered into.
+//!
+//! PNG images written by `save_png` carry the version of this crate, the iteration limit, the
+//! viewport, the fractal and the palette of their render as `tEXt` chunks, which image viewers
+//! list as text properties and `ImageMetadata::load` reads back. Other formats have no place for
+//! them, so `ImageMetadata::save_sidecar` writes them to a JSON file next to the image instead.
+//! The sidecar is a scene file with an extra `version` field, so `scene::Scene::load` renders
+//! the image again from it.
+//!
+//! | Keyword      | Text                                                |
+//! |--------------|-----------------------------------------------------|
+//! | `Software`   | `mandelbrot` and the version of the crate           |
+//! | `Iterations` | The iteration limit                                 |
+//! | `Viewport`   | The viewport as JSON, see `viewport::Viewport`      |
+//! | `Fractal`    | The fractal as JSON, see `fractal::FractalKind`     |
+//! | `Palette`    | The name of the palette, left out for gray images   |
+//! | `Tile`       | The tile as JSON, see `tiles::Tile`, for tiles only |
+//! | `Size`       | The size of the whole render, for tiles only        |
+
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use image::RgbImage;
+use serde::{Deserialize, Serialize};
+
+use crate::{scene::Scene, tiles::Tile};
+
+/// Version of this crate, as written to the images it renders.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The parameters an image was rendered with.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImageMetadata {
+    /// Version of the crate that rendered the image.
+    pub version: String,
+    #[serde(flatten)]
+    pub scene: Scene,
+    /// The part of the render of `scene` the image holds, if it is one of its tiles rather than
+    /// the whole render.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tile: Option<Tile>,
+}
+
+impl ImageMetadata {
+    /// Metadata of a render of `scene` by this version of the crate.
+    pub fn new(scene: Scene) -> Self {
+        Self { version: VERSION.to_string(), scene, tile: None }
+    }
+
+    /// Metadata of `tile` of the render of `scene`.
+    pub fn with_tile(self, tile: Tile) -> Self {
+        Self { tile: Some(tile), ..self }
+    }
+
+    /// The `tEXt` chunks written to PNG images, as keyword and text.
+    pub fn text_chunks(&self) -> Vec<(&'static str, String)> {
+        let scene = &self.scene;
+        let mut chunks = vec![
+            ("Software", format!("mandelbrot {}", self.version)),
+            ("Iterations", scene.iterations.to_string()),
+            ("Viewport", serde_json::to_string(&scene.viewport).expect("viewports serialize")),
+            ("Fractal", serde_json::to_string(&scene.kind).expect("fractals serialize")),
+        ];
+        if let Some(palette) = &scene.palette {
+            chunks.push(("Palette", palette.clone()));
+        }
+        if let Some(tile) = &self.tile {
+            chunks.push(("Tile", serde_json::to_string(tile).expect("tiles serialize")));
+            chunks.push(("Size", format!("{}x{}", scene.width, scene.height)));
+        }
+        chunks
+    }
+
+    /// Metadata of a `width` x `height` image from its `tEXt` chunks, given as keyword and text,
+    /// with the size of the `Size` chunk instead for tiles. Chunks with other keywords are
+    /// ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the chunks were not written by this crate or cannot be parsed.
+    pub fn from_text_chunks<'a>(
+        width: u32,
+        height: u32,
+        chunks: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> io::Result<Self> {
+        let mut version = None;
+        let mut tile = None;
+        let mut scene = Scene { width, height, ..Scene::default() };
+        for (keyword, text) in chunks {
+            match keyword {
+                "Software" => version = text.strip_prefix("mandelbrot ").map(str::to_string),
+                "Iterations" => scene.iterations = text.parse().map_err(invalid_data)?,
+                "Viewport" => scene.viewport = serde_json::from_str(text).map_err(invalid_data)?,
+                "Fractal" => scene.kind = serde_json::from_str(text).map_err(invalid_data)?,
+                "Palette" => scene.palette = Some(text.to_string()),
+                "Tile" => tile = Some(serde_json::from_str(text).map_err(invalid_data)?),
+                "Size" => {
+                    let size = text.split_once('x').and_then(|(width, height)| {
+                        Some((width.parse().ok()?, height.parse().ok()?))
+                    });
+                    (scene.width, scene.height) =
+                        size.ok_or_else(|| invalid_data(format!("invalid size {text}")))?;
+                }
+                _ => {}
+            }
+        }
+        let version =
+            version.ok_or_else(|| invalid_data("the image was not rendered by mandelbrot"))?;
+        Ok(Self { version, scene, tile })
+    }
+
+    /// Loads the metadata of the image at `path`: from its `tEXt` chunks for a PNG image, from
+    /// `path` itself for a `.json` sidecar, and from the sidecar of the image otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or holds no render parameters.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or("");
+        if extension.eq_ignore_ascii_case("png") {
+            read_png(BufReader::new(File::open(path)?))
+        } else if extension.eq_ignore_ascii_case("json") {
+            serde_json::from_str(&fs::read_to_string(path)?).map_err(invalid_data)
+        } else {
+            Self::load(sidecar_path(path))
+        }
+    }
+
+    /// Writes the metadata to the sidecar of the image at `path` and returns the path of the
+    /// sidecar.
+    pub fn save_sidecar(&self, path: impl AsRef<Path>) -> io::Result<PathBuf> {
+        let sidecar = sidecar_path(path);
+        fs::write(&sidecar, serde_json::to_string_pretty(self).map_err(invalid_data)?)?;
+        Ok(sidecar)
+    }
+}
+
+/// The sidecar of the image at `path`, the path with its extension replaced by `.json`.
+///
+/// # Examples
+/// ```
+/// use std::path::PathBuf;
+///
+/// use mandelbrot::metadata::sidecar_path;
+///
+/// assert_eq!(sidecar_path("renders/seahorse.jpg"), PathBuf::from("renders/seahorse.json"));
+/// ```
+pub fn sidecar_path(path: impl AsRef<Path>) -> PathBuf {
+    path.as_ref().with_extension("json")
+}
+
+/// Encodes `image` as a PNG image with the `tEXt` chunks of `metadata`.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::{
+///     mandelbrot_img::compose,
+///     metadata::{read_png, write_png, ImageMetadata},
+///     scene::Scene,
+/// };
+///
+/// let scene = Scene { width: 64, height: 48, iterations: 500, ..Scene::default() };
+/// let mut png = Vec::new();
+/// write_png(&compose(64, 48, 500), &ImageMetadata::new(scene.clone()), &mut png).unwrap();
+/// assert_eq!(read_png(png.as_slice()).unwrap().scene, scene);
+/// ```
+pub fn write_png<W: Write>(image: &RgbImage, metadata: &ImageMetadata, out: W) -> io::Result<()> {
+    let mut encoder = png::Encoder::new(out, image.width(), image.height());
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    for (keyword, text) in metadata.text_chunks() {
+        encoder.add_text_chunk(keyword.to_string(), text)?;
+    }
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(image.as_raw())?;
+    Ok(writer.finish()?)
+}
+
+/// Saves `image` at `path` as a PNG image with the `tEXt` chunks of `metadata`.
+pub fn save_png(
+    image: &RgbImage,
+    metadata: &ImageMetadata,
+    path: impl AsRef<Path>,
+) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    write_png(image, metadata, &mut file)?;
+    file.flush()
+}
+
+/// Reads the metadata from the `tEXt` chunks of a PNG image, see
+/// `ImageMetadata::from_text_chunks`.
+///
+/// # Errors
+///
+/// Returns an error if the image cannot be decoded or holds no render parameters.
+pub fn read_png<R: Read>(input: R) -> io::Result<ImageMetadata> {
+    let reader = png::Decoder::new(input).read_info()?;
+    let info = reader.info();
+    let chunks = info.uncompressed_latin1_text.iter();
+    ImageMetadata::from_text_chunks(
+        info.width,
+        info.height,
+        chunks.map(|chunk| (chunk.keyword.as_str(), chunk.text.as_str())),
+    )
+}
+
+fn invalid_data(e: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+    use crate::{
+        fractal::FractalKind, mandelbrot_img::compose, tiles::TileGrid, viewport::Viewport,
+    };
+
+    #[test]
+    fn test_parameters_survive_png_and_sidecar() {
+        let scene = Scene {
+            width: 40,
+            height: 30,
+            iterations: 2000,
+            palette: Some("ocean".to_string()),
+            kind: FractalKind::Multibrot { power: 3.0 },
+            viewport: Viewport::seahorse_valley(),
+        };
+        let metadata = ImageMetadata::new(scene.clone());
+        let image = compose(40, 30, 100);
+        let path = env::temp_dir().join(format!("mandelbrot-metadata-{}.png", std::process::id()));
+        save_png(&image, &metadata, &path).unwrap();
+        assert_eq!(image::open(&path).unwrap().to_rgb8(), image);
+        assert_eq!(ImageMetadata::load(&path).unwrap(), metadata);
+
+        // The sidecar is read for images without chunks, and as a scene file.
+        let sidecar = metadata.save_sidecar(path.with_extension("bmp")).unwrap();
+        assert_eq!(ImageMetadata::load(path.with_extension("bmp")).unwrap(), metadata);
+        assert_eq!(Scene::load(&sidecar).unwrap(), scene);
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(&sidecar).unwrap();
+
+        // Tiles keep the size of the whole render rather than their own.
+        let tile = TileGrid::new(2, 2).tile(1, 0, 40, 30).unwrap();
+        let metadata = ImageMetadata::new(scene).with_tile(tile);
+        let mut png = Vec::new();
+        write_png(&compose(tile.width, tile.height, 100), &metadata, &mut png).unwrap();
+        assert_eq!(read_png(png.as_slice()).unwrap(), metadata);
+
+        let mut plain = Vec::new();
+        image.write_to(&mut io::Cursor::new(&mut plain), image::ImageOutputFormat::Png).unwrap();
+        assert!(read_png(plain.as_slice()).is_err());
+    }
+}
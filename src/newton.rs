@@ -0,0 +1,342 @@
+//! Renders Newton fractals, the basins of attraction of Newton's method for a polynomial.
+//!
+//! Every pixel is a starting point `z`, which Newton's method `z -> z - p(z) / p'(z)` moves
+//! towards one of the roots of `p`. Pixels are colored by the root they converge to, each root
+//! taking its own stretch of the palette, and darkened by the number of steps it took, so the
+//! fractal boundaries between the basins, where convergence is slowest, stand out. The roots
+//! themselves are found beforehand with the Durand-Kerner method. Starting points that do not
+//! converge within the step limit, such as those that land on a critical point, are black.
+
+use std::fmt;
+
+#[cfg(feature = "image")]
+use image::{ImageBuffer, Rgb, RgbImage};
+use num_complex::Complex64;
+
+#[cfg(feature = "image")]
+use crate::palette::Palette;
+use crate::{
+    scheduler::render_grid_with_progress,
+    viewport::{Viewport, BASE_WIDTH},
+};
+
+/// Default step limit of `NewtonOptions`.
+pub const DEFAULT_NEWTON_ITERATIONS: u32 = 50;
+
+/// Default distance to a root at which `NewtonOptions` considers a point converged.
+pub const DEFAULT_TOLERANCE: f64 = 1e-6;
+
+/// Side of the square around the origin that `default_viewport` shows.
+pub const DEFAULT_SPAN: f64 = 4.0;
+
+/// A polynomial with complex coefficients, of degree at least `2`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polynomial {
+    /// Coefficients from the highest degree down to the constant term; the first is not zero.
+    coefficients: Vec<Complex64>,
+}
+
+impl Polynomial {
+    /// The polynomial with `coefficients`, from the highest degree down to the constant term,
+    /// or `None` if its degree is below `2` once leading zeros are dropped, or a coefficient is
+    /// not finite.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::newton::Polynomial;
+    /// use num_complex::Complex64;
+    ///
+    /// let one = Complex64::new(1.0, 0.0);
+    /// let cubic = Polynomial::new(vec![one, 0.0.into(), 0.0.into(), -one]).unwrap();
+    /// assert_eq!(cubic.degree(), 3);
+    /// assert_eq!(Polynomial::new(vec![0.0.into(), one, -one]), None);
+    /// ```
+    pub fn new(coefficients: Vec<Complex64>) -> Option<Self> {
+        let first = coefficients.iter().position(|c| c.norm_sqr() > 0.0)?;
+        let coefficients = coefficients[first..].to_vec();
+        if coefficients.len() < 3 || coefficients.iter().any(|c| !c.is_finite()) {
+            return None;
+        }
+        Some(Self { coefficients })
+    }
+
+    /// Parses comma-separated coefficients from the highest degree down, each a real or complex
+    /// number such as `1`, `-0.5` or `2+1i`, like `"1,0,0,-1"` for `z^3 - 1`.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::newton::Polynomial;
+    ///
+    /// assert_eq!(Polynomial::parse("1, 0, 0, -1").unwrap().degree(), 3);
+    /// assert_eq!(Polynomial::parse("1,0,i,0,-1").unwrap().to_string(), "z^4 + iz^2 - 1");
+    /// assert_eq!(Polynomial::parse("2,-0.5i,1+1i").unwrap().to_string(), "2z^2 - 0.5iz + (1+1i)");
+    /// assert_eq!(Polynomial::parse("1,-1"), None);
+    /// assert_eq!(Polynomial::parse("1,z,1"), None);
+    /// ```
+    pub fn parse(coefficients: &str) -> Option<Self> {
+        let coefficients = coefficients
+            .split(',')
+            .map(|coefficient| coefficient.trim().parse::<Complex64>().ok())
+            .collect::<Option<Vec<_>>>()?;
+        Self::new(coefficients)
+    }
+
+    pub fn degree(&self) -> usize {
+        self.coefficients.len() - 1
+    }
+
+    /// Coefficients from the highest degree down to the constant term.
+    pub fn coefficients(&self) -> &[Complex64] {
+        &self.coefficients
+    }
+
+    /// `p(z)` and `p'(z)`, by Horner's method.
+    pub fn eval(&self, z: Complex64) -> (Complex64, Complex64) {
+        let zero = Complex64::new(0.0, 0.0);
+        self.coefficients.iter().fold((zero, zero), |(p, dp), &c| (p * z + c, dp * z + p))
+    }
+
+    /// Every root of the polynomial, repeated roots as often as their multiplicity, found with
+    /// the Durand-Kerner method.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::newton::Polynomial;
+    ///
+    /// let roots = Polynomial::parse("1,0,-4").unwrap().roots();
+    /// let mut real: Vec<f64> = roots.iter().map(|root| root.re).collect();
+    /// real.sort_by(f64::total_cmp);
+    /// assert!((real[0] + 2.0).abs() < 1e-9 && (real[1] - 2.0).abs() < 1e-9);
+    /// ```
+    pub fn roots(&self) -> Vec<Complex64> {
+        let lead = self.coefficients[0];
+        // Starting points spread on a circle that is neither real nor symmetric.
+        let seed = Complex64::new(0.4, 0.9);
+        let mut roots: Vec<Complex64> = (0..self.degree()).map(|i| seed.powu(i as u32)).collect();
+        for _ in 0..500 {
+            let mut largest_step = 0.0_f64;
+            for i in 0..roots.len() {
+                let others = (0..roots.len())
+                    .filter(|&j| j != i)
+                    .fold(lead, |product, j| product * (roots[i] - roots[j]));
+                let step = self.eval(roots[i]).0 / others;
+                if step.is_finite() {
+                    roots[i] -= step;
+                    largest_step = largest_step.max(step.norm());
+                }
+            }
+            if largest_step < 1e-14 {
+                break;
+            }
+        }
+        roots
+    }
+}
+
+impl fmt::Display for Polynomial {
+    /// Writes the polynomial in `z`, leaving out the zero terms.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut first = true;
+        for (i, &c) in self.coefficients.iter().enumerate() {
+            let power = self.degree() - i;
+            if c.norm_sqr() == 0.0 {
+                continue;
+            }
+            let negative = (c.im == 0.0 && c.re < 0.0) || (c.re == 0.0 && c.im < 0.0);
+            let (sign, c) = if negative { ("-", -c) } else { ("+", c) };
+            let coefficient = match (c.re, c.im) {
+                (1.0, 0.0) if power > 0 => String::new(),
+                (re, 0.0) => re.to_string(),
+                (0.0, 1.0) => "i".to_string(),
+                (0.0, im) => format!("{im}i"),
+                (re, im) => format!("({re}{im:+}i)"),
+            };
+            match (first, sign) {
+                (true, "-") => write!(f, "-")?,
+                (true, _) => {}
+                (false, sign) => write!(f, " {sign} ")?,
+            }
+            match power {
+                0 => write!(f, "{coefficient}")?,
+                1 => write!(f, "{coefficient}z")?,
+                _ => write!(f, "{coefficient}z^{power}")?,
+            }
+            first = false;
+        }
+        Ok(())
+    }
+}
+
+/// How long Newton's method runs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NewtonOptions {
+    /// Steps after which a point that has not converged is given up.
+    pub iterations: u32,
+    /// Distance to a root at which a point counts as converged to it.
+    pub tolerance: f64,
+}
+
+impl Default for NewtonOptions {
+    fn default() -> Self {
+        Self { iterations: DEFAULT_NEWTON_ITERATIONS, tolerance: DEFAULT_TOLERANCE }
+    }
+}
+
+/// Where Newton's method took a starting point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NewtonResult {
+    /// Index of the root the point converged to, in the order of `Polynomial::roots`, or
+    /// `None` if it did not converge.
+    pub root: Option<usize>,
+    /// Steps taken until the point converged, or the step limit.
+    pub iterations: u32,
+}
+
+/// Runs Newton's method for `polynomial` from `z`, stopping once it comes within the tolerance
+/// of one of `roots`.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::newton::{newton, NewtonOptions, Polynomial};
+/// use num_complex::Complex64;
+///
+/// let cubic = Polynomial::parse("1,0,0,-1").unwrap();
+/// let roots = cubic.roots();
+/// let result = newton(Complex64::new(2.0, 0.1), &cubic, &roots, &NewtonOptions::default());
+/// let root = roots[result.root.unwrap()];
+/// assert!((root - Complex64::new(1.0, 0.0)).norm() < 1e-6);
+/// // Newton's method cannot leave the critical point 0, where p' vanishes.
+/// let stuck = newton(Complex64::new(0.0, 0.0), &cubic, &roots, &NewtonOptions::default());
+/// assert_eq!(stuck.root, None);
+/// ```
+pub fn newton(
+    z: Complex64,
+    polynomial: &Polynomial,
+    roots: &[Complex64],
+    opts: &NewtonOptions,
+) -> NewtonResult {
+    let tolerance_sqr = opts.tolerance * opts.tolerance;
+    let mut z = z;
+    for i in 0..opts.iterations {
+        if let Some(root) = roots.iter().position(|&root| (z - root).norm_sqr() < tolerance_sqr) {
+            return NewtonResult { root: Some(root), iterations: i };
+        }
+        let (p, dp) = polynomial.eval(z);
+        z -= p / dp;
+        if !z.is_finite() {
+            break;
+        }
+    }
+    NewtonResult { root: None, iterations: opts.iterations }
+}
+
+/// The square of side `DEFAULT_SPAN` around the origin, which holds the roots of most small
+/// polynomials.
+pub fn default_viewport() -> Viewport {
+    Viewport::new(0.0, 0.0, BASE_WIDTH / DEFAULT_SPAN)
+}
+
+/// Runs `newton` for every pixel of a `width` x `height` grid over `viewport`, in row-major
+/// order, and calls `on_progress(done, total)` with the number of evaluated pixels after every
+/// finished row.
+pub fn newton_viewport_with_progress<P>(
+    width: u32,
+    height: u32,
+    viewport: &Viewport,
+    polynomial: &Polynomial,
+    opts: &NewtonOptions,
+    on_progress: P,
+) -> Vec<NewtonResult>
+where
+    P: Fn(u64, u64) + Sync,
+{
+    let roots = polynomial.roots();
+    render_grid_with_progress(
+        width,
+        height,
+        |x, y| newton(viewport.point(x, y, width, height), polynomial, &roots, opts),
+        on_progress,
+    )
+}
+
+/// Colors the results of a `width` x `height` grid, in row-major order, with the `n` roots of
+/// the polynomial each taking the color of `palette` at `(root + 0.5) / n`, darkened by the
+/// share of the step limit `opts.iterations` the point took to converge.
+#[cfg(feature = "image")]
+pub fn colorize_newton<P: Palette + ?Sized>(
+    width: u32,
+    height: u32,
+    results: &[NewtonResult],
+    roots: usize,
+    opts: &NewtonOptions,
+    palette: &P,
+) -> RgbImage {
+    ImageBuffer::from_fn(width, height, |x, y| {
+        let result = results[(y * width + x) as usize];
+        let Some(root) = result.root else {
+            return Rgb([0, 0, 0]);
+        };
+        let color = palette.color((root as f64 + 0.5) / roots.max(1) as f64);
+        let shade = 1.0 - (result.iterations as f64 / opts.iterations.max(1) as f64).sqrt();
+        Rgb(color.0.map(|channel| (channel as f64 * shade).round() as u8))
+    })
+}
+
+/// Renders the Newton fractal of `polynomial` over `viewport` at `width` x `height`, colored
+/// by `colorize_newton`.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::{
+///     newton::{compose_newton, default_viewport, NewtonOptions, Polynomial},
+///     palette::BuiltinPalette,
+/// };
+///
+/// let cubic = Polynomial::parse("1,0,0,-1").unwrap();
+/// let opts = NewtonOptions::default();
+/// let image =
+///     compose_newton(64, 64, &default_viewport(), &cubic, &opts, &BuiltinPalette::Rainbow);
+/// assert_eq!(image.dimensions(), (64, 64));
+/// ```
+#[cfg(feature = "image")]
+pub fn compose_newton<P: Palette + ?Sized>(
+    width: u32,
+    height: u32,
+    viewport: &Viewport,
+    polynomial: &Polynomial,
+    opts: &NewtonOptions,
+    palette: &P,
+) -> RgbImage {
+    let results =
+        newton_viewport_with_progress(width, height, viewport, polynomial, opts, |_, _| {});
+    colorize_newton(width, height, &results, polynomial.degree(), opts, palette)
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_basins_follow_the_symmetry_of_the_roots() {
+        // The roots of z^3 - 1 are the cube roots of unity, so rotating a starting point by a
+        // third of a turn moves it to the basin of the next root.
+        let cubic = Polynomial::parse("1,0,0,-1").unwrap();
+        let roots = cubic.roots();
+        for root in &roots {
+            assert!((root.powu(3) - 1.0).norm() < 1e-9, "{root}");
+        }
+        let turn = Complex64::from_polar(1.0, std::f64::consts::TAU / 3.0);
+        let opts = NewtonOptions::default();
+        for z in [Complex64::new(0.3, 0.7), Complex64::new(-1.5, 0.2), Complex64::new(0.1, -0.05)] {
+            let here = newton(z, &cubic, &roots, &opts);
+            let there = newton(z * turn, &cubic, &roots, &opts);
+            let (Some(a), Some(b)) = (here.root, there.root) else {
+                panic!("{z} did not converge");
+            };
+            assert!((roots[a] * turn - roots[b]).norm() < 1e-6);
+            assert!(here.iterations.abs_diff(there.iterations) <= 1);
+        }
+    }
+}
@@ -0,0 +1,205 @@
+//! Options shared by the renderers and point evaluators.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    averages::AverageColoring,
+    fractal::FractalKind,
+    lighting::{DEFAULT_AZIMUTH, DEFAULT_ELEVATION},
+    traps::TrapShape,
+};
+
+/// What images are shaded by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum ColorMode {
+    /// The escape value of every pixel, smooth or whole depending on `RenderOptions::smooth`.
+    #[default]
+    EscapeTime,
+    /// The estimated distance to the set, see `analysis::distance_estimate`, which shows
+    /// filaments that are thinner than a pixel.
+    DistanceEstimate,
+    /// The distance of the orbit to a trap shape, see `traps`.
+    OrbitTrap(TrapShape),
+    /// The relief of the potential lit from `azimuth` and `elevation`, in degrees, see
+    /// `lighting`.
+    Shaded { azimuth: f64, elevation: f64 },
+    /// A statistic averaged over the orbit, see `averages`.
+    Average(AverageColoring),
+}
+
+impl ColorMode {
+    /// Every mode, with the light of `Shaded` at `lighting::DEFAULT_AZIMUTH` and
+    /// `lighting::DEFAULT_ELEVATION`.
+    pub const ALL: [Self; 9] = [
+        Self::EscapeTime,
+        Self::DistanceEstimate,
+        Self::OrbitTrap(TrapShape::Point),
+        Self::OrbitTrap(TrapShape::Line),
+        Self::OrbitTrap(TrapShape::Circle),
+        Self::OrbitTrap(TrapShape::Cross),
+        Self::Shaded { azimuth: DEFAULT_AZIMUTH, elevation: DEFAULT_ELEVATION },
+        Self::Average(AverageColoring::Stripe),
+        Self::Average(AverageColoring::TriangleInequality),
+    ];
+
+    /// Parses the names returned by `name`, ignoring case.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::{options::ColorMode, traps::TrapShape};
+    ///
+    /// assert_eq!(ColorMode::parse("distance"), Some(ColorMode::DistanceEstimate));
+    /// assert_eq!(ColorMode::parse("trap-cross"), Some(ColorMode::OrbitTrap(TrapShape::Cross)));
+    /// assert_eq!(ColorMode::parse("rainbow"), None);
+    /// ```
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|mode| mode.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::EscapeTime => "escape-time",
+            Self::DistanceEstimate => "distance",
+            Self::OrbitTrap(TrapShape::Point) => "trap-point",
+            Self::OrbitTrap(TrapShape::Line) => "trap-line",
+            Self::OrbitTrap(TrapShape::Circle) => "trap-circle",
+            Self::OrbitTrap(TrapShape::Cross) => "trap-cross",
+            Self::Shaded { .. } => "shaded",
+            Self::Average(AverageColoring::Stripe) => "stripe",
+            Self::Average(AverageColoring::TriangleInequality) => "tia",
+        }
+    }
+}
+
+/// How the pixels of a grid are evaluated.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RenderStrategy {
+    /// Every pixel is iterated.
+    #[default]
+    PerPixel,
+    /// Rectangles whose border pixels share an escape count are filled without iterating their
+    /// inside, see `subdivision`. Much faster on views with large flat areas, but may miss
+    /// features smaller than a rectangle.
+    BoundaryTrace,
+}
+
+impl RenderStrategy {
+    pub const ALL: [Self; 2] = [Self::PerPixel, Self::BoundaryTrace];
+
+    /// Parses the names returned by `name`, ignoring case.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::options::RenderStrategy;
+    ///
+    /// assert_eq!(RenderStrategy::parse("boundary-trace"), Some(RenderStrategy::BoundaryTrace));
+    /// assert_eq!(RenderStrategy::parse("adaptive"), None);
+    /// ```
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|strategy| strategy.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::PerPixel => "per-pixel",
+            Self::BoundaryTrace => "boundary-trace",
+        }
+    }
+}
+
+/// Iteration settings used when evaluating points.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RenderOptions {
+    /// Maximum number of iterations before a point is considered not to escape.
+    pub iterations: u32,
+    /// Radius past which an orbit is considered to have escaped.
+    pub bailout: f64,
+    /// Whether renders also record the argument of `z` at escape, see `IterationBuffer::angle`.
+    pub escape_angle: bool,
+    /// Whether images are shaded by the continuous escape value instead of whole iteration
+    /// counts, which removes the bands between neighbouring counts.
+    pub smooth: bool,
+    /// The iteration to run; the bailout is raised to `FractalKind::escape_radius` if needed.
+    pub kind: FractalKind,
+    /// Whether points inside the set are detected early, by the closed-form cardioid and bulb
+    /// checks and by periodicity checking, instead of running through all iterations.
+    pub optimizations: bool,
+    /// What images are shaded by.
+    pub color_mode: ColorMode,
+    /// How `analysis::escape_viewport` evaluates the pixels of a grid. Arbitrary-precision
+    /// renders always iterate every pixel.
+    #[serde(default)]
+    pub strategy: RenderStrategy,
+    /// Whether `analysis::escape_viewport` computes only the top half of viewports that are
+    /// symmetric about the real axis and mirrors it, see `Viewport::is_symmetric`. The mirrored
+    /// rows are identical to computed ones.
+    #[serde(default = "default_symmetry")]
+    pub symmetry: bool,
+}
+
+fn default_symmetry() -> bool {
+    true
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            iterations: 255,
+            bailout: 2.0,
+            escape_angle: false,
+            smooth: true,
+            kind: FractalKind::Mandelbrot,
+            optimizations: false,
+            color_mode: ColorMode::EscapeTime,
+            strategy: RenderStrategy::PerPixel,
+            symmetry: true,
+        }
+    }
+}
+
+impl RenderOptions {
+    pub fn new(iterations: u32) -> Self {
+        Self { iterations, ..Default::default() }
+    }
+
+    /// Returns a copy of the options with the escape radius set to `bailout`.
+    pub fn with_bailout(self, bailout: f64) -> Self {
+        Self { bailout, ..self }
+    }
+
+    /// Returns a copy of the options that records the escape angle if `escape_angle` is set.
+    pub fn with_escape_angle(self, escape_angle: bool) -> Self {
+        Self { escape_angle, ..self }
+    }
+
+    /// Returns a copy of the options that shades by whole iteration counts unless `smooth` is set.
+    pub fn with_smooth(self, smooth: bool) -> Self {
+        Self { smooth, ..self }
+    }
+
+    /// Returns a copy of the options that detects interior points early if `optimizations` is
+    /// set.
+    pub fn with_optimizations(self, optimizations: bool) -> Self {
+        Self { optimizations, ..self }
+    }
+
+    /// Returns a copy of the options that iterates `kind`.
+    pub fn with_kind(self, kind: FractalKind) -> Self {
+        Self { kind, ..self }
+    }
+
+    /// Returns a copy of the options that shades images by `color_mode`.
+    pub fn with_color_mode(self, color_mode: ColorMode) -> Self {
+        Self { color_mode, ..self }
+    }
+
+    /// Returns a copy of the options that evaluates grids with `strategy`.
+    pub fn with_strategy(self, strategy: RenderStrategy) -> Self {
+        Self { strategy, ..self }
+    }
+
+    /// Returns a copy of the options that mirrors symmetric viewports if `symmetry` is set.
+    pub fn with_symmetry(self, symmetry: bool) -> Self {
+        Self { symmetry, ..self }
+    }
+}
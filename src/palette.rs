@@ -0,0 +1,575 @@
+//! Color palettes that map a normalized escape value onto a color.
+//!
+//! A `Palette` turns a value `t` between `0.0` (escapes immediately) and `1.0` (inside the set)
+//! into a color. The built-in palettes are `Gradient`s, lists of color stops that are
+//! interpolated linearly, and any closure `Fn(f64) -> Rgb<u8>` is a palette as well, so callers
+//! can plug in their own color mapping without defining a type.
+//!
+//! Gradients can also be loaded from the palette files fractal programs and image editors share:
+//! Fractint `.map` files, GIMP `.gpl` palettes, Ultra Fractal `.ugr` gradients and images whose
+//! first row of pixels is the gradient, see `Gradient::load`. A `CyclicPalette` repeats any
+//! palette every few iterations instead of spreading it over the whole iteration range.
+
+use std::{fs, path::Path};
+
+use image::{ImageBuffer, ImageError, Rgb, RgbImage};
+
+use crate::{
+    analysis::{escape_viewport, EscapeResult},
+    error::{Error, Result},
+    options::RenderOptions,
+    viewport::Viewport,
+};
+
+/// Number of positions of an Ultra Fractal gradient, over which its `index` values run.
+pub const UGR_POSITIONS: u32 = 400;
+
+/// Maps a normalized escape value onto a color.
+pub trait Palette {
+    /// Color for `t`, where `0.0` is the outside and `1.0` the inside of the set. Values outside
+    /// that range are clamped.
+    fn color(&self, t: f64) -> Rgb<u8>;
+}
+
+impl<F: Fn(f64) -> Rgb<u8>> Palette for F {
+    fn color(&self, t: f64) -> Rgb<u8> {
+        self(t)
+    }
+}
+
+/// A palette that interpolates linearly between color stops.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gradient {
+    stops: Vec<(f64, [u8; 3])>,
+}
+
+impl Gradient {
+    /// Creates a gradient from `(position, color)` stops, sorted by position. Returns `None`
+    /// without stops.
+    ///
+    /// # Examples
+    /// ```
+    /// use image::Rgb;
+    /// use mandelbrot::palette::{Gradient, Palette};
+    ///
+    /// let gradient = Gradient::new(vec![(0.0, [0, 0, 0]), (1.0, [200, 100, 0])]).unwrap();
+    /// assert_eq!(gradient.color(0.5), Rgb([100, 50, 0]));
+    /// ```
+    pub fn new(mut stops: Vec<(f64, [u8; 3])>) -> Option<Self> {
+        if stops.is_empty() {
+            return None;
+        }
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Some(Self { stops })
+    }
+
+    /// Creates a gradient with `colors` spread evenly between `0.0` and `1.0`.
+    pub fn evenly_spaced(colors: &[[u8; 3]]) -> Option<Self> {
+        let last = colors.len().saturating_sub(1).max(1) as f64;
+        Self::new(colors.iter().enumerate().map(|(i, &color)| (i as f64 / last, color)).collect())
+    }
+
+    pub fn stops(&self) -> &[(f64, [u8; 3])] {
+        &self.stops
+    }
+
+    /// Loads a gradient from the palette file at `path`, picking the format from its extension:
+    /// `.map`, `.gpl` or `.ugr`, and an image strip, see `from_image_strip`, for anything else.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::IoError` if the file cannot be read, and `Error::ConfigError`, with the
+    /// path in the message, if it holds no palette.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let extension = path.extension().and_then(|ext| ext.to_str()).map(str::to_lowercase);
+        let parse = match extension.as_deref() {
+            Some("map") => Self::parse_map,
+            Some("gpl") => Self::parse_gpl,
+            Some("ugr") => Self::parse_ugr,
+            _ => {
+                let image = image::open(path).map_err(|e| match e {
+                    ImageError::IoError(e) => Error::io(path, e),
+                    e => Error::ConfigError(format!("{}: {e}", path.display())),
+                })?;
+                return Self::from_image_strip(&image.into_rgb8()).ok_or_else(|| {
+                    Error::ConfigError(format!("{}: the image is empty", path.display()))
+                });
+            }
+        };
+        let contents = fs::read_to_string(path).map_err(|e| Error::io(path, e))?;
+        parse(&contents).map_err(|e| match e {
+            Error::ConfigError(message) => {
+                Error::ConfigError(format!("{}: {message}", path.display()))
+            }
+            e => e,
+        })
+    }
+
+    /// Parses a Fractint `.map` file: one color per line as red, green and blue values from `0`
+    /// to `255`, optionally followed by a comment. The colors are spread evenly.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::ConfigError` for lines that do not start with a color, or without colors.
+    ///
+    /// # Examples
+    /// ```
+    /// use image::Rgb;
+    /// use mandelbrot::palette::{Gradient, Palette};
+    ///
+    /// let gradient = Gradient::parse_map("0 0 0 black\n255 128 0\n").unwrap();
+    /// assert_eq!(gradient.color(1.0), Rgb([255, 128, 0]));
+    /// assert!(Gradient::parse_map("0 0\n").is_err());
+    /// ```
+    pub fn parse_map(contents: &str) -> Result<Self> {
+        let mut colors = Vec::new();
+        for (number, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            colors.push(parse_rgb(line).ok_or_else(|| {
+                Error::ConfigError(format!("line {}: expected red, green and blue", number + 1))
+            })?);
+        }
+        Self::evenly_spaced(&colors).ok_or_else(no_colors)
+    }
+
+    /// Parses a GIMP `.gpl` palette: a `GIMP Palette` header, `Name:` and `Columns:` fields and
+    /// `#` comments, then one color per line, optionally followed by its name. The colors are
+    /// spread evenly.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::ConfigError` without the header, for lines that are no color, or without
+    /// colors.
+    pub fn parse_gpl(contents: &str) -> Result<Self> {
+        let mut lines = contents.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+        if lines.next().map(|(_, line)| line.trim()) != Some("GIMP Palette") {
+            return Err(Error::ConfigError("expected the header \"GIMP Palette\"".to_string()));
+        }
+        let mut colors = Vec::new();
+        for (number, line) in lines {
+            let line = line.trim();
+            if line.starts_with('#') || line.starts_with("Name:") || line.starts_with("Columns:") {
+                continue;
+            }
+            colors.push(parse_rgb(line).ok_or_else(|| {
+                Error::ConfigError(format!("line {}: expected red, green and blue", number + 1))
+            })?);
+        }
+        Self::evenly_spaced(&colors).ok_or_else(no_colors)
+    }
+
+    /// Parses the first gradient of an Ultra Fractal `.ugr` file, whose `index=` and `color=`
+    /// pairs place colors, stored as `red + 256 * green + 65536 * blue`, at positions out of
+    /// `UGR_POSITIONS`. The gradient wraps around like in Ultra Fractal: past its last color it
+    /// blends back into its first, and before its first color it blends in from its last, so
+    /// `0.0` and `1.0` meet without a seam.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::ConfigError` if the file has no gradient or invalid values.
+    ///
+    /// # Examples
+    /// ```
+    /// use image::Rgb;
+    /// use mandelbrot::palette::{Gradient, Palette};
+    ///
+    /// let ugr = "seaside {\ngradient:\n  title=\"Seaside\" smooth=yes\n  index=0 color=16711680\n  \
+    ///            index=200 color=255\nopacity:\n  index=0 opacity=255\n}\n";
+    /// let gradient = Gradient::parse_ugr(ugr).unwrap();
+    /// assert_eq!(gradient.color(0.0), Rgb([0, 0, 255]));
+    /// assert_eq!(gradient.color(0.5), Rgb([255, 0, 0]));
+    /// assert_eq!(gradient.color(1.0), Rgb([0, 0, 255]));
+    /// ```
+    pub fn parse_ugr(contents: &str) -> Result<Self> {
+        let lines = contents.lines().skip_while(|line| line.trim() != "gradient:").skip(1);
+        let mut stops = Vec::new();
+        for line in lines {
+            let line = line.trim();
+            if line.ends_with(':') || line.starts_with('}') {
+                break;
+            }
+            let field = |name: &str| {
+                line.split_whitespace()
+                    .find_map(|pair| pair.strip_prefix(name)?.parse::<u32>().ok())
+            };
+            let (Some(index), Some(color)) = (field("index="), field("color=")) else {
+                continue;
+            };
+            let [red, green, blue, _] = color.to_le_bytes();
+            stops.push((index as f64 / UGR_POSITIONS as f64, [red, green, blue]));
+        }
+        if stops.iter().any(|&(position, _)| position >= 1.0) {
+            let message = format!("gradient indices must be below {UGR_POSITIONS}");
+            return Err(Error::ConfigError(message));
+        }
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let (Some(&first), Some(&last)) = (stops.first(), stops.last()) else {
+            return Err(no_colors());
+        };
+        // Copies of the first and last colors one period on, so positions before the first
+        // color and after the last blend between the two, wherever the first index is.
+        stops.insert(0, (last.0 - 1.0, last.1));
+        stops.push((1.0 + first.0, first.1));
+        Self::new(stops).ok_or_else(no_colors)
+    }
+
+    /// A gradient of the colors of the first row of `image`, spread evenly, or `None` if the
+    /// image is empty. Gradients shared as images are usually a single row of pixels.
+    pub fn from_image_strip(image: &RgbImage) -> Option<Self> {
+        if image.height() == 0 {
+            return None;
+        }
+        let colors: Vec<[u8; 3]> = (0..image.width()).map(|x| image.get_pixel(x, 0).0).collect();
+        Self::evenly_spaced(&colors)
+    }
+}
+
+/// The color at the start of `line`, as three whitespace separated values from `0` to `255`.
+fn parse_rgb(line: &str) -> Option<[u8; 3]> {
+    let mut values = line.split_whitespace().map(str::parse::<u8>);
+    Some([values.next()?.ok()?, values.next()?.ok()?, values.next()?.ok()?])
+}
+
+fn no_colors() -> Error {
+    Error::ConfigError("the palette has no colors".to_string())
+}
+
+impl Palette for Gradient {
+    fn color(&self, t: f64) -> Rgb<u8> {
+        let t = clamp_unit(t);
+        let next = self.stops.partition_point(|&(position, _)| position < t);
+        let (to_pos, to) = self.stops[next.min(self.stops.len() - 1)];
+        let Some(&(from_pos, from)) = next.checked_sub(1).map(|i| &self.stops[i]) else {
+            return Rgb(to);
+        };
+        if next == self.stops.len() || to_pos <= from_pos {
+            return Rgb(from);
+        }
+        mix(from, to, (t - from_pos) / (to_pos - from_pos))
+    }
+}
+
+/// Clamps `t` to `0.0..=1.0`, treating NaN as `0.0`.
+fn clamp_unit(t: f64) -> f64 {
+    if t.is_nan() {
+        0.0
+    } else {
+        t.clamp(0.0, 1.0)
+    }
+}
+
+/// Interpolates linearly from `from` (at `weight = 0.0`) to `to` (at `weight = 1.0`).
+fn mix(from: [u8; 3], to: [u8; 3], weight: f64) -> Rgb<u8> {
+    let channel = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * weight).round() as u8;
+    Rgb([channel(from[0], to[0]), channel(from[1], to[1]), channel(from[2], to[2])])
+}
+
+/// The palettes that come with the crate.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BuiltinPalette {
+    /// Black outside to white inside.
+    #[default]
+    Grayscale,
+    /// Black through red and orange to yellow and white.
+    Fire,
+    /// Deep navy through blue and teal to pale cyan.
+    Ocean,
+    /// The hues of the color wheel, from red through green and blue to violet.
+    Rainbow,
+    /// Pale, through blue to dark purple and back through red; the ends meet, so it suits
+    /// cyclic coloring.
+    Twilight,
+}
+
+impl BuiltinPalette {
+    pub const ALL: [Self; 5] =
+        [Self::Grayscale, Self::Fire, Self::Ocean, Self::Rainbow, Self::Twilight];
+
+    /// Parses the names returned by `name`, ignoring case.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::palette::BuiltinPalette;
+    ///
+    /// assert_eq!(BuiltinPalette::parse("Fire"), Some(BuiltinPalette::Fire));
+    /// assert_eq!(BuiltinPalette::parse("sepia"), None);
+    /// ```
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|palette| palette.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Grayscale => "grayscale",
+            Self::Fire => "fire",
+            Self::Ocean => "ocean",
+            Self::Rainbow => "rainbow",
+            Self::Twilight => "twilight",
+        }
+    }
+
+    /// Colors of the palette, spread evenly from `0.0` to `1.0`.
+    pub fn colors(&self) -> &'static [[u8; 3]] {
+        match self {
+            Self::Grayscale => &[[0, 0, 0], [255, 255, 255]],
+            Self::Fire => &[
+                [0, 0, 0],
+                [128, 0, 0],
+                [230, 60, 0],
+                [255, 160, 0],
+                [255, 240, 120],
+                [255, 255, 255],
+            ],
+            Self::Ocean => {
+                &[[0, 7, 40], [0, 40, 110], [0, 110, 170], [40, 180, 190], [200, 245, 240]]
+            }
+            Self::Rainbow => &[
+                [255, 0, 0],
+                [255, 165, 0],
+                [255, 255, 0],
+                [0, 200, 0],
+                [0, 160, 255],
+                [40, 0, 200],
+                [150, 0, 210],
+            ],
+            Self::Twilight => &[
+                [226, 217, 226],
+                [120, 155, 196],
+                [94, 69, 163],
+                [47, 20, 54],
+                [141, 48, 88],
+                [197, 128, 110],
+                [226, 217, 226],
+            ],
+        }
+    }
+
+    /// The palette as a `Gradient`, for instance to adjust its stops.
+    pub fn gradient(&self) -> Gradient {
+        Gradient::evenly_spaced(self.colors()).expect("built-in palettes have colors")
+    }
+}
+
+impl Palette for BuiltinPalette {
+    fn color(&self, t: f64) -> Rgb<u8> {
+        // Same as `self.gradient().color(t)`, without building the gradient for every pixel.
+        let colors = self.colors();
+        let position = clamp_unit(t) * (colors.len() - 1) as f64;
+        let index = (position.floor() as usize).min(colors.len() - 2);
+        mix(colors[index], colors[index + 1], position - index as f64)
+    }
+}
+
+/// A palette whose colors repeat every `period` iterations, shifted by `offset` iterations.
+///
+/// Deep zooms spend most of the iteration range on pixels that escape late, so a palette spread
+/// over the whole range paints their boundary in a single color; cycling through the palette
+/// every few dozen iterations brings the detail back. The colors jump from the end of `inner`
+/// back to its start at every cycle unless its ends meet, as those of `BuiltinPalette::Twilight`
+/// do. Points inside the set keep the color `inner` gives them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CyclicPalette<P> {
+    pub inner: P,
+    /// Iterations per cycle through `inner`.
+    pub period: f64,
+    /// Iterations the cycles are shifted by; changing it over time animates the colors.
+    pub offset: f64,
+    /// Iteration limit of the render, which turns the normalized values back into iterations.
+    pub iterations: u32,
+}
+
+impl<P: Palette> CyclicPalette<P> {
+    /// Cycles through `inner` every `period` iterations of a render with an iteration limit of
+    /// `iterations`.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::palette::{BuiltinPalette, CyclicPalette, Palette};
+    ///
+    /// let fire = BuiltinPalette::Fire;
+    /// let cyclic = CyclicPalette::new(fire, 20.0, 1000).with_offset(5.0);
+    /// // 15 iterations in, shifted by 5, is the end of the first cycle and the start of the next.
+    /// assert_eq!(cyclic.color(15.0 / 1000.0), fire.color(0.0));
+    /// assert_eq!(cyclic.color(25.0 / 1000.0), fire.color(0.5));
+    /// assert_eq!(cyclic.color(1.0), fire.color(1.0));
+    /// ```
+    pub fn new(inner: P, period: f64, iterations: u32) -> Self {
+        Self { inner, period, offset: 0.0, iterations }
+    }
+
+    pub fn with_offset(mut self, offset: f64) -> Self {
+        self.offset = offset;
+        self
+    }
+}
+
+impl<P: Palette> Palette for CyclicPalette<P> {
+    fn color(&self, t: f64) -> Rgb<u8> {
+        if t >= 1.0 || self.period <= 0.0 || self.period.is_nan() {
+            return self.inner.color(t);
+        }
+        let escaped_at = clamp_unit(t) * self.iterations as f64;
+        self.inner.color(((escaped_at + self.offset) / self.period).rem_euclid(1.0))
+    }
+}
+
+/// Renders `viewport` with every pixel colored by `palette`.
+///
+/// The escape value, smooth or whole depending on `RenderOptions::smooth`, is divided by the
+/// iteration limit, so points inside the set get `palette.color(1.0)`.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::{
+///     palette::{compose_with_palette, BuiltinPalette},
+///     prelude::*,
+/// };
+///
+/// let opts = RenderOptions::new(100);
+/// let image = compose_with_palette(64, 48, &Viewport::default(), &opts, &BuiltinPalette::Fire);
+/// assert_eq!(image.dimensions(), (64, 48));
+/// ```
+pub fn compose_with_palette<P: Palette + ?Sized>(
+    width: u32,
+    height: u32,
+    viewport: &Viewport,
+    opts: &RenderOptions,
+    palette: &P,
+) -> RgbImage {
+    let escapes = escape_viewport(width, height, viewport, opts);
+    colorize_escapes(width, height, &escapes, opts, palette)
+}
+
+/// Colors the escape results of a `width` x `height` grid, in row-major order, like
+/// `compose_with_palette` does.
+pub fn colorize_escapes<P: Palette + ?Sized>(
+    width: u32,
+    height: u32,
+    escapes: &[EscapeResult],
+    opts: &RenderOptions,
+    palette: &P,
+) -> RgbImage {
+    let iterations = opts.iterations.max(1) as f64;
+    let values: Vec<f64> = escapes
+        .iter()
+        .map(|result| match (result.escaped, opts.smooth) {
+            (false, _) => 1.0,
+            (true, true) => result.smooth / iterations,
+            (true, false) => result.iterations as f64 / iterations,
+        })
+        .collect();
+    colorize_values(width, height, &values, palette)
+}
+
+/// Colors a `width` x `height` grid of values between `0.0` and `1.0`, in row-major order, such
+/// as those of `histogram::Histogram::equalize`.
+pub fn colorize_values<P: Palette + ?Sized>(
+    width: u32,
+    height: u32,
+    values: &[f64],
+    palette: &P,
+) -> RgbImage {
+    ImageBuffer::from_fn(width, height, |x, y| palette.color(values[(y * width + x) as usize]))
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gradient_hits_stops_and_clamps() {
+        let gradient = BuiltinPalette::Fire.gradient();
+        for &(position, color) in gradient.stops() {
+            assert_eq!(gradient.color(position), Rgb(color));
+        }
+        assert_eq!(gradient.color(-1.0), Rgb([0, 0, 0]));
+        assert_eq!(gradient.color(2.0), Rgb([255, 255, 255]));
+        assert_eq!(gradient.color(f64::NAN), Rgb([0, 0, 0]));
+        assert_eq!(Gradient::new(Vec::new()), None);
+    }
+
+    #[test]
+    fn test_builtin_names_round_trip() {
+        for palette in BuiltinPalette::ALL {
+            assert_eq!(BuiltinPalette::parse(palette.name()), Some(palette));
+        }
+        let twilight = BuiltinPalette::Twilight;
+        assert_eq!(twilight.color(0.0), twilight.color(1.0));
+
+        for palette in BuiltinPalette::ALL {
+            let gradient = palette.gradient();
+            for i in 0..=100 {
+                let t = i as f64 / 100.0;
+                let (a, b) = (palette.color(t), gradient.color(t));
+                // The two compute the interpolation weight differently and may round apart.
+                assert!((0..3).all(|i| a[i].abs_diff(b[i]) <= 1), "{} at {t}", palette.name());
+            }
+        }
+    }
+
+    #[test]
+    fn test_palette_files_load_by_extension() {
+        let dir = std::env::temp_dir().join(format!("mandelbrot-palettes-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let gpl =
+            "GIMP Palette\nName: Dusk\nColumns: 2\n# comment\n 10  20  30\tnavy\n200 100 0 amber\n";
+        fs::write(dir.join("dusk.GPL"), gpl).unwrap();
+        fs::write(dir.join("dusk.map"), "10 20 30\n200 100 0 the last color\n").unwrap();
+        RgbImage::from_fn(2, 1, |x, _| Rgb(if x == 0 { [10, 20, 30] } else { [200, 100, 0] }))
+            .save(dir.join("dusk.png"))
+            .unwrap();
+        let expected = Gradient::evenly_spaced(&[[10, 20, 30], [200, 100, 0]]).unwrap();
+        for name in ["dusk.GPL", "dusk.map", "dusk.png"] {
+            assert_eq!(Gradient::load(dir.join(name)).unwrap(), expected, "{name}");
+        }
+
+        fs::write(dir.join("broken.map"), "10 20 30\nred green blue\n").unwrap();
+        let message = Gradient::load(dir.join("broken.map")).unwrap_err().to_string();
+        assert!(message.contains("broken.map") && message.contains("line 2"), "{message}");
+        assert!(Gradient::parse_gpl("10 20 30\n").is_err());
+        assert!(matches!(Gradient::load(dir.join("missing.map")), Err(Error::IoError { .. })));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_ugr_gradient_wraps_around_without_a_seam() {
+        // Red at 40 of 400 and blue at 240, blending back into red through 0 and 400.
+        let ugr = "late {\ngradient:\n  index=40 color=255\n  index=240 color=16711680\n}\n";
+        let gradient = Gradient::parse_ugr(ugr).unwrap();
+        assert_eq!(gradient.color(0.1), Rgb([255, 0, 0]));
+        assert_eq!(gradient.color(0.6), Rgb([0, 0, 255]));
+        assert_eq!(gradient.color(0.0), gradient.color(1.0));
+        // Halfway from blue at 240 to red at 440, which is 40 of the next period.
+        assert_eq!(gradient.color(0.85), Rgb([127, 0, 128]));
+        // Before the first color, still blending from blue into red.
+        assert_eq!(gradient.color(0.0), Rgb([204, 0, 51]));
+    }
+
+    #[test]
+    fn test_cyclic_palette_repeats_every_period() {
+        let ocean = BuiltinPalette::Ocean;
+        let cyclic = CyclicPalette::new(ocean, 32.0, 256).with_offset(-8.0);
+        for escaped_at in [8.0, 20.0, 100.5] {
+            let (t, next) = (escaped_at / 256.0, (escaped_at + 32.0) / 256.0);
+            assert_eq!(cyclic.color(t), cyclic.color(next));
+            assert_eq!(cyclic.color(t), ocean.color(((escaped_at - 8.0) / 32.0).rem_euclid(1.0)));
+        }
+        // Without a usable period the palette is used as is.
+        assert_eq!(CyclicPalette::new(ocean, 0.0, 256).color(0.3), ocean.color(0.3));
+    }
+
+    #[test]
+    fn test_closure_palette_colors_interior() {
+        let inside = |t: f64| if t >= 1.0 { Rgb([255, 0, 0]) } else { Rgb([0, 0, 0]) };
+        let image =
+            compose_with_palette(40, 30, &Viewport::default(), &RenderOptions::new(50), &inside);
+        // The center of the default view is inside the main cardioid.
+        assert_eq!(*image.get_pixel(20, 15), Rgb([255, 0, 0]));
+        assert_eq!(*image.get_pixel(0, 0), Rgb([0, 0, 0]));
+    }
+}
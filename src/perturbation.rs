@@ -0,0 +1,356 @@
+//! Perturbation rendering for deep zooms.
+//!
+//! Iterating every pixel with `bigfloat::escape_arbitrary` is exact but slow. Perturbation
+//! theory iterates a single reference point `C` in arbitrary precision and stores its orbit
+//! `Z_n` rounded to `f64`. Every pixel `c = C + dc` then only follows its difference from that
+//! orbit, `dz_(n+1) = 2 Z_n dz_n + dz_n^2 + dc`, which stays small enough for `f64` even when
+//! `c` itself needs dozens of digits.
+//!
+//! A series approximation skips the first iterations altogether: while the orbit difference is
+//! well described by `dz_n = A_n dc + B_n dc^2 + C_n dc^3` for every pixel of the frame, the
+//! iteration starts at that `n` instead of `0`.
+//!
+//! When a pixel's orbit passes much closer to `0` than the reference orbit does, the rounding
+//! error of `dz` outgrows its value and the pixel is "glitched" (Pauldelbrot's criterion
+//! `|Z_n + dz_n| < GLITCH_TOLERANCE |Z_n|`); the same happens when the reference escapes before
+//! the pixel. Glitched pixels are rendered again around a new reference picked among them, up to
+//! `MAX_REFERENCES` references per frame.
+//!
+//! Offsets are stored as `f64`, so frames deeper than about `1e300` are out of reach.
+
+use num_complex::Complex64;
+
+use crate::{
+    analysis::EscapeResult,
+    bigfloat::{parse_decimal, to_f64, DBig, PreciseCenter},
+    options::RenderOptions,
+    scheduler::render_grid,
+    viewport::Viewport,
+};
+
+/// A pixel is glitched when its orbit comes closer to `0` than this fraction of the reference
+/// orbit at the same iteration.
+pub const GLITCH_TOLERANCE: f64 = 1e-3;
+
+/// Most references computed for one frame; pixels still glitched after that keep the result of
+/// the last attempt.
+pub const MAX_REFERENCES: usize = 16;
+
+/// Largest size of the cubic term of the series approximation, relative to the linear term,
+/// for which iterations are skipped.
+pub const SERIES_TOLERANCE: f64 = 1e-8;
+
+/// Orbit of a reference point, iterated in arbitrary precision and stored rounded to `f64`.
+#[derive(Debug, Clone)]
+pub struct ReferenceOrbit {
+    center: PreciseCenter,
+    /// `Z_0 = 0, Z_1 = C, ...` up to the first point past the bailout or the iteration limit.
+    orbit: Vec<Complex64>,
+    escaped: bool,
+}
+
+impl ReferenceOrbit {
+    /// Iterates `center` with `digits` significant decimal digits.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::{
+    ///     bigfloat::PreciseCenter, options::RenderOptions, perturbation::ReferenceOrbit,
+    /// };
+    ///
+    /// let inside = PreciseCenter::parse("-0.1", "0.1").unwrap();
+    /// let orbit = ReferenceOrbit::compute(&inside, &RenderOptions::new(100), 30);
+    /// assert_eq!(orbit.len(), 101);
+    /// assert_eq!(orbit.escaped_at(), None);
+    /// ```
+    pub fn compute(center: &PreciseCenter, opts: &RenderOptions, digits: usize) -> Self {
+        let round = |value: &DBig| value.clone().with_precision(digits).value();
+        let (cx, cy) = (round(&center.x), round(&center.y));
+        let bailout_sqr = opts.bailout * opts.bailout;
+        let (mut zx, mut zy) = (round(&DBig::ZERO), round(&DBig::ZERO));
+        let mut orbit = vec![Complex64::new(0.0, 0.0)];
+        let mut escaped = false;
+        for _ in 0..opts.iterations {
+            let (zx2, zy2) = (&zx * &zx, &zy * &zy);
+            let xy = &zx * &zy;
+            zy = &xy + &xy + &cy;
+            zx = zx2 - zy2 + &cx;
+            let z = Complex64::new(to_f64(&zx), to_f64(&zy));
+            orbit.push(z);
+            if z.norm_sqr() > bailout_sqr {
+                escaped = true;
+                break;
+            }
+        }
+        Self { center: center.clone(), orbit, escaped }
+    }
+
+    pub fn center(&self) -> &PreciseCenter {
+        &self.center
+    }
+
+    /// Number of stored orbit points, including `Z_0`.
+    pub fn len(&self) -> usize {
+        self.orbit.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.orbit.is_empty()
+    }
+
+    /// Iteration at which the reference escaped, numbered like `EscapeResult::iterations`, or
+    /// `None` if it stayed bounded.
+    pub fn escaped_at(&self) -> Option<u32> {
+        self.escaped.then(|| self.orbit.len() as u32 - 2)
+    }
+}
+
+/// Coefficients of `dz_n = A_n dc + B_n dc^2 + C_n dc^3` at iteration `n`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Series {
+    n: usize,
+    a: Complex64,
+    b: Complex64,
+    c: Complex64,
+}
+
+impl Series {
+    /// Advances the coefficients along `orbit` for as long as the cubic term stays below
+    /// `SERIES_TOLERANCE` of the linear term for offsets up to `radius`, and no pixel within
+    /// `radius` can have escaped past `bailout`.
+    fn fit(orbit: &[Complex64], radius: f64, max_iter: u32, bailout: f64) -> Self {
+        let zero = Complex64::new(0.0, 0.0);
+        let mut series = Self { n: 0, a: zero, b: zero, c: zero };
+        // The last orbit point has no successor, and the pixel loop needs one iteration left.
+        let limit = (orbit.len().saturating_sub(2)).min(max_iter.saturating_sub(1) as usize);
+        while series.n < limit {
+            let z = orbit[series.n];
+            let next = Self {
+                n: series.n + 1,
+                a: 2.0 * z * series.a + 1.0,
+                b: 2.0 * z * series.b + series.a * series.a,
+                c: 2.0 * z * series.c + 2.0 * series.a * series.b,
+            };
+            let (linear, cubic) = (next.a.norm() * radius, next.c.norm() * radius.powi(3));
+            if !cubic.is_finite() || cubic > SERIES_TOLERANCE * linear {
+                break;
+            }
+            // Skipped iterations are not checked for escape, so stop while every pixel is
+            // still certainly inside the bailout.
+            let quadratic = next.b.norm() * radius * radius;
+            if orbit[next.n].norm() + linear + quadratic + cubic > bailout {
+                break;
+            }
+            series = next;
+        }
+        series
+    }
+
+    fn delta(&self, dc: Complex64) -> Complex64 {
+        dc * (self.a + dc * (self.b + dc * self.c))
+    }
+}
+
+/// How much work a perturbation render took.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PerturbationStats {
+    /// Reference orbits computed in arbitrary precision.
+    pub references: usize,
+    /// Iterations skipped per pixel by the series approximation.
+    pub skipped_iterations: u32,
+    /// Pixels still glitched after `MAX_REFERENCES` references.
+    pub glitched: usize,
+}
+
+/// Renders a `width` x `height` grid over `viewport` around `center` by perturbation, returning
+/// the escape results in row-major order.
+///
+/// The results match `bigfloat::render_arbitrary` with the same `digits`, up to pixels whose
+/// glitches could not be resolved (see `PerturbationStats::glitched`).
+///
+/// # Examples
+/// ```
+/// use mandelbrot::{
+///     bigfloat::PreciseCenter, options::RenderOptions, perturbation::render_perturbed,
+///     viewport::Viewport,
+/// };
+///
+/// let center = PreciseCenter::parse("-1.749721929742338571710089", "0").unwrap();
+/// let (x, y) = center.to_f64();
+/// let viewport = Viewport::new(x, y, 1e17);
+/// let digits = viewport.required_digits(32, 24);
+/// let (escapes, stats) =
+///     render_perturbed(32, 24, &viewport, &center, &RenderOptions::new(400), digits);
+/// assert_eq!(escapes.len(), 32 * 24);
+/// assert!(escapes.iter().any(|result| result.escaped));
+/// assert!(stats.skipped_iterations > 0);
+/// ```
+pub fn render_perturbed(
+    width: u32,
+    height: u32,
+    viewport: &Viewport,
+    center: &PreciseCenter,
+    opts: &RenderOptions,
+    digits: usize,
+) -> (Vec<EscapeResult>, PerturbationStats) {
+    let (span_x, span_y) = viewport.spans(width, height);
+    let offset = |x: u32, y: u32| {
+        Complex64::new(
+            (x as f64 / width as f64 - 0.5) * span_x,
+            (y as f64 / height as f64 - 0.5) * span_y,
+        )
+    };
+    let mut stats = PerturbationStats::default();
+    let mut results: Vec<Option<EscapeResult>> = vec![None; (width * height) as usize];
+    let mut pending: Vec<usize> = (0..results.len()).collect();
+    let mut reference = ReferenceOrbit::compute(center, opts, digits);
+    let radius = offset(0, 0).norm().max(offset(width, height).norm());
+    let series = Series::fit(&reference.orbit, radius, opts.iterations, opts.bailout);
+    stats.skipped_iterations = series.n as u32;
+    let mut series = Some(series);
+
+    while !pending.is_empty() {
+        stats.references += 1;
+        // Offset of the frame center from the reference, which is tiny next to the center.
+        let shift = Complex64::new(
+            to_f64(&(&center.x - &reference.center.x)),
+            to_f64(&(&center.y - &reference.center.y)),
+        );
+        let outcomes = {
+            let reference = &reference;
+            let series = series.as_ref();
+            let pending = &pending;
+            render_grid(pending.len() as u32, 1, |i, _| {
+                let index = pending[i as usize];
+                let (x, y) = (index as u32 % width, index as u32 / width);
+                iterate(&reference.orbit, offset(x, y) + shift, series, opts)
+            })
+        };
+
+        let mut glitched = Vec::new();
+        for (&index, outcome) in pending.iter().zip(outcomes) {
+            match outcome {
+                Ok(result) => results[index] = Some(result),
+                Err(partial) => {
+                    results[index] = Some(partial);
+                    glitched.push(index);
+                }
+            }
+        }
+        if glitched.is_empty() || stats.references == MAX_REFERENCES {
+            stats.glitched = glitched.len();
+            break;
+        }
+
+        // Re-reference at the middle glitched pixel, which tends to lie inside the glitched
+        // blob rather than on its edge.
+        let index = glitched[glitched.len() / 2];
+        let dc = offset(index as u32 % width, index as u32 / width);
+        let new_center = PreciseCenter {
+            x: &center.x + from_f64(dc.re, digits),
+            y: &center.y + from_f64(dc.im, digits),
+        };
+        reference = ReferenceOrbit::compute(&new_center, opts, digits);
+        // The series was fitted to the first reference only.
+        series = None;
+        pending = glitched;
+    }
+
+    let results = results.into_iter().map(|result| result.expect("every pixel is rendered"));
+    (results.collect(), stats)
+}
+
+/// Follows the orbit of `reference + dc`, returning `Err` with a best-effort result if the
+/// pixel glitched.
+fn iterate(
+    orbit: &[Complex64],
+    dc: Complex64,
+    series: Option<&Series>,
+    opts: &RenderOptions,
+) -> Result<EscapeResult, EscapeResult> {
+    let bailout_sqr = opts.bailout * opts.bailout;
+    let (start, mut dz) = match series {
+        Some(series) => (series.n, series.delta(dc)),
+        None => (0, Complex64::new(0.0, 0.0)),
+    };
+    let mut z = orbit.get(start).copied().unwrap_or_default() + dz;
+    for n in start..opts.iterations as usize {
+        if n + 1 >= orbit.len() {
+            // The reference escaped before this pixel did.
+            return Err(unfinished(opts, z));
+        }
+        dz = 2.0 * orbit[n] * dz + dz * dz + dc;
+        let reference = orbit[n + 1];
+        z = reference + dz;
+        let mag_sqr = z.norm_sqr();
+        if mag_sqr > bailout_sqr {
+            let log_z = mag_sqr.ln() / 2.0;
+            let smooth = (n as f64 + 1.0 - log_z.log2()).max(0.0);
+            return Ok(EscapeResult { iterations: n as u32, escaped: true, smooth, z });
+        }
+        if mag_sqr < GLITCH_TOLERANCE * GLITCH_TOLERANCE * reference.norm_sqr() {
+            return Err(unfinished(opts, z));
+        }
+    }
+    Ok(unfinished(opts, z))
+}
+
+fn unfinished(opts: &RenderOptions, z: Complex64) -> EscapeResult {
+    EscapeResult { iterations: opts.iterations, escaped: false, smooth: opts.iterations as f64, z }
+}
+
+/// `value` as a decimal with `digits` significant digits, so sums with it are not rounded to
+/// the precision of its shortest representation.
+fn from_f64(value: f64, digits: usize) -> DBig {
+    parse_decimal(&value.to_string()).unwrap_or_default().with_precision(digits).value()
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{analysis::escape_viewport, bigfloat::render_arbitrary, viewport::Precision};
+
+    fn agreement(a: &[EscapeResult], b: &[EscapeResult]) -> f64 {
+        let same = a.iter().zip(b).filter(|(a, b)| a.iterations == b.iterations).count();
+        same as f64 / a.len() as f64
+    }
+
+    #[test]
+    fn test_series_skips_iterations_only_when_deep() {
+        let center = PreciseCenter::parse("-0.75", "0.1").unwrap();
+        let reference = ReferenceOrbit::compute(&center, &RenderOptions::new(1000), 30);
+        let shallow = Series::fit(&reference.orbit, 1.0, 1000, 2.0);
+        let deep = Series::fit(&reference.orbit, 1e-12, 1000, 2.0);
+        assert!(shallow.n <= 2, "{}", shallow.n);
+        assert!(deep.n > shallow.n);
+    }
+
+    #[test]
+    fn test_matches_f64_render_of_the_whole_set() {
+        // At a zoom of 1 the whole set is on screen, so most pixels glitch against a single
+        // reference and have to be re-referenced.
+        let viewport = Viewport::default().with_precision(Precision::Double);
+        let opts = RenderOptions::new(200);
+        let center = PreciseCenter::from_f64(viewport.center_x, viewport.center_y);
+        let (perturbed, stats) = render_perturbed(64, 48, &viewport, &center, &opts, 20);
+        let expected = escape_viewport(64, 48, &viewport, &opts);
+        assert!(agreement(&perturbed, &expected) > 0.97, "{stats:?}");
+    }
+
+    #[test]
+    fn test_matches_arbitrary_render_when_deep() {
+        // A minibrot on the real axis, surrounded by points escaping after about 215
+        // iterations.
+        let center = PreciseCenter::parse("-1.749721929742338571710089", "0").unwrap();
+        let (x, y) = center.to_f64();
+        let viewport = Viewport::new(x, y, 1e17);
+        let opts = RenderOptions::new(400);
+        let digits = viewport.required_digits(16, 12);
+        let (perturbed, stats) = render_perturbed(16, 12, &viewport, &center, &opts, digits);
+        let expected = render_arbitrary(16, 12, &viewport, &center, &opts, digits);
+        assert!(agreement(&perturbed, &expected) > 0.95, "{stats:?}");
+        assert!(expected.iter().any(|result| result.escaped));
+    }
+}
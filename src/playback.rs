@@ -0,0 +1,148 @@
+//! Plays a zoom into the Mandelbrot set as an ASCII animation directly in the terminal.
+//!
+//! Every frame shows a viewport interpolated between the default view and a target viewport.
+//! During playback frames are rendered with `xaos::XaosRenderer`, which reuses the rows and
+//! columns of the previous frame that still fit, so only the newly exposed detail is iterated.
+//! Instead of clearing the screen between frames, the cursor is moved back to the top-left corner
+//! (`ESC [ H`) and the next frame is drawn over the previous one, which avoids flicker. Frames are
+//! paced against a fixed frame duration so the playback speed does not depend on how long each
+//! frame takes to render.
+
+use std::{
+    io::{self, Write},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{mandelbrot_ascii, viewport::Viewport, xaos::XaosRenderer};
+
+/// Moves the cursor to the top-left corner of the terminal.
+pub const CURSOR_HOME: &str = "\x1b[H";
+/// Clears the whole terminal screen.
+pub const CLEAR_SCREEN: &str = "\x1b[2J";
+/// Hides the terminal cursor.
+pub const HIDE_CURSOR: &str = "\x1b[?25l";
+/// Shows the terminal cursor.
+pub const SHOW_CURSOR: &str = "\x1b[?25h";
+
+/// A zoom animation from the default view towards `target`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ZoomPlayback {
+    pub start: Viewport,
+    pub target: Viewport,
+    pub frames: u32,
+    pub fps: u32,
+    pub width: u32,
+    pub height: u32,
+    pub iterations: u32,
+}
+
+impl Default for ZoomPlayback {
+    /// Dives into Seahorse Valley at the size of the default ASCII output.
+    fn default() -> Self {
+        Self {
+            start: Viewport::default(),
+            target: Viewport::new(-0.743643887037151, 0.131825904205330, 1e4),
+            frames: 120,
+            fps: 24,
+            width: mandelbrot_ascii::WIDTH,
+            height: mandelbrot_ascii::HEIGHT,
+            iterations: 500,
+        }
+    }
+}
+
+impl ZoomPlayback {
+    /// Viewport shown in frame `index`, where frame `0` is `start` and the last frame is
+    /// `target`.
+    pub fn viewport(&self, index: u32) -> Viewport {
+        let t = match self.frames {
+            0 | 1 => 1.0,
+            frames => index as f64 / (frames - 1) as f64,
+        };
+        self.start.interpolate(&self.target, t)
+    }
+
+    /// Renders frame `index` from scratch as newline separated rows of ASCII.
+    pub fn frame(&self, index: u32) -> String {
+        mandelbrot_ascii::render_frame(
+            &self.viewport(index),
+            self.width,
+            self.height,
+            self.iterations,
+        )
+    }
+
+    /// Time each frame is shown for.
+    pub fn frame_duration(&self) -> Duration {
+        Duration::from_secs_f64(1.0 / self.fps.max(1) as f64)
+    }
+
+    /// Plays the animation to `out`, redrawing every frame from the top-left corner.
+    ///
+    /// The cursor is hidden during playback and shown again afterwards, also when writing a
+    /// frame fails.
+    pub fn play<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        write!(out, "{HIDE_CURSOR}{CLEAR_SCREEN}")?;
+        let result = self.play_frames(out);
+        write!(out, "{SHOW_CURSOR}")?;
+        out.flush()?;
+        result
+    }
+
+    fn play_frames<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let frame_duration = self.frame_duration();
+        let mut renderer = XaosRenderer::new(self.width, self.height, self.iterations);
+        let mut deadline = Instant::now();
+        for index in 0..self.frames {
+            renderer.render(&self.viewport(index));
+            let frame = mandelbrot_ascii::canvas_from_counts(
+                self.width,
+                self.height,
+                renderer.counts(),
+                self.iterations,
+            );
+            write!(out, "{CURSOR_HOME}{frame}")?;
+            out.flush()?;
+
+            deadline += frame_duration;
+            let now = Instant::now();
+            if deadline > now {
+                thread::sleep(deadline - now);
+            } else {
+                // Rendering fell behind; restart pacing instead of rushing through frames.
+                deadline = now;
+            }
+        }
+        Ok(())
+    }
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_and_last_viewport() {
+        let playback = ZoomPlayback::default();
+        assert_eq!(playback.viewport(0), playback.start);
+        assert_eq!(playback.viewport(playback.frames - 1), playback.target);
+    }
+
+    #[test]
+    fn test_play_writes_every_frame() {
+        let playback =
+            ZoomPlayback { frames: 3, fps: 1000, width: 8, height: 4, ..Default::default() };
+        let mut out = Vec::new();
+        playback.play(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        assert!(out.starts_with(HIDE_CURSOR));
+        assert!(out.ends_with(SHOW_CURSOR));
+        assert_eq!(out.matches(CURSOR_HOME).count(), 3);
+        // The first frame has nothing to reuse and matches an exact render.
+        assert!(out.contains(&playback.frame(0)));
+    }
+}
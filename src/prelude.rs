@@ -0,0 +1,29 @@
+//! Re-exports the types and functions most programs need, so they can be imported at once:
+//!
+//! ```
+//! use mandelbrot::prelude::*;
+//!
+//! let c = Viewport::default().to_complex_num(400, 400, 800, 800);
+//! assert_eq!(mandelbrot(c, 100), 100);
+//! ```
+
+pub use num_complex::Complex64;
+
+#[cfg(feature = "image")]
+pub use crate::mandelbrot_img::{
+    compose, compose_dynamic, compose_with_options, compose_with_progress, compose_with_viewport,
+    encode_to_vec,
+};
+#[cfg(feature = "image")]
+pub use crate::palette::{BuiltinPalette, Palette};
+pub use crate::{
+    analysis::{classify, evaluate_points, EscapeResult, Membership},
+    buffer::IterationBuffer,
+    complex::IntoComplex,
+    fractal::FractalKind,
+    mandelbrot_ascii::AsciiCanvas,
+    mandelbrot_img::{compose_raw, mandelbrot, mandelbrot_smooth, multibrot},
+    options::RenderOptions,
+    renderer::Renderer,
+    viewport::{CoordMapper, Viewport},
+};
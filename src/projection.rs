@@ -0,0 +1,426 @@
+//! Pluggable mappings from pixel coordinates to points of the complex plane.
+//!
+//! `Viewport::to_complex_num` maps the output grid linearly onto a rectangle of the plane. A
+//! `Projection` sits in front of the escape-time kernel and replaces that mapping, so the same
+//! kernels can render other views of the plane:
+//!
+//! * `Planar` is the plain linear mapping of the viewport;
+//! * `RiemannSphere` draws the extended complex plane on a globe by inverse stereographic
+//!   projection, so the whole set and the point at infinity fit in one picture;
+//! * `Inverted` renders the image of the set under `c -> 1/c`, which turns the unbounded exterior
+//!   into a bounded figure and shows the structure near infinity at the center of the picture;
+//! * `LogPolar` unrolls the plane around the viewport center, with the angle along the x axis and
+//!   the logarithm of the distance to the center along the y axis. Scrolling the picture down by
+//!   one row equals zooming in by `exp(2 PI / width)`, so a zoom video can be made by rendering one
+//!   tall log-polar strip and scrolling through it, as XaoS does.
+//!
+//! Projections may leave pixels unmapped (the corners around the globe), which renderers draw as
+//! background.
+
+use std::f64::consts::PI;
+
+use num_complex::Complex64;
+
+use crate::{mandelbrot_img, scheduler::render_grid, viewport::Viewport};
+
+/// How pixels of the output grid are mapped onto the complex plane.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Projection {
+    /// The linear mapping of `Viewport::to_complex_num`.
+    #[default]
+    Planar,
+    /// The plane seen on the Riemann sphere, with the viewport center at the front of the globe.
+    ///
+    /// The equator of the sphere maps to the circle of radius `Viewport::height` around the
+    /// center, and `tilt` rotates the globe about the horizontal axis, in radians. A tilt of
+    /// `PI` turns the point at infinity to the front.
+    RiemannSphere { tilt: f64 },
+    /// The image of the plane under `c -> 1/c`. Everything beyond the radius-2 disk maps into
+    /// the disk of radius `1/2`, so the exterior of the set becomes a bounded figure reaching
+    /// from `-1/2` to `4`, the image of the cusp. `default_viewport` frames that figure.
+    Inverted,
+    /// The plane around the viewport center in log-polar coordinates. The top row is the circle
+    /// of radius `Viewport::height / 2`, each column is one direction, and the radius shrinks by
+    /// `exp(-2 PI / width)` per row, which keeps the mapping conformal.
+    LogPolar,
+    /// The points of the viewport sent through a Möbius transformation.
+    Mobius(Mobius),
+    /// One face of a cubemap around the Riemann sphere, scaled like `RiemannSphere`. The
+    /// viewport center lies in the middle of `CubeFace::NegativeZ` and the point at infinity in
+    /// the middle of `CubeFace::PositiveZ`.
+    CubeFace(CubeFace),
+}
+
+/// Faces of a cubemap, in the order and orientation of OpenGL cube map textures, which Bevy,
+/// Godot and Unity's six-sided skyboxes also use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CubeFace {
+    PositiveX,
+    NegativeX,
+    PositiveY,
+    NegativeY,
+    PositiveZ,
+    NegativeZ,
+}
+
+impl CubeFace {
+    pub const ALL: [CubeFace; 6] = [
+        CubeFace::PositiveX,
+        CubeFace::NegativeX,
+        CubeFace::PositiveY,
+        CubeFace::NegativeY,
+        CubeFace::PositiveZ,
+        CubeFace::NegativeZ,
+    ];
+
+    /// Short name used for file names, such as `px` for `PositiveX`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            CubeFace::PositiveX => "px",
+            CubeFace::NegativeX => "nx",
+            CubeFace::PositiveY => "py",
+            CubeFace::NegativeY => "ny",
+            CubeFace::PositiveZ => "pz",
+            CubeFace::NegativeZ => "nz",
+        }
+    }
+
+    /// Direction from the center of the cube through pixel `(x, y)` of a face that is `size`
+    /// pixels wide, with `y` pointing up.
+    pub fn direction(&self, x: u32, y: u32, size: u32) -> (f64, f64, f64) {
+        let s = 2.0 * (x as f64 + 0.5) / size as f64 - 1.0;
+        let t = 2.0 * (y as f64 + 0.5) / size as f64 - 1.0;
+        match self {
+            CubeFace::PositiveX => (1.0, -t, -s),
+            CubeFace::NegativeX => (-1.0, -t, s),
+            CubeFace::PositiveY => (s, 1.0, t),
+            CubeFace::NegativeY => (s, -1.0, -t),
+            CubeFace::PositiveZ => (s, -t, 1.0),
+            CubeFace::NegativeZ => (-s, -t, -1.0),
+        }
+    }
+}
+
+/// The Möbius transformation `w -> (a w + b) / (c w + d)`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mobius {
+    pub a: Complex64,
+    pub b: Complex64,
+    pub c: Complex64,
+    pub d: Complex64,
+}
+
+impl Mobius {
+    /// Creates the transformation with the given coefficients, or returns `None` if
+    /// `a d - b c` is zero, which would collapse the plane onto a single point.
+    pub fn new(a: Complex64, b: Complex64, c: Complex64, d: Complex64) -> Option<Self> {
+        if (a * d - b * c).norm_sqr() == 0.0 {
+            return None;
+        }
+        Some(Self { a, b, c, d })
+    }
+
+    /// Parses the four coefficients `a,b,c,d` separated by commas, each written like `1`, `-2i`
+    /// or `0.5+1.5i`.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::projection::Mobius;
+    /// use num_complex::Complex64;
+    ///
+    /// let mobius = Mobius::parse("0,1,1,0").unwrap();
+    /// assert_eq!(mobius.apply(Complex64::new(0.0, 2.0)), Some(Complex64::new(0.0, -0.5)));
+    /// assert!(Mobius::parse("1,2,2,4").is_none());
+    /// ```
+    pub fn parse(coefficients: &str) -> Option<Self> {
+        let coefficients = coefficients
+            .split(',')
+            .map(|coefficient| coefficient.trim().parse::<Complex64>().ok())
+            .collect::<Option<Vec<_>>>()?;
+        match coefficients[..] {
+            [a, b, c, d] => Self::new(a, b, c, d),
+            _ => None,
+        }
+    }
+
+    /// Applies the transformation to `w`, or returns `None` if `w` maps to infinity.
+    pub fn apply(&self, w: Complex64) -> Option<Complex64> {
+        let denominator = self.c * w + self.d;
+        if denominator.norm_sqr() == 0.0 {
+            return None;
+        }
+        Some((self.a * w + self.b) / denominator)
+    }
+
+    /// The transformation that undoes this one.
+    pub fn inverse(&self) -> Self {
+        Self { a: self.d, b: -self.b, c: -self.c, d: self.a }
+    }
+
+    /// The transformation that applies `self` first and `next` second.
+    pub fn then(&self, next: &Mobius) -> Self {
+        Self {
+            a: next.a * self.a + next.b * self.c,
+            b: next.a * self.b + next.b * self.d,
+            c: next.c * self.a + next.d * self.c,
+            d: next.c * self.b + next.d * self.d,
+        }
+    }
+}
+
+impl Projection {
+    /// Parses the names used on the command line: `planar`, `sphere`, `inverted` and
+    /// `log-polar`.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::projection::Projection;
+    ///
+    /// assert_eq!(Projection::parse("sphere"), Some(Projection::RiemannSphere { tilt: 0.0 }));
+    /// assert_eq!(Projection::parse("fisheye"), None);
+    /// ```
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "planar" => Some(Self::Planar),
+            "sphere" => Some(Self::RiemannSphere { tilt: 0.0 }),
+            "inverted" => Some(Self::Inverted),
+            "log-polar" => Some(Self::LogPolar),
+            _ => None,
+        }
+    }
+
+    /// Viewport that shows the whole set through this projection.
+    pub fn default_viewport(&self) -> Viewport {
+        match self {
+            Projection::Planar
+            | Projection::RiemannSphere { .. }
+            | Projection::LogPolar
+            | Projection::Mobius(_)
+            | Projection::CubeFace(_) => Viewport::default(),
+            Projection::Inverted => Viewport::new(1.5, 0.0, 0.5),
+        }
+    }
+
+    /// Maps pixel `(x, y)` of a `width` x `height` grid to the complex plane, or returns `None`
+    /// if the pixel shows background.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::{projection::Projection, viewport::Viewport};
+    ///
+    /// let viewport = Viewport::classic();
+    /// let sphere = Projection::RiemannSphere { tilt: 0.0 };
+    /// assert_eq!(sphere.map(&viewport, 0, 0, 800, 800), None);
+    /// assert_eq!(sphere.map(&viewport, 400, 400, 800, 800), Some((-0.75, 0.0)));
+    /// ```
+    pub fn map(
+        &self,
+        viewport: &Viewport,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Option<(f64, f64)> {
+        match *self {
+            Projection::Planar => Some(viewport.to_complex_num(x, y, width, height)),
+            Projection::RiemannSphere { tilt } => {
+                let radius = width.min(height) as f64 / 2.0;
+                let u = (x as f64 - width as f64 / 2.0) / radius;
+                let v = (y as f64 - height as f64 / 2.0) / radius;
+                let depth = 1.0 - u * u - v * v;
+                if depth < 0.0 {
+                    return None;
+                }
+                let (sin, cos) = tilt.sin_cos();
+                let z = -depth.sqrt();
+                let w = sphere_to_plane(u, v * cos - z * sin, v * sin + z * cos)?;
+                let c =
+                    Complex64::new(viewport.center_x, viewport.center_y) + w * viewport.height();
+                Some((c.re, c.im))
+            }
+            Projection::Inverted => {
+                let (re, im) = viewport.to_complex_num(x, y, width, height);
+                let w = Complex64::new(re, im);
+                if w.norm_sqr() == 0.0 {
+                    return None;
+                }
+                let c = w.inv();
+                Some((c.re, c.im))
+            }
+            Projection::LogPolar => {
+                let step = 2.0 * PI / width as f64;
+                let radius = viewport.height() / 2.0 * (-step * y as f64).exp();
+                let c = Complex64::new(viewport.center_x, viewport.center_y)
+                    + Complex64::from_polar(radius, step * x as f64);
+                Some((c.re, c.im))
+            }
+            Projection::Mobius(mobius) => {
+                let (re, im) = viewport.to_complex_num(x, y, width, height);
+                let c = mobius.apply(Complex64::new(re, im))?;
+                Some((c.re, c.im))
+            }
+            Projection::CubeFace(face) => {
+                let (dx, dy, dz) = face.direction(x, y, width.min(height));
+                let len = (dx * dx + dy * dy + dz * dz).sqrt();
+                // The rows of the plane grow downwards, so flip the up axis.
+                let w = sphere_to_plane(dx / len, -dy / len, dz / len)?;
+                let c =
+                    Complex64::new(viewport.center_x, viewport.center_y) + w * viewport.height();
+                Some((c.re, c.im))
+            }
+        }
+    }
+}
+
+/// Maps the point `(x, y, z)` of the unit sphere to the plane by stereographic projection from
+/// the north pole `(0, 0, 1)`, which is the point at infinity. The south pole maps to `0` and the
+/// equator to the unit circle.
+fn sphere_to_plane(x: f64, y: f64, z: f64) -> Option<Complex64> {
+    let denominator = 1.0 - z;
+    if denominator <= f64::EPSILON {
+        return None;
+    }
+    Some(Complex64::new(x, y) / denominator)
+}
+
+/// Computes escape counts for a `width` x `height` grid seen through `projection`, with `None`
+/// for background pixels.
+pub fn render_projected(
+    width: u32,
+    height: u32,
+    iterations: u32,
+    viewport: &Viewport,
+    projection: &Projection,
+) -> Vec<Option<u32>> {
+    render_grid(width, height, |x, y| {
+        let c = projection.map(viewport, x, y, width, height)?;
+        Some(mandelbrot_img::mandelbrot(c, iterations))
+    })
+}
+
+/// Like `mandelbrot_img::compose`, but seen through `projection`. Background pixels are black.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::{
+///     projection::{compose_projected, Projection},
+///     viewport::Viewport,
+/// };
+///
+/// let sphere = Projection::RiemannSphere { tilt: 0.0 };
+/// let image = compose_projected(64, 64, 100, &Viewport::default(), &sphere);
+/// assert_eq!(image.get_pixel(0, 0).0, [0, 0, 0]);
+/// ```
+#[cfg(feature = "image")]
+pub fn compose_projected(
+    width: u32,
+    height: u32,
+    iterations: u32,
+    viewport: &Viewport,
+    projection: &Projection,
+) -> image::RgbImage {
+    let counts = render_projected(width, height, iterations, viewport, projection);
+    image::ImageBuffer::from_fn(width, height, |x, y| {
+        let i = counts[(y * width + x) as usize].unwrap_or(0);
+        image::Rgb([i as u8, i as u8, i as u8])
+    })
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::viewport::FitMode;
+
+    #[test]
+    fn test_planar_matches_viewport() {
+        let viewport = Viewport::new(-0.5, 0.25, 3.0);
+        for (x, y) in [(0, 0), (17, 99), (199, 149)] {
+            let mapped = Projection::Planar.map(&viewport, x, y, 200, 150);
+            assert_eq!(mapped, Some(viewport.to_complex_num(x, y, 200, 150)));
+        }
+    }
+
+    #[test]
+    fn test_sphere_equator_and_poles() {
+        let viewport = Viewport::new(0.0, 0.0, 1.0);
+        let globe = Projection::RiemannSphere { tilt: 0.0 };
+        // The front of the untilted globe is the center and its rim the equator.
+        assert_eq!(globe.map(&viewport, 50, 50, 100, 100), Some((0.0, 0.0)));
+        let (x, y) = globe.map(&viewport, 50, 0, 100, 100).unwrap();
+        assert!(x.abs() < 1e-12 && (y + viewport.height()).abs() < 1e-12);
+
+        // Tilting by PI brings the point at infinity to the front and the origin to the back.
+        let tilted = Projection::RiemannSphere { tilt: PI };
+        assert_eq!(tilted.map(&viewport, 50, 50, 100, 100), None);
+        let (x, y) = tilted.map(&viewport, 50, 55, 100, 100).unwrap();
+        assert!(x.hypot(y) > 10.0);
+    }
+
+    #[test]
+    fn test_cube_faces_are_seamless() {
+        let viewport = Viewport::default();
+        let size = 64;
+        // The front face shows the viewport center.
+        let front = Projection::CubeFace(CubeFace::NegativeZ);
+        let (x, y) = front.map(&viewport, size / 2, size / 2, size, size).unwrap();
+        assert!((x + 0.75).abs() < 0.1 && y.abs() < 0.1);
+
+        // Neighbouring pixels across the edge between the front and the right face are close.
+        let right = Projection::CubeFace(CubeFace::PositiveX);
+        let (ax, ay) = front.map(&viewport, 0, 10, size, size).unwrap();
+        let (bx, by) = right.map(&viewport, size - 1, 10, size, size).unwrap();
+        assert!((ax - bx).hypot(ay - by) < 0.2);
+    }
+
+    #[test]
+    fn test_inverted_maps_through_reciprocal() {
+        // Pixel (400, 200) is a quarter of the viewport height above the center: `-0.25i`.
+        let viewport = Viewport::new(0.0, 0.0, 2.0).with_fit(FitMode::Stretch);
+        let (re, im) = Projection::Inverted.map(&viewport, 400, 200, 800, 800).unwrap();
+        assert!(re.abs() < 1e-12 && (im - 4.0).abs() < 1e-12);
+
+        // The origin is the image of the point at infinity.
+        let centered = Viewport::new(0.0, 0.0, 1.0);
+        assert_eq!(Projection::Inverted.map(&centered, 400, 400, 800, 800), None);
+    }
+
+    #[test]
+    fn test_log_polar_scroll_equals_zoom() {
+        let viewport = Viewport::new(-0.75, 0.1, 2.0);
+        let (width, height) = (360, 200);
+        let rows = 37;
+        let zoomed = viewport.zoomed((2.0 * PI * rows as f64 / width as f64).exp());
+        for (x, y) in [(0, 0), (90, 10), (359, 150)] {
+            let (ax, ay) = Projection::LogPolar.map(&viewport, x, y + rows, width, height).unwrap();
+            let (bx, by) = Projection::LogPolar.map(&zoomed, x, y, width, height).unwrap();
+            assert!((ax - bx).abs() < 1e-12 && (ay - by).abs() < 1e-12);
+        }
+
+        // The top row is the circle of half the viewport height around the center.
+        let (x, y) = Projection::LogPolar.map(&viewport, 90, 0, width, height).unwrap();
+        assert!((x + 0.75).abs() < 1e-12 && (y - 0.1 - viewport.height() / 2.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_mobius_inverse_and_composition() {
+        let mobius = Mobius::parse("1+i, 2, -0.5i, 3").unwrap();
+        let w = Complex64::new(0.3, -1.2);
+        let back = mobius.inverse().apply(mobius.apply(w).unwrap()).unwrap();
+        assert!((back - w).norm() < 1e-12);
+
+        let shift = Mobius::parse("1,0.25,0,1").unwrap();
+        let combined = mobius.then(&shift).apply(w).unwrap();
+        assert!((combined - (mobius.apply(w).unwrap() + 0.25)).norm() < 1e-12);
+
+        // `1 / w` through `Mobius` matches `Inverted`.
+        let inversion = Projection::Mobius(Mobius::parse("0,1,1,0").unwrap());
+        let viewport = Viewport::new(1.5, 0.0, 0.5);
+        for (x, y) in [(0, 0), (123, 456), (700, 20)] {
+            let (ax, ay) = inversion.map(&viewport, x, y, 800, 800).unwrap();
+            let (bx, by) = Projection::Inverted.map(&viewport, x, y, 800, 800).unwrap();
+            assert!((ax - bx).abs() < 1e-12 && (ay - by).abs() < 1e-12);
+        }
+    }
+}
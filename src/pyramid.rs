@@ -0,0 +1,207 @@
+//! Map tiles of the set in the `z/x/y` numbering of web maps, rendered on request by
+//! `server` or ahead of time into a directory by `render_pyramid`.
+//!
+//! Zoom level `z` splits the square of side `WORLD_SIZE` around `WORLD_CENTER` into `2^z` by
+//! `2^z` tiles of `TILE_SIZE` pixels, numbered from the top left, so Leaflet and similar
+//! libraries browse the set like a map. A pyramid holds every tile of the levels `0` to a
+//! maximum, saved as `{z}/{x}/{y}.png`, the layout static map viewers load tiles from, and
+//! every tile carries its render parameters like the images of `metadata::save_png`.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use image::RgbImage;
+
+use crate::{
+    mandelbrot_img::compose_with_options,
+    metadata::{save_png, ImageMetadata},
+    options::RenderOptions,
+    palette::{compose_with_palette, BuiltinPalette},
+    scene::Scene,
+    scheduler::map_slice,
+    viewport::{Viewport, BASE_WIDTH},
+};
+
+/// Side of the map tiles, in pixels.
+pub const TILE_SIZE: u32 = 256;
+
+/// Side of the square of the plane covered by the single tile at zoom level `0`.
+pub const WORLD_SIZE: f64 = 4.0;
+
+/// Center of the square covered by the tile at zoom level `0`, the middle of the set.
+pub const WORLD_CENTER: (f64, f64) = (-0.75, 0.0);
+
+/// Deepest zoom level served; beyond it the tiles are smaller than `f64` resolves.
+pub const MAX_TILE_ZOOM: u32 = 45;
+
+/// The viewport of tile `(x, y)` at zoom level `z`, or `None` if there is no such tile.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::pyramid::{tile_viewport, TILE_SIZE};
+///
+/// let world = tile_viewport(0, 0, 0).unwrap();
+/// assert_eq!(world.to_complex_num(0, 0, TILE_SIZE, TILE_SIZE), (-2.75, -2.0));
+/// // The bottom right tile of level 1 starts at the center of the world.
+/// let quarter = tile_viewport(1, 1, 1).unwrap();
+/// assert_eq!(quarter.to_complex_num(0, 0, TILE_SIZE, TILE_SIZE), (-0.75, 0.0));
+/// assert_eq!(tile_viewport(1, 2, 0), None);
+/// ```
+pub fn tile_viewport(z: u32, x: u32, y: u32) -> Option<Viewport> {
+    let tiles = 1u64 << z.min(MAX_TILE_ZOOM);
+    if z > MAX_TILE_ZOOM || x as u64 >= tiles || y as u64 >= tiles {
+        return None;
+    }
+    let side = WORLD_SIZE / tiles as f64;
+    let (left, top) = (WORLD_CENTER.0 - WORLD_SIZE / 2.0, WORLD_CENTER.1 - WORLD_SIZE / 2.0);
+    let center_x = left + (x as f64 + 0.5) * side;
+    let center_y = top + (y as f64 + 0.5) * side;
+    // A square grid shows `BASE_WIDTH / zoom` of the plane along both axes.
+    Some(Viewport::new(center_x, center_y, BASE_WIDTH / side))
+}
+
+/// Path of tile `(x, y)` at zoom level `z` in the pyramid at `dir`.
+///
+/// # Examples
+/// ```
+/// use std::path::PathBuf;
+///
+/// use mandelbrot::pyramid::tile_path;
+///
+/// assert_eq!(tile_path("map", 3, 5, 2), PathBuf::from("map/3/5/2.png"));
+/// ```
+pub fn tile_path(dir: impl AsRef<Path>, z: u32, x: u32, y: u32) -> PathBuf {
+    dir.as_ref().join(z.to_string()).join(x.to_string()).join(format!("{y}.png"))
+}
+
+/// Number of tiles in the levels `0` to `max_zoom`.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::pyramid::tile_count;
+///
+/// assert_eq!(tile_count(2), 1 + 4 + 16);
+/// ```
+pub fn tile_count(max_zoom: u32) -> u64 {
+    (0..=max_zoom.min(MAX_TILE_ZOOM)).map(|z| 1u64 << (2 * z)).sum()
+}
+
+/// How to render the tiles of a pyramid.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PyramidOptions {
+    /// Iteration limit of every tile, or `None` for the `Viewport::suggested_iterations` of
+    /// each level.
+    pub iterations: Option<u32>,
+    /// Palette of the tiles, or `None` for gray.
+    pub palette: Option<BuiltinPalette>,
+}
+
+/// Renders tile `(x, y)` at zoom level `z` with `opts`, and returns it with its render
+/// parameters, or `None` if there is no such tile.
+pub fn render_tile(z: u32, x: u32, y: u32, opts: &PyramidOptions) -> Option<(RgbImage, Scene)> {
+    let viewport = tile_viewport(z, x, y)?;
+    let iterations = opts.iterations.unwrap_or_else(|| viewport.suggested_iterations());
+    let render_opts = RenderOptions::new(iterations);
+    let image = match &opts.palette {
+        Some(palette) => {
+            compose_with_palette(TILE_SIZE, TILE_SIZE, &viewport, &render_opts, palette)
+        }
+        None => compose_with_options(TILE_SIZE, TILE_SIZE, &viewport, &render_opts),
+    };
+    let scene = Scene {
+        width: TILE_SIZE,
+        height: TILE_SIZE,
+        iterations,
+        palette: opts.palette.map(|palette| palette.name().to_string()),
+        viewport,
+        ..Scene::default()
+    };
+    Some((image, scene))
+}
+
+/// What `render_pyramid` did.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PyramidSummary {
+    pub rendered: u64,
+    /// Tiles whose file already existed.
+    pub skipped: u64,
+}
+
+/// Renders every tile of the levels `0` to `max_zoom` into the pyramid at `dir`, several at a
+/// time with the `parallel` feature, and calls `on_progress(done, total)` after every tile.
+/// Tiles whose file already exists are skipped, so an interrupted run resumes where it
+/// stopped; tiles are written to a temporary file first, so none is left half written.
+///
+/// # Errors
+///
+/// Returns an error if a directory or tile cannot be written. The tiles finished by then are
+/// kept.
+pub fn render_pyramid<P>(
+    dir: impl AsRef<Path>,
+    max_zoom: u32,
+    opts: &PyramidOptions,
+    on_progress: P,
+) -> io::Result<PyramidSummary>
+where
+    P: Fn(u64, u64) + Sync,
+{
+    let dir = dir.as_ref();
+    let max_zoom = max_zoom.min(MAX_TILE_ZOOM);
+    let tiles: Vec<(u32, u32, u32)> = (0..=max_zoom)
+        .flat_map(|z| (0..1 << z).flat_map(move |x| (0..1 << z).map(move |y| (z, x, y))))
+        .collect();
+    let total = tiles.len() as u64;
+    let done = AtomicU64::new(0);
+    let results = map_slice(&tiles, |&(z, x, y)| {
+        let path = tile_path(dir, z, x, y);
+        let rendered =
+            if path.exists() { Ok(false) } else { save_tile(&path, z, x, y, opts).map(|_| true) };
+        on_progress(done.fetch_add(1, Ordering::Relaxed) + 1, total);
+        rendered
+    });
+    let mut summary = PyramidSummary::default();
+    for rendered in results {
+        if rendered? {
+            summary.rendered += 1;
+        } else {
+            summary.skipped += 1;
+        }
+    }
+    Ok(summary)
+}
+
+fn save_tile(path: &Path, z: u32, x: u32, y: u32, opts: &PyramidOptions) -> io::Result<()> {
+    let (image, scene) = render_tile(z, x, y, opts).expect("the pyramid only has valid tiles");
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let partial = path.with_extension("png.part");
+    save_png(&image, &ImageMetadata::new(scene), &partial)?;
+    fs::rename(&partial, path)
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    #[test]
+    fn test_pyramid_resumes() {
+        let dir = env::temp_dir().join(format!("mandelbrot-pyramid-{}", std::process::id()));
+        let opts = PyramidOptions { iterations: Some(50), palette: Some(BuiltinPalette::Fire) };
+        let first = render_pyramid(&dir, 1, &opts, |_, _| {}).unwrap();
+        assert_eq!(first, PyramidSummary { rendered: 5, skipped: 0 });
+        fs::remove_file(tile_path(&dir, 1, 0, 1)).unwrap();
+        let second = render_pyramid(&dir, 1, &opts, |_, _| {}).unwrap();
+        assert_eq!(second, PyramidSummary { rendered: 1, skipped: 4 });
+        let metadata = ImageMetadata::load(tile_path(&dir, 1, 0, 1)).unwrap();
+        assert_eq!(metadata.scene.viewport, tile_viewport(1, 0, 1).unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,187 @@
+//! Python bindings, built with PyO3, that return images as NumPy arrays.
+//!
+//! With the `python` feature the library builds as a Python extension module named
+//! `mandelbrot`, for instance with `maturin develop --features python,pyo3/extension-module`.
+//! Renders come back as arrays, so they can be plotted or analyzed in a notebook right away:
+//!
+//! ```python
+//! import mandelbrot
+//! import matplotlib.pyplot as plt
+//!
+//! view = mandelbrot.Viewport.preset("seahorse-valley")
+//! counts = mandelbrot.escape_counts(640, 480, 500, view)  # uint32, shape (480, 640)
+//! plt.imshow(counts ** 0.5, cmap="twilight")
+//! mandelbrot.mandelbrot(complex(-0.75, 0.1), 1000)         # escape count of one point
+//! ```
+//!
+//! Renders release the GIL, so other Python threads keep running meanwhile.
+
+use num_complex::Complex64;
+use numpy::{IntoPyArray, PyArray2, PyArray3, PyArrayMethods};
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::{
+    analysis::escape_viewport,
+    error::{check_dimensions, check_iterations},
+    mandelbrot_img::{self, render_rgba8},
+    options::RenderOptions,
+    viewport,
+};
+
+/// A region of the complex plane, see `viewport::Viewport`.
+#[pyclass(name = "Viewport", module = "mandelbrot")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PyViewport {
+    viewport: viewport::Viewport,
+}
+
+#[pymethods]
+impl PyViewport {
+    #[new]
+    #[pyo3(signature = (center_x=-0.765, center_y=0.0, zoom=0.8))]
+    fn new(center_x: f64, center_y: f64, zoom: f64) -> Self {
+        Self { viewport: viewport::Viewport::new(center_x, center_y, zoom) }
+    }
+
+    /// The preset named `name`, such as `"seahorse-valley"`.
+    #[staticmethod]
+    fn preset(name: &str) -> PyResult<Self> {
+        let viewport = viewport::Viewport::preset(name).ok_or_else(|| {
+            PyValueError::new_err(format!(
+                "unknown preset {name:?}, expected one of {}",
+                viewport::PRESETS.join(", ")
+            ))
+        })?;
+        Ok(Self { viewport })
+    }
+
+    #[getter]
+    fn center_x(&self) -> f64 {
+        self.viewport.center_x
+    }
+
+    #[getter]
+    fn center_y(&self) -> f64 {
+        self.viewport.center_y
+    }
+
+    #[getter]
+    fn zoom(&self) -> f64 {
+        self.viewport.zoom
+    }
+
+    /// The point of pixel `(x, y)` of a `width` x `height` render.
+    fn point(&self, x: u32, y: u32, width: u32, height: u32) -> Complex64 {
+        self.viewport.point(x, y, width, height)
+    }
+
+    /// The viewport centered on pixel `(x, y)` of a `width` x `height` render, zoomed by
+    /// `factor`.
+    fn zoom_at(&self, x: f64, y: f64, width: u32, height: u32, factor: f64) -> Self {
+        Self { viewport: self.viewport.zoom_at(x, y, width, height, factor) }
+    }
+
+    /// An iteration limit that shows the detail of the view.
+    fn suggested_iterations(&self) -> u32 {
+        self.viewport.suggested_iterations()
+    }
+
+    fn __repr__(&self) -> String {
+        let viewport::Viewport { center_x, center_y, zoom, .. } = self.viewport;
+        format!("Viewport({center_x}, {center_y}, {zoom})")
+    }
+}
+
+/// Escape count of `c` with an iteration limit of `iterations`, which it returns for points
+/// inside the set.
+#[pyfunction]
+#[pyo3(name = "mandelbrot")]
+fn escape_count(c: Complex64, iterations: u32) -> u32 {
+    mandelbrot_img::mandelbrot(c, iterations)
+}
+
+/// Escape counts of a `width` x `height` render of `viewport`, the default view if it is
+/// `None`, as a `uint32` array of shape `(height, width)`.
+#[pyfunction]
+#[pyo3(signature = (width, height, iterations, viewport=None))]
+fn escape_counts<'py>(
+    py: Python<'py>,
+    width: u32,
+    height: u32,
+    iterations: u32,
+    viewport: Option<PyViewport>,
+) -> PyResult<Bound<'py, PyArray2<u32>>> {
+    check(py, width, height, iterations)?;
+    let viewport = viewport.map_or_else(viewport::Viewport::default, |view| view.viewport);
+    let counts: Vec<u32> = py.allow_threads(|| {
+        let opts = RenderOptions::new(iterations);
+        escape_viewport(width, height, &viewport, &opts).iter().map(|r| r.iterations).collect()
+    });
+    counts.into_pyarray(py).reshape([height as usize, width as usize])
+}
+
+/// A `width` x `height` render of `viewport`, the default view if it is `None`, as a `uint8`
+/// array of shape `(height, width, 4)` holding the RGBA pixels of
+/// `mandelbrot_img::render_rgba8`.
+#[pyfunction]
+#[pyo3(signature = (width, height, iterations, viewport=None, smooth=true))]
+fn compose<'py>(
+    py: Python<'py>,
+    width: u32,
+    height: u32,
+    iterations: u32,
+    viewport: Option<PyViewport>,
+    smooth: bool,
+) -> PyResult<Bound<'py, PyArray3<u8>>> {
+    check(py, width, height, iterations)?;
+    let viewport = viewport.map_or_else(viewport::Viewport::default, |view| view.viewport);
+    let pixels = py.allow_threads(|| {
+        let opts = RenderOptions::new(iterations).with_smooth(smooth);
+        render_rgba8(width, height, &viewport, &opts)
+    });
+    pixels.into_pyarray(py).reshape([height as usize, width as usize, 4])
+}
+
+/// Raises `ImportError` if NumPy is not installed, which the array functions would otherwise
+/// panic on, and `ValueError` for sizes and iteration limits that cannot be rendered.
+fn check(py: Python<'_>, width: u32, height: u32, iterations: u32) -> PyResult<()> {
+    py.import("numpy")?;
+    check_dimensions(width, height)
+        .and(check_iterations(iterations))
+        .map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+#[pymodule]
+#[pyo3(name = "mandelbrot")]
+fn python_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyViewport>()?;
+    m.add_function(wrap_pyfunction!(escape_count, m)?)?;
+    m.add_function(wrap_pyfunction!(escape_counts, m)?)?;
+    m.add_function(wrap_pyfunction!(compose, m)?)?;
+    Ok(())
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use numpy::PyUntypedArrayMethods;
+
+    use super::*;
+
+    #[test]
+    fn test_viewport_and_arguments_are_checked() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let view = PyViewport::preset("seahorse-valley").unwrap();
+            assert_eq!(view.__repr__(), "Viewport(-0.745, 0.11, 30)");
+            assert!(PyViewport::preset("nowhere").unwrap_err().is_instance_of::<PyValueError>(py));
+            assert_eq!(escape_count(Complex64::new(0.0, 0.0), 50), 50);
+            if py.import("numpy").is_ok() {
+                assert!(check(py, 0, 10, 10).unwrap_err().is_instance_of::<PyValueError>(py));
+                let pixels = compose(py, 8, 6, 50, Some(view), true).unwrap();
+                assert_eq!(pixels.shape(), [6, 8, 4]);
+            }
+        });
+    }
+}
@@ -0,0 +1,208 @@
+//! A builder that describes a render once and hands it to any output backend.
+//!
+//! The free functions of `mandelbrot_img`, `mandelbrot_ascii` and `buffer` each take the size,
+//! viewport and iteration settings as separate arguments, and grew one variant per new setting.
+//! A `Renderer` collects those settings instead, starting from the defaults of the binary, and
+//! renders them as an image, as ASCII art or as raw escape counts, all from the same escape-time
+//! kernel.
+//!
+//! ```
+//! use mandelbrot::{renderer::Renderer, viewport::Viewport};
+//!
+//! let renderer = Renderer::new().size(80, 40).iterations(200).viewport(Viewport::default());
+//! let canvas = renderer.render_ascii();
+//! let counts = renderer.render_raw();
+//! assert_eq!((canvas.width(), counts.width()), (80, 80));
+//! ```
+
+#[cfg(feature = "image")]
+use image::RgbImage;
+
+#[cfg(feature = "image")]
+use crate::palette::{colorize_escapes, colorize_values, BuiltinPalette};
+use crate::{
+    analysis::escape_viewport,
+    buffer::IterationBuffer,
+    mandelbrot_ascii::{canvas_from_counts, AsciiCanvas},
+    options::RenderOptions,
+    viewport::Viewport,
+};
+
+/// Size of the render when none is set, the size of the binary's images.
+pub const DEFAULT_SIZE: (u32, u32) = (800, 800);
+
+/// Settings of a render, built up step by step.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Renderer {
+    width: u32,
+    height: u32,
+    viewport: Viewport,
+    options: RenderOptions,
+    #[cfg(feature = "image")]
+    palette: Option<BuiltinPalette>,
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Self {
+            width: DEFAULT_SIZE.0,
+            height: DEFAULT_SIZE.1,
+            viewport: Viewport::default(),
+            options: RenderOptions::default(),
+            #[cfg(feature = "image")]
+            palette: None,
+        }
+    }
+}
+
+impl Renderer {
+    /// A renderer of the default view at `DEFAULT_SIZE` with the default `RenderOptions`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the size of the output in pixels or characters.
+    pub fn size(self, width: u32, height: u32) -> Self {
+        Self { width, height, ..self }
+    }
+
+    /// Sets the iteration limit, keeping the other options.
+    pub fn iterations(self, iterations: u32) -> Self {
+        Self { options: RenderOptions { iterations, ..self.options }, ..self }
+    }
+
+    /// Sets the region of the complex plane to render.
+    pub fn viewport(self, viewport: Viewport) -> Self {
+        Self { viewport, ..self }
+    }
+
+    /// Replaces all render options, including the iteration limit.
+    pub fn options(self, options: RenderOptions) -> Self {
+        Self { options, ..self }
+    }
+
+    /// Colors images with `palette` instead of the gray ramp of `compose`.
+    #[cfg(feature = "image")]
+    pub fn palette(self, palette: BuiltinPalette) -> Self {
+        Self { palette: Some(palette), ..self }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn render_options(&self) -> &RenderOptions {
+        &self.options
+    }
+
+    /// Renders an image, shaded like `mandelbrot_img::compose_with_options` or colored like
+    /// `palette::compose_with_palette` if a palette is set.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::{palette::BuiltinPalette, renderer::Renderer};
+    ///
+    /// let image = Renderer::new().size(64, 48).palette(BuiltinPalette::Fire).render();
+    /// assert_eq!(image.dimensions(), (64, 48));
+    /// ```
+    #[cfg(feature = "image")]
+    pub fn render(&self) -> RgbImage {
+        use crate::{
+            analysis::distance_viewport,
+            averages::average_viewport,
+            lighting::{light_value, normal_viewport},
+            mandelbrot_img::{compose_with_options, distance_value, trap_value},
+            options::ColorMode,
+            traps::trap_viewport,
+        };
+
+        let (width, height, opts) = (self.width, self.height, &self.options);
+        match (self.palette, opts.color_mode) {
+            (None, _) => compose_with_options(width, height, &self.viewport, opts),
+            (Some(palette), ColorMode::EscapeTime) => {
+                let escapes = escape_viewport(width, height, &self.viewport, opts);
+                colorize_escapes(width, height, &escapes, opts, &palette)
+            }
+            (Some(palette), ColorMode::DistanceEstimate) => {
+                let pixel_size = self.viewport.spans(width, height).0 / width as f64;
+                let values: Vec<f64> =
+                    distance_viewport(width, height, &self.viewport, opts.iterations)
+                        .into_iter()
+                        .map(|distance| distance_value(distance, pixel_size))
+                        .collect();
+                colorize_values(width, height, &values, &palette)
+            }
+            (Some(palette), ColorMode::OrbitTrap(shape)) => {
+                let values: Vec<f64> = trap_viewport(width, height, &self.viewport, opts, shape)
+                    .into_iter()
+                    .map(trap_value)
+                    .collect();
+                colorize_values(width, height, &values, &palette)
+            }
+            (Some(palette), ColorMode::Average(coloring)) => {
+                let values: Vec<f64> =
+                    average_viewport(width, height, &self.viewport, opts, coloring)
+                        .into_iter()
+                        .map(|average| average.unwrap_or(0.0))
+                        .collect();
+                colorize_values(width, height, &values, &palette)
+            }
+            (Some(palette), ColorMode::Shaded { azimuth, elevation }) => {
+                let values: Vec<f64> =
+                    normal_viewport(width, height, &self.viewport, opts.iterations)
+                        .into_iter()
+                        .map(|normal| light_value(normal, azimuth, elevation))
+                        .collect();
+                colorize_values(width, height, &values, &palette)
+            }
+        }
+    }
+
+    /// Renders ASCII art, with the escape counts scaled onto the character ramp like
+    /// `mandelbrot_ascii::render_canvas` does.
+    pub fn render_ascii(&self) -> AsciiCanvas {
+        let counts = self.render_raw().into_counts();
+        canvas_from_counts(self.width, self.height, &counts, self.options.iterations)
+    }
+
+    /// Renders the escape counts, and the escape angles if `RenderOptions::escape_angle` is set.
+    pub fn render_raw(&self) -> IterationBuffer {
+        let escapes = escape_viewport(self.width, self.height, &self.viewport, &self.options);
+        IterationBuffer::from_escapes(self.width, self.height, &escapes, &self.options)
+    }
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mandelbrot_ascii::render_canvas;
+
+    #[test]
+    fn test_backends_match_the_free_functions() {
+        let viewport = Viewport::new(-0.745, 0.11, 40.0);
+        let renderer = Renderer::new().size(72, 30).iterations(300).viewport(viewport);
+        assert_eq!(renderer.render_ascii(), render_canvas(&viewport, 72, 30, 300));
+
+        let opts = RenderOptions::new(300);
+        assert_eq!(renderer.render_raw(), IterationBuffer::render(72, 30, &viewport, &opts));
+        #[cfg(feature = "image")]
+        assert_eq!(
+            renderer.render(),
+            crate::mandelbrot_img::compose_with_options(72, 30, &viewport, &opts)
+        );
+    }
+
+    #[test]
+    fn test_iterations_keep_other_options() {
+        let opts = RenderOptions::new(50).with_smooth(false).with_bailout(8.0);
+        let renderer = Renderer::new().options(opts).iterations(500);
+        assert_eq!(*renderer.render_options(), RenderOptions { iterations: 500, ..opts });
+        assert_eq!((renderer.width(), renderer.height()), DEFAULT_SIZE);
+    }
+}
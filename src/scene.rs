@@ -0,0 +1,238 @@
+//! Scene files that store everything needed to render a picture again.
+//!
+//! A scene holds the size, viewport, iteration limit, fractal and palette of a render. It is
+//! saved as TOML or JSON, chosen by the extension like a dive's, see `dive::DiveFormat`, and every
+//! field may be left out to keep its default:
+//!
+//! ```toml
+//! width = 1920
+//! height = 1080
+//! iterations = 1000
+//! palette = "fire"
+//!
+//! [kind]
+//! name = "multibrot"
+//! power = 3.0
+//!
+//! [viewport]
+//! center_x = -0.743643887037151
+//! center_y = 0.13182590420533
+//! zoom = 5000.0
+//! ```
+
+use std::{fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "image")]
+use crate::palette::BuiltinPalette;
+use crate::{
+    dive::DiveFormat,
+    error::{check_dimensions, check_iterations, Error, Result},
+    fractal::FractalKind,
+    options::RenderOptions,
+    renderer::{Renderer, DEFAULT_SIZE},
+    viewport::Viewport,
+};
+
+/// The settings of a render.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Scene {
+    pub width: u32,
+    pub height: u32,
+    pub iterations: u32,
+    /// Name of the palette, see `palette::BuiltinPalette::parse`. Images are shaded with the
+    /// gray ramp of `mandelbrot_img::compose` without one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub palette: Option<String>,
+    pub kind: FractalKind,
+    pub viewport: Viewport,
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Self {
+            width: DEFAULT_SIZE.0,
+            height: DEFAULT_SIZE.1,
+            iterations: RenderOptions::default().iterations,
+            palette: None,
+            kind: FractalKind::default(),
+            viewport: Viewport::default(),
+        }
+    }
+}
+
+impl Scene {
+    /// Loads a scene from `path`, as JSON if the extension is `.json` and as TOML otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::IoError` if the file cannot be read, and the errors of `parse`, with the
+    /// path in the message, if it holds no valid scene.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path).map_err(|e| Error::io(path, e))?;
+        Self::parse(&contents, DiveFormat::from_path(path)).map_err(|e| match e {
+            Error::ConfigError(message) => {
+                Error::ConfigError(format!("{}: {message}", path.display()))
+            }
+            e => e,
+        })
+    }
+
+    /// Saves the scene to `path`, as JSON if the extension is `.json` and as TOML otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::IoError` if the file cannot be written.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        fs::write(path, self.serialize(DiveFormat::from_path(path))?)
+            .map_err(|e| Error::io(path, e))
+    }
+
+    /// Parses a scene from `contents` in the given format.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::ConfigError` if `contents` is not a scene in `format`, and the errors of
+    /// `check` if the scene cannot be rendered.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::{dive::DiveFormat, scene::Scene};
+    ///
+    /// let toml = "iterations = 1000\n[viewport]\ncenter_x = -0.1\ncenter_y = 0.65\nzoom = 40.0\n";
+    /// let scene = Scene::parse(toml, DiveFormat::Toml).unwrap();
+    /// assert_eq!((scene.iterations, scene.viewport.zoom), (1000, 40.0));
+    /// assert_eq!(scene.width, Scene::default().width);
+    /// ```
+    pub fn parse(contents: &str, format: DiveFormat) -> Result<Self> {
+        let scene: Self = match format {
+            DiveFormat::Toml => toml::from_str(contents).map_err(config_error)?,
+            DiveFormat::Json => serde_json::from_str(contents).map_err(config_error)?,
+        };
+        scene.check()?;
+        Ok(scene)
+    }
+
+    /// Serializes the scene in the given format.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::ConfigError` if the scene cannot be represented in `format`.
+    pub fn serialize(&self, format: DiveFormat) -> Result<String> {
+        match format {
+            DiveFormat::Toml => toml::to_string(self).map_err(config_error),
+            DiveFormat::Json => serde_json::to_string_pretty(self).map_err(config_error),
+        }
+    }
+
+    /// Checks that the scene can be rendered.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidDimensions` if its size is empty or too large and
+    /// `Error::IterationOverflow` if its iteration limit is too high.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::{dive::DiveFormat, error::Error, scene::Scene};
+    ///
+    /// let empty = Scene::parse("width = 0", DiveFormat::Toml);
+    /// assert!(matches!(empty, Err(Error::InvalidDimensions { width: 0, .. })));
+    /// ```
+    pub fn check(&self) -> Result<()> {
+        check_dimensions(self.width, self.height)?;
+        check_iterations(self.iterations)
+    }
+
+    /// Render options of the scene: its iteration limit and fractal, with the other options at
+    /// their defaults.
+    pub fn render_options(&self) -> RenderOptions {
+        RenderOptions::new(self.iterations).with_kind(self.kind)
+    }
+
+    /// The palette named by the scene, or `None` if it names none or one that does not exist.
+    #[cfg(feature = "image")]
+    pub fn palette(&self) -> Option<BuiltinPalette> {
+        self.palette.as_deref().and_then(BuiltinPalette::parse)
+    }
+
+    /// A renderer of the scene.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::scene::Scene;
+    ///
+    /// let scene = Scene { width: 64, height: 48, ..Scene::default() };
+    /// let canvas = scene.renderer().render_ascii();
+    /// assert_eq!((canvas.width(), canvas.height()), (64, 48));
+    /// ```
+    pub fn renderer(&self) -> Renderer {
+        let renderer = Renderer::new()
+            .size(self.width, self.height)
+            .viewport(self.viewport)
+            .options(self.render_options());
+        #[cfg(feature = "image")]
+        let renderer = match self.palette() {
+            Some(palette) => renderer.palette(palette),
+            None => renderer,
+        };
+        renderer
+    }
+}
+
+fn config_error(e: impl std::error::Error) -> Error {
+    Error::ConfigError(e.to_string())
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    #[test]
+    fn test_round_trip_in_both_formats() {
+        let scene = Scene {
+            width: 1920,
+            height: 1080,
+            iterations: 1000,
+            palette: Some("fire".to_string()),
+            kind: FractalKind::Multibrot { power: 3.0 },
+            viewport: Viewport::new(-0.743643887037151, 0.13182590420533, 5000.0),
+        };
+        for extension in ["toml", "json"] {
+            let path = env::temp_dir()
+                .join(format!("mandelbrot-scene-{}.{extension}", std::process::id()));
+            scene.save(&path).unwrap();
+            let loaded = Scene::load(&path).unwrap();
+            fs::remove_file(&path).unwrap();
+            assert_eq!(loaded, scene);
+        }
+        let plain = Scene::default();
+        let text = plain.serialize(DiveFormat::Toml).unwrap();
+        assert_eq!(Scene::parse(&text, DiveFormat::Toml).unwrap(), plain);
+    }
+
+    #[test]
+    fn test_errors_name_the_problem() {
+        let missing = env::temp_dir().join("mandelbrot-missing-dir").join("scene.toml");
+        match Scene::load(&missing) {
+            Err(Error::IoError { path, .. }) => assert_eq!(path, Some(missing)),
+            other => panic!("expected an I/O error, got {other:?}"),
+        }
+        assert!(matches!(
+            Scene::parse("iterations = 4000000000", DiveFormat::Toml),
+            Err(Error::IterationOverflow { iterations: 4_000_000_000 })
+        ));
+        assert!(matches!(
+            Scene::parse("width = \"wide\"", DiveFormat::Toml),
+            Err(Error::ConfigError(_))
+        ));
+    }
+}
@@ -0,0 +1,238 @@
+//! Schedules per-pixel work over a `width` x `height` grid.
+//!
+//! Both the image and the ASCII renderers describe a render as a function from pixel coordinates
+//! to a value and let `render_grid` evaluate it. With the `parallel` feature enabled, rows are
+//! distributed across a rayon thread pool; without it the grid is evaluated serially. The result
+//! is always stored row by row, so callers do not need to care which path was taken.
+//!
+//! `map_slice` does the same for arbitrary lists of work items instead of grids.
+//!
+//! `render_grid_with_progress` additionally reports the number of finished pixels after every
+//! completed row, which is what drives the progress bars of the binary.
+//!
+//! `try_render_grid` can also be stopped half way by setting an `AtomicBool`, such as one shared
+//! through an `Arc` with the thread of a user interface.
+
+#[cfg(feature = "parallel")]
+use std::sync::atomic::AtomicU64;
+use std::{
+    error::Error,
+    fmt,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Error of a render that was stopped through its cancellation flag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderCancelled;
+
+impl fmt::Display for RenderCancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("the render was cancelled")
+    }
+}
+
+impl Error for RenderCancelled {}
+
+/// Evaluates `f(x, y)` for every pixel of a `width` x `height` grid and returns the values in
+/// row-major order.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::scheduler::render_grid;
+///
+/// let grid = render_grid(3, 2, |x, y| (x, y));
+/// assert_eq!(grid, vec![(0, 0), (1, 0), (2, 0), (0, 1), (1, 1), (2, 1)]);
+/// ```
+pub fn render_grid<T, F>(width: u32, height: u32, f: F) -> Vec<T>
+where
+    T: Send,
+    F: Fn(u32, u32) -> T + Sync,
+{
+    render_grid_with_progress(width, height, f, |_, _| {})
+}
+
+/// Like `render_grid`, but calls `on_progress(done, total)` with the number of evaluated pixels
+/// out of `width * height` every time a row is finished.
+///
+/// With the `parallel` feature rows finish out of order and `on_progress` may be called from
+/// several threads, but `done` only ever increases and reaches `total` exactly once.
+///
+/// # Examples
+/// ```
+/// use std::sync::atomic::{AtomicU64, Ordering};
+///
+/// use mandelbrot::scheduler::render_grid_with_progress;
+///
+/// let last = AtomicU64::new(0);
+/// render_grid_with_progress(
+///     4,
+///     3,
+///     |x, y| x + y,
+///     |done, _total| {
+///         last.fetch_max(done, Ordering::Relaxed);
+///     },
+/// );
+/// assert_eq!(last.into_inner(), 12);
+/// ```
+pub fn render_grid_with_progress<T, F, P>(width: u32, height: u32, f: F, on_progress: P) -> Vec<T>
+where
+    T: Send,
+    F: Fn(u32, u32) -> T + Sync,
+    P: Fn(u64, u64) + Sync,
+{
+    let never = AtomicBool::new(false);
+    match try_render_grid(width, height, f, on_progress, &never) {
+        Ok(grid) => grid,
+        Err(RenderCancelled) => unreachable!("the render was never cancelled"),
+    }
+}
+
+/// Like `render_grid_with_progress`, but stops once `cancel` is set, which another thread can do
+/// to abort a render. The flag is checked before every row, so rows that already started are
+/// finished first.
+///
+/// # Errors
+///
+/// Returns `RenderCancelled` if `cancel` was set before the last row started.
+///
+/// # Examples
+/// ```
+/// use std::sync::atomic::AtomicBool;
+///
+/// use mandelbrot::scheduler::{try_render_grid, RenderCancelled};
+///
+/// let cancel = AtomicBool::new(true);
+/// assert_eq!(try_render_grid(4, 3, |x, y| x + y, |_, _| {}, &cancel), Err(RenderCancelled));
+/// ```
+pub fn try_render_grid<T, F, P>(
+    width: u32,
+    height: u32,
+    f: F,
+    on_progress: P,
+    cancel: &AtomicBool,
+) -> Result<Vec<T>, RenderCancelled>
+where
+    T: Send,
+    F: Fn(u32, u32) -> T + Sync,
+    P: Fn(u64, u64) + Sync,
+{
+    let total = width as u64 * height as u64;
+    #[cfg(feature = "parallel")]
+    {
+        let done = AtomicU64::new(0);
+        let rows: Option<Vec<Vec<T>>> = (0..height)
+            .into_par_iter()
+            .map(|y| {
+                if cancel.load(Ordering::Relaxed) {
+                    return None;
+                }
+                let row: Vec<T> = (0..width).map(|x| f(x, y)).collect();
+                let finished = done.fetch_add(width as u64, Ordering::Relaxed) + width as u64;
+                on_progress(finished, total);
+                Some(row)
+            })
+            .collect();
+        rows.map(|rows| rows.into_iter().flatten().collect()).ok_or(RenderCancelled)
+    }
+    #[cfg(not(feature = "parallel"))]
+    {
+        let mut grid = Vec::with_capacity(total as usize);
+        for y in 0..height {
+            if cancel.load(Ordering::Relaxed) {
+                return Err(RenderCancelled);
+            }
+            grid.extend((0..width).map(|x| f(x, y)));
+            on_progress(grid.len() as u64, total);
+        }
+        Ok(grid)
+    }
+}
+
+/// Applies `f` to every item of `items`, in parallel with the `parallel` feature, and returns
+/// the results in the same order.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::scheduler::map_slice;
+///
+/// assert_eq!(map_slice(&[1, 2, 3], |n| n * 2), vec![2, 4, 6]);
+/// ```
+pub fn map_slice<T, U, F>(items: &[T], f: F) -> Vec<U>
+where
+    T: Sync,
+    U: Send,
+    F: Fn(&T) -> U + Sync + Send,
+{
+    #[cfg(feature = "parallel")]
+    {
+        items.par_iter().map(f).collect()
+    }
+    #[cfg(not(feature = "parallel"))]
+    {
+        items.iter().map(f).collect()
+    }
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_grid_is_row_major() {
+        let width = 37;
+        let height = 23;
+        let grid = render_grid(width, height, |x, y| y * width + x);
+        assert_eq!(grid, (0..width * height).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_progress_reaches_total_once_per_row() {
+        use std::sync::Mutex;
+
+        let reports = Mutex::new(Vec::new());
+        render_grid_with_progress(
+            5,
+            7,
+            |x, y| x * y,
+            |done, total| {
+                reports.lock().unwrap().push((done, total));
+            },
+        );
+        let mut reports = reports.into_inner().unwrap();
+        reports.sort();
+        assert_eq!(reports.len(), 7);
+        assert_eq!(reports.last(), Some(&(35, 35)));
+        assert!(reports.iter().all(|(done, _)| done % 5 == 0));
+    }
+
+    #[test]
+    fn test_cancelling_stops_remaining_rows() {
+        use std::sync::atomic::AtomicU32;
+
+        let cancel = AtomicBool::new(false);
+        let rows = AtomicU32::new(0);
+        let result = try_render_grid(
+            4,
+            1000,
+            |x, _| {
+                if x == 0 && rows.fetch_add(1, Ordering::Relaxed) == 10 {
+                    cancel.store(true, Ordering::Relaxed);
+                }
+                x
+            },
+            |_, _| {},
+            &cancel,
+        );
+        assert_eq!(result, Err(RenderCancelled));
+        // Rows already running when the flag was set still finish.
+        assert!(rows.into_inner() < 1000);
+
+        let cancel = AtomicBool::new(false);
+        assert_eq!(try_render_grid(2, 2, |x, y| x + y, |_, _| {}, &cancel), Ok(vec![0, 1, 1, 2]));
+    }
+}
@@ -0,0 +1,511 @@
+//! A small HTTP server that renders views and map tiles as PNG images on request, started by
+//! `mandelbrot serve`.
+//!
+//! | Request                           | Response                                              |
+//! |-----------------------------------|-------------------------------------------------------|
+//! | `GET /`                           | A Leaflet map of the set built from the tiles         |
+//! | `GET /render?cx=&cy=&zoom=&w=&h=` | A `w` x `h` render of the view centered on `cx + cyi` |
+//! | `GET /tiles/{z}/{x}/{y}.png`      | A `pyramid::TILE_SIZE` tile in slippy-map numbering   |
+//! | `GET /reload`                     | Reloads the settings file                             |
+//!
+//! Both image endpoints take `iters` and `palette`, the name of a `BuiltinPalette`. Without them
+//! they fall back on the `iterations` and `palette` of the `ServerSettings`, and then on
+//! `Viewport::suggested_iterations` and gray. Missing view parameters are those of the default
+//! view, and images carry their render parameters like the images of `metadata::save_png`.
+//!
+//! A `Server` created with `Server::with_settings_file` reads its settings from a
+//! `settings.toml`, and `serve` reloads them whenever a `watch::FileWatcher` sees the file
+//! change, as does `GET /reload`. The last `cache_size` images are kept and served again for
+//! identical requests until the settings change.
+//!
+//! Tiles follow the `z/x/y` numbering of web maps described in `pyramid`, which also renders
+//! them ahead of time for static hosting.
+//!
+//! The server only understands `GET` requests, answers each on its own thread and closes the
+//! connection afterwards. It is meant for local use and has no limits beyond `MAX_SIZE`.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fs,
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, RwLock},
+    thread,
+    time::Duration,
+};
+
+use image::RgbImage;
+use serde::Deserialize;
+
+use crate::{
+    error::{check_dimensions, check_iterations},
+    mandelbrot_img::compose_with_options,
+    metadata::{write_png, ImageMetadata},
+    options::RenderOptions,
+    palette::{compose_with_palette, BuiltinPalette},
+    pyramid::{tile_viewport, TILE_SIZE},
+    scene::Scene,
+    viewport::Viewport,
+    watch::FileWatcher,
+};
+
+/// Largest width or height `/render` accepts.
+pub const MAX_SIZE: u32 = 4096;
+
+/// Images kept for repeated requests unless the settings say otherwise.
+pub const DEFAULT_CACHE_SIZE: usize = 64;
+
+/// How often `serve` checks the settings file for changes.
+pub const RELOAD_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Defaults of the server, read from the `palette`, `iterations` and `cache_size` entries of a
+/// settings file. Other entries are left to the rest of the binary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ServerSettings {
+    /// Palette of images that do not ask for one; gray if `None`.
+    pub palette: Option<BuiltinPalette>,
+    /// Iteration limit of images that do not ask for one; suggested by the zoom if `None`.
+    pub iterations: Option<u32>,
+    /// Number of images kept for repeated requests, `0` to keep none.
+    pub cache_size: usize,
+}
+
+impl Default for ServerSettings {
+    fn default() -> Self {
+        Self { palette: None, iterations: None, cache_size: DEFAULT_CACHE_SIZE }
+    }
+}
+
+/// The entries of a settings file `ServerSettings` reads, before they are checked.
+#[derive(Deserialize)]
+struct SettingsFile {
+    palette: Option<String>,
+    iterations: Option<u32>,
+    cache_size: Option<usize>,
+}
+
+impl ServerSettings {
+    /// Reads the settings from the TOML file at `path`, with the defaults for missing entries
+    /// and for a missing file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `InvalidData` error if the file is not TOML, names an unknown palette or has
+    /// an iteration limit of `0` or above `error::MAX_ITERATIONS`, and the error of reading it
+    /// otherwise.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e),
+        };
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+        let file: SettingsFile = toml::from_str(&contents).map_err(|e| invalid(e.to_string()))?;
+        let palette = match file.palette {
+            Some(name) => Some(
+                BuiltinPalette::parse(&name)
+                    .ok_or_else(|| invalid(format!("unknown palette {name:?}")))?,
+            ),
+            None => None,
+        };
+        if let Some(iterations) = file.iterations {
+            if iterations == 0 {
+                return Err(invalid("iterations must be a positive integer".to_string()));
+            }
+            check_iterations(iterations).map_err(|e| invalid(e.to_string()))?;
+        }
+        Ok(Self {
+            palette,
+            iterations: file.iterations,
+            cache_size: file.cache_size.unwrap_or(DEFAULT_CACHE_SIZE),
+        })
+    }
+}
+
+/// Settings and cached images shared by the threads answering requests.
+#[derive(Debug, Default)]
+pub struct Server {
+    settings: RwLock<ServerSettings>,
+    settings_path: Option<PathBuf>,
+    /// Responses to image requests by target, the most recent last.
+    cache: Mutex<VecDeque<(String, Response)>>,
+}
+
+impl Server {
+    pub fn new(settings: ServerSettings) -> Self {
+        Self { settings: RwLock::new(settings), ..Self::default() }
+    }
+
+    /// A server with the settings of the file at `path`, which `reload` reads again.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of `ServerSettings::load`.
+    pub fn with_settings_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let settings = ServerSettings::load(path)?;
+        Ok(Self { settings_path: Some(path.to_path_buf()), ..Self::new(settings) })
+    }
+
+    /// The settings requests are answered with.
+    pub fn settings(&self) -> ServerSettings {
+        *self.settings.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn settings_path(&self) -> Option<&Path> {
+        self.settings_path.as_deref()
+    }
+
+    /// Reads the settings file again and drops the cached images, which may no longer match
+    /// the settings. Servers without a settings file keep their settings.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of `ServerSettings::load`, keeping the previous settings.
+    pub fn reload(&self) -> io::Result<()> {
+        let Some(path) = &self.settings_path else {
+            return Ok(());
+        };
+        let settings = ServerSettings::load(path)?;
+        *self.settings.write().unwrap_or_else(|e| e.into_inner()) = settings;
+        self.cache.lock().unwrap_or_else(|e| e.into_inner()).clear();
+        Ok(())
+    }
+
+    /// Reloads the settings if `watcher` saw their file change, returning whether it did.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of `reload`.
+    pub fn reload_if_changed(&self, watcher: &mut FileWatcher) -> io::Result<bool> {
+        if !watcher.changed() {
+            return Ok(false);
+        }
+        self.reload().map(|()| true)
+    }
+
+    /// Answers a `GET` request for `target`, the path and query of the request line.
+    pub fn handle(&self, target: &str) -> Response {
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let query: HashMap<&str, &str> =
+            query.split('&').filter_map(|pair| pair.split_once('=')).collect();
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+        let cacheable = matches!(segments.as_slice(), ["render"] | ["tiles", ..]);
+        if cacheable {
+            if let Some(response) = self.cached(target) {
+                return response;
+            }
+        }
+        let settings = self.settings();
+        let result = match segments.as_slice() {
+            [""] => Ok(Response::ok("text/html; charset=utf-8", INDEX.as_bytes().to_vec())),
+            ["render"] => render(&query, &settings),
+            ["tiles", z, x, y] => {
+                tile(z, x, y.strip_suffix(".png").unwrap_or(y), &query, &settings)
+            }
+            ["reload"] => match (&self.settings_path, self.reload()) {
+                (None, _) => Err(Response::error(404, "the server has no settings file")),
+                (Some(path), Ok(())) => Ok(Response::ok(
+                    "text/plain; charset=utf-8",
+                    format!("reloaded {}\n", path.display()).into_bytes(),
+                )),
+                (Some(_), Err(e)) => Err(Response::error(500, e.to_string())),
+            },
+            _ => Err(Response::error(404, format!("no such page: {path}"))),
+        };
+        let response = result.unwrap_or_else(|response| response);
+        if cacheable && response.status == 200 {
+            self.store(target, &response, settings.cache_size);
+        }
+        response
+    }
+
+    fn cached(&self, target: &str) -> Option<Response> {
+        let cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        cache.iter().find(|(cached, _)| cached == target).map(|(_, response)| response.clone())
+    }
+
+    fn store(&self, target: &str, response: &Response, cache_size: usize) {
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        cache.retain(|(cached, _)| cached != target);
+        cache.push_back((target.to_string(), response.clone()));
+        while cache.len() > cache_size {
+            cache.pop_front();
+        }
+    }
+}
+
+/// An HTTP response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    fn ok(content_type: &'static str, body: Vec<u8>) -> Self {
+        Self { status: 200, content_type, body }
+    }
+
+    fn error(status: u16, message: impl Into<String>) -> Self {
+        let body = format!("{}\n", message.into()).into_bytes();
+        Self { status, content_type: "text/plain; charset=utf-8", body }
+    }
+
+    /// Writes the response as HTTP/1.1 to `out`, with a header that closes the connection.
+    pub fn write_to<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let reason = match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            _ => "Internal Server Error",
+        };
+        write!(
+            out,
+            "HTTP/1.1 {} {reason}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: \
+             close\r\n\r\n",
+            self.status,
+            self.content_type,
+            self.body.len()
+        )?;
+        out.write_all(&self.body)?;
+        out.flush()
+    }
+}
+
+/// Answers a `GET` request for `target`, the path and query of the request line, with the
+/// default settings.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::server::handle;
+///
+/// let response = handle("/render?w=64&h=48&iters=100&palette=fire");
+/// assert_eq!((response.status, response.content_type), (200, "image/png"));
+/// assert_eq!(handle("/render?w=0").status, 400);
+/// assert_eq!(handle("/tiles/1/2/0.png").status, 404);
+/// ```
+pub fn handle(target: &str) -> Response {
+    Server::default().handle(target)
+}
+
+/// Accepts connections on `listener` and answers each on its own thread with `server`, until
+/// accepting fails. The settings file of `server`, if it has one, is checked for changes every
+/// `RELOAD_INTERVAL`.
+pub fn serve(listener: TcpListener, server: Server) -> io::Result<()> {
+    let server = Arc::new(server);
+    if let Some(path) = server.settings_path() {
+        let mut watcher = FileWatcher::new(path);
+        let server = Arc::clone(&server);
+        thread::spawn(move || loop {
+            thread::sleep(RELOAD_INTERVAL);
+            // A file caught halfway through being written is read again on its next change,
+            // and until then the previous settings stay.
+            let _ = server.reload_if_changed(&mut watcher);
+        });
+    }
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let server = Arc::clone(&server);
+        thread::spawn(move || {
+            // A client that hangs up early is not the server's problem.
+            let _ = answer(&server, stream);
+        });
+    }
+    Ok(())
+}
+
+/// Reads one request from `stream` and writes the response of `server`.
+fn answer(server: &Server, stream: TcpStream) -> io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // The headers say nothing a `GET` without a body needs.
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+    let response = match request_line.split_whitespace().collect::<Vec<_>>().as_slice() {
+        ["GET", target, ..] => server.handle(target),
+        _ => Response::error(405, "only GET requests are supported"),
+    };
+    response.write_to(reader.get_mut())
+}
+
+fn render(query: &HashMap<&str, &str>, settings: &ServerSettings) -> Result<Response, Response> {
+    let default = Viewport::default();
+    let viewport = Viewport::new(
+        param(query, "cx", default.center_x)?,
+        param(query, "cy", default.center_y)?,
+        param(query, "zoom", default.zoom)?,
+    );
+    let view = [viewport.center_x, viewport.center_y, viewport.zoom];
+    if !view.iter().all(|value| value.is_finite()) || viewport.zoom <= 0.0 {
+        return Err(Response::error(400, "cx and cy must be finite and zoom positive"));
+    }
+    let (width, height) = (param(query, "w", 800)?, param(query, "h", 600)?);
+    if width > MAX_SIZE || height > MAX_SIZE {
+        return Err(Response::error(400, format!("w and h must be at most {MAX_SIZE}")));
+    }
+    png_response(width, height, viewport, query, settings)
+}
+
+fn tile(
+    z: &str,
+    x: &str,
+    y: &str,
+    query: &HashMap<&str, &str>,
+    settings: &ServerSettings,
+) -> Result<Response, Response> {
+    let not_found = || Response::error(404, format!("no such tile: {z}/{x}/{y}"));
+    let [z, x, y] = [z, x, y].map(|value| value.parse::<u32>().ok());
+    let viewport = match (z, x, y) {
+        (Some(z), Some(x), Some(y)) => tile_viewport(z, x, y).ok_or_else(not_found)?,
+        _ => return Err(not_found()),
+    };
+    png_response(TILE_SIZE, TILE_SIZE, viewport, query, settings)
+}
+
+/// Renders `viewport` with the `iters` and `palette` of `query`, or those of `settings`, into a
+/// PNG response.
+fn png_response(
+    width: u32,
+    height: u32,
+    viewport: Viewport,
+    query: &HashMap<&str, &str>,
+    settings: &ServerSettings,
+) -> Result<Response, Response> {
+    let default_iterations = settings.iterations.unwrap_or(viewport.suggested_iterations());
+    let iterations = param(query, "iters", default_iterations)?;
+    let palette = match query.get("palette") {
+        Some(name) => Some(
+            BuiltinPalette::parse(name)
+                .ok_or_else(|| Response::error(400, format!("unknown palette {name:?}")))?,
+        ),
+        None => settings.palette,
+    };
+    check_dimensions(width, height)
+        .and(check_iterations(iterations))
+        .map_err(|e| Response::error(400, e.to_string()))?;
+
+    let opts = RenderOptions::new(iterations);
+    let image: RgbImage = match &palette {
+        Some(palette) => compose_with_palette(width, height, &viewport, &opts, palette),
+        None => compose_with_options(width, height, &viewport, &opts),
+    };
+    let palette = palette.map(|palette| palette.name().to_string());
+    let scene = Scene { width, height, iterations, palette, viewport, ..Scene::default() };
+    let mut png = Vec::new();
+    write_png(&image, &ImageMetadata::new(scene), &mut png)
+        .map_err(|e| Response::error(500, e.to_string()))?;
+    Ok(Response::ok("image/png", png))
+}
+
+/// The value of `name` in `query`, or `default` if it is missing.
+fn param<T: std::str::FromStr>(
+    query: &HashMap<&str, &str>,
+    name: &str,
+    default: T,
+) -> Result<T, Response> {
+    match query.get(name) {
+        Some(value) => value
+            .parse()
+            .map_err(|_| Response::error(400, format!("invalid value for {name}: {value:?}"))),
+        None => Ok(default),
+    }
+}
+
+/// The page served at `/`, a full-window Leaflet map of the tiles.
+const INDEX: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>mandelbrot</title>
+<link rel="stylesheet" href="https://unpkg.com/leaflet@1.9.4/dist/leaflet.css">
+<script src="https://unpkg.com/leaflet@1.9.4/dist/leaflet.js"></script>
+<style>html, body, #map { height: 100%; margin: 0; background: #000; }</style>
+</head>
+<body>
+<div id="map"></div>
+<script>
+const map = L.map("map", { crs: L.CRS.Simple, minZoom: 0, maxZoom: 45 }).setView([-128, 128], 1);
+const palette = new URLSearchParams(location.search).get("palette");
+const query = palette ? "?palette=" + encodeURIComponent(palette) : "";
+L.tileLayer("/tiles/{z}/{x}/{y}.png" + query, { noWrap: true, maxZoom: 45 }).addTo(map);
+</script>
+</body>
+</html>
+"#;
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs::File, time::SystemTime};
+
+    use super::*;
+    use crate::metadata::read_png;
+
+    #[test]
+    fn test_neighbouring_tiles_meet() {
+        let (z, x, y) = (5, 11, 14);
+        let tile = tile_viewport(z, x, y).unwrap();
+        let right = tile_viewport(z, x + 1, y).unwrap();
+        let below = tile_viewport(z, x, y + 1).unwrap();
+        let (edge_x, edge_y) = tile.to_complex_num(TILE_SIZE, TILE_SIZE, TILE_SIZE, TILE_SIZE);
+        let (right_x, _) = right.to_complex_num(0, 0, TILE_SIZE, TILE_SIZE);
+        let (_, below_y) = below.to_complex_num(0, 0, TILE_SIZE, TILE_SIZE);
+        assert!((edge_x - right_x).abs() < 1e-12 && (edge_y - below_y).abs() < 1e-12);
+
+        let response = handle("/tiles/5/11/14.png?iters=300");
+        assert_eq!(response.status, 200);
+        let metadata = read_png(response.body.as_slice()).unwrap();
+        assert_eq!((metadata.scene.viewport, metadata.scene.iterations), (tile, 300));
+        assert_eq!(handle("/tiles/5/11/x.png").status, 404);
+        assert_eq!(handle("/render?zoom=deep").status, 400);
+        for bad in ["zoom=0", "zoom=-1", "cx=NaN", "cy=inf"] {
+            assert_eq!(handle(&format!("/render?{bad}")).status, 400, "{bad}");
+        }
+        assert_eq!(handle("/").content_type, "text/html; charset=utf-8");
+    }
+
+    #[test]
+    fn test_changed_settings_change_later_responses() {
+        let path = env::temp_dir().join(format!("mandelbrot-serve-{}.toml", std::process::id()));
+        fs::write(&path, "key = \"kept\"\npalette = \"fire\"\niterations = 100\n").unwrap();
+        let server = Server::with_settings_file(&path).unwrap();
+        let scene = |response: Response| {
+            let scene = read_png(response.body.as_slice()).unwrap().scene;
+            (scene.palette, scene.iterations)
+        };
+        let target = "/render?w=16&h=12";
+        assert_eq!(scene(server.handle(target)), (Some("fire".to_string()), 100));
+        assert_eq!(scene(server.handle("/render?w=16&h=12&iters=50")).1, 50);
+
+        // Cached until the settings are reloaded, by the watcher or by `/reload`.
+        fs::write(&path, "palette = \"ocean\"\niterations = 200\n").unwrap();
+        assert_eq!(scene(server.handle(target)).1, 100);
+        let mut watcher = FileWatcher::new(&path);
+        let file = File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(5)).unwrap();
+        assert!(server.reload_if_changed(&mut watcher).unwrap());
+        assert_eq!(scene(server.handle(target)), (Some("ocean".to_string()), 200));
+
+        fs::write(&path, "iterations = 300\ncache_size = 0\n").unwrap();
+        assert_eq!(server.handle("/reload").status, 200);
+        assert_eq!(scene(server.handle(target)), (None, 300));
+        assert_eq!(server.settings().cache_size, 0);
+
+        // A broken file keeps the previous settings.
+        fs::write(&path, "palette = \"plaid\"\n").unwrap();
+        assert_eq!(server.handle("/reload").status, 500);
+        fs::write(&path, "iterations = 0\n").unwrap();
+        assert_eq!(server.handle("/reload").status, 500);
+        assert_eq!(server.settings().iterations, Some(300));
+        fs::remove_file(&path).unwrap();
+        assert_eq!(handle("/reload").status, 404);
+    }
+}
@@ -0,0 +1,89 @@
+//! Session state that survives between runs, so exploration picks up where it left off.
+//!
+//! The binary saves the viewport, iteration count, palette and backend it finished with to
+//! `session.toml` on exit and restores them on the next launch unless `--fresh` is passed.
+
+use std::{fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::viewport::Viewport;
+
+pub const DEFAULT_SESSION_FILE: &str = "session.toml";
+
+/// State of the last viewer session.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    pub iterations: u32,
+    /// Name of the built-in palette, see `palette::BuiltinPalette::parse`.
+    #[serde(default)]
+    pub palette: Option<String>,
+    /// Name of the backend, see `bench::Backend::parse`.
+    #[serde(default)]
+    pub backend: Option<String>,
+    // Last, since TOML writes tables after the plain values.
+    pub viewport: Viewport,
+}
+
+impl Session {
+    /// A session without a palette or backend.
+    pub fn new(viewport: Viewport, iterations: u32) -> Self {
+        Self { iterations, viewport, palette: None, backend: None }
+    }
+
+    /// Loads the session saved at `path`, returning `None` if no session has been saved yet.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Option<Self>> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        toml::from_str(&contents)
+            .map(Some)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Saves the session to `path`, overwriting any previous session.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let contents =
+            toml::to_string(self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(path, contents)
+    }
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    #[test]
+    fn test_save_and_load_round_trip() {
+        let path = env::temp_dir().join(format!("mandelbrot-session-{}.toml", std::process::id()));
+        let session = Session {
+            palette: Some("ocean".to_string()),
+            backend: Some("scalar".to_string()),
+            ..Session::new(Viewport::new(-0.1, 0.65, 40.0), 750)
+        };
+        session.save(&path).unwrap();
+        let loaded = Session::load(&path).unwrap();
+        assert_eq!(loaded, Some(session));
+
+        // Sessions saved before the palette and backend were remembered still load.
+        fs::write(
+            &path,
+            "iterations = 300\n[viewport]\ncenter_x = -0.5\ncenter_y = 0.0\nzoom = 2.0\n",
+        )
+        .unwrap();
+        let old = Session::load(&path).unwrap().unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!((old.iterations, old.palette, old.backend), (300, None, None));
+    }
+
+    #[test]
+    fn test_load_missing_file_is_none() {
+        assert_eq!(Session::load("does-not-exist/session.toml").unwrap(), None);
+    }
+}
@@ -0,0 +1,165 @@
+//! Sixel graphics, which terminals such as xterm, mlterm, foot and WezTerm draw inline.
+//!
+//! A sixel image is a DEC device control string: a set of numbered color registers followed by
+//! bands of six pixel rows, each drawn once per color as a run of characters whose six low bits
+//! say which of the band's pixels in that column have the color. Runs of the same character are
+//! run-length encoded. Terminals offer at least 256 registers, so `render_sixel` samples the
+//! palette at 256 levels, and `encode_sixel` uses the colors of an image as they are when there
+//! are few enough of them and the 216 colors of a 6x6x6 cube otherwise.
+
+use std::{collections::HashMap, fmt::Write as _};
+
+use image::RgbImage;
+
+use crate::{buffer::IterationBuffer, palette::Palette};
+
+/// Number of color registers used, the minimum terminals provide.
+pub const REGISTERS: usize = 256;
+
+/// Pixel rows per band.
+const BAND: u32 = 6;
+
+/// Runs at least this long are run-length encoded.
+const MIN_RUN: usize = 4;
+
+/// Encodes the escape counts of `buffer` as sixels colored with `palette`, like
+/// `palette::colorize_escapes` colors whole counts. Points that did not escape get
+/// `palette.color(1.0)`.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::{palette::BuiltinPalette, prelude::*, sixel::render_sixel};
+///
+/// let buffer = IterationBuffer::render(64, 48, &Viewport::default(), &RenderOptions::new(100));
+/// let sixel = render_sixel(&buffer, &BuiltinPalette::Fire);
+/// assert!(sixel.starts_with("\x1bPq\"1;1;64;48"));
+/// assert!(sixel.ends_with("\x1b\\"));
+/// ```
+pub fn render_sixel<P: Palette + ?Sized>(buffer: &IterationBuffer, palette: &P) -> String {
+    let levels = REGISTERS - 1;
+    let registers: Vec<[u8; 3]> =
+        (0..=levels).map(|level| palette.color(level as f64 / levels as f64).0).collect();
+    let iterations = buffer.iterations().max(1) as usize;
+    let indices: Vec<u8> = buffer
+        .counts()
+        .iter()
+        .map(|&count| ((count as usize).min(iterations) * levels / iterations) as u8)
+        .collect();
+    encode(buffer.width(), buffer.height(), &indices, &registers)
+}
+
+/// Encodes `image` as sixels.
+pub fn encode_sixel(image: &RgbImage) -> String {
+    let mut registers = Vec::new();
+    let mut lookup = HashMap::new();
+    for pixel in image.pixels() {
+        if lookup.len() > REGISTERS {
+            break;
+        }
+        lookup.entry(pixel.0).or_insert_with(|| {
+            registers.push(pixel.0);
+            registers.len() - 1
+        });
+    }
+    let indices: Vec<u8> = if lookup.len() <= REGISTERS {
+        image.pixels().map(|pixel| lookup[&pixel.0] as u8).collect()
+    } else {
+        registers = (0..216)
+            .map(|index| [index / 36, index / 6 % 6, index % 6].map(|level| (level * 51) as u8))
+            .collect();
+        let level = |channel: u8| (channel as u16 * 5 + 127) / 255;
+        image
+            .pixels()
+            .map(|pixel| {
+                let [r, g, b] = pixel.0.map(level);
+                (36 * r + 6 * g + b) as u8
+            })
+            .collect()
+    };
+    encode(image.width(), image.height(), &indices, &registers)
+}
+
+/// Encodes a `width` x `height` grid of indices into `registers`, in row-major order.
+fn encode(width: u32, height: u32, indices: &[u8], registers: &[[u8; 3]]) -> String {
+    let mut out = format!("\x1bPq\"1;1;{width};{height}");
+    for (index, color) in registers.iter().enumerate() {
+        let [r, g, b] = color.map(|channel| (channel as u32 * 100 + 127) / 255);
+        let _ = write!(out, "#{index};2;{r};{g};{b}");
+    }
+    for top in (0..height).step_by(BAND as usize) {
+        let rows = BAND.min(height - top);
+        let band = &indices[(top * width) as usize..((top + rows) * width) as usize];
+        // The sixel characters of every register used in the band.
+        let mut planes: Vec<Option<Vec<u8>>> = vec![None; registers.len()];
+        for (i, &index) in band.iter().enumerate() {
+            let columns = planes[index as usize].get_or_insert_with(|| vec![0; width as usize]);
+            columns[i % width as usize] |= 1 << (i / width as usize);
+        }
+        let used = planes
+            .iter()
+            .enumerate()
+            .filter_map(|(register, columns)| columns.as_ref().map(|columns| (register, columns)));
+        for (n, (register, columns)) in used.enumerate() {
+            if n > 0 {
+                out.push('$');
+            }
+            let _ = write!(out, "#{register}");
+            write_runs(&mut out, columns);
+        }
+        out.push('-');
+    }
+    out.push_str("\x1b\\");
+    out
+}
+
+/// Writes the sixel characters of `columns`, run-length encoding repeats.
+fn write_runs(out: &mut String, columns: &[u8]) {
+    let mut rest = columns;
+    while let Some(&bits) = rest.first() {
+        let run = rest.iter().take_while(|&&other| other == bits).count();
+        let sixel = char::from(b'?' + bits);
+        if run >= MIN_RUN {
+            let _ = write!(out, "!{run}{sixel}");
+        } else {
+            out.extend(std::iter::repeat_n(sixel, run));
+        }
+        rest = &rest[run..];
+    }
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use image::Rgb;
+
+    use super::*;
+
+    #[test]
+    fn test_encodes_bands_and_runs() {
+        // Two columns, seven rows: a red first band with a white pixel at the bottom right, and
+        // a second band of one white row.
+        let image = RgbImage::from_fn(2, 7, |x, y| {
+            if y == 6 || (x, y) == (1, 5) {
+                Rgb([255, 255, 255])
+            } else {
+                Rgb([255, 0, 0])
+            }
+        });
+        let sixel = encode_sixel(&image);
+        assert_eq!(sixel, "\x1bPq\"1;1;2;7#0;2;100;0;0#1;2;100;100;100#0~^$#1?_-#1@@-\x1b\\");
+
+        let mut runs = String::new();
+        write_runs(&mut runs, &[0, 0, 0, 0, 63, 1, 1]);
+        assert_eq!(runs, "!4?~@@");
+    }
+
+    #[test]
+    fn test_many_colors_fall_back_to_the_cube() {
+        let image = RgbImage::from_fn(40, 12, |x, y| Rgb([x as u8 * 6, y as u8 * 20, 128]));
+        let sixel = encode_sixel(&image);
+        assert!(sixel.contains("#215;2;100;100;100"));
+        assert!(!sixel.contains("#216"));
+        assert_eq!(sixel.matches('-').count(), 2);
+    }
+}
@@ -0,0 +1,168 @@
+//! Statistics of the escape counts of a render, for tuning it before the final one.
+//!
+//! A small render says most of what matters about the iteration limit of a big one of the same
+//! view: how much of it is inside the set, how the escape counts are spread, and whether pixels
+//! still escape close to the limit, which is the sign that it is too low. `RenderStats` gathers
+//! these, along with the iteration limits `viewport::Viewport::suggested_iterations` and
+//! `analysis::refine_iterations` suggest. It serializes to JSON for scripts.
+
+use serde::Serialize;
+
+use crate::{
+    analysis::{refine_iterations, EscapeResult},
+    histogram::Histogram,
+    options::RenderOptions,
+    viewport::Viewport,
+};
+
+/// Number of bins of `RenderStats::histogram` unless another one is given.
+pub const DEFAULT_BINS: u32 = 10;
+
+/// Share of the escaping pixels that `SuggestedIterations::lowest` keeps escaping.
+pub const KEPT_FRACTION: f64 = 0.999;
+
+/// Escaping pixels whose iteration counts fall in `start..end`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Bin {
+    pub start: u32,
+    pub end: u32,
+    pub count: u64,
+}
+
+/// Iteration limits suggested for a render.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct SuggestedIterations {
+    /// The rule of thumb of `Viewport::suggested_iterations`, from the zoom alone.
+    pub zoom: u32,
+    /// The limit of `analysis::refine_iterations`, raised while raising it shows more detail.
+    pub refined: u32,
+    /// Lowest limit at which `KEPT_FRACTION` of the escaping pixels still escape, below which
+    /// lowering it would visibly grow the set. `0` if no pixel escaped.
+    pub lowest: u32,
+}
+
+/// Statistics of the escape counts of a `width` x `height` render.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RenderStats {
+    pub width: u32,
+    pub height: u32,
+    pub iterations: u32,
+    /// Pixels that did not escape within `iterations`.
+    pub inside: u64,
+    /// Mean iteration count of the escaping pixels, `0.0` if none escaped.
+    pub mean_iterations: f64,
+    /// Largest iteration count of an escaping pixel, `0` if none escaped.
+    pub max_iterations: u32,
+    /// Escaping pixels in bins of equal width from `0` to `iterations`.
+    pub histogram: Vec<Bin>,
+    pub suggested: SuggestedIterations,
+}
+
+impl RenderStats {
+    /// Gathers the statistics of `escapes`, a `width` x `height` render of `viewport` with
+    /// `opts`, with the histogram in `bins` bins, or fewer if there are fewer iterations. The
+    /// refined suggestion renders a small preview of `viewport` at increasing limits.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::{analysis::escape_viewport, prelude::*, stats::RenderStats};
+    ///
+    /// let (viewport, opts) = (Viewport::default(), RenderOptions::new(100));
+    /// let escapes = escape_viewport(70, 40, &viewport, &opts);
+    /// let stats = RenderStats::from_escapes(70, 40, &escapes, &viewport, &opts, 4);
+    /// assert!(stats.inside > 0 && stats.inside < 70 * 40);
+    /// assert_eq!(stats.histogram.len(), 4);
+    /// assert_eq!(stats.histogram.iter().map(|bin| bin.count).sum::<u64>(), 70 * 40 - stats.inside);
+    /// ```
+    pub fn from_escapes(
+        width: u32,
+        height: u32,
+        escapes: &[EscapeResult],
+        viewport: &Viewport,
+        opts: &RenderOptions,
+        bins: u32,
+    ) -> Self {
+        let iterations = opts.iterations;
+        let histogram = Histogram::from_escapes(escapes, iterations);
+        let counts = histogram.counts();
+        let escaped = histogram.total();
+
+        let bin_width = iterations.div_ceil(bins.max(1)).max(1);
+        let histogram_bins = (0..iterations)
+            .step_by(bin_width as usize)
+            .map(|start| {
+                let end = start.saturating_add(bin_width).min(iterations);
+                let count = counts[start as usize..end as usize].iter().sum();
+                Bin { start, end, count }
+            })
+            .collect();
+
+        let weighted: f64 = counts.iter().enumerate().map(|(i, &n)| i as f64 * n as f64).sum();
+        let mean_iterations = if escaped > 0 { weighted / escaped as f64 } else { 0.0 };
+        let max_iterations = counts.iter().rposition(|&n| n > 0).unwrap_or(0) as u32;
+
+        // Pixels escape once their count is below the limit, so the limit is one past it.
+        let kept = (KEPT_FRACTION * escaped as f64).ceil() as u64;
+        let lowest = counts
+            .iter()
+            .scan(0, |total, &n| {
+                *total += n;
+                Some(*total)
+            })
+            .position(|total| escaped > 0 && total >= kept)
+            .map_or(0, |i| i as u32 + 1);
+
+        Self {
+            width,
+            height,
+            iterations,
+            inside: escapes.len() as u64 - escaped,
+            mean_iterations,
+            max_iterations,
+            histogram: histogram_bins,
+            suggested: SuggestedIterations {
+                zoom: viewport.suggested_iterations(),
+                refined: refine_iterations(width, height, viewport, opts),
+                lowest,
+            },
+        }
+    }
+
+    /// Number of pixels of the render.
+    pub fn pixels(&self) -> u64 {
+        self.width as u64 * self.height as u64
+    }
+
+    /// Share of the pixels inside the set, between `0.0` and `1.0`.
+    pub fn inside_fraction(&self) -> f64 {
+        if self.pixels() == 0 {
+            0.0
+        } else {
+            self.inside as f64 / self.pixels() as f64
+        }
+    }
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::escape_viewport;
+
+    #[test]
+    fn test_stats_add_up_and_suggest_more_iterations_for_deep_views() {
+        let viewport = Viewport::new(-0.743643887037151, 0.13182590420533, 5000.0);
+        let opts = RenderOptions::new(50);
+        let escapes = escape_viewport(60, 40, &viewport, &opts);
+        let stats = RenderStats::from_escapes(60, 40, &escapes, &viewport, &opts, 7);
+        assert_eq!(stats.histogram.first().map(|bin| bin.start), Some(0));
+        assert_eq!(stats.histogram.last().map(|bin| bin.end), Some(50));
+        let escaped: u64 = stats.histogram.iter().map(|bin| bin.count).sum();
+        assert_eq!(escaped + stats.inside, stats.pixels());
+        assert!(stats.max_iterations < 50 && stats.mean_iterations <= stats.max_iterations as f64);
+        assert!(stats.suggested.lowest <= stats.max_iterations + 1);
+        // Fifty iterations are far too few at this zoom.
+        assert!(stats.suggested.refined > 50 && stats.suggested.zoom > 50);
+    }
+}
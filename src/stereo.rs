@@ -0,0 +1,178 @@
+//! Stereoscopic 3D output, treating the smooth escape value as a heightfield.
+//!
+//! The continuous escape value of `mandelbrot_smooth` is normalized into a height between `0.0`
+//! (escapes immediately) and `1.0` (inside the set), and lit with hillshading so the relief is
+//! visible in each view. The two eye views are made by shifting every pixel horizontally in
+//! proportion to its height, up to `StereoOptions::parallax` pixels between the eyes, and are
+//! either placed next to each other for parallel viewing or combined into a red-cyan anaglyph.
+
+use image::{ImageBuffer, Rgb, RgbImage};
+
+use crate::{mandelbrot_img::mandelbrot_smooth, scheduler::render_grid, viewport::Viewport};
+
+/// Default maximum horizontal shift between the two eye views, in pixels.
+pub const DEFAULT_PARALLAX: f64 = 12.0;
+
+/// Direction the light comes from, in degrees clockwise from the top of the image.
+pub const LIGHT_AZIMUTH: f64 = 315.0;
+/// Angle of the light above the horizon, in degrees.
+pub const LIGHT_ALTITUDE: f64 = 45.0;
+
+/// How the two eye views are combined into one image.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StereoLayout {
+    /// Left view on the left, right view on the right, at twice the width.
+    SideBySide,
+    /// Left view in the red channel, right view in the green and blue channels.
+    #[default]
+    Anaglyph,
+}
+
+impl StereoLayout {
+    /// Parses the names used on the command line: `side-by-side` and `anaglyph`.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "side-by-side" => Some(Self::SideBySide),
+            "anaglyph" => Some(Self::Anaglyph),
+            _ => None,
+        }
+    }
+}
+
+/// Settings for `compose_stereo`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StereoOptions {
+    pub layout: StereoLayout,
+    /// Horizontal shift between the eye views of the highest points, in pixels.
+    pub parallax: f64,
+}
+
+impl Default for StereoOptions {
+    fn default() -> Self {
+        Self { layout: StereoLayout::default(), parallax: DEFAULT_PARALLAX }
+    }
+}
+
+/// Heights between `0.0` and `1.0` of a `width` x `height` grid over `viewport`, in row-major
+/// order. Points inside the set have a height of `1.0`.
+pub fn heightfield(width: u32, height: u32, iterations: u32, viewport: &Viewport) -> Vec<f64> {
+    render_grid(width, height, |x, y| {
+        let c = viewport.to_complex_num(x, y, width, height);
+        (mandelbrot_smooth(c, iterations, 2.0) / iterations as f64).min(1.0)
+    })
+}
+
+/// Hillshades `heights` of a `width` x `height` grid with a light from `LIGHT_AZIMUTH` and
+/// `LIGHT_ALTITUDE`. Returns brightness values between `0.0` and `1.0`.
+///
+/// `relief` scales the heights before the slopes are computed; larger values exaggerate the
+/// terrain.
+pub fn hillshade(heights: &[f64], width: u32, height: u32, relief: f64) -> Vec<f64> {
+    let (w, h) = (width as i64, height as i64);
+    let at = |x: i64, y: i64| heights[(y.clamp(0, h - 1) * w + x.clamp(0, w - 1)) as usize];
+    let azimuth = LIGHT_AZIMUTH.to_radians();
+    let altitude = LIGHT_ALTITUDE.to_radians();
+    let light = (azimuth.sin() * altitude.cos(), -azimuth.cos() * altitude.cos(), altitude.sin());
+    render_grid(width, height, |x, y| {
+        let (x, y) = (x as i64, y as i64);
+        let dx = (at(x + 1, y) - at(x - 1, y)) * relief / 2.0;
+        let dy = (at(x, y + 1) - at(x, y - 1)) * relief / 2.0;
+        // Normal of the surface z = relief * height(x, y).
+        let len = (dx * dx + dy * dy + 1.0).sqrt();
+        let normal = (-dx / len, -dy / len, 1.0 / len);
+        (normal.0 * light.0 + normal.1 * light.1 + normal.2 * light.2).max(0.0)
+    })
+}
+
+/// Renders the set over `viewport` as a stereo pair or anaglyph.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::{
+///     stereo::{compose_stereo, StereoLayout, StereoOptions},
+///     viewport::Viewport,
+/// };
+///
+/// let opts = StereoOptions { layout: StereoLayout::SideBySide, ..Default::default() };
+/// let pair = compose_stereo(64, 48, 100, &Viewport::default(), &opts);
+/// assert_eq!((pair.width(), pair.height()), (128, 48));
+/// ```
+pub fn compose_stereo(
+    width: u32,
+    height: u32,
+    iterations: u32,
+    viewport: &Viewport,
+    opts: &StereoOptions,
+) -> RgbImage {
+    let heights = heightfield(width, height, iterations, viewport);
+    // Scale the relief with the size of the image so shading looks the same at any resolution.
+    let shade = hillshade(&heights, width, height, width.min(height) as f64 / 4.0);
+    // Near points appear further right to the left eye than to the right eye.
+    let left = eye_view(&heights, &shade, width, height, opts.parallax / 2.0);
+    let right = eye_view(&heights, &shade, width, height, -opts.parallax / 2.0);
+    let luma = |value: f64| (value * 255.0).round() as u8;
+    match opts.layout {
+        StereoLayout::SideBySide => ImageBuffer::from_fn(width * 2, height, |x, y| {
+            let value = if x < width {
+                left[(y * width + x) as usize]
+            } else {
+                right[(y * width + x - width) as usize]
+            };
+            Rgb([luma(value); 3])
+        }),
+        StereoLayout::Anaglyph => ImageBuffer::from_fn(width, height, |x, y| {
+            let index = (y * width + x) as usize;
+            let (red, cyan) = (luma(left[index]), luma(right[index]));
+            Rgb([red, cyan, cyan])
+        }),
+    }
+}
+
+/// Shifts every pixel of `shade` horizontally by `shift * height` pixels. Where several pixels
+/// land on the same spot the highest one wins, so near points cover the ones behind them.
+fn eye_view(heights: &[f64], shade: &[f64], width: u32, height: u32, shift: f64) -> Vec<f64> {
+    let mut view = shade.to_vec();
+    let mut depth = vec![f64::NEG_INFINITY; view.len()];
+    for y in 0..height as usize {
+        let row = y * width as usize;
+        for x in 0..width as usize {
+            let h = heights[row + x];
+            let target = (x as f64 + shift * h).round();
+            if target < 0.0 || target >= width as f64 {
+                continue;
+            }
+            let target = row + target as usize;
+            if h >= depth[target] {
+                depth[target] = h;
+                view[target] = shade[row + x];
+            }
+        }
+    }
+    view
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hillshade_flat_ground_faces_light() {
+        let flat = vec![0.5; 16];
+        let shade = hillshade(&flat, 4, 4, 10.0);
+        let expected = LIGHT_ALTITUDE.to_radians().sin();
+        assert!(shade.iter().all(|s| (s - expected).abs() < 1e-12));
+    }
+
+    #[test]
+    fn test_eye_views_differ_only_with_parallax() {
+        let viewport = Viewport::default();
+        let flat = StereoOptions { parallax: 0.0, ..Default::default() };
+        let image = compose_stereo(80, 60, 100, &viewport, &flat);
+        assert!(image.pixels().all(|p| p[0] == p[1] && p[1] == p[2]));
+
+        let image = compose_stereo(80, 60, 100, &viewport, &StereoOptions::default());
+        assert!(image.pixels().any(|p| p[0] != p[1]));
+    }
+}
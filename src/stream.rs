@@ -0,0 +1,203 @@
+//! Renders a viewport a few rows at a time, for images too large to hold in memory.
+//!
+//! A 32768x32768 poster is three gigabytes as an RGB image, and its escape results take several
+//! times that. `RowStream` instead iterates the rows of a render in order, computing `BATCH_ROWS`
+//! of them at once so the `parallel` feature still has work to spread across threads, and keeps
+//! only the current batch. With the `image` feature `RowStream::write_png` feeds the rows
+//! straight into a PNG encoder, so a render of any size needs memory for one batch of rows.
+//!
+//! Rows are always iterated with `f64` arithmetic, see `analysis::escape`; the arbitrary
+//! precision kernels need the whole image at once.
+
+use std::collections::VecDeque;
+#[cfg(feature = "image")]
+use std::io::Write;
+
+#[cfg(feature = "image")]
+use image::{
+    error::{EncodingError, ImageFormatHint},
+    ImageError, ImageFormat, ImageResult,
+};
+use num_complex::Complex64;
+
+use crate::{
+    analysis::{escape, EscapeResult},
+    options::RenderOptions,
+    scheduler::render_grid,
+    viewport::Viewport,
+};
+#[cfg(feature = "image")]
+use crate::{
+    mandelbrot_img::shade_escapes,
+    palette::{colorize_escapes, Palette},
+};
+
+/// Number of rows rendered together.
+pub const BATCH_ROWS: u32 = 16;
+
+/// An iterator over the rows of a render, top to bottom, each as the escape results of its
+/// pixels from left to right.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::{analysis::escape_viewport, prelude::*, stream::RowStream};
+///
+/// let opts = RenderOptions::new(100);
+/// let rows: Vec<_> = RowStream::new(64, 40, Viewport::default(), opts).collect();
+/// assert_eq!(rows.len(), 40);
+/// assert_eq!(rows.concat(), escape_viewport(64, 40, &Viewport::default(), &opts));
+/// ```
+#[derive(Debug, Clone)]
+pub struct RowStream {
+    width: u32,
+    height: u32,
+    viewport: Viewport,
+    opts: RenderOptions,
+    /// Index of the first row not rendered yet.
+    next_row: u32,
+    batch: VecDeque<Vec<EscapeResult>>,
+}
+
+impl RowStream {
+    pub fn new(width: u32, height: u32, viewport: Viewport, opts: RenderOptions) -> Self {
+        Self { width, height, viewport, opts, next_row: 0, batch: VecDeque::new() }
+    }
+
+    /// Starts the stream at `row` instead of the top, skipping the rows above it.
+    pub fn with_first_row(self, row: u32) -> Self {
+        Self { next_row: row.min(self.height), batch: VecDeque::new(), ..self }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Writes the rows as a PNG to `out`, shaded like `mandelbrot_img::compose_with_options`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the PNG cannot be encoded or written.
+    #[cfg(feature = "image")]
+    pub fn write_png<W: Write>(self, out: W) -> ImageResult<()> {
+        let (width, opts) = (self.width, self.opts);
+        self.write_png_rows(out, |row| shade_escapes(width, 1, row, &opts).into_raw())
+    }
+
+    /// Writes the rows as a PNG to `out`, colored with `palette` like
+    /// `palette::compose_with_palette`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the PNG cannot be encoded or written.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::{palette::BuiltinPalette, prelude::*, stream::RowStream};
+    ///
+    /// let stream = RowStream::new(64, 40, Viewport::default(), RenderOptions::new(100));
+    /// let mut png = Vec::new();
+    /// stream.write_png_with_palette(&mut png, &BuiltinPalette::Ocean).unwrap();
+    /// let image = image::load_from_memory(&png).unwrap();
+    /// assert_eq!((image.width(), image.height()), (64, 40));
+    /// ```
+    #[cfg(feature = "image")]
+    pub fn write_png_with_palette<W: Write, P: Palette + ?Sized>(
+        self,
+        out: W,
+        palette: &P,
+    ) -> ImageResult<()> {
+        let (width, opts) = (self.width, self.opts);
+        self.write_png_rows(out, |row| colorize_escapes(width, 1, row, &opts, palette).into_raw())
+    }
+
+    #[cfg(feature = "image")]
+    fn write_png_rows<W, F>(self, out: W, mut shade: F) -> ImageResult<()>
+    where
+        W: Write,
+        F: FnMut(&[EscapeResult]) -> Vec<u8>,
+    {
+        let mut encoder = png::Encoder::new(out, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(png_error)?;
+        let mut stream = writer.stream_writer().map_err(png_error)?;
+        for row in self {
+            stream.write_all(&shade(&row))?;
+        }
+        stream.finish().map_err(png_error)?;
+        writer.finish().map_err(png_error)
+    }
+}
+
+impl Iterator for RowStream {
+    type Item = Vec<EscapeResult>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.batch.is_empty() && self.next_row < self.height {
+            let (width, height, top) = (self.width, self.height, self.next_row);
+            let rows = BATCH_ROWS.min(height - top);
+            let results = render_grid(width, rows, |x, y| {
+                let (re, im) = self.viewport.to_complex_num(x, top + y, width, height);
+                escape(Complex64::new(re, im), &self.opts)
+            });
+            self.batch.extend(results.chunks(width.max(1) as usize).map(<[_]>::to_vec));
+            self.next_row += rows;
+        }
+        self.batch.pop_front()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = self.batch.len() + (self.height - self.next_row) as usize;
+        (left, Some(left))
+    }
+}
+
+impl ExactSizeIterator for RowStream {}
+
+#[cfg(feature = "image")]
+fn png_error(error: png::EncodingError) -> ImageError {
+    match error {
+        png::EncodingError::IoError(error) => ImageError::IoError(error),
+        error => ImageError::Encoding(EncodingError::new(
+            ImageFormatHint::Exact(ImageFormat::Png),
+            error,
+        )),
+    }
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "image")]
+    fn test_streamed_png_matches_composed_image() {
+        use crate::mandelbrot_img::compose_with_options;
+
+        // More rows than a batch, and a last batch that is only partly filled.
+        let (width, height) = (50, BATCH_ROWS * 2 + 5);
+        let viewport = Viewport::new(-0.745, 0.11, 30.0);
+        let opts = RenderOptions::new(200);
+        let mut png = Vec::new();
+        RowStream::new(width, height, viewport, opts).write_png(&mut png).unwrap();
+        let streamed = image::load_from_memory(&png).unwrap().to_rgb8();
+        assert_eq!(streamed, compose_with_options(width, height, &viewport, &opts));
+    }
+
+    #[test]
+    fn test_size_hint_counts_remaining_rows() {
+        let mut stream =
+            RowStream::new(8, BATCH_ROWS + 3, Viewport::default(), RenderOptions::new(20));
+        assert_eq!(stream.len(), BATCH_ROWS as usize + 3);
+        stream.next();
+        assert_eq!(stream.len(), BATCH_ROWS as usize + 2);
+        assert_eq!(stream.by_ref().count(), BATCH_ROWS as usize + 2);
+        assert_eq!(stream.next(), None);
+    }
+}
@@ -0,0 +1,197 @@
+//! Mariani-Silver subdivision, which fills rectangles whose border agrees without iterating them.
+//!
+//! The Mandelbrot set is connected and has no holes, and the bands of equal escape count around
+//! it are rings that enclose it. So when every pixel on the border of a rectangle has the same
+//! escape count, the pixels inside almost always have it too, and the rectangle is filled with
+//! the value of its border. Otherwise the rectangle is split in two along its longer side and
+//! both halves are checked the same way, down to `MIN_SIZE`, below which every pixel is
+//! evaluated. Views with large areas inside the set or far outside it render several times
+//! faster, because only the pixels along the edges of the bands are iterated.
+//!
+//! Features that fit entirely inside a rectangle, such as a copy of the set smaller than a
+//! rectangle that happens to be surrounded by a single band, are filled over, so subdivision is
+//! only used when asked for with `options::RenderStrategy::BoundaryTrace`. Filled pixels are
+//! copies of a border pixel, including its smooth escape value.
+//!
+//! The image is split into tiles of `TILE_SIZE` pixels that are subdivided independently, in
+//! parallel with the `parallel` feature.
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use crate::scheduler::{map_slice, RenderCancelled};
+
+/// Side of the tiles the image is split into before subdividing.
+pub const TILE_SIZE: u32 = 64;
+
+/// Rectangles with a side of this many pixels or fewer are evaluated pixel by pixel when their
+/// border does not agree.
+pub const MIN_SIZE: u32 = 4;
+
+/// Evaluates `f(x, y)` for the pixels of a `width` x `height` grid that subdivision needs and
+/// returns the values of every pixel in row-major order. Two values are treated as the same
+/// escape count if `same` returns `true` for them. `on_progress(done, total)` is called with the
+/// number of finished pixels after every tile, and the render stops once `cancel` is set.
+///
+/// # Errors
+///
+/// Returns `RenderCancelled` if the render was cancelled.
+///
+/// # Examples
+/// ```
+/// use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+///
+/// use mandelbrot::subdivision::try_render_subdivided;
+///
+/// let evaluated = AtomicU32::new(0);
+/// let count = |x: u32, _y: u32| {
+///     evaluated.fetch_add(1, Ordering::Relaxed);
+///     x / 32
+/// };
+/// let never = AtomicBool::new(false);
+/// let grid = try_render_subdivided(64, 64, count, |a, b| a == b, |_, _| {}, &never).unwrap();
+/// assert_eq!(grid, (0..64 * 64).map(|i| i % 64 / 32).collect::<Vec<_>>());
+/// assert!(evaluated.into_inner() < 64 * 64 / 2);
+/// ```
+pub fn try_render_subdivided<T, F, S, P>(
+    width: u32,
+    height: u32,
+    f: F,
+    same: S,
+    on_progress: P,
+    cancel: &AtomicBool,
+) -> Result<Vec<T>, RenderCancelled>
+where
+    T: Clone + Send + Sync,
+    F: Fn(u32, u32) -> T + Sync,
+    S: Fn(&T, &T) -> bool + Sync,
+    P: Fn(u64, u64) + Sync,
+{
+    let total = width as u64 * height as u64;
+    let (columns, rows) = (width.div_ceil(TILE_SIZE), height.div_ceil(TILE_SIZE));
+    let tiles: Vec<Rect> = (0..rows)
+        .flat_map(|row| (0..columns).map(move |column| (column, row)))
+        .map(|(column, row)| {
+            let (x, y) = (column * TILE_SIZE, row * TILE_SIZE);
+            Rect { x, y, width: TILE_SIZE.min(width - x), height: TILE_SIZE.min(height - y) }
+        })
+        .collect();
+    let done = AtomicU64::new(0);
+    let values: Option<Vec<Vec<T>>> = map_slice(&tiles, |tile| {
+        if cancel.load(Ordering::Relaxed) {
+            return None;
+        }
+        let values = subdivide(tile, &f, &same);
+        let pixels = tile.width as u64 * tile.height as u64;
+        on_progress(done.fetch_add(pixels, Ordering::Relaxed) + pixels, total);
+        Some(values)
+    })
+    .into_iter()
+    .collect();
+    let values = values.ok_or(RenderCancelled)?;
+
+    let mut grid = Vec::with_capacity(total as usize);
+    for (tile_row, tiles) in tiles.chunks(columns.max(1) as usize).enumerate() {
+        let tile_row = &values[tile_row * columns as usize..][..tiles.len()];
+        for y in 0..tiles[0].height as usize {
+            for (tile, values) in tiles.iter().zip(tile_row) {
+                let width = tile.width as usize;
+                grid.extend_from_slice(&values[y * width..(y + 1) * width]);
+            }
+        }
+    }
+    Ok(grid)
+}
+
+/// A rectangle of pixels.
+#[derive(Debug, Clone, Copy)]
+struct Rect {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+/// The values of every pixel of `tile`, in row-major order.
+fn subdivide<T, F, S>(tile: &Rect, f: &F, same: &S) -> Vec<T>
+where
+    T: Clone,
+    F: Fn(u32, u32) -> T,
+    S: Fn(&T, &T) -> bool,
+{
+    let width = tile.width as usize;
+    let mut cells: Vec<Option<T>> = vec![None; width * tile.height as usize];
+    let value = |cells: &mut Vec<Option<T>>, x: u32, y: u32| {
+        cells[y as usize * width + x as usize]
+            .get_or_insert_with(|| f(tile.x + x, tile.y + y))
+            .clone()
+    };
+    let mut pending = vec![Rect { x: 0, y: 0, ..*tile }];
+    while let Some(rect) = pending.pop() {
+        let (right, bottom) = (rect.x + rect.width - 1, rect.y + rect.height - 1);
+        let border = (rect.x..=right)
+            .flat_map(|x| [(x, rect.y), (x, bottom)])
+            .chain((rect.y..=bottom).flat_map(|y| [(rect.x, y), (right, y)]));
+        let first = value(&mut cells, rect.x, rect.y);
+        let mut uniform = true;
+        for (x, y) in border {
+            // Every border pixel is evaluated, so the halves of a split can reuse them.
+            uniform &= same(&value(&mut cells, x, y), &first);
+        }
+        if uniform {
+            for y in rect.y + 1..bottom {
+                for x in rect.x + 1..right {
+                    cells[y as usize * width + x as usize] = Some(first.clone());
+                }
+            }
+        } else if rect.width <= MIN_SIZE || rect.height <= MIN_SIZE {
+            for y in rect.y + 1..bottom {
+                for x in rect.x + 1..right {
+                    value(&mut cells, x, y);
+                }
+            }
+        } else if rect.width >= rect.height {
+            // The halves share the middle column, so its pixels are evaluated only once.
+            let middle = rect.width / 2;
+            pending.push(Rect { width: middle + 1, ..rect });
+            pending.push(Rect { x: rect.x + middle, width: rect.width - middle, ..rect });
+        } else {
+            let middle = rect.height / 2;
+            pending.push(Rect { height: middle + 1, ..rect });
+            pending.push(Rect { y: rect.y + middle, height: rect.height - middle, ..rect });
+        }
+    }
+    cells.into_iter().map(|cell| cell.expect("every pixel is evaluated or filled")).collect()
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        analysis::escape_viewport,
+        options::{RenderOptions, RenderStrategy},
+        viewport::Viewport,
+    };
+
+    #[test]
+    fn test_boundary_trace_matches_per_pixel_render() {
+        // A view with a large interior and smooth bands, and sizes that leave partial tiles.
+        let viewport = Viewport::new(-0.4, 0.1, 3.0);
+        let opts = RenderOptions::new(200).with_smooth(false);
+        let (width, height) = (150, 97);
+        let per_pixel = escape_viewport(width, height, &viewport, &opts);
+        let traced = escape_viewport(
+            width,
+            height,
+            &viewport,
+            &opts.with_strategy(RenderStrategy::BoundaryTrace),
+        );
+        assert_eq!(traced.len(), per_pixel.len());
+        let differing = traced
+            .iter()
+            .zip(&per_pixel)
+            .filter(|(a, b)| (a.iterations, a.escaped) != (b.iterations, b.escaped))
+            .count();
+        assert!(differing * 1000 < per_pixel.len(), "{differing} pixels differ");
+    }
+}
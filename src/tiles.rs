@@ -0,0 +1,257 @@
+//! Splits a render into a grid of tiles that are rendered on their own and stitched together.
+//!
+//! Every pixel of a tile is mapped onto the complex plane as the same pixel of the whole image
+//! would be, so the stitched image is identical to rendering it at once. Tiles can therefore be
+//! rendered by different runs, on different machines, and in any order. Each rendered tile is
+//! saved next to the image it belongs to, see `Tile::path`, with the parameters of the render,
+//! see `metadata::ImageMetadata::tile`, so an interrupted render resumes by rendering only the
+//! tiles that have no file of the same render yet.
+
+#[cfg(feature = "image")]
+use std::path::Path;
+use std::path::PathBuf;
+
+#[cfg(feature = "image")]
+use image::{imageops, ImageResult, RgbImage};
+use num_complex::Complex64;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    analysis::{escape, EscapeResult},
+    options::RenderOptions,
+    scheduler::render_grid,
+    viewport::Viewport,
+};
+
+/// How many tiles a render is split into across and down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileGrid {
+    pub columns: u32,
+    pub rows: u32,
+}
+
+impl Default for TileGrid {
+    fn default() -> Self {
+        Self { columns: 1, rows: 1 }
+    }
+}
+
+/// The pixels of one tile of a `TileGrid`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tile {
+    pub column: u32,
+    pub row: u32,
+    /// Left edge of the tile in the whole image.
+    pub x: u32,
+    /// Top edge of the tile in the whole image.
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl TileGrid {
+    pub fn new(columns: u32, rows: u32) -> Self {
+        Self { columns: columns.max(1), rows: rows.max(1) }
+    }
+
+    /// Parses a grid written as `COLUMNSxROWS`, like `4x3`.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::tiles::TileGrid;
+    ///
+    /// assert_eq!(TileGrid::parse("4x3"), Some(TileGrid::new(4, 3)));
+    /// assert_eq!(TileGrid::parse("0x3"), None);
+    /// ```
+    pub fn parse(grid: &str) -> Option<Self> {
+        let (columns, rows) = grid.split_once('x')?;
+        let (columns, rows) = (columns.trim().parse().ok()?, rows.trim().parse().ok()?);
+        (columns > 0 && rows > 0).then_some(Self { columns, rows })
+    }
+
+    /// Number of tiles in the grid.
+    pub fn len(&self) -> u64 {
+        self.columns as u64 * self.rows as u64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The tile at `column` and `row` of a `width` x `height` image, or `None` if the grid has no
+    /// such tile. Tiles differ in size by at most one pixel when the image does not divide evenly.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::tiles::TileGrid;
+    ///
+    /// let tile = TileGrid::new(3, 2).tile(2, 1, 800, 600).unwrap();
+    /// assert_eq!((tile.x, tile.y, tile.width, tile.height), (533, 300, 267, 300));
+    /// ```
+    pub fn tile(&self, column: u32, row: u32, width: u32, height: u32) -> Option<Tile> {
+        if column >= self.columns || row >= self.rows {
+            return None;
+        }
+        let edge =
+            |index: u32, count: u32, size: u32| (index as u64 * size as u64 / count as u64) as u32;
+        let (x, y) = (edge(column, self.columns, width), edge(row, self.rows, height));
+        Some(Tile {
+            column,
+            row,
+            x,
+            y,
+            width: edge(column + 1, self.columns, width) - x,
+            height: edge(row + 1, self.rows, height) - y,
+        })
+    }
+
+    /// Whether every tile of a `width` x `height` image has at least one pixel, which takes no
+    /// more columns and rows than the image has pixels across and down.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::tiles::TileGrid;
+    ///
+    /// assert!(TileGrid::new(8, 8).fits(8, 8));
+    /// assert!(!TileGrid::new(16, 16).fits(8, 8));
+    /// ```
+    pub fn fits(&self, width: u32, height: u32) -> bool {
+        self.columns <= width && self.rows <= height
+    }
+
+    /// Every tile of a `width` x `height` image, row by row.
+    pub fn tiles(&self, width: u32, height: u32) -> impl Iterator<Item = Tile> + '_ {
+        (0..self.rows).flat_map(move |row| {
+            (0..self.columns).filter_map(move |column| self.tile(column, row, width, height))
+        })
+    }
+}
+
+impl Tile {
+    /// The PNG file a tile of the image at `path` is saved to, the path with its extension
+    /// replaced by `.tile-COLUMN-ROW.png`. Tiles are PNG images whatever the format of the image,
+    /// so they can carry the parameters of the render.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::path::PathBuf;
+    ///
+    /// use mandelbrot::tiles::TileGrid;
+    ///
+    /// let tile = TileGrid::new(4, 4).tile(1, 3, 800, 800).unwrap();
+    /// assert_eq!(tile.path("out/poster.png"), PathBuf::from("out/poster.tile-1-3.png"));
+    /// assert_eq!(tile.path("out/poster.jpg"), PathBuf::from("out/poster.tile-1-3.png"));
+    /// ```
+    pub fn path(&self, path: impl Into<PathBuf>) -> PathBuf {
+        let mut path = path.into();
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+        path.set_file_name(format!("{stem}.tile-{}-{}.png", self.column, self.row));
+        path
+    }
+}
+
+/// Renders the escape results of `tile` of a `width` x `height` render of `viewport`, in
+/// row-major order within the tile. Like `stream::RowStream`, tiles are iterated with `f64`
+/// arithmetic.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::{
+///     analysis::escape_viewport,
+///     prelude::*,
+///     tiles::{render_tile, TileGrid},
+/// };
+///
+/// let (viewport, opts) = (Viewport::default(), RenderOptions::new(100));
+/// let tile = TileGrid::new(2, 2).tile(1, 0, 40, 30).unwrap();
+/// let escapes = render_tile(40, 30, &viewport, &opts, &tile);
+/// let whole = escape_viewport(40, 30, &viewport, &opts);
+/// assert_eq!(escapes[..20], whole[20..40]);
+/// ```
+pub fn render_tile(
+    width: u32,
+    height: u32,
+    viewport: &Viewport,
+    opts: &RenderOptions,
+    tile: &Tile,
+) -> Vec<EscapeResult> {
+    render_grid(tile.width, tile.height, |x, y| {
+        let (re, im) = viewport.to_complex_num(tile.x + x, tile.y + y, width, height);
+        escape(Complex64::new(re, im), opts)
+    })
+}
+
+/// Copies the image of every tile into a `width` x `height` image at the tile's position.
+#[cfg(feature = "image")]
+pub fn stitch<I>(width: u32, height: u32, tiles: I) -> RgbImage
+where
+    I: IntoIterator<Item = (Tile, RgbImage)>,
+{
+    let mut image = RgbImage::new(width, height);
+    for (tile, pixels) in tiles {
+        imageops::replace(&mut image, &pixels, tile.x as i64, tile.y as i64);
+    }
+    image
+}
+
+/// Stitches the tiles of `grid` saved next to the image at `path`, see `Tile::path`.
+///
+/// # Errors
+///
+/// Returns an error if a tile file is missing or cannot be decoded.
+#[cfg(feature = "image")]
+pub fn stitch_files(
+    width: u32,
+    height: u32,
+    grid: &TileGrid,
+    path: impl AsRef<Path>,
+) -> ImageResult<RgbImage> {
+    let tiles = grid
+        .tiles(width, height)
+        .map(|tile| Ok((tile, image::open(tile.path(path.as_ref()))?.to_rgb8())))
+        .collect::<ImageResult<Vec<_>>>()?;
+    Ok(stitch(width, height, tiles))
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tiles_cover_the_image_once() {
+        let grid = TileGrid::new(3, 4);
+        let (width, height) = (101, 57);
+        let mut covered = vec![0; (width * height) as usize];
+        for tile in grid.tiles(width, height) {
+            for y in tile.y..tile.y + tile.height {
+                for x in tile.x..tile.x + tile.width {
+                    covered[(y * width + x) as usize] += 1;
+                }
+            }
+        }
+        assert_eq!(grid.tiles(width, height).count(), 12);
+        assert!(covered.iter().all(|&count| count == 1));
+        assert_eq!(grid.tile(3, 0, width, height), None);
+        assert!(grid.fits(3, 4) && !grid.fits(2, 57));
+        assert_eq!(TileGrid::new(70_000, 70_000).len(), 4_900_000_000);
+    }
+
+    #[test]
+    #[cfg(feature = "image")]
+    fn test_stitched_tiles_match_the_whole_image() {
+        use crate::mandelbrot_img::{compose_with_options, shade_escapes};
+
+        let (width, height) = (90, 70);
+        let viewport = Viewport::new(-0.745, 0.11, 30.0);
+        let opts = RenderOptions::new(200);
+        let grid = TileGrid::new(4, 3);
+        let tiles = grid.tiles(width, height).map(|tile| {
+            let escapes = render_tile(width, height, &viewport, &opts, &tile);
+            (tile, shade_escapes(tile.width, tile.height, &escapes, &opts))
+        });
+        let stitched = stitch(width, height, tiles);
+        assert_eq!(stitched, compose_with_options(width, height, &viewport, &opts));
+    }
+}
@@ -0,0 +1,196 @@
+//! Orbit traps, which color a point by how close its orbit comes to a shape.
+//!
+//! While a point is iterated, the distance of every orbit point to the trap is measured and the
+//! smallest one is kept. Escape-time coloring only looks at when the orbit leaves, so traps show
+//! structure escape time cannot: points that pass near the origin light up as rings and eyes, and
+//! orbits that graze the axes draw crosses and filaments. Traps are selected with
+//! `options::ColorMode::OrbitTrap` and shaded with `mandelbrot_img::trap_value`.
+
+use std::sync::atomic::AtomicBool;
+
+use num_complex::Complex64;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    options::RenderOptions,
+    scheduler::{try_render_grid, RenderCancelled},
+    viewport::Viewport,
+};
+
+/// Radius of `TrapShape::Circle`.
+pub const CIRCLE_TRAP_RADIUS: f64 = 0.5;
+
+/// The shape an orbit is trapped by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TrapShape {
+    /// The origin.
+    #[default]
+    Point,
+    /// The real axis.
+    Line,
+    /// The circle of `CIRCLE_TRAP_RADIUS` around the origin.
+    Circle,
+    /// Both axes.
+    Cross,
+}
+
+impl TrapShape {
+    pub const ALL: [Self; 4] = [Self::Point, Self::Line, Self::Circle, Self::Cross];
+
+    /// Parses the names returned by `name`, ignoring case.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::traps::TrapShape;
+    ///
+    /// assert_eq!(TrapShape::parse("Cross"), Some(TrapShape::Cross));
+    /// assert_eq!(TrapShape::parse("square"), None);
+    /// ```
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|shape| shape.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Point => "point",
+            Self::Line => "line",
+            Self::Circle => "circle",
+            Self::Cross => "cross",
+        }
+    }
+
+    /// Distance from `z` to the shape.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::traps::TrapShape;
+    /// use num_complex::Complex64;
+    ///
+    /// let z = Complex64::new(0.3, -0.4);
+    /// assert_eq!(TrapShape::Point.distance(z), 0.5);
+    /// assert_eq!(TrapShape::Circle.distance(z), 0.0);
+    /// assert_eq!(TrapShape::Cross.distance(z), 0.3);
+    /// ```
+    pub fn distance(&self, z: Complex64) -> f64 {
+        match self {
+            Self::Point => z.norm(),
+            Self::Line => z.im.abs(),
+            Self::Circle => (z.norm() - CIRCLE_TRAP_RADIUS).abs(),
+            Self::Cross => z.re.abs().min(z.im.abs()),
+        }
+    }
+}
+
+/// Smallest distance between `shape` and the orbit of `c` under the iteration of `opts.kind`,
+/// over every orbit point up to the one that leaves the bailout radius, or up to
+/// `opts.iterations` points if the orbit does not escape.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::{
+///     options::RenderOptions,
+///     traps::{trap_distance, TrapShape},
+/// };
+/// use num_complex::Complex64;
+///
+/// // The orbit of -1 alternates between -1 and 0.
+/// let d = trap_distance(Complex64::new(-1.0, 0.0), &RenderOptions::new(100), TrapShape::Point);
+/// assert_eq!(d, 0.0);
+/// ```
+pub fn trap_distance(c: Complex64, opts: &RenderOptions, shape: TrapShape) -> f64 {
+    let bailout = opts.bailout.max(opts.kind.escape_radius());
+    let bailout_sqr = bailout * bailout;
+    let mut z = Complex64::new(0.0, 0.0);
+    let mut nearest = f64::INFINITY;
+    for _ in 0..opts.iterations {
+        z = opts.kind.step(z, c);
+        nearest = nearest.min(shape.distance(z));
+        if z.norm_sqr() > bailout_sqr {
+            break;
+        }
+    }
+    nearest
+}
+
+/// Runs `trap_distance` for every pixel of a `width` x `height` grid over `viewport`, in
+/// row-major order. Always uses `f64` arithmetic.
+pub fn trap_viewport(
+    width: u32,
+    height: u32,
+    viewport: &Viewport,
+    opts: &RenderOptions,
+    shape: TrapShape,
+) -> Vec<f64> {
+    trap_viewport_with_progress(width, height, viewport, opts, shape, |_, _| {})
+}
+
+/// Like `trap_viewport`, but calls `on_progress(done, total)` with the number of evaluated
+/// pixels after every finished row.
+pub fn trap_viewport_with_progress<P>(
+    width: u32,
+    height: u32,
+    viewport: &Viewport,
+    opts: &RenderOptions,
+    shape: TrapShape,
+    on_progress: P,
+) -> Vec<f64>
+where
+    P: Fn(u64, u64) + Sync,
+{
+    let never = AtomicBool::new(false);
+    match try_trap_viewport(width, height, viewport, opts, shape, on_progress, &never) {
+        Ok(distances) => distances,
+        Err(RenderCancelled) => unreachable!("the render was never cancelled"),
+    }
+}
+
+/// Like `trap_viewport_with_progress`, but stops once `cancel` is set, see
+/// `scheduler::try_render_grid`.
+///
+/// # Errors
+///
+/// Returns `RenderCancelled` if the render was cancelled.
+pub fn try_trap_viewport<P>(
+    width: u32,
+    height: u32,
+    viewport: &Viewport,
+    opts: &RenderOptions,
+    shape: TrapShape,
+    on_progress: P,
+    cancel: &AtomicBool,
+) -> Result<Vec<f64>, RenderCancelled>
+where
+    P: Fn(u64, u64) + Sync,
+{
+    try_render_grid(
+        width,
+        height,
+        |x, y| trap_distance(viewport.point(x, y, width, height), opts, shape),
+        on_progress,
+        cancel,
+    )
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escaping_orbits_stop_at_the_bailout() {
+        let opts = RenderOptions::new(1000);
+        // The orbit of 1 + i escapes at 1 + 3i, so 1 + i itself is the nearest point.
+        let c = Complex64::new(1.0, 1.0);
+        assert_eq!(trap_distance(c, &opts, TrapShape::Line), 1.0);
+        assert_eq!(trap_distance(c, &opts, TrapShape::Point), 2.0_f64.sqrt());
+
+        // Shapes only ever bring the orbit closer as the limit grows.
+        let c = Complex64::new(-0.745, 0.11);
+        for shape in TrapShape::ALL {
+            let short = trap_distance(c, &RenderOptions::new(10), shape);
+            let long = trap_distance(c, &opts, shape);
+            assert!(long <= short);
+        }
+    }
+}
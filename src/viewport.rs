@@ -0,0 +1,656 @@
+//! A `Viewport` describes which region of the complex plane is mapped onto the output grid.
+//!
+//! The region is stored as a center point and a zoom factor. At a zoom of `1.0` the viewport
+//! spans `BASE_WIDTH` along the real axis and `BASE_HEIGHT` along the imaginary axis, which is the
+//! classic `[-2.5, 1.0] x [-1.0, 1.0]` region used by `to_complex_num`. Doubling the zoom halves
+//! both spans around the same center.
+//!
+//! The default viewport, `Viewport::default_set`, is centered on the set and zoomed out far
+//! enough to show all of it. It and the other presets, such as `Viewport::seahorse_valley`, can
+//! be looked up by name with `Viewport::preset`.
+//!
+//! A grid whose width to height ratio differs from `BASE_WIDTH` to `BASE_HEIGHT` cannot show
+//! exactly that region without stretching the set. The `FitMode` of a viewport decides which
+//! spans a grid of a given size covers, see `Viewport::spans`: by default one axis is widened so
+//! the whole region stays visible and pixels stay square.
+//!
+//! An `f64` center can only be moved in steps of about `1e-16` times its magnitude, so past a
+//! zoom of roughly `1e13` neighbouring pixels collapse onto the same coordinates. The
+//! `Precision` of a viewport says which arithmetic renders should use; `Precision::Auto` picks
+//! `f64` while it can still tell the pixels apart and perturbation around an arbitrary-precision
+//! reference orbit (with the `bigfloat` feature) beyond that.
+
+use num_complex::Complex64;
+use serde::{Deserialize, Serialize};
+
+use crate::complex::IntoComplex;
+
+/// Span of the real axis covered by a viewport with a zoom of `1.0`.
+pub const BASE_WIDTH: f64 = 3.5;
+/// Span of the imaginary axis covered by a viewport with a zoom of `1.0`.
+pub const BASE_HEIGHT: f64 = 2.0;
+
+/// Names of the viewports returned by `Viewport::preset`.
+pub const PRESETS: [&str; 6] = [
+    "default",
+    "classic",
+    "seahorse-valley",
+    "elephant-valley",
+    "triple-spiral-valley",
+    "mini-mandelbrot",
+];
+
+/// Smallest pixel size, relative to the magnitude of the coordinates, that `f64` renders still
+/// resolve; `Precision::Auto` switches to arbitrary precision below it.
+pub const F64_RELATIVE_PIXEL: f64 = 1e-13;
+
+/// Decimal digits kept beyond the pixel size so rounding errors do not reach the image.
+pub const GUARD_DIGITS: usize = 10;
+
+/// Iteration limit `Viewport::suggested_iterations` gives viewports at a zoom of `1.0` or less.
+pub const BASE_ITERATIONS: f64 = 250.0;
+
+/// Arithmetic used to iterate the points of a viewport.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Precision {
+    /// `Double` while it resolves the pixels, `Perturbation` after.
+    #[default]
+    Auto,
+    /// Hardware `f64` arithmetic.
+    Double,
+    /// Decimal floats with `digits` significant digits for every pixel, see
+    /// `bigfloat::mandelbrot_arbitrary`. Exact but slow.
+    Arbitrary { digits: usize },
+    /// `f64` offsets from a reference orbit computed with `Viewport::required_digits`, see
+    /// `perturbation::render_perturbed`.
+    Perturbation,
+}
+
+impl Precision {
+    /// Parses the names used on the command line: `auto`, `double`, `perturbation`, or a number
+    /// of digits for `Arbitrary`.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::viewport::Precision;
+    ///
+    /// assert_eq!(Precision::parse("double"), Some(Precision::Double));
+    /// assert_eq!(Precision::parse("60"), Some(Precision::Arbitrary { digits: 60 }));
+    /// assert_eq!(Precision::parse("0"), None);
+    /// ```
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "auto" => Some(Self::Auto),
+            "double" => Some(Self::Double),
+            "perturbation" => Some(Self::Perturbation),
+            digits => match digits.parse() {
+                Ok(0) | Err(_) => None,
+                Ok(digits) => Some(Self::Arbitrary { digits }),
+            },
+        }
+    }
+
+    pub fn is_auto(&self) -> bool {
+        *self == Self::Auto
+    }
+}
+
+/// How a viewport is mapped onto a grid whose aspect ratio is not `BASE_WIDTH` to `BASE_HEIGHT`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FitMode {
+    /// The axis along which the grid is relatively longer is widened, so the whole region is
+    /// shown with square pixels, letterboxed by more of the plane.
+    #[default]
+    Contain,
+    /// The axis along which the grid is relatively shorter is narrowed, so the region fills the
+    /// grid with square pixels and its edges are cropped.
+    Cover,
+    /// Both spans are kept and each is scaled to the grid on its own, which stretches the set.
+    /// This is how every grid was mapped before fit modes.
+    Stretch,
+}
+
+impl FitMode {
+    pub const ALL: [Self; 3] = [Self::Contain, Self::Cover, Self::Stretch];
+
+    /// Parses the names returned by `name`, ignoring case.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::viewport::FitMode;
+    ///
+    /// assert_eq!(FitMode::parse("Stretch"), Some(FitMode::Stretch));
+    /// assert_eq!(FitMode::parse("fill"), None);
+    /// ```
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|fit| fit.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Contain => "contain",
+            Self::Cover => "cover",
+            Self::Stretch => "stretch",
+        }
+    }
+
+    pub fn is_contain(&self) -> bool {
+        *self == Self::Contain
+    }
+}
+
+/// A region of the complex plane described by its center and zoom factor.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Viewport {
+    pub center_x: f64,
+    pub center_y: f64,
+    pub zoom: f64,
+    #[serde(default, skip_serializing_if = "Precision::is_auto")]
+    pub precision: Precision,
+    #[serde(default, skip_serializing_if = "FitMode::is_contain")]
+    pub fit: FitMode,
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Self::default_set()
+    }
+}
+
+impl Viewport {
+    pub fn new(center_x: f64, center_y: f64, zoom: f64) -> Self {
+        Self { center_x, center_y, zoom, precision: Precision::Auto, fit: FitMode::Contain }
+    }
+
+    /// The whole set, centered on the middle of its bounding box, `[-2.0, 0.47] x [-1.12, 1.12]`,
+    /// with a margin around it.
+    pub fn default_set() -> Self {
+        Self::new(-0.765, 0.0, 0.8)
+    }
+
+    /// The `[-2.5, 1.0] x [-1.0, 1.0]` region of `mandelbrot_img::to_complex_num`, which is left
+    /// of the center of the set and crops its top and bottom.
+    pub fn classic() -> Self {
+        Self::new(-0.75, 0.0, 1.0)
+    }
+
+    /// The valley between the main cardioid and the period-2 bulb, lined with seahorse tails.
+    pub fn seahorse_valley() -> Self {
+        Self::new(-0.745, 0.11, 30.0)
+    }
+
+    /// The valley at the cusp of the main cardioid, lined with spirals shaped like elephants.
+    pub fn elephant_valley() -> Self {
+        Self::new(0.275, 0.0, 25.0)
+    }
+
+    /// The valley between the main cardioid and the period-3 bulb on top of it.
+    pub fn triple_spiral_valley() -> Self {
+        Self::new(-0.088, 0.654, 40.0)
+    }
+
+    /// The largest copy of the set on the real axis, at the period-3 point of the antenna.
+    pub fn mini_mandelbrot() -> Self {
+        Self::new(-1.7549, 0.0, 60.0)
+    }
+
+    /// The preset named `name`, one of `PRESETS`, ignoring case.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::viewport::Viewport;
+    ///
+    /// assert_eq!(Viewport::preset("Seahorse-Valley"), Some(Viewport::seahorse_valley()));
+    /// assert_eq!(Viewport::preset("default"), Some(Viewport::default()));
+    /// assert_eq!(Viewport::preset("nowhere"), None);
+    /// ```
+    pub fn preset(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "default" => Some(Self::default_set()),
+            "classic" => Some(Self::classic()),
+            "seahorse-valley" => Some(Self::seahorse_valley()),
+            "elephant-valley" => Some(Self::elephant_valley()),
+            "triple-spiral-valley" => Some(Self::triple_spiral_valley()),
+            "mini-mandelbrot" => Some(Self::mini_mandelbrot()),
+            _ => None,
+        }
+    }
+
+    /// Returns a copy of the viewport rendered with `precision`.
+    pub fn with_precision(self, precision: Precision) -> Self {
+        Self { precision, ..self }
+    }
+
+    /// Returns a copy of the viewport fitted to grids with `fit`.
+    pub fn with_fit(self, fit: FitMode) -> Self {
+        Self { fit, ..self }
+    }
+
+    /// An iteration limit for renders of the viewport. Deeper zooms show structure that takes
+    /// longer to escape, so the limit grows with the number of decades of zoom `d` as
+    /// `BASE_ITERATIONS * (1 + d)^1.5`, a common rule of thumb. `analysis::refine_iterations`
+    /// raises it further where the view needs more.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::viewport::Viewport;
+    ///
+    /// assert_eq!(Viewport::default().suggested_iterations(), 250);
+    /// assert_eq!(Viewport::new(-0.745, 0.11, 1e6).suggested_iterations(), 4630);
+    /// ```
+    pub fn suggested_iterations(&self) -> u32 {
+        let decades = self.zoom.max(1.0).log10();
+        (BASE_ITERATIONS * (1.0 + decades).powf(1.5)).round().min(u32::MAX as f64) as u32
+    }
+
+    /// Whether the viewport is centered on the real axis. The Mandelbrot and Multibrot sets are
+    /// symmetric about it, so the bottom half of a render of such a viewport mirrors the top
+    /// half, see `options::RenderOptions::symmetry`.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::viewport::Viewport;
+    ///
+    /// assert!(Viewport::default().is_symmetric());
+    /// assert!(!Viewport::seahorse_valley().is_symmetric());
+    /// ```
+    pub fn is_symmetric(&self) -> bool {
+        self.center_y == 0.0
+    }
+
+    /// Significant decimal digits needed to address every pixel of a `width` x `height` render,
+    /// including `GUARD_DIGITS`.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::viewport::Viewport;
+    ///
+    /// assert_eq!(Viewport::default().required_digits(800, 800), 13);
+    /// assert_eq!(Viewport::new(-0.75, 0.1, 1e30).required_digits(800, 800), 43);
+    /// ```
+    pub fn required_digits(&self, width: u32, height: u32) -> usize {
+        (-self.relative_pixel(width, height).log10()).ceil().max(0.0) as usize + GUARD_DIGITS
+    }
+
+    /// The precision a `width` x `height` render of the viewport uses, with `Precision::Auto`
+    /// resolved to `Double` or `Perturbation`.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::viewport::{Precision, Viewport};
+    ///
+    /// assert_eq!(Viewport::default().resolved_precision(800, 800), Precision::Double);
+    /// let deep = Viewport::new(-0.75, 0.1, 1e20);
+    /// assert_eq!(deep.resolved_precision(800, 800), Precision::Perturbation);
+    /// ```
+    pub fn resolved_precision(&self, width: u32, height: u32) -> Precision {
+        if !self.precision.is_auto() {
+            return self.precision;
+        }
+        if self.relative_pixel(width, height) >= F64_RELATIVE_PIXEL {
+            Precision::Double
+        } else {
+            Precision::Perturbation
+        }
+    }
+
+    /// Size of the smaller side of a pixel relative to the magnitude of the center, which is
+    /// taken to be at least `1.0`.
+    fn relative_pixel(&self, width: u32, height: u32) -> f64 {
+        let (span_x, span_y) = self.spans(width, height);
+        let pixel = (span_x / width.max(1) as f64).min(span_y / height.max(1) as f64);
+        pixel / self.center_x.abs().max(self.center_y.abs()).max(1.0)
+    }
+
+    /// Smallest viewport that shows the whole rectangle `[min_x, max_x] x [min_y, max_y]`.
+    ///
+    /// The viewport keeps the `BASE_WIDTH` to `BASE_HEIGHT` aspect ratio, so if the rectangle has
+    /// a different shape the viewport extends past it along one axis.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::viewport::Viewport;
+    ///
+    /// assert_eq!(Viewport::from_bounds(-2.5, 1.0, -1.0, 1.0), Viewport::classic());
+    /// let square = Viewport::from_bounds(-1.0, 0.0, -0.5, 0.5);
+    /// assert_eq!(square.bounds(), (-1.375, 0.375, -0.5, 0.5));
+    /// ```
+    pub fn from_bounds(min_x: f64, max_x: f64, min_y: f64, max_y: f64) -> Self {
+        let zoom = (BASE_WIDTH / (max_x - min_x)).min(BASE_HEIGHT / (max_y - min_y));
+        Self::new((min_x + max_x) / 2.0, (min_y + max_y) / 2.0, zoom)
+    }
+
+    /// Region covered by the viewport as `(min_x, max_x, min_y, max_y)`, before it is fitted to a
+    /// grid.
+    pub fn bounds(&self) -> (f64, f64, f64, f64) {
+        let (half_width, half_height) = (self.width() / 2.0, self.height() / 2.0);
+        (
+            self.center_x - half_width,
+            self.center_x + half_width,
+            self.center_y - half_height,
+            self.center_y + half_height,
+        )
+    }
+
+    /// Span of the real axis covered by the viewport, before it is fitted to a grid.
+    pub fn width(&self) -> f64 {
+        BASE_WIDTH / self.zoom
+    }
+
+    /// Span of the imaginary axis covered by the viewport, before it is fitted to a grid.
+    pub fn height(&self) -> f64 {
+        BASE_HEIGHT / self.zoom
+    }
+
+    /// Spans of the real and imaginary axes covered by a `width` x `height` grid, which are
+    /// `width()` and `height()` fitted to the aspect ratio of the grid with `fit`.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::viewport::{FitMode, Viewport};
+    ///
+    /// let viewport = Viewport::classic();
+    /// assert_eq!(viewport.spans(800, 800), (3.5, 3.5));
+    /// assert_eq!(viewport.with_fit(FitMode::Cover).spans(800, 800), (2.0, 2.0));
+    /// assert_eq!(viewport.with_fit(FitMode::Stretch).spans(800, 800), (3.5, 2.0));
+    /// assert_eq!(viewport.spans(700, 400), (3.5, 2.0));
+    /// ```
+    pub fn spans(&self, width: u32, height: u32) -> (f64, f64) {
+        let (span_x, span_y) = (self.width(), self.height());
+        let aspect = width as f64 / height as f64;
+        let base_aspect = BASE_WIDTH / BASE_HEIGHT;
+        if self.fit == FitMode::Stretch || !aspect.is_normal() || aspect == base_aspect {
+            return (span_x, span_y);
+        }
+        match (self.fit, aspect > base_aspect) {
+            (FitMode::Contain, true) | (FitMode::Cover, false) => (span_y * aspect, span_y),
+            _ => (span_x, span_x / aspect),
+        }
+    }
+
+    /// Maps pixel coordinates of a `width` x `height` grid to complex plane coordinates, with the
+    /// grid covering `spans`. Pixels `y` and `height - y` map to exactly opposite offsets from
+    /// the center, so the rows of a symmetric viewport mirror each other bit for bit.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::viewport::{FitMode, Viewport};
+    ///
+    /// let c = Viewport::classic().to_complex_num(100, 200, 800, 800);
+    /// assert_eq!(c, (-2.0625, -0.875));
+    /// let stretched = Viewport::classic().with_fit(FitMode::Stretch);
+    /// assert_eq!(stretched.to_complex_num(100, 200, 800, 800), (-2.0625, -0.5));
+    /// ```
+    pub fn to_complex_num(&self, x: u32, y: u32, width: u32, height: u32) -> (f64, f64) {
+        let (span_x, span_y) = self.spans(width, height);
+        // The offset is computed from integers, so negating the pixel negates it exactly.
+        let offset = |pixel: u32, size: u32| {
+            (2 * pixel as i64 - size as i64) as f64 / (2 * size as i64) as f64
+        };
+        let cx = self.center_x + offset(x, width) * span_x;
+        let cy = self.center_y + offset(y, height) * span_y;
+        (cx, cy)
+    }
+
+    /// The point of the plane at pixel `(x, y)`, like `to_complex_num`.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::prelude::*;
+    ///
+    /// let c = Viewport::classic().point(100, 200, 800, 800);
+    /// assert_eq!(c, Complex64::new(-2.0625, -0.875));
+    /// ```
+    pub fn point(&self, x: u32, y: u32, width: u32, height: u32) -> Complex64 {
+        self.to_complex_num(x, y, width, height).into_complex()
+    }
+
+    /// The pixel position of `z` in a `width` x `height` grid, the inverse of `point`. The
+    /// position is fractional and lies outside `0..width` and `0..height` for points outside the
+    /// viewport.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::viewport::Viewport;
+    ///
+    /// let viewport = Viewport::default();
+    /// assert_eq!(viewport.pixel(viewport.point(100, 200, 800, 800), 800, 800), (100.0, 200.0));
+    /// ```
+    pub fn pixel(&self, z: impl IntoComplex, width: u32, height: u32) -> (f64, f64) {
+        let z = z.into_complex();
+        let (span_x, span_y) = self.spans(width, height);
+        let x = ((z.re - self.center_x) / span_x + 0.5) * width as f64;
+        let y = ((z.im - self.center_y) / span_y + 0.5) * height as f64;
+        (x, y)
+    }
+
+    /// The mapping between the pixels of a `width` x `height` grid and the points of the
+    /// viewport.
+    pub fn mapper(&self, width: u32, height: u32) -> CoordMapper {
+        CoordMapper::new(*self, width, height)
+    }
+
+    /// The center of the viewport.
+    pub fn center(&self) -> Complex64 {
+        Complex64::new(self.center_x, self.center_y)
+    }
+
+    /// A viewport centered on `center`, such as a `Complex64` or an `(re, im)` tuple.
+    pub fn centered_at(center: impl IntoComplex, zoom: f64) -> Self {
+        let center = center.into_complex();
+        Self::new(center.re, center.im, zoom)
+    }
+
+    /// Returns a copy of the viewport with the zoom multiplied by `factor`.
+    pub fn zoomed(&self, factor: f64) -> Self {
+        Self { zoom: self.zoom * factor, ..*self }
+    }
+
+    /// A copy of the viewport centered on the point at pixel position `(pixel_x, pixel_y)` of a
+    /// `width` x `height` render of it, such as a click, with the zoom multiplied by `factor`.
+    /// A `factor` below `1.0` zooms out, and `1.0` only recenters. The position may be
+    /// fractional, see `CoordMapper::point_at`.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::viewport::Viewport;
+    ///
+    /// let viewport = Viewport::classic();
+    /// let clicked = viewport.point(100, 200, 800, 800);
+    /// let zoomed = viewport.zoom_at(100.0, 200.0, 800, 800, 4.0);
+    /// assert_eq!((zoomed.center(), zoomed.zoom), (clicked, 4.0));
+    /// ```
+    pub fn zoom_at(
+        &self,
+        pixel_x: f64,
+        pixel_y: f64,
+        width: u32,
+        height: u32,
+        factor: f64,
+    ) -> Self {
+        let center = self.mapper(width, height).point_at(pixel_x, pixel_y);
+        Self { center_x: center.re, center_y: center.im, ..self.zoomed(factor) }
+    }
+
+    /// Interpolates between `self` (at `t = 0.0`) and `end` (at `t = 1.0`).
+    ///
+    /// The zoom is interpolated geometrically so that every step magnifies by the same factor,
+    /// and the center converges on `end` at the same rate the view shrinks, which keeps the
+    /// target point steady on screen during a dive.
+    ///
+    /// # Examples
+    /// ```
+    /// use mandelbrot::viewport::Viewport;
+    ///
+    /// let start = Viewport::classic();
+    /// let end = Viewport::new(-0.75, 0.1, 100.0);
+    /// assert_eq!(start.interpolate(&end, 0.0), start);
+    /// assert_eq!(start.interpolate(&end, 1.0), end);
+    /// assert!((start.interpolate(&end, 0.5).zoom - 10.0).abs() < 1e-9);
+    /// ```
+    pub fn interpolate(&self, end: &Viewport, t: f64) -> Self {
+        if t <= 0.0 {
+            return *self;
+        }
+        if t >= 1.0 {
+            return *end;
+        }
+        let zoom = self.zoom * (end.zoom / self.zoom).powf(t);
+        let weight = if (end.zoom - self.zoom).abs() < f64::EPSILON {
+            1.0 - t
+        } else {
+            let ratio = self.zoom / end.zoom;
+            (self.zoom / zoom - ratio) / (1.0 - ratio)
+        };
+        Self {
+            center_x: end.center_x + (self.center_x - end.center_x) * weight,
+            center_y: end.center_y + (self.center_y - end.center_y) * weight,
+            zoom,
+            precision: end.precision,
+            fit: end.fit,
+        }
+    }
+}
+
+/// Converts between the pixels of a `width` x `height` grid and the points of the plane a
+/// viewport shows on it, in both directions.
+///
+/// Frontends keep one for the image on screen and map mouse positions with `point_at`, for
+/// instance to center the next view on a click. Pixel `(x, y)` maps to its top left corner, the
+/// point renders evaluate for it, and `nearest_pixel` maps such points back to their pixel.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::viewport::Viewport;
+///
+/// let mapper = Viewport::default().mapper(800, 600);
+/// let c = mapper.point(123, 456);
+/// assert_eq!(mapper.nearest_pixel(c), Some((123, 456)));
+/// assert_eq!(mapper.point_at(400.0, 300.0), Viewport::default().center());
+/// assert_eq!(mapper.nearest_pixel((5.0, 0.0)), None);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CoordMapper {
+    pub viewport: Viewport,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl CoordMapper {
+    pub fn new(viewport: Viewport, width: u32, height: u32) -> Self {
+        Self { viewport, width, height }
+    }
+
+    /// The point of the plane renders evaluate for pixel `(x, y)`, see
+    /// `Viewport::to_complex_num`.
+    pub fn point(&self, x: u32, y: u32) -> Complex64 {
+        self.viewport.point(x, y, self.width, self.height)
+    }
+
+    /// The point of the plane at the fractional pixel position `(x, y)`, such as the position
+    /// of the mouse. Whole positions give the same points as `point`.
+    pub fn point_at(&self, x: f64, y: f64) -> Complex64 {
+        let (span_x, span_y) = self.viewport.spans(self.width, self.height);
+        let offset = |pixel: f64, size: u32| (2.0 * pixel - size as f64) / (2.0 * size as f64);
+        Complex64::new(
+            self.viewport.center_x + offset(x, self.width) * span_x,
+            self.viewport.center_y + offset(y, self.height) * span_y,
+        )
+    }
+
+    /// The fractional pixel position of `z`, the inverse of `point_at`, see `Viewport::pixel`.
+    pub fn pixel(&self, z: impl IntoComplex) -> (f64, f64) {
+        self.viewport.pixel(z, self.width, self.height)
+    }
+
+    /// The pixel whose point is nearest to `z`, or `None` if `z` lies outside the grid.
+    pub fn nearest_pixel(&self, z: impl IntoComplex) -> Option<(u32, u32)> {
+        let (x, y) = self.pixel(z);
+        let (x, y) = (x.round(), y.round());
+        let inside =
+            (0.0..self.width as f64).contains(&x) && (0.0..self.height as f64).contains(&y);
+        inside.then_some((x as u32, y as u32))
+    }
+
+    /// Width and height of one pixel in the plane.
+    pub fn pixel_size(&self) -> (f64, f64) {
+        let (span_x, span_y) = self.viewport.spans(self.width, self.height);
+        (span_x / self.width as f64, span_y / self.height as f64)
+    }
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mandelbrot_img;
+
+    #[test]
+    fn test_stretched_classic_matches_fixed_mapping() {
+        let viewport = Viewport::classic().with_fit(FitMode::Stretch);
+        for (x, y) in [(0, 0), (1, 1), (400, 400), (799, 0), (123, 456)] {
+            let (ax, ay) = viewport.to_complex_num(x, y, 800, 800);
+            let (bx, by) = mandelbrot_img::to_complex_num(x, y, 800, 800);
+            assert!((ax - bx).abs() < 1e-12 && (ay - by).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_fitted_pixels_are_square() {
+        let viewport = Viewport::new(-0.745, 0.11, 30.0);
+        for (width, height) in [(800, 800), (1920, 1080), (300, 900), (700, 400)] {
+            for fit in [FitMode::Contain, FitMode::Cover] {
+                let (span_x, span_y) = viewport.with_fit(fit).spans(width, height);
+                let (pixel_x, pixel_y) = (span_x / width as f64, span_y / height as f64);
+                assert!((pixel_x / pixel_y - 1.0).abs() < 1e-12, "{fit:?} {width}x{height}");
+                let contains = span_x >= viewport.width() && span_y >= viewport.height();
+                let covered = span_x <= viewport.width() && span_y <= viewport.height();
+                assert!(if fit == FitMode::Contain { contains } else { covered });
+            }
+        }
+    }
+
+    #[test]
+    fn test_from_bounds_round_trips() {
+        let viewport = Viewport::new(-0.745, 0.11, 250.0);
+        let (min_x, max_x, min_y, max_y) = viewport.bounds();
+        let restored = Viewport::from_bounds(min_x, max_x, min_y, max_y);
+        assert!((restored.center_x - viewport.center_x).abs() < 1e-12);
+        assert!((restored.center_y - viewport.center_y).abs() < 1e-12);
+        assert!((restored.zoom / viewport.zoom - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_mapper_round_trips_every_pixel() {
+        for fit in FitMode::ALL {
+            let mapper = Viewport::seahorse_valley().with_fit(fit).mapper(320, 180);
+            for y in 0..mapper.height {
+                for x in 0..mapper.width {
+                    let c = mapper.point(x, y);
+                    assert_eq!(mapper.point_at(x as f64, y as f64), c);
+                    assert_eq!(mapper.nearest_pixel(c), Some((x, y)), "{fit:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_zoomed_keeps_center() {
+        let viewport = Viewport::new(-0.5, 0.25, 2.0).zoomed(4.0);
+        assert_eq!(viewport, Viewport::new(-0.5, 0.25, 8.0));
+        assert_eq!(viewport.to_complex_num(50, 50, 100, 100), (-0.5, 0.25));
+    }
+
+    #[test]
+    fn test_interpolate_zoom_is_monotonic() {
+        let start = Viewport::default();
+        let end = Viewport::new(-0.743643887037151, 0.131825904205330, 1e4);
+        let mut previous = start.zoom;
+        for i in 1..=10 {
+            let zoom = start.interpolate(&end, i as f64 / 10.0).zoom;
+            assert!(zoom > previous);
+            previous = zoom;
+        }
+    }
+}
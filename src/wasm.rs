@@ -0,0 +1,103 @@
+//! JavaScript bindings for running the renderer in a browser, built with `wasm-bindgen`.
+//!
+//! Without optional features the library has no file, terminal or thread dependencies a browser
+//! lacks, so building it for `wasm32-unknown-unknown` with the `wasm` feature, for instance with
+//! `wasm-pack build --target web -- --features wasm`, gives a module that renders straight into
+//! a canvas:
+//!
+//! ```js
+//! import init, { View, renderRgba } from "./pkg/mandelbrot.js";
+//!
+//! await init();
+//! let view = View.preset("seahorse-valley");
+//! const pixels = renderRgba(canvas.width, canvas.height, 500, view);
+//! const image = new ImageData(new Uint8ClampedArray(pixels), canvas.width, canvas.height);
+//! canvas.getContext("2d").putImageData(image, 0, 0);
+//! canvas.onclick = (e) => { view = view.zoomAt(e.offsetX, e.offsetY, canvas.width, canvas.height, 2); };
+//! ```
+
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    analysis::escape_viewport, ansi::escape_color, options::RenderOptions, viewport::Viewport,
+};
+
+/// A viewport, passed to and from JavaScript.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct View {
+    viewport: Viewport,
+}
+
+#[wasm_bindgen]
+impl View {
+    #[wasm_bindgen(constructor)]
+    pub fn new(center_x: f64, center_y: f64, zoom: f64) -> View {
+        Self { viewport: Viewport::new(center_x, center_y, zoom) }
+    }
+
+    /// The preset named `name`, see `Viewport::preset`, or `undefined`.
+    pub fn preset(name: &str) -> Option<View> {
+        Viewport::preset(name).map(|viewport| Self { viewport })
+    }
+
+    #[wasm_bindgen(getter, js_name = centerX)]
+    pub fn center_x(&self) -> f64 {
+        self.viewport.center_x
+    }
+
+    #[wasm_bindgen(getter, js_name = centerY)]
+    pub fn center_y(&self) -> f64 {
+        self.viewport.center_y
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn zoom(&self) -> f64 {
+        self.viewport.zoom
+    }
+
+    /// The view centered on pixel `(x, y)` of a `width` x `height` render with the zoom
+    /// multiplied by `factor`, see `Viewport::zoom_at`.
+    #[wasm_bindgen(js_name = zoomAt)]
+    pub fn zoom_at(&self, x: f64, y: f64, width: u32, height: u32, factor: f64) -> View {
+        Self { viewport: self.viewport.zoom_at(x, y, width, height, factor) }
+    }
+
+    /// An iteration limit for the view, see `Viewport::suggested_iterations`.
+    #[wasm_bindgen(js_name = suggestedIterations)]
+    pub fn suggested_iterations(&self) -> u32 {
+        self.viewport.suggested_iterations()
+    }
+}
+
+/// Renders `view` at `width` x `height` as RGBA pixels, four bytes per pixel row by row, the
+/// layout of `ImageData`. Points are colored by escape count like the colored ASCII art, and
+/// every pixel is opaque.
+#[wasm_bindgen(js_name = renderRgba)]
+pub fn render_rgba(width: u32, height: u32, iterations: u32, view: &View) -> Vec<u8> {
+    let opts = RenderOptions::new(iterations);
+    escape_viewport(width, height, &view.viewport, &opts)
+        .iter()
+        .flat_map(|result| {
+            let [r, g, b] = escape_color(result.iterations, iterations);
+            [r, g, b, u8::MAX]
+        })
+        .collect()
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rgba_has_four_opaque_bytes_per_pixel() {
+        let view = View::preset("default").unwrap();
+        let pixels = render_rgba(40, 30, 100, &view);
+        assert_eq!(pixels.len(), 40 * 30 * 4);
+        assert!(pixels.chunks(4).all(|pixel| pixel[3] == u8::MAX));
+        let zoomed = view.zoom_at(20.0, 15.0, 40, 30, 2.0);
+        assert_eq!(zoomed.zoom(), view.zoom() * 2.0);
+    }
+}
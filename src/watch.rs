@@ -0,0 +1,71 @@
+//! Detects changes to a file by polling its modification time.
+//!
+//! Long-running frontends use `FileWatcher` to notice edits to `settings.toml` and reload it
+//! between requests without restarting. Polling keeps this dependency-free and works the same on
+//! every platform; callers decide how often to poll.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// Watches a single file for modifications.
+#[derive(Debug, Clone)]
+pub struct FileWatcher {
+    path: PathBuf,
+    last_modified: Option<SystemTime>,
+}
+
+impl FileWatcher {
+    /// Starts watching `path`. The current state of the file is taken as unchanged.
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let last_modified = modified(&path);
+        Self { path, last_modified }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns `true` if the file was modified, created or removed since the last call (or since
+    /// the watcher was created).
+    pub fn changed(&mut self) -> bool {
+        let modified = modified(&self.path);
+        if modified == self.last_modified {
+            return false;
+        }
+        self.last_modified = modified;
+        true
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs::File, time::Duration};
+
+    use super::*;
+
+    #[test]
+    fn test_changed_after_modification() {
+        let path = env::temp_dir().join(format!("mandelbrot-watch-{}.toml", std::process::id()));
+        fs::write(&path, "iterations = 100").unwrap();
+        let mut watcher = FileWatcher::new(&path);
+        assert!(!watcher.changed());
+
+        let file = File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(5)).unwrap();
+        assert!(watcher.changed());
+        assert!(!watcher.changed());
+
+        fs::remove_file(&path).unwrap();
+        assert!(watcher.changed());
+    }
+}
@@ -0,0 +1,224 @@
+//! XaoS-style frame reuse for continuous zooming.
+//!
+//! Consecutive frames of a zoom or pan cover mostly the same part of the plane. Because the real
+//! part of a pixel only depends on its column and the imaginary part only on its row, a column
+//! of the new frame whose real coordinate lies within half a pixel of a column of the previous
+//! frame can take its values from that column, and likewise for rows. Only pixels on a column or
+//! row without such a match are iterated again, which for small zoom steps is a small fraction of
+//! the frame.
+//!
+//! Reused pixels are off by at most half a pixel, so the approximation error never accumulates
+//! beyond that: every frame is built from exact values of the frame before it, and each old
+//! column or row is reused at most once so magnified areas are recomputed rather than stretched.
+
+use crate::{mandelbrot_img::mandelbrot, scheduler::render_grid, viewport::Viewport};
+
+/// How much work a frame took.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrameStats {
+    /// Pixels copied from the previous frame.
+    pub reused: u64,
+    /// Pixels that were iterated.
+    pub computed: u64,
+}
+
+/// Renders iteration counts of successive viewports, reusing what it can of the previous frame.
+///
+/// # Examples
+/// ```
+/// use mandelbrot::{viewport::Viewport, xaos::XaosRenderer};
+///
+/// let mut renderer = XaosRenderer::new(80, 60, 100);
+/// let viewport = Viewport::default();
+/// let stats = renderer.render(&viewport);
+/// assert_eq!(stats.computed, 80 * 60);
+///
+/// let stats = renderer.render(&viewport.zoomed(1.05));
+/// assert!(stats.reused > stats.computed);
+/// ```
+#[derive(Debug, Clone)]
+pub struct XaosRenderer {
+    width: u32,
+    height: u32,
+    iterations: u32,
+    /// Real coordinate of every column of the last frame; empty before the first frame.
+    columns: Vec<f64>,
+    /// Imaginary coordinate of every row of the last frame.
+    rows: Vec<f64>,
+    counts: Vec<u32>,
+}
+
+impl XaosRenderer {
+    pub fn new(width: u32, height: u32, iterations: u32) -> Self {
+        Self {
+            width,
+            height,
+            iterations,
+            columns: Vec::new(),
+            rows: Vec::new(),
+            counts: Vec::new(),
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn iterations(&self) -> u32 {
+        self.iterations
+    }
+
+    /// Renders `width` x `height` frames from now on. The previous frame no longer fits them,
+    /// so a change of size also `reset`s the renderer.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        if (width, height) != (self.width, self.height) {
+            (self.width, self.height) = (width, height);
+            self.reset();
+        }
+    }
+
+    /// Iterates up to `iterations` from now on. The counts of the previous frame were capped at
+    /// the old limit, so a change of limit also `reset`s the renderer.
+    pub fn set_iterations(&mut self, iterations: u32) {
+        if iterations != self.iterations {
+            self.iterations = iterations;
+            self.reset();
+        }
+    }
+
+    /// Iteration counts of the last rendered frame in row-major order; empty before the first.
+    pub fn counts(&self) -> &[u32] {
+        &self.counts
+    }
+
+    /// Forgets the previous frame so the next one is computed from scratch.
+    pub fn reset(&mut self) {
+        self.columns.clear();
+        self.rows.clear();
+        self.counts.clear();
+    }
+
+    /// Renders `viewport`, copying the pixels of columns and rows that match the previous frame
+    /// to within half a pixel and iterating the rest.
+    pub fn render(&mut self, viewport: &Viewport) -> FrameStats {
+        let (width, height) = (self.width, self.height);
+        let columns = (0..width).map(|x| viewport.to_complex_num(x, 0, width, height).0).collect();
+        let rows = (0..height).map(|y| viewport.to_complex_num(0, y, width, height).1).collect();
+        self.render_coordinates(columns, rows)
+    }
+
+    /// Like `render`, for the frame whose columns have the real coordinates `columns` and whose
+    /// rows have the imaginary coordinates `rows`, both evenly spaced and increasing, for grids
+    /// laid out other than by `Viewport::to_complex_num`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are not `width` columns and `height` rows.
+    pub fn render_coordinates(&mut self, columns: Vec<f64>, rows: Vec<f64>) -> FrameStats {
+        let (width, height) = (self.width, self.height);
+        assert_eq!((columns.len(), rows.len()), (width as usize, height as usize));
+        let step = |coordinates: &[f64]| match coordinates {
+            [first, second, ..] => second - first,
+            _ => 0.0,
+        };
+        let column_sources = match_coordinates(&self.columns, &columns, step(&columns));
+        let row_sources = match_coordinates(&self.rows, &rows, step(&rows));
+
+        let previous = &self.counts;
+        let iterations = self.iterations;
+        let counts = render_grid(width, height, |x, y| {
+            match (column_sources[x as usize], row_sources[y as usize]) {
+                (Some(old_x), Some(old_y)) => previous[old_y * width as usize + old_x],
+                _ => mandelbrot((columns[x as usize], rows[y as usize]), iterations),
+            }
+        });
+
+        let matched_columns = column_sources.iter().flatten().count() as u64;
+        let matched_rows = row_sources.iter().flatten().count() as u64;
+        let reused = matched_columns * matched_rows;
+        self.columns = columns;
+        self.rows = rows;
+        self.counts = counts;
+        FrameStats { reused, computed: width as u64 * height as u64 - reused }
+    }
+}
+
+/// For every coordinate in `new`, the index of a coordinate in `old` at most half of `step` away,
+/// if there is one. Both slices must be increasing; each old coordinate is used at most once.
+fn match_coordinates(old: &[f64], new: &[f64], step: f64) -> Vec<Option<usize>> {
+    let tolerance = step.abs() / 2.0;
+    let mut next_free = 0;
+    new.iter()
+        .map(|&coordinate| {
+            // Index of the first old coordinate that is not smaller than `coordinate`.
+            let upper = next_free + old[next_free..].partition_point(|&o| o < coordinate);
+            let nearest = [upper.checked_sub(1), Some(upper)]
+                .into_iter()
+                .flatten()
+                .filter(|&i| i >= next_free && i < old.len())
+                .min_by(|&a, &b| {
+                    (old[a] - coordinate).abs().total_cmp(&(old[b] - coordinate).abs())
+                })?;
+            if (old[nearest] - coordinate).abs() > tolerance {
+                return None;
+            }
+            next_free = nearest + 1;
+            Some(nearest)
+        })
+        .collect()
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exact(viewport: &Viewport, width: u32, height: u32, iterations: u32) -> Vec<u32> {
+        render_grid(width, height, |x, y| {
+            mandelbrot(viewport.to_complex_num(x, y, width, height), iterations)
+        })
+    }
+
+    #[test]
+    fn test_match_coordinates_uses_each_old_coordinate_once() {
+        let old = [0.0, 1.0, 2.0, 3.0];
+        // Zooming in by two: new coordinates sit between the old ones half the time.
+        let new = [1.0, 1.5, 2.0, 2.5];
+        assert_eq!(match_coordinates(&old, &new, 0.5), vec![Some(1), None, Some(2), None]);
+        assert_eq!(match_coordinates(&[], &new, 0.5), vec![None; 4]);
+    }
+
+    #[test]
+    fn test_unchanged_viewport_is_fully_reused() {
+        let viewport = Viewport::default();
+        let mut renderer = XaosRenderer::new(40, 30, 100);
+        renderer.render(&viewport);
+        let stats = renderer.render(&viewport);
+        assert_eq!(stats, FrameStats { reused: 40 * 30, computed: 0 });
+        assert_eq!(renderer.counts(), exact(&viewport, 40, 30, 100));
+    }
+
+    #[test]
+    fn test_zoom_stays_close_to_exact_render() {
+        let (width, height, iterations) = (120, 90, 200);
+        let mut renderer = XaosRenderer::new(width, height, iterations);
+        let mut viewport = Viewport::default();
+        let mut computed = renderer.render(&viewport).computed;
+        for _ in 0..20 {
+            viewport = viewport.zoomed(1.05);
+            computed += renderer.render(&viewport).computed;
+        }
+        // Twenty-one frames cost far less than twenty-one full renders.
+        assert!(computed < 21 * (width * height) as u64 / 3, "{computed}");
+
+        let inside = |counts: &[u32]| counts.iter().filter(|&&c| c == iterations).count() as f64;
+        let approximate = inside(renderer.counts());
+        let expected = inside(&exact(&viewport, width, height, iterations));
+        assert!((approximate - expected).abs() / expected < 0.05, "{approximate} vs {expected}");
+    }
+}